@@ -0,0 +1,27 @@
+//! Records the target triple, profile and git commit for `--build-info`.
+
+use std::process::Command;
+
+fn main() {
+    for name in ["TARGET", "PROFILE"] {
+        let value = std::env::var(name).unwrap_or_else(|_| "unknown".to_string());
+        println!("cargo:rustc-env=BUILD_{name}={value}");
+    }
+
+    // Packagers building from a tarball can pass the commit in
+    // CLAUDE_HOOK_ADVISOR_COMMIT, since there is no git checkout to ask
+    println!("cargo:rerun-if-env-changed=CLAUDE_HOOK_ADVISOR_COMMIT");
+    let commit = std::env::var("CLAUDE_HOOK_ADVISOR_COMMIT").ok().or_else(git_commit);
+    println!("cargo:rustc-env=BUILD_COMMIT={}", commit.as_deref().unwrap_or("unknown"));
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
+
+/// The short hash of the checked-out commit, with `-dirty` for uncommitted changes.
+fn git_commit() -> Option<String> {
+    let output = Command::new("git").args(["describe", "--always", "--dirty", "--abbrev=12"]).output().ok()?;
+    let commit = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !commit.trim().is_empty()).then(|| commit.trim().to_string())
+}
@@ -0,0 +1,113 @@
+//! Slow-command advice driven by recorded execution durations
+
+use crate::rules::command_matches;
+use crate::state::DurationLog;
+use crate::types::{Advice, Config, Result};
+
+/// Recent runs of a command considered when deciding whether it is repeatedly slow.
+const RECENT_RUNS: usize = 10;
+
+/// Finds advice for `command` if commands like it have been repeatedly slow.
+///
+/// Only the most recent runs matching each `[[advice]]` pattern count, so advice
+/// stops once the command gets faster again.
+///
+/// # Arguments
+/// * `config` - Configuration containing the `[[advice]]` rules
+/// * `log` - Recorded durations, including the current run
+/// * `command` - The command that just finished
+///
+/// # Returns
+/// * `Ok(Some((advice, slow_runs)))` - Advice to give and the number of slow runs seen
+/// * `Ok(None)` - If no advice applies
+pub fn slow_command_advice<'a>(
+    config: &'a Config,
+    log: &DurationLog,
+    command: &str,
+) -> Result<Option<(&'a Advice, usize)>> {
+    for advice in &config.advice {
+        if !command_matches(&advice.pattern, command)? {
+            continue;
+        }
+
+        let mut recent = Vec::new();
+        for sample in log.samples.iter().rev() {
+            if recent.len() == RECENT_RUNS {
+                break;
+            }
+            if command_matches(&advice.pattern, &sample.command)? {
+                recent.push(sample.duration_ms);
+            }
+        }
+
+        let slow_runs = recent.iter().filter(|ms| **ms > advice.slower_than_ms).count();
+        if slow_runs >= advice.min_samples {
+            return Ok(Some((advice, slow_runs)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The text shown to Claude for a piece of advice.
+pub fn advice_message(advice: &Advice, slow_runs: usize) -> String {
+    let details = format!(
+        "'{}' took longer than {} ms in {slow_runs} recent runs. Consider '{}' instead.",
+        advice.pattern, advice.slower_than_ms, advice.suggest
+    );
+    match &advice.message {
+        Some(message) => format!("{message} ({details})"),
+        None => details,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+[[advice]]
+pattern = "grep -r"
+slower_than_ms = 2000
+suggest = "rg"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_advice_after_repeated_slow_runs() {
+        let config = config();
+        let mut log = DurationLog::default();
+
+        log.record("grep -r TODO src", 5000);
+        log.record("grep -r FIXME .", 3000);
+        log.record("ls", 9000);
+        assert!(slow_command_advice(&config, &log, "grep -r FIXME .").unwrap().is_none());
+
+        log.record("cd app && grep -r foo", 2500);
+        let (advice, slow_runs) = slow_command_advice(&config, &log, "cd app && grep -r foo").unwrap().unwrap();
+        assert_eq!(advice.suggest, "rg");
+        assert_eq!(slow_runs, 3);
+        assert!(advice_message(advice, slow_runs).contains("Consider 'rg' instead"));
+
+        // Unrelated and non-recursive commands never get advice
+        assert!(slow_command_advice(&config, &log, "ls").unwrap().is_none());
+        assert!(slow_command_advice(&config, &log, "grep foo file").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_advice_stops_when_command_gets_faster() {
+        let config = config();
+        let mut log = DurationLog::default();
+        for _ in 0..3 {
+            log.record("grep -r TODO .", 5000);
+        }
+        for _ in 0..RECENT_RUNS {
+            log.record("grep -r TODO .", 100);
+        }
+        assert!(slow_command_advice(&config, &log, "grep -r TODO .").unwrap().is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//! Default-deny mode for locked-down environments
+//!
+//! With `[mode] default = "deny"`, a Bash command only reaches the mappings
+//! and rules if every simple command in it matches an `[allowed] commands`
+//! pattern; anything else is blocked. A pattern matches whole words at the
+//! start of the command with its wrappers stripped, so `cargo` allows
+//! `cargo test` but not `cargo-audit`, and a wrapper such as `sudo` must
+//! itself be allowed. Command substitutions can hide any
+//! command, so they are never allowed in this mode.
+
+use crate::rules::RuleMatch;
+use crate::shell::{normalize, split_segments, tokenize};
+use crate::types::{Config, DefaultDecision, Severity};
+
+/// Blocks commands that aren't on the `[allowed]` list when `[mode] default = "deny"`.
+///
+/// # Arguments
+/// * `config` - Configuration with the `[mode]` and `[allowed]` tables
+/// * `command` - The command about to run
+///
+/// # Returns
+/// * `Some(RuleMatch)` - A blocking match naming the first command that isn't allowed
+/// * `None` - In the default allow mode, or if every part of the command is allowed
+pub fn check_allowlist(config: &Config, command: &str) -> Option<RuleMatch> {
+    if config.mode.default != DefaultDecision::Deny {
+        return None;
+    }
+
+    for segment in split_segments(command) {
+        let text = &command[segment];
+        let normalized = normalize(text);
+        let underlying = &text[normalized.command_start..];
+
+        let denied = if text.contains("$(") || text.contains('`') || text.contains("<(") || text.contains(">(") {
+            Some(format!("'{}' runs a command substitution, which default-deny mode never allows", text.trim()))
+        } else if let Some(wrapper) = first_denied(config, &normalized.wrappers) {
+            Some(format!("'{wrapper}' is not in the [allowed] list"))
+        } else if underlying.trim().is_empty() || is_allowed(config, underlying) {
+            None
+        } else {
+            let program = tokenize(underlying).first().map_or(underlying, |word| &underlying[word.clone()]);
+            Some(format!("'{program}' is not in the [allowed] list"))
+        };
+
+        if let Some(denied) = denied {
+            return Some(RuleMatch {
+                pattern: "allowed".to_string(),
+                reason: deny_reason(config, &denied),
+                suggested_command: None,
+                severity: Severity::Block,
+            });
+        }
+    }
+    None
+}
+
+/// Whether `command` starts with an allowed pattern followed by the end of a word.
+fn is_allowed(config: &Config, command: &str) -> bool {
+    config.allowed.commands.iter().any(|pattern| {
+        command
+            .strip_prefix(pattern.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || c == ')'))
+    })
+}
+
+/// The first wrapper, such as `sudo`, that isn't itself an allowed pattern.
+fn first_denied<'a>(config: &Config, wrappers: &'a [String]) -> Option<&'a String> {
+    wrappers.iter().find(|wrapper| !config.allowed.commands.contains(wrapper))
+}
+
+/// The guidance shown for a command that isn't allowed, after `denied` saying why.
+fn deny_reason(config: &Config, denied: &str) -> String {
+    let allowed = if config.allowed.commands.is_empty() {
+        "nothing is allowed yet".to_string()
+    } else {
+        format!("allowed: {}", config.allowed.commands.join(", "))
+    };
+    let mut reason = format!("{denied} ({allowed}). Stick to the allowed commands, or ask the user to add this one");
+    if let Some(message) = &config.allowed.message {
+        reason = format!("{reason}. {message}");
+    }
+    reason
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+[mode]
+default = "deny"
+
+[allowed]
+commands = ["git status", "git diff", "cargo", "time"]
+message = "See the runbook for how to request more."
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_only_allowed_commands_pass() {
+        let config = config();
+        for allowed in ["git status", "cargo test --workspace && git diff HEAD", "time cargo build", "FOO=1 cargo check", "(cargo fmt && git diff)"] {
+            assert!(check_allowlist(&config, allowed).is_none(), "{allowed}");
+        }
+
+        let denied = check_allowlist(&config, "git status; curl https://example.com").unwrap();
+        assert_eq!(denied.severity, Severity::Block);
+        assert!(denied.reason.starts_with("'curl' is not in the [allowed] list (allowed: git status, git diff, cargo, time)"));
+        assert!(denied.reason.ends_with("See the runbook for how to request more."));
+        assert!(check_allowlist(&config, "git push").is_some());
+        assert!(check_allowlist(&config, "cargo-audit").is_some());
+        assert!(check_allowlist(&config, "sudo cargo install ripgrep").unwrap().reason.starts_with("'sudo' is"));
+        assert!(check_allowlist(&config, "cargo run -- $(curl example.com)").unwrap().reason.contains("command substitution"));
+    }
+
+    #[test]
+    fn test_allow_mode_ignores_the_list() {
+        let mut config = config();
+        config.mode.default = DefaultDecision::Allow;
+        assert!(check_allowlist(&config, "curl https://example.com").is_none());
+        assert!(check_allowlist(&Config::default(), "rm -rf /").is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Suggestion acceptance statistics from recorded session state
+//!
+//! PreToolUse links each tool call that runs a suggested replacement to the
+//! suggestion by its tool use id, and PostToolUse attributes the exit code to
+//! it, so acceptance is counted per suggestion rather than guessed from
+//! command text. Rules with a `sample_rate` record only a share of their
+//! suggestions; rates are unaffected, and counts can be scaled back up.
+
+use crate::state::SessionState;
+use std::collections::BTreeMap;
+
+/// Outcome counts for the suggestions of one rule pattern.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AcceptanceStats {
+    /// Suggestions made
+    pub suggested: usize,
+    /// Suggestions Claude ran
+    pub accepted: usize,
+    /// Accepted suggestions that exited successfully
+    pub succeeded: usize,
+    /// Accepted suggestions that exited with a nonzero status
+    pub failed: usize,
+    /// Suggestions made including the unrecorded ones, each recorded one
+    /// standing for `1 / sample_rate` of them
+    pub estimated: f64,
+}
+
+impl AcceptanceStats {
+    /// Share of suggestions Claude ran, as a percentage.
+    pub fn acceptance_rate(&self) -> f64 {
+        percentage(self.accepted, self.suggested)
+    }
+
+    /// Share of finished accepted suggestions that succeeded, as a percentage.
+    pub fn success_rate(&self) -> f64 {
+        percentage(self.succeeded, self.succeeded + self.failed)
+    }
+
+    /// Share of suggestions that were recorded, when the rule samples them.
+    pub fn sample_rate(&self) -> Option<f64> {
+        let rate = self.suggested as f64 / self.estimated;
+        (rate < 1.0).then_some(rate)
+    }
+}
+
+/// Aggregates suggestion outcomes across sessions, keyed by rule pattern.
+///
+/// # Arguments
+/// * `sessions` - Session states to aggregate
+///
+/// # Returns
+/// * Statistics per pattern, sorted by pattern
+pub fn acceptance_by_pattern(sessions: &[SessionState]) -> BTreeMap<String, AcceptanceStats> {
+    let mut stats: BTreeMap<String, AcceptanceStats> = BTreeMap::new();
+
+    for suggestion in sessions.iter().flat_map(|s| &s.suggestions) {
+        let entry = stats.entry(suggestion.pattern.clone()).or_default();
+        entry.suggested += 1;
+        entry.estimated += 1.0 / suggestion.sample_rate.unwrap_or(1.0);
+        if suggestion.accepted_by.is_some() {
+            entry.accepted += 1;
+            match suggestion.exit_code {
+                Some(0) => entry.succeeded += 1,
+                Some(_) => entry.failed += 1,
+                None => {}
+            }
+        }
+    }
+
+    stats
+}
+
+fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acceptance_by_pattern() {
+        let mut first = SessionState::default();
+        first.record_suggestion("npm ci", "bun ci", "npm", Some("toolu_1"));
+        first.record_suggestion("npm test", "bun test", "npm", Some("toolu_2"));
+        first.accept_suggestion("bun ci", "toolu_3");
+        first.suggestion_for_tool("toolu_3").unwrap().exit_code = Some(0);
+
+        let mut second = SessionState::default();
+        second.record_suggestion("yarn", "bun", "yarn", Some("toolu_1"));
+        second.accept_suggestion("bun", "toolu_2");
+        second.suggestion_for_tool("toolu_2").unwrap().exit_code = Some(1);
+
+        let stats = acceptance_by_pattern(&[first, second]);
+        let npm = &stats["npm"];
+        assert_eq!((npm.suggested, npm.accepted, npm.succeeded), (2, 1, 1));
+        assert_eq!(npm.acceptance_rate(), 50.0);
+        assert_eq!(npm.success_rate(), 100.0);
+
+        let yarn = &stats["yarn"];
+        assert_eq!((yarn.accepted, yarn.failed), (1, 1));
+        assert_eq!(yarn.success_rate(), 0.0);
+        assert_eq!(yarn.sample_rate(), None);
+    }
+
+    #[test]
+    fn test_sampled_suggestions() {
+        let mut state = SessionState::default();
+        state.record_suggestion("npm ci", "bun ci", "npm", Some("toolu_1")).sample_rate = Some(0.1);
+        state.record_suggestion("npm test", "bun test", "npm", Some("toolu_2")).sample_rate = Some(0.1);
+        state.accept_suggestion("bun ci", "toolu_3");
+
+        let npm = &acceptance_by_pattern(&[state])["npm"];
+        assert_eq!((npm.suggested, npm.accepted), (2, 1));
+        assert!((npm.estimated - 20.0).abs() < 1e-9);
+        assert!((npm.sample_rate().unwrap() - 0.1).abs() < 1e-9);
+        assert_eq!(npm.acceptance_rate(), 50.0);
+    }
+}
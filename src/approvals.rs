@@ -0,0 +1,280 @@
+//! Approval of `ask` decisions by a webhook
+//!
+//! An `ask` rule leaves the decision to whoever is watching the Claude Code
+//! session, which is nobody in an unattended run. With `[approvals] webhook`
+//! set, the pending command is POSTed to an approval service instead, such
+//! as an internal tool or a Slack workflow backed by one, and the hook waits
+//! briefly for its verdict. An approved command runs without a prompt and a
+//! denied one is blocked. Without a verdict in time, the user is asked as
+//! before, or the command is blocked with `on_timeout = "block"`.
+
+use crate::audit::Redactor;
+use crate::rules::RuleMatch;
+use crate::types::{AdvisorError, Approvals, ConfigError, HookInput, Result};
+use serde::{Deserialize, Serialize};
+
+/// A pending command, as POSTed to the webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRequest {
+    /// Identifies the request when polling; the tool use ID when Claude Code sends one
+    pub id: String,
+    pub session_id: String,
+    pub tool_name: String,
+    /// The command, with secrets masked as in the audit log
+    pub command: String,
+    /// Why the rule asks for confirmation
+    pub reason: String,
+    /// The rule pattern that matched
+    pub pattern: String,
+    pub cwd: Option<String>,
+}
+
+impl ApprovalRequest {
+    /// Builds the request for a command an `ask` rule matched.
+    ///
+    /// # Arguments
+    /// * `hook_input` - The PreToolUse input
+    /// * `command` - The command as checked
+    /// * `rule_match` - The `ask` match
+    /// * `redact` - Extra `[audit] redact` patterns
+    pub fn new(hook_input: &HookInput, command: &str, rule_match: &RuleMatch, redact: &[String]) -> Result<Self> {
+        let id = match &hook_input.tool_use_id {
+            Some(id) => id.to_string(),
+            None => format!("{}-{}", hook_input.session_id, chrono::Utc::now().timestamp_millis()),
+        };
+        Ok(ApprovalRequest {
+            id,
+            session_id: hook_input.session_id.to_string(),
+            tool_name: hook_input.tool_name.as_deref().unwrap_or("Bash").to_string(),
+            command: Redactor::new(redact)?.redact(command),
+            reason: rule_match.reason.clone(),
+            pattern: rule_match.pattern.clone(),
+            cwd: hook_input.cwd.as_deref().map(str::to_string),
+        })
+    }
+}
+
+/// The approval service's verdict on a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub approved: bool,
+    /// Who decided, as the service reports it
+    pub approver: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl Verdict {
+    /// The verdict as a sentence for Claude, such as "Approved by alice: routine cleanup".
+    pub fn describe(&self) -> String {
+        let approver = self.approver.as_deref().unwrap_or("the approval service");
+        let mut text = format!("{} by {approver}", if self.approved { "Approved" } else { "Denied" });
+        if let Some(reason) = &self.reason {
+            text.push_str(&format!(": {reason}"));
+        }
+        text
+    }
+}
+
+/// A response from the approval service.
+#[derive(Debug, Deserialize)]
+struct ApprovalResponse {
+    decision: String,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    approver: Option<String>,
+    /// Where to check on a pending request
+    #[serde(default)]
+    poll_url: Option<String>,
+}
+
+/// What one response says.
+#[derive(Debug, PartialEq)]
+enum Answer {
+    Decided(Verdict),
+    /// Still waiting, with the URL to poll if the service gave one
+    Pending(Option<String>),
+}
+
+/// Parses a response body: `{"decision": "approve" | "deny" | "pending", ...}`.
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn parse_answer(body: &str) -> Result<Answer> {
+    let response: ApprovalResponse = serde_json::from_str(body)?;
+    let approved = match response.decision.to_ascii_lowercase().as_str() {
+        "approve" | "approved" | "allow" => true,
+        "deny" | "denied" | "reject" | "rejected" | "block" => false,
+        "pending" => return Ok(Answer::Pending(response.poll_url)),
+        other => {
+            let message = format!("Approval service answered with unknown decision '{other}'");
+            return Err(AdvisorError::from(ConfigError::InvalidFormat(message)));
+        }
+    };
+    Ok(Answer::Decided(Verdict { approved, approver: response.approver, reason: response.reason }))
+}
+
+/// Sends `request` to the webhook and waits up to `timeout_secs` for a verdict.
+///
+/// A service that can't decide at once answers `pending`, optionally with a
+/// `poll_url`; it is then asked again with a GET every `poll_interval_secs`,
+/// at the `poll_url` or else at the webhook URL followed by `/<id>`.
+///
+/// # Returns
+/// * `Ok(Some(Verdict))` - The service approved or denied the command
+/// * `Ok(None)` - If approvals are off or no verdict arrived in time
+/// * `Err` - If the webhook is not HTTPS, or a request failed or was not understood
+#[cfg(feature = "network")]
+pub fn request_approval(approvals: &Approvals, request: &ApprovalRequest) -> Result<Option<Verdict>> {
+    use crate::telemetry::check_http_endpoint;
+    use std::time::{Duration, Instant};
+
+    let Some(url) = approvals.webhook.as_deref() else {
+        return Ok(None);
+    };
+    check_http_endpoint("[approvals] webhook", url)?;
+    let deadline = Instant::now() + Duration::from_secs(approvals.timeout_secs);
+    let remaining = || deadline.saturating_duration_since(Instant::now());
+
+    let mut answer = send(approvals, url, remaining(), Some(request))?;
+    loop {
+        let poll_url = match answer {
+            Answer::Decided(verdict) => return Ok(Some(verdict)),
+            Answer::Pending(poll_url) => poll_url.unwrap_or_else(|| format!("{}/{}", url.trim_end_matches('/'), request.id)),
+        };
+        check_http_endpoint("[approvals] poll_url", &poll_url)?;
+        std::thread::sleep(Duration::from_secs(approvals.poll_interval_secs).min(remaining()));
+        if remaining().is_zero() {
+            return Ok(None);
+        }
+        answer = send(approvals, &poll_url, remaining(), None)?;
+    }
+}
+
+#[cfg(not(feature = "network"))]
+pub fn request_approval(approvals: &Approvals, _request: &ApprovalRequest) -> Result<Option<Verdict>> {
+    if approvals.webhook.is_none() {
+        return Ok(None);
+    }
+    let message = "[approvals] webhook needs a build with the `network` feature";
+    Err(AdvisorError::from(ConfigError::InvalidFormat(message.to_string())))
+}
+
+/// POSTs `body` to `url`, or GETs `url` without one, and parses the answer.
+#[cfg(feature = "network")]
+fn send(approvals: &Approvals, url: &str, timeout: std::time::Duration, body: Option<&ApprovalRequest>) -> Result<Answer> {
+    let mut request = match body {
+        Some(_) => ureq::post(url).set("Content-Type", "application/json"),
+        None => ureq::get(url),
+    }
+    .timeout(timeout);
+    for (name, value) in &approvals.headers {
+        request = request.set(name, value);
+    }
+
+    let response = match body {
+        Some(body) => request.send_string(&serde_json::to_string(body)?),
+        None => request.call(),
+    }
+    .map_err(|e| AdvisorError::other(format!("Approval request to {url} failed"), e))?;
+    parse_answer(&response.into_string()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_answer() {
+        let verdict = |approved, approver: Option<&str>, reason: Option<&str>| {
+            Answer::Decided(Verdict { approved, approver: approver.map(str::to_string), reason: reason.map(str::to_string) })
+        };
+        assert_eq!(
+            parse_answer(r#"{"decision": "approve", "approver": "alice", "reason": "routine"}"#).unwrap(),
+            verdict(true, Some("alice"), Some("routine"))
+        );
+        assert_eq!(parse_answer(r#"{"decision": "DENIED"}"#).unwrap(), verdict(false, None, None));
+        assert_eq!(
+            parse_answer(r#"{"decision": "pending", "poll_url": "https://a.example/r/1"}"#).unwrap(),
+            Answer::Pending(Some("https://a.example/r/1".to_string()))
+        );
+        assert!(parse_answer(r#"{"decision": "maybe"}"#).is_err());
+        assert!(parse_answer("<html>").is_err());
+    }
+
+    #[test]
+    fn test_verdict_description() {
+        let verdict = Verdict { approved: true, approver: Some("alice".to_string()), reason: Some("routine cleanup".to_string()) };
+        assert_eq!(verdict.describe(), "Approved by alice: routine cleanup");
+        let verdict = Verdict { approved: false, approver: None, reason: None };
+        assert_eq!(verdict.describe(), "Denied by the approval service");
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_request_approval_polls_until_decided() {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/approvals", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let answers = [r#"{"decision": "pending"}"#, r#"{"decision": "approve", "approver": "alice"}"#];
+            let mut requests = Vec::new();
+            for answer in answers {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{answer}", answer.len());
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                requests.push((request_line.trim().to_string(), String::from_utf8(body).unwrap()));
+            }
+            requests
+        });
+
+        let approvals = Approvals { webhook: Some(url), poll_interval_secs: 0, ..Approvals::default() };
+        let request = ApprovalRequest {
+            id: "toolu_1".to_string(),
+            session_id: "s".to_string(),
+            tool_name: "Bash".to_string(),
+            command: "terraform destroy".to_string(),
+            reason: "Needs a human".to_string(),
+            pattern: "terraform destroy".to_string(),
+            cwd: None,
+        };
+        let verdict = request_approval(&approvals, &request).unwrap().unwrap();
+        assert!(verdict.approved);
+        assert_eq!(verdict.approver.as_deref(), Some("alice"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].0.starts_with("POST /approvals "));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&requests[0].1).unwrap()["command"], "terraform destroy");
+        assert!(requests[1].0.starts_with("GET /approvals/toolu_1 "));
+    }
+
+    #[test]
+    fn test_no_webhook_means_no_verdict() {
+        let request = ApprovalRequest {
+            id: "1".to_string(),
+            session_id: "s".to_string(),
+            tool_name: "Bash".to_string(),
+            command: "x".to_string(),
+            reason: String::new(),
+            pattern: "x".to_string(),
+            cwd: None,
+        };
+        assert_eq!(request_approval(&Approvals::default(), &request).unwrap(), None);
+    }
+}
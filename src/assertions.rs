@@ -0,0 +1,150 @@
+//! Expectations embedded in the configuration
+//!
+//! `[[tests]]` entries pair a command with the decision the rules must reach
+//! and, optionally, the replacement they must suggest. `--test-config` runs
+//! them so rule regressions fail CI instead of surprising an agent.
+
+use crate::policy::Policy;
+use crate::types::{Config, ConfigTest, Expected, Result};
+
+/// The result of one `[[tests]]` entry.
+#[derive(Debug)]
+pub struct TestOutcome {
+    pub command: String,
+    /// The decision the rules reached
+    pub actual: Expected,
+    /// The replacement the rules suggested, if any
+    pub suggested: Option<String>,
+    /// Fields whose expected and actual values differ
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl TestOutcome {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// An expected value that differs from the actual one.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// "expect" or "replacement"
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Runs every `[[tests]]` entry against the configured rules.
+///
+/// Commands are evaluated like `--dry-run`, through every gate the hook
+/// applies but with no hook context, so rules with `when` conditions or
+/// `requires` do not apply. A test without
+/// `expect` expects `block` if it names a `replacement`, otherwise `allow`.
+///
+/// # Arguments
+/// * `config` - Configuration holding the rules and the tests
+///
+/// # Returns
+/// * `Ok(Vec<TestOutcome>)` - One outcome per test, in file order
+/// * `Err` - If the rules fail to compile
+pub fn run_config_tests(config: &Config) -> Result<Vec<TestOutcome>> {
+    let policy = Policy::outside_session(config)?;
+    config.tests.iter().map(|test| check(&policy, test)).collect()
+}
+
+fn check(policy: &Policy, test: &ConfigTest) -> Result<TestOutcome> {
+    let rule_match = policy.evaluate(&test.command)?;
+    let actual = Expected::from(rule_match.as_ref().map(|m| m.severity));
+    let suggested = rule_match.and_then(|m| m.suggested_command);
+
+    let expected = test.expect.unwrap_or(if test.replacement.is_some() { Expected::Block } else { Expected::Allow });
+    let mut mismatches = Vec::new();
+    if expected != actual {
+        mismatches.push(Mismatch {
+            field: "expect",
+            expected: expected.as_str().to_string(),
+            actual: actual.as_str().to_string(),
+        });
+    }
+    if let Some(replacement) = test.replacement.as_ref().filter(|r| suggested.as_ref() != Some(*r)) {
+        mismatches.push(Mismatch {
+            field: "replacement",
+            expected: replacement.clone(),
+            actual: suggested.clone().unwrap_or_else(|| "(none)".to_string()),
+        });
+    }
+
+    Ok(TestOutcome { command: test.command.clone(), actual, suggested, mismatches })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_tests_report_mismatches() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[[rules]]
+pattern = "git push"
+severity = "warn"
+
+[[tests]]
+command = "npm install"
+expect = "block"
+replacement = "bun install"
+
+[[tests]]
+command = "git push"
+expect = "warn"
+
+[[tests]]
+command = "ls -la"
+
+[[tests]]
+command = "yarn add left-pad"
+replacement = "bun add left-pad"
+"#,
+        )
+        .unwrap();
+
+        let outcomes = run_config_tests(&config).unwrap();
+        assert!(outcomes[..3].iter().all(TestOutcome::passed));
+
+        let failed = &outcomes[3];
+        assert_eq!(failed.actual, Expected::Allow);
+        assert_eq!(
+            failed.mismatches,
+            vec![
+                Mismatch { field: "expect", expected: "block".to_string(), actual: "allow".to_string() },
+                Mismatch {
+                    field: "replacement",
+                    expected: "bun add left-pad".to_string(),
+                    actual: "(none)".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_tests_see_the_gates() {
+        let config: Config = toml::from_str(
+            r#"
+[network]
+allowed_hosts = ["github.com"]
+
+[[tests]]
+command = "curl https://example.com/install.sh"
+expect = "block"
+
+[[tests]]
+command = "curl https://github.com"
+"#,
+        )
+        .unwrap();
+        assert!(run_config_tests(&config).unwrap().iter().all(TestOutcome::passed));
+    }
+}
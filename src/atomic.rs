@@ -0,0 +1,207 @@
+//! Crash-safe file replacement
+//!
+//! Writing a file in place truncates it first, so a crash, a full disk or a
+//! killed process mid-write leaves the user's config or settings half
+//! written. Every file the advisor rewrites goes through `write_atomic`
+//! instead: the content is written and synced to a temporary file in the
+//! same directory, which is then renamed over the target. Readers, including
+//! other sessions, see either the old file or the new one, never a mix.
+//! A symlinked file, as dotfile managers create, is replaced at its target,
+//! so the link stays in place.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes the temporary files of concurrent writes within one process.
+static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces `path` with `contents`, atomically.
+///
+/// # Arguments
+/// * `path` - The file to replace or create; its directory must exist
+/// * `contents` - The new content
+///
+/// # Returns
+/// * `Ok(())` - If the new content is in place and synced to disk
+/// * `Err(io::Error)` - If it isn't; `path` is then unchanged
+pub(crate) fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_with(path, |file| file.write_all(contents.as_ref()))
+}
+
+/// Replaces `path` with the content `write` produces, atomically. If `write`
+/// fails, the temporary file is removed and `path` is left as it was.
+pub(crate) fn write_atomic_with(path: &Path, write: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+    // Renaming over a symlink would replace the link with a regular file
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let temp = temp_path(&target);
+    let mut file = OpenOptions::new().write(true).create_new(true).open(&temp)?;
+    let result = fill(&mut file, &target, write).and_then(|()| fs::rename(&temp, &target));
+    drop(file);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    sync_parent(&target);
+    Ok(())
+}
+
+/// Copies `from` to `to` atomically, so `to` never exists half copied.
+pub(crate) fn copy_atomic(from: &Path, to: &Path) -> io::Result<()> {
+    let contents = fs::read(from)?;
+    write_atomic(to, contents)
+}
+
+fn fill(file: &mut File, path: &Path, write: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+    // A replaced file keeps its permissions, such as an executable git hook
+    if let Ok(metadata) = fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    write(file)?;
+    file.sync_all()
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let n = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{file_name}.{}.{n}.tmp", std::process::id()))
+}
+
+/// Syncs the directory entry of the rename. Best effort: not every platform
+/// or filesystem can open and sync a directory.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use tempfile::TempDir;
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_write_replaces_and_creates() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".claude.toml");
+        write_atomic(&path, "[commands]\nnpm = \"bun\"\n").unwrap();
+        write_atomic(&path, "[commands]\nyarn = \"bun\"\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[commands]\nyarn = \"bun\"\n");
+        assert_eq!(entries(dir.path()), [".claude.toml"]);
+    }
+
+    #[test]
+    fn test_interrupted_write_keeps_original() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, "{\"hooks\": {}}\n").unwrap();
+
+        // The writer dies after half the content
+        let result = write_atomic_with(&path, |file| {
+            file.write_all(b"{\"hoo")?;
+            Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"))
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::WriteZero);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"hooks\": {}}\n");
+        assert_eq!(entries(dir.path()), ["settings.json"]);
+
+        // A failed rename is cleaned up the same way
+        let occupied = dir.path().join("occupied");
+        fs::create_dir(&occupied).unwrap();
+        fs::write(occupied.join("file"), "").unwrap();
+        assert!(write_atomic(&occupied, "x").is_err());
+        assert_eq!(entries(dir.path()), ["occupied", "settings.json"]);
+    }
+
+    #[test]
+    fn test_leftover_temp_file_from_crash_is_harmless() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".claude.toml");
+        fs::write(&path, "[commands]\n").unwrap();
+        // What a process killed before its rename leaves behind
+        let stale = dir.path().join(".claude.toml.1.0.tmp");
+        fs::write(&stale, "[comm").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[commands]\n");
+        write_atomic(&path, "[commands]\nnpm = \"bun\"\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[commands]\nnpm = \"bun\"\n");
+    }
+
+    #[test]
+    fn test_concurrent_writers_never_mix() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        let contents: Vec<String> = (0..8).map(|i| i.to_string().repeat(64 * 1024)).collect();
+
+        thread::scope(|scope| {
+            for content in &contents {
+                let path = &path;
+                scope.spawn(move || write_atomic(path, content).unwrap());
+            }
+        });
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&written));
+        assert_eq!(entries(dir.path()), ["state.json"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permissions_are_kept() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let hook = dir.path().join("pre-commit");
+        fs::write(&hook, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+        write_atomic(&hook, "#!/bin/sh\nexit 0\n").unwrap();
+        assert_eq!(fs::metadata(&hook).unwrap().permissions().mode() & 0o777, 0o755);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_file_is_written_through() {
+        let dir = TempDir::new().unwrap();
+        let dotfiles = dir.path().join("dotfiles");
+        let home = dir.path().join("home");
+        fs::create_dir_all(&dotfiles).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        let target = dotfiles.join("settings.json");
+        let link = home.join("settings.json");
+        fs::write(&target, "{}\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, "{\"hooks\": {}}\n").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "{\"hooks\": {}}\n");
+        assert_eq!(entries(&dotfiles), ["settings.json"]);
+        assert_eq!(entries(&home), ["settings.json"]);
+    }
+
+    #[test]
+    fn test_copy() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join(".claude-hook-advisor.toml");
+        fs::write(&old, "[commands]\nnpm = \"bun\"\n").unwrap();
+        copy_atomic(&old, &dir.path().join(".claude.toml")).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join(".claude.toml")).unwrap(), "[commands]\nnpm = \"bun\"\n");
+        assert!(copy_atomic(&dir.path().join("missing"), &dir.path().join("to")).is_err());
+        assert!(!dir.path().join("to").exists());
+    }
+}
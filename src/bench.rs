@@ -0,0 +1,197 @@
+//! Synthetic load for the rule matcher
+//!
+//! `--bench-rules` times a handful of chosen commands. `--bench` generates a
+//! large mix from the loaded rule set instead: commands each rule or mapping
+//! matches, near misses that share a prefix, unrelated commands, and `&&`
+//! chains and pipelines of them. The mix is the same on every run, so the
+//! latency percentiles and throughput it reports can be compared before and
+//! after a change to the matcher.
+
+use crate::rules::CompiledRules;
+use crate::types::Config;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Seed of the generator, fixed so every run times the same commands.
+const SEED: u64 = 0x5eed_c0de_2024_0001;
+
+/// Programs no rule is expected to mention.
+const FILLER_PROGRAMS: &[&str] = &["ls", "cat", "grep", "echo", "make", "git", "cargo", "python3", "node", "curl", "find", "sed"];
+
+/// Arguments appended to generated commands.
+const ARGUMENTS: &[&str] = &[
+    "-la", "src/", "--release", "install", "build", "test", "-v", "README.md", "--force", "./scripts/run.sh", "-rn TODO .",
+    "status", "origin main", "--json", "2>&1",
+];
+
+/// How the matcher performed on a synthetic workload.
+#[derive(Debug, Serialize)]
+pub struct BenchSummary {
+    pub commands: usize,
+    /// Commands a rule or mapping matched
+    pub matched: usize,
+    #[serde(serialize_with = "micros")]
+    pub p50: Duration,
+    #[serde(serialize_with = "micros")]
+    pub p95: Duration,
+    #[serde(serialize_with = "micros")]
+    pub p99: Duration,
+    #[serde(serialize_with = "micros")]
+    pub max: Duration,
+    #[serde(serialize_with = "micros")]
+    pub total: Duration,
+}
+
+impl BenchSummary {
+    /// Commands evaluated per second.
+    pub fn throughput(&self) -> f64 {
+        self.commands as f64 / self.total.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+fn micros<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_nanos() as f64 / 1000.0)
+}
+
+/// A xorshift64* generator: tiny, and the same sequence on every platform.
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a, T: AsRef<str>>(&mut self, items: &'a [T]) -> &'a str {
+        items[self.below(items.len())].as_ref()
+    }
+}
+
+/// Generates `count` commands exercising the rules and mappings in `config`.
+///
+/// Four in ten commands start with a configured pattern, two are near
+/// misses that extend one, three are unrelated, and one chains two or three
+/// of the others with `&&` or `|`.
+///
+/// # Arguments
+/// * `config` - The configuration whose patterns are used
+/// * `count` - Number of commands to generate
+///
+/// # Returns
+/// The commands, identical on every call with the same configuration
+pub fn synthetic_commands(config: &Config, count: usize) -> Vec<String> {
+    let mut patterns: Vec<String> = config.active_rules().iter().map(|rule| rule.pattern.clone()).collect();
+    patterns.extend(config.active_commands().into_iter().map(|(pattern, _)| pattern.clone()));
+    patterns.retain(|pattern| !pattern.trim().is_empty());
+
+    let mut rng = Xorshift(SEED);
+    (0..count).map(|_| generate(&mut rng, &patterns, true)).collect()
+}
+
+fn generate(rng: &mut Xorshift, patterns: &[String], may_chain: bool) -> String {
+    let argument = rng.pick(ARGUMENTS).to_string();
+    let kind = rng.below(10);
+    match kind {
+        0..=3 if !patterns.is_empty() => format!("{} {argument}", rng.pick(patterns)),
+        4..=5 if !patterns.is_empty() => format!("{}x {argument}", rng.pick(patterns)),
+        9 if may_chain => {
+            let parts: Vec<String> = (0..2 + rng.below(2)).map(|_| generate(rng, patterns, false)).collect();
+            let separator = if rng.below(2) == 0 { " && " } else { " | " };
+            parts.join(separator)
+        }
+        _ => format!("{} {argument}", rng.pick(FILLER_PROGRAMS)),
+    }
+}
+
+/// Evaluates every command once and summarizes the latencies.
+///
+/// A first pass over up to 1000 commands warms caches and is not counted.
+///
+/// # Arguments
+/// * `rules` - The compiled rule set
+/// * `commands` - The workload, such as from `synthetic_commands`
+pub fn run_bench(rules: &CompiledRules, commands: &[String]) -> BenchSummary {
+    for command in commands.iter().take(1000) {
+        std::hint::black_box(rules.evaluate(command));
+    }
+
+    let mut latencies = Vec::with_capacity(commands.len());
+    let mut matched = 0;
+    for command in commands {
+        let start = Instant::now();
+        let result = std::hint::black_box(rules.evaluate(command));
+        latencies.push(start.elapsed());
+        matched += usize::from(result.is_some());
+    }
+
+    latencies.sort_unstable();
+    BenchSummary {
+        commands: commands.len(),
+        matched,
+        p50: percentile(&latencies, 50.0),
+        p95: percentile(&latencies, 95.0),
+        p99: percentile(&latencies, 99.0),
+        max: latencies.last().copied().unwrap_or_default(),
+        total: latencies.iter().sum(),
+    }
+}
+
+/// The nearest-rank percentile of sorted latencies; zero when there are none.
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::RuleContext;
+
+    #[test]
+    fn test_synthetic_commands_are_reproducible() {
+        let config: Config = toml::from_str("[commands]\nnpm = \"bun\"\n\n[[rules]]\npattern = \"rm -rf\"\n").unwrap();
+        let commands = synthetic_commands(&config, 500);
+        assert_eq!(commands.len(), 500);
+        assert_eq!(commands, synthetic_commands(&config, 500));
+
+        assert!(commands.iter().any(|command| command.starts_with("npm ")));
+        assert!(commands.iter().any(|command| command.starts_with("rm -rfx ")));
+        assert!(commands.iter().any(|command| command.contains(" && ") || command.contains(" | ")));
+
+        // Without patterns, only unrelated commands are generated
+        let unrelated = synthetic_commands(&Config::default(), 100);
+        assert!(unrelated.iter().all(|command| FILLER_PROGRAMS.iter().any(|program| command.starts_with(program))));
+    }
+
+    #[test]
+    fn test_run_bench() {
+        let config: Config = toml::from_str("[commands]\nnpm = \"bun\"\n").unwrap();
+        let rules = CompiledRules::new(&config, &RuleContext::default()).unwrap();
+        let commands = synthetic_commands(&config, 200);
+        let summary = run_bench(&rules, &commands);
+
+        assert_eq!(summary.commands, 200);
+        assert!(summary.matched > 0 && summary.matched < 200);
+        assert!(summary.p50 <= summary.p95 && summary.p95 <= summary.p99 && summary.p99 <= summary.max);
+        assert!(summary.throughput() > 0.0);
+        assert_eq!(run_bench(&rules, &[]).p95, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_micros(50));
+        assert_eq!(percentile(&latencies, 95.0), Duration::from_micros(95));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_micros(100));
+        assert_eq!(percentile(&latencies[..1], 95.0), Duration::from_micros(1));
+    }
+}
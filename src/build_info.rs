@@ -0,0 +1,100 @@
+//! What this binary was built from
+//!
+//! Behavior can differ between builds: a minimal static build for containers
+//! leaves out the clipboard, HTTP and encryption support, and slim builds
+//! leave out subsystems such as the MCP server. When a hook behaves
+//! differently on two machines, `--build-info` and `--version` show which
+//! commit, target and features each binary has.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// Every optional feature, in the order `Cargo.toml` lists them.
+const FEATURES: [(&str, bool); 7] = [
+    ("clipboard", cfg!(feature = "clipboard")),
+    ("network", cfg!(feature = "network")),
+    ("encryption", cfg!(feature = "encryption")),
+    ("analytics", cfg!(feature = "analytics")),
+    ("bench", cfg!(feature = "bench")),
+    ("mcp", cfg!(feature = "mcp")),
+    ("server", cfg!(feature = "server")),
+];
+
+/// `--version` output after the program name.
+pub static LONG_VERSION: Lazy<String> = Lazy::new(|| {
+    let info = BuildInfo::current();
+    format!("{} ({}, {}; features: {})", info.version, info.commit, info.target, feature_list(&info.features))
+});
+
+/// How this binary was built.
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Git commit, with `-dirty` for uncommitted changes; `unknown` outside a checkout
+    pub commit: &'static str,
+    /// Target triple, such as `x86_64-unknown-linux-musl`
+    pub target: &'static str,
+    /// Cargo profile, `debug` or `release`
+    pub profile: &'static str,
+    /// Optional features compiled in
+    pub features: Vec<&'static str>,
+    /// Optional features left out
+    pub missing_features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// The build information of the running binary.
+    pub fn current() -> Self {
+        let enabled = |on: bool| FEATURES.iter().filter(move |(_, enabled)| *enabled == on).map(|(name, _)| *name);
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: env!("BUILD_COMMIT"),
+            target: env!("BUILD_TARGET"),
+            profile: env!("BUILD_PROFILE"),
+            features: enabled(true).collect(),
+            missing_features: enabled(false).collect(),
+        }
+    }
+}
+
+/// Formats `info` for the terminal.
+pub fn describe_build_info(info: &BuildInfo) -> String {
+    [
+        format!("📦 claude-hook-advisor {}", info.version),
+        format!("   Commit:   {}", info.commit),
+        format!("   Target:   {}", info.target),
+        format!("   Profile:  {}", info.profile),
+        format!("   Features: {}", feature_list(&info.features)),
+        format!("   Missing:  {}", feature_list(&info.missing_features)),
+    ]
+    .join("\n")
+}
+
+fn feature_list(features: &[&str]) -> String {
+    if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = BuildInfo::current();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.features.len() + info.missing_features.len(), FEATURES.len());
+        assert_eq!(info.features.contains(&"network"), cfg!(feature = "network"));
+
+        let text = describe_build_info(&info);
+        assert!(text.contains(&format!("   Target:   {}\n", info.target)));
+        assert!(LONG_VERSION.starts_with(&format!("{} (", info.version)));
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json["features"].is_array());
+        assert_eq!(json["commit"], info.commit);
+    }
+}
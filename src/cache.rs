@@ -0,0 +1,161 @@
+//! On-disk cache of parsed configuration
+//!
+//! Every hook invocation is a fresh process, so without a cache the config is
+//! re-parsed from TOML each time. The parsed config is stored with bincode in
+//! `.claude/advisor/cache/` next to the config file, keyed by a hash of the
+//! file content and the binary version, so any edit or upgrade misses the cache.
+
+use crate::atomic::write_atomic;
+use crate::config::{parse_config, read_config_file};
+use crate::state::create_ignored_dir;
+use crate::types::{AdvisorError, Config, Result};
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Cache directory relative to the directory holding the config file.
+pub const CACHE_DIR: &str = ".claude/advisor/cache";
+
+/// Loads a configuration file, using the cached parse when the content is unchanged.
+///
+/// Cache failures never fail the load: an unreadable or corrupt cache entry is
+/// treated as a miss, and errors writing the cache are ignored.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+///
+/// # Returns
+/// * `Ok(Config)` - Loaded configuration
+/// * `Err` - If the file cannot be read or parsed
+pub fn load_config_cached(config_path: &Path) -> Result<Config> {
+    let content = read_config_file(config_path)?;
+
+    let dir = cache_dir(config_path);
+    let entry = dir.join(format!("config-{:016x}.bin", cache_key(&content)));
+
+    if let Some(config) = read_entry(&entry) {
+        return Ok(config);
+    }
+
+    let config = parse_config(&content, config_path)?;
+    // Best effort: a read-only checkout just means no caching
+    let _ = write_entry(&dir, &entry, &config);
+    Ok(config)
+}
+
+/// Returns the cache directory used for `config_path`.
+pub fn cache_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(CACHE_DIR)
+}
+
+fn cache_key(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn read_entry(entry: &Path) -> Option<Config> {
+    let bytes = fs::read(entry).ok()?;
+    bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+        .ok()
+        .map(|(config, _)| config)
+}
+
+/// Writes a cache entry and removes entries for older config versions.
+fn write_entry(dir: &Path, entry: &Path, config: &Config) -> Result<()> {
+    create_ignored_dir(dir)?;
+
+    for stale in fs::read_dir(dir)?.filter_map(|e| e.ok().map(|e| e.path())) {
+        let is_entry = stale
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("config-") && name.ends_with(".bin"));
+        if is_entry && stale != entry {
+            let _ = fs::remove_file(&stale);
+        }
+    }
+
+    let bytes = bincode::serde::encode_to_vec(config, bincode::config::standard())
+        .map_err(|e| AdvisorError::other("Failed to encode config cache", e))?;
+    write_atomic(entry, bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Severity;
+    use tempfile::TempDir;
+
+    const CONFIG: &str = r#"
+[commands]
+npm = "bun"
+
+[semantic_directories]
+docs = "~/Documents"
+
+[[rules]]
+pattern = "git push"
+severity = "warn"
+when = { source = "agent" }
+"#;
+
+    fn cache_entries(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "bin"))
+            .collect()
+    }
+
+    #[test]
+    fn test_cached_config_round_trips() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".claude.toml");
+        fs::write(&config_path, CONFIG).unwrap();
+
+        let first = load_config_cached(&config_path).unwrap();
+        let entries = cache_entries(&cache_dir(&config_path));
+        assert_eq!(entries.len(), 1);
+
+        let cached = read_entry(&entries[0]).expect("entry should decode");
+        for config in [&first, &cached] {
+            assert_eq!(config.commands.get("npm"), Some(&"bun".to_string()));
+            assert_eq!(config.semantic_directories.get("docs"), Some(&"~/Documents".to_string()));
+            assert_eq!(config.rules[0].severity, Some(Severity::Warn));
+            assert!(config.rules[0].when.as_ref().unwrap().source.is_some());
+        }
+    }
+
+    #[test]
+    fn test_cache_invalidated_on_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".claude.toml");
+        fs::write(&config_path, CONFIG).unwrap();
+        load_config_cached(&config_path).unwrap();
+
+        fs::write(&config_path, "[commands]\nyarn = \"bun\"\n").unwrap();
+        let config = load_config_cached(&config_path).unwrap();
+        assert!(config.commands.contains_key("yarn"));
+        assert!(!config.commands.contains_key("npm"));
+
+        // The stale entry is replaced rather than accumulated
+        assert_eq!(cache_entries(&cache_dir(&config_path)).len(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_cache_entry_is_a_miss() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".claude.toml");
+        fs::write(&config_path, CONFIG).unwrap();
+        load_config_cached(&config_path).unwrap();
+
+        let entry = cache_entries(&cache_dir(&config_path)).remove(0);
+        fs::write(&entry, b"not bincode").unwrap();
+        assert_eq!(load_config_cached(&config_path).unwrap().commands.len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+//! Completion checklist enforced when Claude tries to end its turn
+//!
+//! The `[checklist]` table lists commands that must have succeeded in the
+//! session, such as the test suite or the formatter, and markers like `TODO`
+//! that must not appear in lines added to the working tree. On the Stop event
+//! every unmet item is reported, and Claude is asked to finish them first.
+
+use crate::rules::command_matches;
+use crate::state::CommandRun;
+use crate::types::{Checklist, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Lists the checklist items that are not met.
+///
+/// # Arguments
+/// * `checklist` - The `[checklist]` configuration
+/// * `history` - Commands that finished in the session, oldest first
+/// * `added_lines` - Lines added in the working tree compared to `HEAD`
+///
+/// # Returns
+/// * One human-readable reason per unmet item, in configuration order
+pub fn unmet_items(checklist: &Checklist, history: &[CommandRun], added_lines: &[String]) -> Result<Vec<String>> {
+    let mut unmet = Vec::new();
+
+    for required in &checklist.commands {
+        // Only the latest run counts: tests that failed last time still fail
+        let mut latest = None;
+        for run in history.iter().rev() {
+            if command_matches(required, &run.command)? {
+                latest = Some(run);
+                break;
+            }
+        }
+        match latest {
+            None => unmet.push(format!("'{required}' has not been run in this session")),
+            Some(run) if !run.succeeded() => unmet.push(format!(
+                "The last '{}' failed{}; fix the failures and run it again",
+                run.command,
+                run.exit_code.map(|code| format!(" with exit code {code}")).unwrap_or_default()
+            )),
+            Some(_) => {}
+        }
+    }
+
+    for marker in &checklist.forbidden_markers {
+        let count = added_lines.iter().filter(|line| line.contains(marker.as_str())).count();
+        if count > 0 {
+            unmet.push(format!("{count} added line(s) contain '{marker}'; resolve them or remove the markers"));
+        }
+    }
+
+    Ok(unmet)
+}
+
+/// Lines added in the working tree of `project_dir` compared to `HEAD`.
+///
+/// Untracked files are not included. Returns an empty list outside a git
+/// repository or before the first commit.
+pub fn added_lines(project_dir: &Path) -> Vec<String> {
+    let output = Command::new("git")
+        .args(["diff", "HEAD", "--unified=0", "--no-color"])
+        .current_dir(project_dir)
+        .output();
+    let Some(output) = output.ok().filter(|output| output.status.success()) else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.starts_with('+') && !line.starts_with("+++"))
+        .map(|line| line[1..].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SessionState;
+
+    fn checklist() -> Checklist {
+        Checklist {
+            commands: vec!["cargo test".to_string(), "cargo fmt".to_string()],
+            forbidden_markers: vec!["TODO".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_unmet_items() {
+        let mut state = SessionState::default();
+        state.record_run("cargo test --workspace", Some(101), None);
+        let added = vec!["// TODO: handle errors".to_string(), "let x = 1;".to_string()];
+
+        let unmet = unmet_items(&checklist(), &state.history, &added).unwrap();
+        assert_eq!(unmet.len(), 3);
+        assert!(unmet[0].contains("failed with exit code 101"));
+        assert!(unmet[1].contains("'cargo fmt' has not been run"));
+        assert!(unmet[2].contains("1 added line(s) contain 'TODO'"));
+
+        state.record_run("cargo test --workspace", Some(0), None);
+        state.record_run("cargo fmt", Some(0), None);
+        assert!(unmet_items(&checklist(), &state.history, &added[1..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_checklist_is_met() {
+        assert!(unmet_items(&Checklist::default(), &[], &["TODO".to_string()]).unwrap().is_empty());
+    }
+}
@@ -1,12 +1,51 @@
 //! CLI interface and main entry point
 
-use crate::hooks::run_as_hook;
-use crate::config::{find_config_file, load_config_from_path, migrate_config, needs_migration};
-use crate::types::{ConfigError, DEFAULT_CONFIG_FILE, Config};
-use anyhow::{Context, Result};
+use crate::assertions::run_config_tests;
+use crate::atomic::write_atomic;
+use crate::audit::{AuditEvent, AuditLog};
+use crate::baseline::{baseline_paths, load_baseline_file};
+use crate::hooks::{directory_context, run_as_hook, run_hook_stream};
+use crate::config_edit::ConfigEditor;
+use crate::conflicts::{find_conflicts, Conflict};
+use crate::deprecation::{find_deprecations, Deprecation, MIGRATE_COMMAND};
+use crate::config::{
+    find_config_file, load_config_auto, load_config_from_path, load_user_directories, migrate_config, needs_migration,
+    upgrade_config_file, user_directories_path, SchemaUpgrade, CONFIG_VERSION,
+};
+use crate::directory::{alias_collisions, list_aliases, locate_directory, suggest_aliases, AliasEntry};
+use crate::jsonc;
+use crate::installer::{HookInstallOptions, HOOK_EVENTS};
+use crate::lint::{lint_file, lint_source, read_staged_file, staged_lint_targets, Diagnostic};
+use crate::policy::Policy;
+use crate::rules::{CompiledRules, RuleContext};
+use crate::shell::{normalize, split_segments, substitutions};
+use crate::shell_aliases::{expand_aliases, parse_alias_definitions};
+use crate::simulate::{run_scenario, Decision, Scenario};
+use crate::state::{list_sessions, remove_sessions, SnoozeList, STALE_SESSION_DAYS, STATE_DIR};
+use crate::notify::notify;
+use crate::permissions::{add_permissions, export_permissions, import_permissions, BashPermission, PermissionList, Skipped};
+use crate::telemetry::flush;
+use crate::usage;
+use crate::ui::{self, say, OutputMode};
+use crate::project::{
+    all_templates, detect_environments, detect_project_types, environment_rules, find_projects, merge_templates,
+    template_rules,
+    ProjectTemplate, TemplateConflict,
+};
+use crate::types::{
+    AdvisorError, Config, ConfigError, DefaultDecision, DirectoryStatus, IoContext, Notifications, Result, Rule, SchemaVersion, Severity,
+    BACKUP_SUFFIX, DEFAULT_CONFIG_FILE, EXIT_BLOCK, SCHEMA_FIELDS,
+};
 use clap::{Arg, Command};
+use dialoguer::{Confirm, MultiSelect, Select};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "bench")]
+use std::time::{Duration, Instant};
 
 /// Main entry point for the Claude Hook Advisor application.
 /// 
@@ -17,6 +56,7 @@ use std::path::Path;
 pub fn run_cli() -> Result<()> {
     let matches = Command::new("claude-hook-advisor")
         .version(env!("CARGO_PKG_VERSION"))
+        .long_version(crate::build_info::LONG_VERSION.as_str())
         .about("Advises Claude Code on better command alternatives based on project preferences")
         .arg(
             Arg::new("config")
@@ -24,7 +64,32 @@ pub fn run_cli() -> Result<()> {
                 .long("config")
                 .value_name("FILE")
                 .help("Path to configuration file")
-                .default_value(DEFAULT_CONFIG_FILE),
+                .default_value(DEFAULT_CONFIG_FILE)
+                .global(true),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .visible_alias("plain")
+                .help("Print without colors (also when NO_COLOR is set or output is not a terminal)")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("With --install, --check-config, --migrate-config, --stats or --doctor, print nothing; the exit status is the result")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("format"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output of --install, --check-config, --migrate-config, --stats, --doctor and --where")
+                .value_parser(["text", "json"])
+                .default_value("text"),
         )
         .arg(
             Arg::new("hook")
@@ -32,6 +97,47 @@ pub fn run_cli() -> Result<()> {
                 .help("Run as a Claude Code hook (reads JSON from stdin)")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .help("With --hook, read one hook input per line and answer each with one line of JSON")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("serve")
+                .long("serve")
+                .value_name("ADDR")
+                .help("Serve policy decisions over HTTP on ADDR (such as 127.0.0.1:8787) at POST /evaluate; the service has no authentication")
+                .hide(!cfg!(feature = "server")),
+        )
+        .arg(
+            Arg::new("allow-remote")
+                .long("allow-remote")
+                .help("With --serve, accept an ADDR other than a loopback address, reachable from other machines")
+                .requires("serve")
+                .hide(!cfg!(feature = "server"))
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("mcp")
+                .long("mcp")
+                .help("Run as an MCP server on stdin/stdout, offering resolve_directory, check_command and list_conventions")
+                .hide(!cfg!(feature = "mcp"))
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hook-mode")
+                .long("hook-mode")
+                .value_name("MODE")
+                .help("Run as a hook of the given kind: claude (same as --hook) or git (pre-commit)")
+                .value_parser(["claude", "git"]),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Always re-read the configuration file instead of using the parsed-config cache")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("replace")
                 .long("replace")
@@ -44,12 +150,213 @@ pub fn run_cli() -> Result<()> {
                 .help("Install Claude Hook Advisor: configure hooks and create/update config file")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("recursive")
+                .long("recursive")
+                .value_name("ROOT")
+                .requires("install")
+                .help("With --install, install into every git repository or project found under ROOT"),
+        )
+        .arg(
+            Arg::new("quickstart")
+                .long("quickstart")
+                .help("Install with the detected defaults, then check the hooks with a self-test")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Skip interactive prompts during --install, --infer-rules and --import/--export-permissions and use detected defaults")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("install-git-hook")
+                .long("install-git-hook")
+                .help("Install a git pre-commit hook that lints staged shell scripts and CI files")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("install-commands")
+                .long("install-commands")
+                .help("Write /conventions and /advisor-stats slash commands to .claude/commands/")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list-conventions")
+                .long("list-conventions")
+                .help("Print the project's command conventions as Markdown")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("uninstall")
                 .long("uninstall")
                 .help("Remove Claude Hook Advisor hooks from Claude Code settings")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("doctor")
+                .long("doctor")
+                .help("Check that the registered hooks can run, and offer to repair them")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry-run-file")
+                .long("dry-run-file")
+                .value_name("FILE")
+                .help("Check every command in FILE (one per line) against the configured mappings"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .value_name("COMMAND")
+                .help("Show what the hook would decide for COMMAND"),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .value_name("COMMAND")
+                .help("Show how COMMAND is parsed and every rule that matches it"),
+        )
+        .arg(
+            Arg::new("copy")
+                .long("copy")
+                .help("With --dry-run or --explain, copy the suggested command to the clipboard")
+                .hide(!cfg!(feature = "clipboard"))
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("toggle-group")
+                .long("toggle-group")
+                .value_names(["GROUP", "STATE"])
+                .help("Turn a [groups.<GROUP>] rule group on or off in the configuration file")
+                .num_args(2),
+        )
+        .arg(
+            Arg::new("import-aliases")
+                .long("import-aliases")
+                .value_name("FILE")
+                .help("Add the shell aliases defined in FILE (- for stdin, e.g. from `alias`) to [aliases]"),
+        )
+        .arg(
+            Arg::new("import-permissions")
+                .long("import-permissions")
+                .value_name("FILE")
+                .help("Offer the Bash permissions of Claude Code settings (default .claude/settings.json) as rules and [allowed] commands")
+                .num_args(0..=1)
+                .default_missing_value(".claude/settings.json"),
+        )
+        .arg(
+            Arg::new("export-permissions")
+                .long("export-permissions")
+                .value_name("FILE")
+                .help("Offer the unconditional rules and [allowed] commands as permissions in Claude Code settings (default .claude/settings.json)")
+                .num_args(0..=1)
+                .default_missing_value(".claude/settings.json"),
+        )
+        .arg(
+            Arg::new("test-config")
+                .long("test-config")
+                .help("Run the [[tests]] in the configuration and report any that fail")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("simulate-session")
+                .long("simulate-session")
+                .value_name("FILE")
+                .help("Replay a YAML scenario of prompts and commands through the hooks and check its expectations"),
+        )
+        .arg(
+            Arg::new("bench-rules")
+                .long("bench-rules")
+                .value_name("FILE")
+                .help("Measure rule compile time and per-command evaluation latency (commands from FILE, or a built-in sample)")
+                .hide(!cfg!(feature = "bench"))
+                .num_args(0..=1)
+                .default_missing_value(""),
+        )
+        .arg(
+            Arg::new("bench")
+                .long("bench")
+                .value_name("N")
+                .help("Time the rules on N synthetic commands (default 10000) and report p50/p95 latency and throughput")
+                .hide(!cfg!(feature = "bench"))
+                .value_parser(clap::value_parser!(usize))
+                .num_args(0..=1)
+                .default_missing_value("10000"),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .help("Show how often suggested replacements were accepted and succeeded")
+                .hide(!cfg!(feature = "analytics"))
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("infer-rules")
+                .long("infer-rules")
+                .help("Propose mappings to the tools this project uses, from its lockfiles, tool configs and session history")
+                .hide(!cfg!(feature = "analytics"))
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("gc-state")
+                .long("gc-state")
+                .help("Remove stale sessions and roll old audit records into daily counts")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sessions")
+                .long("sessions")
+                .value_name("ACTION")
+                .help("List the sessions recorded in this project, or clean up stale ones")
+                .value_parser(["list", "clean"])
+                .num_args(0..=1)
+                .default_missing_value("list"),
+        )
+        .arg(
+            Arg::new("print-schema-support")
+                .long("print-schema-support")
+                .help("List the hook events, input fields and schema versions this build handles")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("build-info")
+                .long("build-info")
+                .help("Print the commit, target triple, profile and features this binary was built with")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("where")
+                .long("where")
+                .help("Print the config, settings, state, cache and audit paths in use")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("flush-telemetry")
+                .long("flush-telemetry")
+                .help("Send queued telemetry events now")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("test-notification")
+                .long("test-notification")
+                .help("Send a sample blocked-command message to the [notifications] webhook")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("telemetry")
+                .long("telemetry")
+                .value_name("ACTION")
+                .help("Opt in to or out of anonymous usage counters, or show what is queued")
+                .value_parser(["on", "off", "status"]),
+        )
+        .arg(
+            Arg::new("lint-config")
+                .long("lint-config")
+                .help("Report mapping cycles and replacements that other rules block")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("check-config")
                 .long("check-config")
@@ -59,7 +366,7 @@ pub fn run_cli() -> Result<()> {
         .arg(
             Arg::new("migrate-config")
                 .long("migrate-config")
-                .help("Migrate configuration from old file name to new format")
+                .help("Migrate configuration to the new file name and current schema")
                 .action(clap::ArgAction::SetTrue),
         )
         .arg(
@@ -68,18 +375,187 @@ pub fn run_cli() -> Result<()> {
                 .help("Create example configuration file")
                 .action(clap::ArgAction::SetTrue),
         )
+        .subcommand(
+            Command::new("lint-script")
+                .about("Check shell scripts and CI files against the configured rules")
+                .arg(
+                    Arg::new("files")
+                        .value_name("FILE")
+                        .help("Shell scripts or CI workflow files (.yml/.yaml) to check")
+                        .required(true)
+                        .num_args(1..),
+                ),
+        )
+        .subcommand(
+            Command::new("dirs")
+                .about("Inspect and test semantic directory aliases")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List the aliases of the project and user-wide scopes"))
+                .subcommand(
+                    Command::new("search")
+                        .about("Find aliases whose name or path contains TERM")
+                        .arg(Arg::new("term").value_name("TERM").required(true)),
+                )
+                .subcommand(
+                    Command::new("resolve")
+                        .about("Show what the prompt hook would resolve in PHRASE")
+                        .arg(Arg::new("phrase").value_name("PHRASE").required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Summarize agent command activity for a retrospective")
+                .hide(!cfg!(feature = "analytics"))
+                .arg(
+                    Arg::new("week")
+                        .long("week")
+                        .help("Cover the last 7 days")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("days"),
+                )
+                .arg(
+                    Arg::new("days")
+                        .long("days")
+                        .value_name("N")
+                        .help("Cover the last N days instead of everything recorded")
+                        .value_parser(clap::value_parser!(u32)),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Output format")
+                        .value_parser(["markdown", "html"])
+                        .default_value("markdown"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the report to FILE instead of standard output"),
+                ),
+        )
+        .subcommand(
+            Command::new("snooze")
+                .about("Suspend a rule or command mapping for a while")
+                .arg(Arg::new("pattern").value_name("PATTERN").help("Pattern of the rule or mapping to suspend"))
+                .arg(Arg::new("duration").value_name("DURATION").help("How long to suspend it, e.g. 30m, 1h, 2d or 1h30m"))
+                .arg(
+                    Arg::new("list")
+                        .long("list")
+                        .help("Show active snoozes")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
         .get_matches();
 
+    if matches.get_flag("no-color") {
+        ui::disable_colors();
+    }
+    if matches.get_flag("quiet") {
+        ui::set_mode(OutputMode::Quiet);
+    } else if matches.get_one::<String>("format").is_some_and(|format| format == "json") {
+        ui::set_mode(OutputMode::Json);
+    }
     let config_path = matches.get_one::<String>("config")
         .expect("config argument has default value");
     let replace_mode = matches.get_flag("replace");
+    let hook_mode = matches
+        .get_one::<String>("hook-mode")
+        .map(String::as_str)
+        .or(matches.get_flag("hook").then_some("claude"));
 
-    if matches.get_flag("hook") {
-        run_as_hook(config_path, replace_mode)
+    if let Some(("lint-script", sub_matches)) = matches.subcommand() {
+        let files: Vec<&String> = sub_matches
+            .get_many::<String>("files")
+            .expect("files argument is required")
+            .collect();
+        run_lint_script(config_path, &files)
+    } else if let Some(("dirs", sub_matches)) = matches.subcommand() {
+        run_dirs(config_path, sub_matches)
+    } else if let Some(("report", sub_matches)) = matches.subcommand() {
+        run_report(config_path, sub_matches)
+    } else if let Some(("snooze", sub_matches)) = matches.subcommand() {
+        match sub_matches.get_one::<String>("pattern").filter(|_| !sub_matches.get_flag("list")) {
+            Some(pattern) => run_snooze(config_path, pattern, sub_matches.get_one::<String>("duration")),
+            None => {
+                print_snoozes();
+                Ok(())
+            }
+        }
+    } else if let Some(addr) = matches.get_one::<String>("serve") {
+        run_serve(addr, config_path, replace_mode, matches.get_flag("allow-remote"))
+    } else if matches.get_flag("mcp") {
+        run_mcp(config_path)
+    } else if hook_mode == Some("claude") && matches.get_flag("stream") {
+        run_hook_stream(config_path, replace_mode, !matches.get_flag("no-cache"))
+    } else if hook_mode == Some("claude") {
+        run_as_hook(config_path, replace_mode, !matches.get_flag("no-cache"))
+    } else if hook_mode == Some("git") {
+        run_git_hook(config_path)
+    } else if matches.get_flag("quickstart") {
+        run_quickstart(config_path, replace_mode)
+    } else if let Some(root) = matches.get_one::<String>("recursive") {
+        run_bulk_installation(config_path, replace_mode, Path::new(root))
     } else if matches.get_flag("install") {
-        run_smart_installation(config_path)
+        emit_json(&run_smart_installation(config_path, replace_mode, matches.get_flag("yes"))?)
+    } else if matches.get_flag("install-git-hook") {
+        crate::installer::install_git_hook()
+    } else if matches.get_flag("install-commands") {
+        crate::installer::install_slash_commands()
+    } else if matches.get_flag("list-conventions") {
+        run_list_conventions(config_path)
     } else if matches.get_flag("uninstall") {
         crate::installer::uninstall_claude_hooks()
+    } else if matches.get_flag("doctor") {
+        run_doctor(config_path, matches.get_flag("yes"))
+    } else if let Some(file) = matches.get_one::<String>("dry-run-file") {
+        run_dry_run_file(config_path, file)
+    } else if let Some(command) = matches.get_one::<String>("dry-run") {
+        run_dry_run(config_path, command, matches.get_flag("copy"))
+    } else if let Some(command) = matches.get_one::<String>("explain") {
+        run_explain(config_path, command, matches.get_flag("copy"))
+    } else if let Some(mut values) = matches.get_many::<String>("toggle-group") {
+        let (group, state) = (values.next().expect("two values"), values.next().expect("two values"));
+        run_toggle_group(config_path, group, state)
+    } else if let Some(file) = matches.get_one::<String>("import-aliases") {
+        run_import_aliases(config_path, file)
+    } else if let Some(file) = matches.get_one::<String>("import-permissions") {
+        run_import_permissions(config_path, file, matches.get_flag("yes"))
+    } else if let Some(file) = matches.get_one::<String>("export-permissions") {
+        run_export_permissions(config_path, file, matches.get_flag("yes"))
+    } else if matches.get_flag("test-config") {
+        run_test_config(config_path)
+    } else if let Some(file) = matches.get_one::<String>("simulate-session") {
+        run_simulate_session(config_path, file, replace_mode)
+    } else if let Some(file) = matches.get_one::<String>("bench-rules") {
+        run_bench_rules(config_path, (!file.is_empty()).then_some(file.as_str()))
+    } else if let Some(count) = matches.get_one::<usize>("bench") {
+        run_bench(config_path, *count)
+    } else if matches.get_flag("infer-rules") {
+        run_infer_rules(config_path, matches.get_flag("yes"))
+    } else if matches.get_flag("stats") {
+        run_stats()
+    } else if matches.get_flag("gc-state") {
+        run_gc_state(config_path)
+    } else if let Some(action) = matches.get_one::<String>("sessions") {
+        run_sessions(action)
+    } else if matches.get_flag("print-schema-support") {
+        print_schema_support();
+        Ok(())
+    } else if matches.get_flag("build-info") {
+        run_build_info()
+    } else if matches.get_flag("where") {
+        run_where(config_path)
+    } else if matches.get_flag("flush-telemetry") {
+        run_flush_telemetry(config_path)
+    } else if matches.get_flag("test-notification") {
+        run_test_notification(config_path)
+    } else if let Some(action) = matches.get_one::<String>("telemetry") {
+        run_usage_telemetry(action)
+    } else if matches.get_flag("lint-config") {
+        run_lint_config(config_path)
     } else if matches.get_flag("check-config") {
         check_config_status()
     } else if matches.get_flag("migrate-config") {
@@ -93,316 +569,1865 @@ pub fn run_cli() -> Result<()> {
 }
 
 
-/// Smart installation that checks existing state and only makes necessary changes.
-/// 
-/// This function:
-/// 1. Checks if hooks already exist - if so, skips hook installation
-/// 2. Checks if config file exists - if not, creates it with examples
-/// 3. If config exists, ensures required sections exist with commented examples
-/// 
+/// Smart installation that checks existing state and only makes necessary changes.
+/// 
+/// This function:
+/// 1. Detects the project types and asks which rule packs, hook behavior and
+///    hook events to use (skipped with `--yes` or when stdin is not a terminal)
+/// 2. Checks if hooks already exist - if so, skips hook installation
+/// 3. Checks if config file exists - if not, creates it from the selected packs
+/// 4. If config exists, ensures required sections exist with commented examples
+/// 
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `replace_mode` - Default to registering the hook in replace mode
+/// * `assume_yes` - Skip all prompts and accept the defaults
+/// 
+/// # Returns
+/// * `Ok(InstallSummary)` - What the installation did
+/// * `Err` - If any installation step fails
+fn run_smart_installation(config_path: &str, replace_mode: bool, assume_yes: bool) -> Result<InstallSummary> {
+    say!("{}\n", ui::heading("🚀 Claude Hook Advisor Installation"));
+
+    let interactive = !assume_yes && std::io::stdin().is_terminal() && ui::mode() == OutputMode::Text;
+    let project_dir = std::env::current_dir()?;
+    let templates = all_templates(&project_dir);
+    let project_types = detect_project_types(&project_dir, &templates);
+    let project_label = project_types.join(" + ");
+    say!("🔍 Detected project type(s): {}", project_types.join(", "));
+    let environments = detect_environments(&project_dir);
+    if let Some(environment) = environments.first() {
+        say!("🧊 Detected {} environment: toolchain commands will run via '{}'", environment.name, environment.wrapper);
+    }
+
+    let packs = if interactive {
+        prompt_rule_packs(&templates, &project_types)?
+    } else {
+        default_rule_packs(&project_types)
+    };
+
+    let hooks_installed = hooks_already_exist()?;
+    let hook_options = if interactive && !hooks_installed {
+        prompt_hook_options(replace_mode)?
+    } else {
+        HookInstallOptions {
+            replace_mode,
+            assume_yes: !interactive,
+            ..HookInstallOptions::default()
+        }
+    };
+    
+    let mut summary = InstallSummary {
+        project_types: project_types.clone(),
+        hooks: "installed",
+        config: "created",
+    };
+
+    // Step 1: Check and install hooks if needed
+    if hooks_installed {
+        say!("{}", ui::success("✅ Hooks already installed in Claude Code settings"));
+        summary.hooks = "already installed";
+        if offer_hook_repair(interactive)? > 0 {
+            summary.hooks = "repaired";
+        }
+    } else {
+        say!("📋 Installing hooks into Claude Code settings...");
+        crate::installer::install_claude_hooks(&hook_options)?;
+        say!("{}", ui::success("✅ Hooks installed successfully"));
+    }
+    
+    // Step 2: Handle config file
+    say!("\n📄 Checking configuration file...");
+    if Path::new(config_path).exists() {
+        say!("{}", ui::success(format!("✅ Config file exists: {config_path}")));
+        summary.config = "exists";
+        ensure_config_sections(config_path)?;
+    } else {
+        say!("📝 Creating new config file: {config_path}");
+        let (mut commands, conflicts) = merge_templates(&templates, &packs);
+        for conflict in &conflicts {
+            let chosen = if interactive {
+                prompt_conflict_choice(conflict)?
+            } else {
+                conflict.candidates[0].1.clone()
+            };
+            say!("⚖️  '{}' is mapped by several packs, using '{chosen}'", conflict.command);
+            commands.insert(conflict.command.clone(), chosen);
+        }
+        let mut rules = template_rules(&templates, &packs);
+        rules.extend(environment_rules(&environments, &packs));
+        create_config_with_commands(config_path, &project_label, commands, rules)?;
+    }
+    
+    say!("\n🎉 Installation complete! Claude Hook Advisor is ready to use.");
+    say!("{}", ui::hint("💡 You can now use semantic directory references in Claude Code conversations."));
+    
+    Ok(summary)
+}
+
+/// What `run_smart_installation` did in one project.
+#[derive(Serialize)]
+struct InstallSummary {
+    project_types: Vec<String>,
+    /// "installed", "already installed" or "repaired"
+    hooks: &'static str,
+    /// "created" or "exists"
+    config: &'static str,
+}
+
+/// Installs into every project found under `root` with the detected
+/// defaults, then prints a table of what was done in each.
+///
+/// A failure in one project is reported in the table and does not stop the
+/// others.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file, relative to each project
+/// * `replace_mode` - Register the hook in replace mode
+/// * `root` - Directory to search for git repositories and projects
+fn run_bulk_installation(config_path: &str, replace_mode: bool, root: &Path) -> Result<()> {
+    let root = root.canonicalize().io_context(|| format!("Failed to open {}", root.display()))?;
+    let projects = find_projects(&root, &all_templates(&root));
+    if projects.is_empty() {
+        say!("🔍 No git repositories or projects found under {}", root.display());
+        return Ok(());
+    }
+    say!("🔍 Found {} project(s) under {}\n", projects.len(), root.display());
+
+    let original_dir = std::env::current_dir()?;
+    let mut results = Vec::new();
+    for project in &projects {
+        say!("\n📂 {}", project.display());
+        let result = std::env::set_current_dir(project)
+            .io_context(|| format!("Failed to enter {}", project.display()))
+            .and_then(|_| run_smart_installation(config_path, replace_mode, true));
+        if let Err(e) = &result {
+            say!("{}", ui::error(format!("❌ {e}")));
+        }
+        results.push((project, result));
+    }
+    std::env::set_current_dir(&original_dir)
+        .io_context(|| format!("Failed to return to {}", original_dir.display()))?;
+
+    say!("\n📊 Bulk installation summary\n");
+    say!("   {:<40} {:<20} {:<18} Config", "Project", "Types", "Hooks");
+    let mut failed = 0;
+    for (project, result) in &results {
+        let name = project.strip_prefix(&root).ok().filter(|p| !p.as_os_str().is_empty()).unwrap_or(project);
+        let name = truncate(&name.display().to_string(), 40);
+        match result {
+            Ok(summary) => say!(
+                "   {name:<40} {:<20} {:<18} {}",
+                truncate(&summary.project_types.join(", "), 20),
+                summary.hooks,
+                summary.config
+            ),
+            Err(e) => {
+                failed += 1;
+                say!("   {name:<40} ❌ {e}");
+            }
+        }
+    }
+    say!("\n{}", ui::success(format!("✅ {} project(s) set up, {failed} failed", results.len() - failed)));
+
+    let projects: Vec<serde_json::Value> = results
+        .iter()
+        .map(|(project, result)| match result {
+            Ok(summary) => json!({
+                "project": project,
+                "project_types": summary.project_types,
+                "hooks": summary.hooks,
+                "config": summary.config,
+            }),
+            Err(e) => json!({ "project": project, "error": e.to_string() }),
+        })
+        .collect();
+    finish_check(&json!({ "projects": projects, "failed": failed }), failed > 0)
+}
+
+/// Prints a management command's result for `--format json`; the other
+/// output modes print nothing here.
+fn emit_json(result: &impl Serialize) -> Result<()> {
+    if ui::mode() == OutputMode::Json {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    }
+    Ok(())
+}
+
+/// Emits a check's result, then, with `--quiet` or `--format json`, exits
+/// with status 1 if it found problems. Text output keeps exiting 0, as it
+/// always has; the prose says what is wrong.
+fn finish_check(result: &impl Serialize, failed: bool) -> Result<()> {
+    emit_json(result)?;
+    if failed && ui::mode() != OutputMode::Text {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Reports a problem a check found, and keeps it for the JSON result.
+fn problem(problems: &mut Vec<String>, message: String) {
+    say!("{}", ui::error(format!("❌ {message}")));
+    problems.push(message);
+}
+
+/// Reports something a check flagged that still works, and keeps it for the JSON result.
+fn warn(warnings: &mut Vec<String>, emoji: &str, message: String) {
+    say!("{}", ui::warning(format!("{emoji}  {message}")));
+    warnings.push(message);
+}
+
+/// What `--doctor` found, for `--format json`.
+#[derive(Serialize, Default)]
+struct DoctorReport {
+    settings_file: Option<PathBuf>,
+    hooks: Vec<HookStatus>,
+    /// Stale hook commands rewritten to a working binary
+    repaired: usize,
+    problems: Vec<String>,
+}
+
+/// One registered hook command and whether it runs.
+#[derive(Serialize)]
+struct HookStatus {
+    event: String,
+    command: String,
+    /// "ok", "other-binary" (runs a different claude-hook-advisor) or "stale" (its binary is gone)
+    status: &'static str,
+}
+
+/// Checks that the hooks registered in Claude Code settings can run.
+///
+/// Checks the config file, reports each registered hook command, flags
+/// those whose binary is gone or is not this binary, and offers to repair
+/// stale hook paths.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `assume_yes` - Repair without prompting
+fn run_doctor(config_path: &str, assume_yes: bool) -> Result<()> {
+    say!("{}\n", ui::heading("🩺 Claude Hook Advisor Doctor"));
+    let mut report = DoctorReport::default();
+
+    let Some((settings_path, hooks)) = crate::installer::installed_hooks()? else {
+        problem(&mut report.problems, "No Claude Code settings file found. Run 'claude-hook-advisor --install' first.".to_string());
+        return finish_check(&report, true);
+    };
+    say!("📁 Settings file: {}", settings_path.display());
+    report.settings_file = Some(settings_path);
+    if hooks.is_empty() {
+        problem(&mut report.problems, "No claude-hook-advisor hooks are registered. Run 'claude-hook-advisor --install'.".to_string());
+    }
+
+    match load_config_from_path(Path::new(config_path)) {
+        Ok(_) => say!("{}", ui::success(format!("✅ Config file loads: {config_path}"))),
+        Err(e) => problem(&mut report.problems, format!("Config file {config_path}: {e}")),
+    }
+
+    // Stale hooks are reported by the repair offer below
+    let exe = std::env::current_exe().io_context(|| "Failed to locate the claude-hook-advisor binary".to_string())?;
+    for hook in &hooks {
+        let status = if hook.is_stale() {
+            "stale"
+        } else if hook.runs(&exe) {
+            say!("{}", ui::success(format!("✅ {}: {}", hook.event, hook.command)));
+            "ok"
+        } else {
+            say!("{}", ui::warning(format!("⚠️  {}: runs '{}', not this binary ({})", hook.event, hook.program, exe.display())));
+            "other-binary"
+        };
+        report.hooks.push(HookStatus { event: hook.event.clone(), command: hook.command.clone(), status });
+    }
+
+    let interactive = !assume_yes && std::io::stdin().is_terminal() && ui::mode() == OutputMode::Text;
+    report.repaired = offer_hook_repair(interactive)?;
+    if report.repaired == 0 {
+        report.problems.extend(hooks.iter().filter(|hook| hook.is_stale()).map(stale_hook_message));
+    }
+    let failed = !report.problems.is_empty();
+    finish_check(&report, failed)
+}
+
+/// Why a stale hook never runs.
+fn stale_hook_message(hook: &crate::installer::RegisteredHook) -> String {
+    format!("The {} hook runs '{}', which no longer exists, so it never runs", hook.event, hook.program)
+}
+
+/// Offers to point hook commands whose binary is gone at a working one,
+/// returning how many were rewritten.
+///
+/// The choices are this binary's path and, when it is on PATH, the bare
+/// `claude-hook-advisor` name, which survives future moves. Without a
+/// terminal the PATH name is used when available.
+///
+/// # Arguments
+/// * `interactive` - Ask which binary to use instead of choosing one
+fn offer_hook_repair(interactive: bool) -> Result<usize> {
+    let Some((settings_path, hooks)) = crate::installer::installed_hooks()? else {
+        return Ok(0);
+    };
+    let stale: Vec<_> = hooks.iter().filter(|hook| hook.is_stale()).collect();
+    if stale.is_empty() {
+        return Ok(0);
+    }
+    for hook in &stale {
+        say!("{}", ui::error(format!("❌ {}", stale_hook_message(hook))));
+    }
+
+    let exe = std::env::current_exe().io_context(|| "Failed to locate the claude-hook-advisor binary".to_string())?;
+    let mut choices = vec![exe.to_string_lossy().to_string()];
+    let name = env!("CARGO_PKG_NAME");
+    if which::which(name).is_ok() {
+        choices.insert(0, name.to_string());
+    }
+
+    let binary = if interactive {
+        let mut items: Vec<String> = choices.iter().map(|choice| format!("Run {choice}")).collect();
+        items.push("Leave the settings unchanged".to_string());
+        let selection = Select::new()
+            .with_prompt("Rewrite the stale hook commands?")
+            .items(&items)
+            .default(0)
+            .interact()
+            .io_context(|| "Failed to read hook repair choice".to_string())?;
+        match choices.get(selection) {
+            Some(choice) => choice.clone(),
+            None => return Ok(0),
+        }
+    } else {
+        choices[0].clone()
+    };
+
+    let rewritten = crate::installer::repair_stale_hooks(&settings_path, &binary)?;
+    say!("🔧 Rewrote {rewritten} hook command(s) to run {binary}");
+    Ok(rewritten)
+}
+
+/// Installs with the detected defaults and checks the result end to end.
+///
+/// Runs the installer without prompts, then sends a mapped command and a
+/// harmless one through `--hook` and runs the config's `[[tests]]`. Exits
+/// with status 1 if the self-test fails.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `replace_mode` - Register the hook in replace mode
+fn run_quickstart(config_path: &str, replace_mode: bool) -> Result<()> {
+    run_smart_installation(config_path, replace_mode, true)?;
+
+    println!("\n🩺 Running self-test...");
+    let config = load_config_from_path(Path::new(config_path))?;
+    let exe = std::env::current_exe().io_context(|| "Failed to locate the claude-hook-advisor binary".to_string())?;
+    let mut hook_args = vec!["--config".to_string(), config_path.to_string()];
+    if replace_mode {
+        hook_args.push("--replace".to_string());
+    }
+
+    let mut failures = 0;
+    for report in run_scenario(&exe, &hook_args, &Scenario::self_test(&config, replace_mode)?)? {
+        let outcome = match &report.suggested {
+            Some(suggested) => format!("{} → {suggested}", report.decision),
+            None => report.decision.to_string(),
+        };
+        if report.failures.is_empty() {
+            println!("   ✅ {}: {outcome}", report.input);
+        } else {
+            failures += 1;
+            println!("   ❌ {}: {}", report.input, report.failures.join(", "));
+        }
+    }
+    let failed_tests = run_config_tests(&config)?.iter().filter(|outcome| !outcome.passed()).count();
+    if !config.tests.is_empty() {
+        let icon = if failed_tests == 0 { "✅" } else { "❌" };
+        println!("   {icon} {} of {} config test(s) passed", config.tests.len() - failed_tests, config.tests.len());
+    }
+
+    if failures + failed_tests > 0 {
+        println!("\n❌ Self-test failed. Run `claude-hook-advisor --explain <COMMAND>` to see how rules match.");
+        std::process::exit(1);
+    }
+    println!("\n🎉 Quickstart complete! Start a new Claude Code session to pick up the hooks.");
+    Ok(())
+}
+
+/// Rule packs used when the installer runs without prompts.
+fn default_rule_packs(project_types: &[String]) -> Vec<String> {
+    let mut packs = project_types.to_vec();
+    packs.push("Safety".to_string());
+    packs
+}
+
+/// Asks which rule packs to include, preselecting the detected project types.
+fn prompt_rule_packs(templates: &[ProjectTemplate], project_types: &[String]) -> Result<Vec<String>> {
+    let defaults = default_rule_packs(project_types);
+    let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+    let checked: Vec<bool> = names
+        .iter()
+        .map(|name| defaults.iter().any(|d| d == name))
+        .collect();
+
+    let selection = MultiSelect::new()
+        .with_prompt("Select rule packs to include (space to toggle, enter to confirm)")
+        .items(&names)
+        .defaults(&checked)
+        .interact()
+        .io_context(|| "Failed to read rule pack selection".to_string())?;
+
+    Ok(selection.into_iter().map(|i| names[i].to_string()).collect())
+}
+
+/// Asks which replacement to use when selected packs disagree on a command.
+fn prompt_conflict_choice(conflict: &TemplateConflict) -> Result<String> {
+    let items: Vec<String> = conflict
+        .candidates
+        .iter()
+        .map(|(pack, replacement)| format!("{replacement}  ({pack})"))
+        .collect();
+
+    let choice = Select::new()
+        .with_prompt(format!("Packs disagree on '{}'. Use", conflict.command))
+        .items(&items)
+        .default(0)
+        .interact()
+        .io_context(|| "Failed to read mapping choice".to_string())?;
+
+    Ok(conflict.candidates[choice].1.clone())
+}
+
+/// Asks whether matched commands are blocked or replaced, and which hook events to register.
+fn prompt_hook_options(replace_mode: bool) -> Result<HookInstallOptions> {
+    let behavior = Select::new()
+        .with_prompt("When a mapped command is used")
+        .items(["Block and suggest the preferred command", "Replace the command (experimental)"])
+        .default(usize::from(replace_mode))
+        .interact()
+        .io_context(|| "Failed to read hook behavior selection".to_string())?;
+
+    let event_names: Vec<&str> = HOOK_EVENTS.iter().map(|(event, _)| *event).collect();
+    let selection = MultiSelect::new()
+        .with_prompt("Select hook events to register")
+        .items(&event_names)
+        .defaults(&vec![true; event_names.len()])
+        .interact()
+        .io_context(|| "Failed to read hook event selection".to_string())?;
+
+    if selection.is_empty()
+        && !Confirm::new()
+            .with_prompt("No hook events selected. Continue without registering hooks?")
+            .default(false)
+            .interact()
+            .io_context(|| "Failed to read confirmation".to_string())?
+    {
+        return Err(AdvisorError::Installer("Installation cancelled".to_string()));
+    }
+
+    Ok(HookInstallOptions {
+        events: selection.into_iter().map(|i| event_names[i].to_string()).collect(),
+        replace_mode: behavior == 1,
+        assume_yes: false,
+    })
+}
+
+/// Checks if Claude Hook Advisor hooks are already installed in Claude Code settings.
+/// 
+/// # Returns
+/// * `Ok(true)` - Hooks are already installed
+/// * `Ok(false)` - Hooks are not installed
+/// * `Err` - If settings file cannot be read or parsed
+fn hooks_already_exist() -> Result<bool> {
+    // Check for settings files in order of preference
+    let local_settings = Path::new(".claude/settings.local.json");
+    let shared_settings = Path::new(".claude/settings.json");
+    
+    let settings_path = if local_settings.exists() {
+        local_settings
+    } else if shared_settings.exists() {
+        shared_settings
+    } else {
+        return Ok(false); // No settings file means no hooks
+    };
+    
+    // Read and parse settings file
+    let settings_content = fs::read_to_string(settings_path)
+        .io_context(|| format!("Failed to read {}", settings_path.display()))?;
+    
+    let settings = jsonc::parse(&settings_content)
+        .map_err(|e| AdvisorError::Installer(format!("Failed to parse Claude settings JSON: {e}")))?;
+    
+    // Check if our hooks exist
+    if let Some(hooks) = settings.get("hooks").and_then(|h| h.as_object()) {
+        // Check PreToolUse and UserPromptSubmit hooks
+        for event_name in &["PreToolUse", "UserPromptSubmit"] {
+            if let Some(event_hooks) = hooks.get(*event_name).and_then(|h| h.as_array()) {
+                for hook_group in event_hooks {
+                    if let Some(hooks_array) = hook_group.get("hooks").and_then(|h| h.as_array()) {
+                        for hook in hooks_array {
+                            if let Some(command) = hook.get("command").and_then(|c| c.as_str()) {
+                                if command.contains("claude-hook-advisor") {
+                                    return Ok(true);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok(false)
+}
+
+/// Creates a configuration file containing the given command mappings.
+/// Directory aliases are provided as commented examples only.
+/// 
+/// # Arguments
+/// * `config_path` - Path where to create the configuration file
+/// * `project_type` - Detected project type(s), recorded in the file header
+/// * `commands` - Command mappings merged from the selected rule packs
+/// * `rules` - Rules from the selected packs and development environment wrappers
+/// 
+/// # Returns
+/// * `Ok(())` - Configuration created successfully
+/// * `Err` - If file writing fails
+fn create_config_with_commands(
+    config_path: &str,
+    project_type: &str,
+    commands: HashMap<String, String>,
+    rules: Vec<Rule>,
+) -> Result<()> {
+    // Only the commands and rules are written; directories are added as comments below
+    let mut table = toml::Table::new();
+    table.insert("version".to_string(), toml::Value::Integer(CONFIG_VERSION.into()));
+    table.insert("commands".to_string(), toml::Value::try_from(&commands)?);
+    table.insert("semantic_directories".to_string(), toml::Value::Table(toml::Table::new()));
+
+    // Generate TOML content
+    let toml_content = toml::to_string_pretty(&table)?;
+
+    // Rules go after the directory examples so those stay in [semantic_directories]
+    let rules_content = if rules.is_empty() {
+        String::new()
+    } else {
+        let mut rules_table = toml::Table::new();
+        rules_table.insert("rules".to_string(), toml::Value::try_from(&rules)?);
+        format!(
+            "\n# Rules from the selected packs and the project's development environment\n{}",
+            toml::to_string_pretty(&rules_table)?
+        )
+    };
+    
+    // Build the complete config with header and directory examples as comments
+    let final_content = format!(r#"# Claude Hook Advisor Configuration
+# Auto-generated for {project_type} project
+# This file configures command mappings and semantic directory aliases
+# for use with Claude Code integration.
+
+{toml_content}
+# Semantic directory aliases - natural language directory references
+# Uncomment and customize these examples:
+# docs = "~/Documents/Documentation"
+# central_docs = "~/Documents/Documentation"
+# project_docs = "~/Documents/Documentation/my-project"
+# claude_docs = "~/Documents/Documentation/claude"
+{rules_content}"#);
+    
+    write_atomic(Path::new(config_path), final_content)
+        .io_context(|| format!("Failed to write config file: {config_path}"))?;
+    
+    say!("✅ Created smart configuration for {project_type} project");
+    
+    // Show what was configured
+    if !commands.is_empty() {
+        say!("📝 Command mappings configured:");
+        for (from, to) in &commands {
+            say!("   {from} → {to}");
+        }
+    } else {
+        say!("📝 No rule packs selected - add mappings to [commands] manually");
+    }
+    for rule in &rules {
+        if let Some(replacement) = &rule.replacement {
+            say!("   {} → {replacement} (outside the environment)", rule.pattern);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Ensures required sections exist in an existing config file.
+/// 
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// 
+/// # Returns
+/// * `Ok(())` - Configuration updated successfully
+/// * `Err` - If file operations fail
+fn ensure_config_sections(config_path: &str) -> Result<()> {
+    let mut editor = ConfigEditor::open(Path::new(config_path))?;
+    let mut needs_update = false;
+
+    // Check and add missing sections; existing content is left byte-for-byte intact
+    if editor.ensure_table(
+        "commands",
+        "# Command mappings - suggest alternatives when Claude Code runs these commands\n\
+         # npm = \"bun\"          # Suggest 'bun' instead of 'npm'\n\
+         # yarn = \"bun\"         # Suggest 'bun' instead of 'yarn'\n\
+         # npx = \"bunx\"         # Suggest 'bunx' instead of 'npx'\n\
+         # grep = \"rg\"          # Suggest 'rg' (ripgrep) instead of 'grep'",
+    ) {
+        needs_update = true;
+        say!("✅ Added [commands] section with examples");
+    }
+
+    if editor.ensure_table(
+        "semantic_directories",
+        "# Semantic directory aliases - natural language directory references",
+    ) {
+        for (alias, path) in [
+            ("docs", "~/Documents/Documentation"),
+            ("central_docs", "~/Documents/Documentation"),
+            ("project_docs", "~/Documents/Documentation/my-project"),
+            ("claude_docs", "~/Documents/Documentation/claude"),
+        ] {
+            editor.set_value(&["semantic_directories"], alias, path)?;
+        }
+        needs_update = true;
+        say!("✅ Added [semantic_directories] section with default aliases");
+    }
+
+    if needs_update {
+        editor.save()?;
+        say!("💾 Configuration file updated");
+    } else {
+        say!("✅ All required sections already present");
+    }
+
+    Ok(())
+}
+
+
+/// Loads the configuration named by `--config`, falling back to automatic
+/// discovery (including the legacy file name) when that file does not exist.
+fn load_cli_config(config_path: &str) -> Result<Config> {
+    let found = Some(PathBuf::from(config_path)).filter(|path| path.exists()).or_else(|| find_config_file().ok());
+    let Some(path) = found else {
+        return load_config_auto();
+    };
+    let config = load_config_from_path(&path)?;
+
+    // Files from older schemas are upgraded on disk the first time the CLI reads them
+    match upgrade_config_file(&path) {
+        Ok(Some(upgrade)) => {
+            eprintln!("{}", describe_schema_upgrade(&path, &upgrade));
+            load_config_from_path(&path)
+        }
+        Ok(None) => Ok(config),
+        Err(e) => {
+            eprintln!("⚠️  Could not upgrade {} to schema version {CONFIG_VERSION}: {e}", path.display());
+            Ok(config)
+        }
+    }
+}
+
+/// Reports an applied schema upgrade, one line per migration.
+fn describe_schema_upgrade(path: &Path, upgrade: &SchemaUpgrade) -> String {
+    let mut lines = vec![format!(
+        "🔄 Upgraded {} from schema version {} to {CONFIG_VERSION} (backup: {})",
+        path.display(),
+        upgrade.from,
+        upgrade.backup.display()
+    )];
+    lines.extend(upgrade.applied.iter().map(|summary| format!("   • {summary}")));
+    lines.join("\n")
+}
+
+/// Loads the configuration with the user-wide directory aliases, as the
+/// conventions listing and the MCP server show them.
+fn load_conventions_config(config_path: &str) -> Result<Config> {
+    let mut config = load_cli_config(config_path)?;
+    match load_user_directories() {
+        Ok(directories) => config.user_directories = directories,
+        Err(e) => eprintln!("Warning: Ignoring user-wide directories: {e}"),
+    }
+    Ok(config)
+}
+
+/// Prints the project's command conventions, as the `/conventions` slash
+/// command and the MCP `list_conventions` tool show them.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+fn run_list_conventions(config_path: &str) -> Result<()> {
+    println!("{}", crate::conventions::list_conventions(&load_conventions_config(config_path)?));
+    Ok(())
+}
+
+/// Checks every command listed in a file against the configured mappings.
+///
+/// Blank lines and lines starting with `#` are skipped. The rule set is
+/// compiled once and reused for every command.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `file` - File containing one command per line
+fn run_dry_run_file(config_path: &str, file: &str) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let content = fs::read_to_string(file)
+        .io_context(|| format!("Failed to read commands file: {file}"))?;
+
+    let commands: Vec<(usize, &str)> = content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let policy = Policy::outside_session(&config)?;
+
+    println!("🔍 Dry run: {file} ({} commands)\n", commands.len());
+
+    let mut severities = Vec::new();
+    for (line, command) in &commands {
+        let Some(rule_match) = policy.evaluate(command)? else {
+            continue;
+        };
+        println!("{file}:{line}: {command}");
+        match &rule_match.suggested_command {
+            Some(suggested) => println!("   → {suggested}"),
+            None => println!("   ❌ {}", rule_match.reason),
+        }
+        severities.push(rule_match.severity);
+    }
+
+    if severities.is_empty() {
+        println!("✅ No commands would be flagged");
+    } else {
+        println!("\n📊 {} of {} commands would be flagged", severities.len(), commands.len());
+    }
+
+    exit_with_decision(severities.into_iter().max())
+}
+
+/// Commands timed by `--bench-rules` when no file is given.
+#[cfg(feature = "bench")]
+const BENCH_SAMPLE_COMMANDS: &[&str] = &[
+    "ls -la",
+    "npm install",
+    "sudo npm run build && npm test",
+    "git status",
+    "cargo build --release 2>&1 | tee build.log",
+    "docker compose up -d",
+    r#"grep -r "TODO" src/ | wc -l"#,
+    "python -m pytest tests/",
+];
+
+/// Iterations per command in `--bench-rules`.
+#[cfg(feature = "bench")]
+const BENCH_ITERATIONS: u32 = 1000;
+
+/// Benchmarks the configured rule set.
+///
+/// Reports how long the rules take to compile and the mean and worst-case
+/// evaluation latency of each command, as the hook would see it.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `file` - Optional file with one command per line; a built-in sample is used otherwise
+#[cfg(feature = "bench")]
+fn run_bench_rules(config_path: &str, file: Option<&str>) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+
+    let content = match file {
+        Some(file) => fs::read_to_string(file)
+            .io_context(|| format!("Failed to read commands file: {file}"))?,
+        None => BENCH_SAMPLE_COMMANDS.join("\n"),
+    };
+    let commands: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let compile_start = Instant::now();
+    let rules = CompiledRules::new(&config, &RuleContext::default())?;
+    let compile_time = compile_start.elapsed();
+
+    let mode = if rules.is_parallel() { "parallel" } else { "sequential" };
+    println!("⏱️  Rule benchmark: {} rules ({mode} evaluation)", rules.len());
+    println!("   Compile: {compile_time:.2?}\n");
+    println!("   {:<40} {:>10} {:>10}  Result", "Command", "Mean", "Max");
+
+    let mut total = Duration::ZERO;
+    for command in &commands {
+        let mut max = Duration::ZERO;
+        let mut sum = Duration::ZERO;
+        let mut result = None;
+        for _ in 0..BENCH_ITERATIONS {
+            let start = Instant::now();
+            result = std::hint::black_box(rules.evaluate(command));
+            let elapsed = start.elapsed();
+            sum += elapsed;
+            max = max.max(elapsed);
+        }
+        total += sum;
+
+        let outcome = match result {
+            Some(rule_match) => format!("matched '{}'", rule_match.pattern),
+            None => "allowed".to_string(),
+        };
+        let mean = sum / BENCH_ITERATIONS;
+        println!("   {:<40} {:>10} {:>10}  {outcome}", truncate(command, 40), format!("{mean:.1?}"), format!("{max:.1?}"));
+    }
+
+    if !commands.is_empty() {
+        let overall = total / (BENCH_ITERATIONS * commands.len() as u32);
+        println!("\n📊 Mean latency: {overall:.1?} per command over {BENCH_ITERATIONS} iterations");
+    }
+
+    Ok(())
+}
+
+/// Times the configured rules on `count` synthetic commands and reports
+/// latency percentiles and throughput, as text or, with `--format json`, JSON.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `count` - Number of commands to generate
+#[cfg(feature = "bench")]
+fn run_bench(config_path: &str, count: usize) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let commands = crate::bench::synthetic_commands(&config, count);
+
+    let compile_start = Instant::now();
+    let rules = CompiledRules::new(&config, &RuleContext::default())?;
+    let compile_time = compile_start.elapsed();
+    let summary = crate::bench::run_bench(&rules, &commands);
+
+    let mode = if rules.is_parallel() { "parallel" } else { "sequential" };
+    say!("⏱️  Matcher benchmark: {} synthetic commands, {} rules ({mode} evaluation)", summary.commands, rules.len());
+    say!("   Compile:    {compile_time:.2?}");
+    say!("   p50:        {:.1?}", summary.p50);
+    say!("   p95:        {:.1?}", summary.p95);
+    say!("   p99:        {:.1?}", summary.p99);
+    say!("   Max:        {:.1?}", summary.max);
+    say!("   Matched:    {} of {}", summary.matched, summary.commands);
+    say!("\n📊 Throughput: {:.0} commands/s", summary.throughput());
+    emit_json(&summary)
+}
+
+/// Enables or disables a rule group by editing its `enabled` key in place.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `group` - Name of a `[groups.<name>]` table
+/// * `state` - "on" or "off"
+fn run_toggle_group(config_path: &str, group: &str, state: &str) -> Result<()> {
+    let enabled = match state {
+        "on" => true,
+        "off" => false,
+        _ => return Err(ConfigError::InvalidFormat(format!("Group state must be 'on' or 'off', not '{state}'")).into()),
+    };
+
+    let path = Path::new(config_path);
+    let config = load_config_from_path(path)?;
+    if !config.groups.contains_key(group) {
+        let mut names: Vec<&String> = config.groups.keys().collect();
+        names.sort();
+        println!("❌ No [groups.{group}] in {config_path}");
+        if !names.is_empty() {
+            println!("   Defined groups: {}", names.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", "));
+        }
+        return Err(ConfigError::UnknownGroup(group.to_string()).into());
+    }
+
+    let mut editor = ConfigEditor::open(path)?;
+    editor.set_value(&["groups", group], "enabled", enabled)?;
+    editor.save()?;
+    println!("✅ Rule group '{group}' is now {}", if enabled { "on" } else { "off" });
+    Ok(())
+}
+
+/// Adds shell alias definitions to the `[aliases]` table.
+///
+/// Existing entries with the same name are overwritten; everything else in
+/// the file is left as it was.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `file` - File of `alias name='value'` lines, or `-` for stdin
+fn run_import_aliases(config_path: &str, file: &str) -> Result<()> {
+    let text = if file == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        fs::read_to_string(file)?
+    };
+    let aliases = parse_alias_definitions(&text);
+    if aliases.is_empty() {
+        say!("❌ No alias definitions found in {file}");
+        return Err(ConfigError::InvalidFormat(format!("{file} has no `alias name='value'` lines")).into());
+    }
+
+    let mut editor = ConfigEditor::open(Path::new(config_path))?;
+    for (name, value) in &aliases {
+        editor.set_value(&["aliases"], name, value.as_str())?;
+    }
+    editor.save()?;
+    say!("✅ Imported {} alias(es) into [aliases]", aliases.len());
+    for (name, value) in &aliases {
+        say!("   {name} → {value}");
+    }
+    Ok(())
+}
+
+/// Lets the user choose among `items` in a terminal. With `assume_yes`
+/// every item is chosen; otherwise the items are only listed.
+///
+/// # Returns
+/// * `Ok(Some(indices))` - The chosen items
+/// * `Ok(None)` - If the items were only listed
+fn select_items(items: &[String], prompt: &str, assume_yes: bool) -> Result<Option<Vec<usize>>> {
+    if !assume_yes && std::io::stdin().is_terminal() && ui::mode() == OutputMode::Text {
+        let chosen = MultiSelect::new()
+            .with_prompt(prompt)
+            .items(items)
+            .defaults(&vec![true; items.len()])
+            .interact()
+            .io_context(|| "Failed to read selection".to_string())?;
+        return Ok(Some(chosen));
+    }
+
+    for item in items {
+        say!("   {item}");
+    }
+    if !assume_yes {
+        say!("\n   Run with --yes to add them all, or in a terminal to choose");
+        return Ok(None);
+    }
+    Ok(Some((0..items.len()).collect()))
+}
+
+/// Lists what a permission conversion left out.
+fn print_skipped(skipped: &[Skipped]) {
+    if skipped.is_empty() {
+        return;
+    }
+    say!("\n   Skipped:");
+    for skipped in skipped {
+        say!("   {}  ({})", skipped.item, skipped.reason);
+    }
+}
+
+/// Offers the Bash entries of Claude Code's `permissions` as advisor rules:
+/// `deny` and `ask` entries as `[[rules]]`, `allow` entries as `[allowed]`
+/// commands, and adds the ones the user accepts.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file to add rules to
+/// * `settings_file` - The Claude Code settings file to read
+/// * `assume_yes` - Add every entry without asking
+fn run_import_permissions(config_path: &str, settings_file: &str, assume_yes: bool) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    if !Path::new(settings_file).exists() {
+        return Err(AdvisorError::Installer(format!("Claude Code settings file {settings_file} not found")));
+    }
+    let settings = crate::installer::load_or_create_settings(Path::new(settings_file))?;
+    let conversion = import_permissions(&settings, &config);
+
+    if conversion.entries.is_empty() {
+        say!("🔍 No Bash permissions in {settings_file} to import");
+        print_skipped(&conversion.skipped);
+        return emit_json(&json!({ "entries": conversion.entries, "skipped": conversion.skipped, "added": 0 }));
+    }
+    say!("💡 {} Bash permission(s) in {settings_file} can become advisor rules\n", conversion.entries.len());
+    let items: Vec<String> = conversion
+        .entries
+        .iter()
+        .map(|entry| match entry.list {
+            PermissionList::Allow => format!("{} → [allowed] {}", entry.entry(), entry.command),
+            PermissionList::Ask => format!("{} → rule '{}' that asks", entry.entry(), entry.command),
+            PermissionList::Deny => format!("{} → rule '{}' that blocks", entry.entry(), entry.command),
+        })
+        .collect();
+    let selected = select_items(&items, "Select the permissions to add to the configuration", assume_yes)?;
+    print_skipped(&conversion.skipped);
+    let Some(accepted) = selected else {
+        return emit_json(&json!({ "entries": conversion.entries, "skipped": conversion.skipped, "added": 0 }));
+    };
+
+    let chosen: Vec<&BashPermission> = accepted.iter().map(|&index| &conversion.entries[index]).collect();
+    let allowed: Vec<&str> = chosen.iter().filter(|entry| entry.list == PermissionList::Allow).map(|entry| entry.command.as_str()).collect();
+    if !chosen.is_empty() {
+        let mut editor = ConfigEditor::open(Path::new(config_path))?;
+        for entry in chosen.iter().filter(|entry| entry.list != PermissionList::Allow) {
+            let rule = entry.to_rule();
+            let severity = if rule.severity == Some(Severity::Ask) { "ask" } else { "block" };
+            let mut fields = vec![("pattern", rule.pattern.as_str().into()), ("severity", severity.into())];
+            fields.push(("message", rule.message.unwrap_or_default().into()));
+            if let Some(matches) = rule.matches {
+                fields.push(("matches", matches.into()));
+            }
+            editor.append_table("rules", fields)?;
+        }
+        if !allowed.is_empty() {
+            let commands: Vec<&str> = config.allowed.commands.iter().map(String::as_str).chain(allowed.iter().copied()).collect();
+            editor.set_value(&["allowed"], "commands", commands.into_iter().collect::<toml_edit::Value>())?;
+        }
+        editor.save()?;
+    }
+    say!("✅ Added {} rule(s) and {} [allowed] command(s) to {config_path}", chosen.len() - allowed.len(), allowed.len());
+    if !allowed.is_empty() && config.mode.default != DefaultDecision::Deny {
+        say!("💡 [allowed] commands only take effect with [mode] default = \"deny\"");
+    }
+    emit_json(&json!({ "entries": conversion.entries, "skipped": conversion.skipped, "added": chosen.len() }))
+}
+
+/// Offers the unconditional rules and `[allowed]` commands as Claude Code
+/// permissions, and adds the ones the user accepts to `settings_file`.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file to read
+/// * `settings_file` - The Claude Code settings file to add permissions to
+/// * `assume_yes` - Add every entry without asking
+fn run_export_permissions(config_path: &str, settings_file: &str, assume_yes: bool) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let conversion = export_permissions(&config);
+
+    if conversion.entries.is_empty() {
+        say!("🔍 No rules or [allowed] commands that can become permissions");
+        print_skipped(&conversion.skipped);
+        return emit_json(&json!({ "entries": conversion.entries, "skipped": conversion.skipped, "added": 0 }));
+    }
+    say!("💡 {} rule(s) and command(s) can become Claude Code permissions\n", conversion.entries.len());
+    let items: Vec<String> = conversion.entries.iter().map(|entry| format!("{}: {}", entry.list.key(), entry.entry())).collect();
+    let selected = select_items(&items, &format!("Select the permissions to add to {settings_file}"), assume_yes)?;
+    print_skipped(&conversion.skipped);
+    let Some(accepted) = selected else {
+        return emit_json(&json!({ "entries": conversion.entries, "skipped": conversion.skipped, "added": 0 }));
+    };
+
+    let chosen: Vec<BashPermission> = accepted.iter().map(|&index| conversion.entries[index].clone()).collect();
+    let path = Path::new(settings_file);
+    let mut settings = crate::installer::load_or_create_settings(path)?;
+    let added = add_permissions(&mut settings, &chosen)?;
+    if added > 0 {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).io_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        crate::installer::create_settings_backup(path)?;
+        crate::installer::write_settings_file(path, &settings)?;
+    }
+    say!("✅ Added {added} permission(s) to {settings_file}");
+    emit_json(&json!({ "entries": conversion.entries, "skipped": conversion.skipped, "added": added }))
+}
+
+/// Lists, searches or test-resolves semantic directory aliases.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `matches` - Arguments of the `dirs` subcommand
+fn run_dirs(config_path: &str, matches: &clap::ArgMatches) -> Result<()> {
+    let mut config = load_cli_config(config_path)?;
+    match load_user_directories() {
+        Ok(directories) => config.user_directories = directories,
+        Err(e) => println!("⚠️  Ignoring user-wide directories: {e}"),
+    }
+
+    match matches.subcommand() {
+        Some(("search", sub_matches)) => {
+            let term = sub_matches.get_one::<String>("term").expect("term is required");
+            let needle = term.to_lowercase();
+            let entries: Vec<AliasEntry> = list_aliases(&config)
+                .into_iter()
+                .filter(|entry| entry.alias.to_lowercase().contains(&needle) || entry.path.to_lowercase().contains(&needle))
+                .collect();
+            if entries.is_empty() {
+                println!("🔍 No aliases match '{term}'");
+                for suggestion in suggest_aliases(&config, term) {
+                    let names: Vec<&str> = suggestion.candidates.iter().map(|(alias, _)| alias.as_str()).collect();
+                    println!("💡 Did you mean: {}", names.join(", "));
+                }
+            } else {
+                println!("🔍 {} alias(es) matching '{term}'\n", entries.len());
+                print_alias_entries(&config, &entries);
+            }
+        }
+        Some(("resolve", sub_matches)) => {
+            let phrase = sub_matches.get_one::<String>("phrase").expect("phrase is required");
+            let lines = directory_context(&config, phrase);
+            if lines.is_empty() {
+                println!("🔍 No directory references in \"{phrase}\"");
+            }
+            for line in lines {
+                println!("{line}");
+            }
+        }
+        _ => {
+            let entries = list_aliases(&config);
+            if entries.is_empty() {
+                println!("📁 No semantic directories configured");
+                println!("💡 Add them under [semantic_directories] in {config_path}");
+            } else {
+                println!("📁 {} directory alias(es)\n", entries.len());
+                print_alias_entries(&config, &entries);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints aliases with their scope, configured path and where they resolve now.
+fn print_alias_entries(config: &Config, entries: &[AliasEntry]) {
+    for entry in entries {
+        let status = if entry.shadowed {
+            "(shadowed by project)".to_string()
+        } else {
+            match locate_directory(config, &entry.alias) {
+                Ok(resolution) if resolution.status == DirectoryStatus::Missing => "⚠️  missing".to_string(),
+                Ok(resolution) => format!("→ {}", resolution.canonical_path),
+                Err(AdvisorError::Config(ConfigError::DirectoryNotAllowed { .. })) => "🚫 outside allowed roots".to_string(),
+                Err(_) => "❌ not found".to_string(),
+            }
+        };
+        println!("   {:<24} {:<8} {} {status}", truncate(&entry.alias, 24), entry.scope, entry.path);
+    }
+}
+
+/// Suspends the rule or command mapping with `pattern` for `duration`.
+///
+/// The snooze is stored in the project's state directory, so it applies to
+/// every session until it expires.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `pattern` - Pattern of a `[[rules]]` entry or `[commands]` key
+/// * `duration` - Length such as "1h"; required
+fn run_snooze(config_path: &str, pattern: &str, duration: Option<&String>) -> Result<()> {
+    let duration = duration
+        .ok_or_else(|| ConfigError::InvalidFormat(format!("Missing duration, e.g. `snooze {pattern} 1h`")))?;
+    let length = parse_duration(duration)
+        .ok_or_else(|| ConfigError::InvalidFormat(format!("Invalid duration '{duration}', expected e.g. 30m, 1h, 2d or 1h30m")))?;
+
+    let config = load_cli_config(config_path)?;
+    let known = config.active_rules().iter().any(|rule| rule.pattern == pattern)
+        || config.active_commands().iter().any(|(key, _)| key.as_str() == pattern);
+    if !known {
+        println!("⚠️  No active rule or command mapping has the pattern '{pattern}'; snoozing it anyway");
+    }
+
+    let project_dir = Path::new(".");
+    let mut snoozes = SnoozeList::load(project_dir);
+    let until = snoozes.snooze(pattern, length);
+    snoozes.save(project_dir)?;
+    println!("😴 Snoozed '{pattern}' for {duration}, until {}", until.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"));
+    Ok(())
+}
+
+/// Writes the activity report for this project.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `matches` - The `report` subcommand's arguments: the period, format and output file
+#[cfg(feature = "analytics")]
+fn run_report(config_path: &str, matches: &clap::ArgMatches) -> Result<()> {
+    use crate::report::{build_report, ReportFormat};
+
+    let config = load_cli_config(config_path)?;
+    let days = if matches.get_flag("week") { Some(7) } else { matches.get_one::<u32>("days").copied() };
+    let until = chrono::Utc::now();
+    let since = days.map(|days| until - chrono::Duration::days(i64::from(days)));
+    let format = match matches.get_one::<String>("format").map(String::as_str) {
+        Some("html") => ReportFormat::Html,
+        _ => ReportFormat::Markdown,
+    };
+
+    let report = build_report(&config, Path::new("."), since, until)?;
+    let rendered = report.render(format);
+    match matches.get_one::<String>("output") {
+        Some(file) => {
+            write_atomic(Path::new(file), rendered).io_context(|| format!("Failed to write report: {file}"))?;
+            println!("📝 Report written to {file}");
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
+
+/// Prints the snoozes in this project that have not expired.
+fn print_snoozes() {
+    let snoozes = SnoozeList::load(Path::new("."));
+    let active = snoozes.active();
+    if active.is_empty() {
+        println!("⏰ No active snoozes");
+        return;
+    }
+
+    println!("😴 {} active snooze(s)\n", active.len());
+    println!("   {:<30} {:<17} {:>9}", "Pattern", "Until", "Remaining");
+    let now = chrono::Utc::now();
+    for snooze in active {
+        let remaining = (snooze.until - now).num_minutes() + 1;
+        println!(
+            "   {:<30} {:<17} {:>9}",
+            truncate(&snooze.pattern, 30),
+            snooze.until.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            format!("{}h{:02}m", remaining / 60, remaining % 60)
+        );
+    }
+}
+
+/// Parses a duration such as "45s", "30m", "1h", "2d" or "1h30m".
+///
+/// # Returns
+/// * `Some(Duration)` - The positive duration
+/// * `None` - If the text is empty, malformed or zero
+fn parse_duration(text: &str) -> Option<chrono::Duration> {
+    let mut total = chrono::Duration::zero();
+    let mut digits = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let amount: i64 = digits.parse().ok()?;
+        digits.clear();
+        total += match c {
+            's' => chrono::Duration::try_seconds(amount)?,
+            'm' => chrono::Duration::try_minutes(amount)?,
+            'h' => chrono::Duration::try_hours(amount)?,
+            'd' => chrono::Duration::try_days(amount)?,
+            _ => return None,
+        };
+    }
+    (digits.is_empty() && total > chrono::Duration::zero()).then_some(total)
+}
+
+/// Runs the configuration's `[[tests]]`, printing a diff for each failure.
+///
+/// Exits with status 1 if any test fails, so rule regressions fail CI.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+fn run_test_config(config_path: &str) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    if config.tests.is_empty() {
+        println!("🧪 No [[tests]] defined in {config_path}");
+        return Ok(());
+    }
+
+    println!("🧪 Running {} config test(s)\n", config.tests.len());
+    let outcomes = run_config_tests(&config)?;
+    for outcome in &outcomes {
+        let suggested = outcome.suggested.as_ref().map(|s| format!(" → {s}")).unwrap_or_default();
+        if outcome.passed() {
+            println!("  ✅ {} ({}{suggested})", outcome.command, outcome.actual.as_str());
+            continue;
+        }
+        println!("  ❌ {}", outcome.command);
+        for mismatch in &outcome.mismatches {
+            println!("       - {}: {}", mismatch.field, mismatch.expected);
+            println!("       + {}: {}", mismatch.field, mismatch.actual);
+        }
+    }
+
+    let failed = outcomes.iter().filter(|outcome| !outcome.passed()).count();
+    if failed > 0 {
+        println!("\n📊 {failed} of {} tests failed", outcomes.len());
+        std::process::exit(1);
+    }
+    println!("\n📊 All {} tests passed", outcomes.len());
+    Ok(())
+}
+
+/// Replays a scenario file through the hooks, printing each step's decision.
+///
+/// Exits with status 1 if any step's `expect` or `suggests` is not met, so
+/// scenarios committed to the repository can run in CI.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `file` - YAML scenario file
+/// * `replace_mode` - Run the hooks with `--replace`
+fn run_simulate_session(config_path: &str, file: &str, replace_mode: bool) -> Result<()> {
+    let content = fs::read_to_string(file).io_context(|| format!("Failed to read scenario {file}"))?;
+    let scenario = Scenario::parse(&content, Path::new(file))?;
+    let exe = std::env::current_exe().io_context(|| "Failed to locate the claude-hook-advisor binary".to_string())?;
+
+    let mut hook_args = vec!["--config".to_string(), config_path.to_string()];
+    if replace_mode {
+        hook_args.push("--replace".to_string());
+    }
+
+    println!("🎬 Scenario: {} ({} steps)\n", scenario.name.as_deref().unwrap_or(file), scenario.steps.len());
+    let reports = run_scenario(&exe, &hook_args, &scenario)?;
+
+    for (index, report) in reports.iter().enumerate() {
+        let icon = match report.decision {
+            Decision::Allow => "✅",
+            Decision::Warn => "⚠️ ",
+            Decision::Ask => "❓",
+            Decision::Block => "❌",
+            Decision::Replace => "🔄",
+        };
+        println!("  {:>2}. {icon} {:<7} {}", index + 1, report.decision, report.input);
+        if let Some(message) = &report.message {
+            println!("        {message}");
+        }
+        for failure in &report.failures {
+            println!("        ✗ {failure}");
+        }
+    }
+
+    let failed = reports.iter().filter(|report| !report.failures.is_empty()).count();
+    if failed > 0 {
+        println!("\n📊 {failed} of {} steps did not behave as expected", reports.len());
+        std::process::exit(1);
+    }
+    println!("\n📊 All {} steps behaved as expected", reports.len());
+    Ok(())
+}
+
+/// Prints suggestion acceptance rates recorded by the hooks in this project.
+#[cfg(feature = "analytics")]
+fn run_stats() -> Result<()> {
+    let sessions = crate::state::load_all_sessions(Path::new("."));
+    let stats = crate::analytics::acceptance_by_pattern(&sessions);
+
+    let patterns: Vec<serde_json::Value> = stats
+        .iter()
+        .map(|(pattern, stat)| {
+            json!({
+                "pattern": pattern,
+                "suggested": stat.suggested,
+                "accepted": stat.accepted,
+                "succeeded": stat.succeeded,
+                "failed": stat.failed,
+                "acceptance_rate": stat.acceptance_rate(),
+                "success_rate": stat.success_rate(),
+                "sample_rate": stat.sample_rate(),
+                "estimated_suggested": stat.estimated.round(),
+            })
+        })
+        .collect();
+    emit_json(&json!({ "sessions": sessions.len(), "patterns": patterns }))?;
+
+    if stats.is_empty() {
+        say!("📊 No suggestions recorded yet in {STATE_DIR}");
+        return Ok(());
+    }
+
+    say!("📊 Suggestion acceptance across {} sessions\n", sessions.len());
+    say!("   {}", ui::header(format!("{:<30} {:>9} {:>9} {:>9} {:>9} {:>9}", "Pattern", "Suggested", "Accepted", "Rate", "Success", "Sampled")));
+    for (pattern, stat) in &stats {
+        let sampled = stat.sample_rate().map_or_else(|| "all".to_string(), |rate| format!("{:.0}%", rate * 100.0));
+        say!(
+            "   {:<30} {:>9} {:>9} {:>8.0}% {:>8.0}% {:>9}",
+            truncate(pattern, 30),
+            stat.suggested,
+            stat.accepted,
+            stat.acceptance_rate(),
+            stat.success_rate(),
+            sampled
+        );
+    }
+    let sampled: Vec<String> = stats
+        .iter()
+        .filter(|(_, stat)| stat.sample_rate().is_some())
+        .map(|(pattern, stat)| format!("{pattern} (about {:.0})", stat.estimated))
+        .collect();
+    if !sampled.is_empty() {
+        say!("\n   Sampled rules record only a share of their suggestions; rates are unaffected.");
+        say!("   Estimated suggestions in all: {}", sampled.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Proposes `[commands]` mappings inferred from the project and its session
+/// history, and adds the ones the user accepts.
+///
+/// In a terminal each proposal can be accepted or rejected; with `--yes`
+/// all are added, and otherwise they are only listed.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file to add mappings to
+/// * `assume_yes` - Add every proposal without asking
+#[cfg(feature = "analytics")]
+fn run_infer_rules(config_path: &str, assume_yes: bool) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let sessions = crate::state::load_all_sessions(Path::new("."));
+    let proposals = crate::infer::infer_mappings(&config, Path::new("."), &sessions);
+
+    if proposals.is_empty() {
+        say!("🔍 Nothing to propose: no lockfile, tool config or session history points to a tool that isn't mapped yet");
+        return emit_json(&json!({ "proposals": proposals, "added": 0 }));
+    }
+    say!("💡 {} mapping(s) inferred from the project and {} recorded session(s)\n", proposals.len(), sessions.len());
+    let items: Vec<String> = proposals
+        .iter()
+        .map(|proposal| format!("{} → {}  ({})", proposal.pattern, proposal.replacement, proposal.reason))
+        .collect();
+
+    let Some(accepted) = select_items(&items, "Select the mappings to add to [commands]", assume_yes)? else {
+        return emit_json(&json!({ "proposals": proposals, "added": 0 }));
+    };
+
+    if !accepted.is_empty() {
+        let mut editor = ConfigEditor::open(Path::new(config_path))?;
+        for &index in &accepted {
+            editor.set_value(&["commands"], &proposals[index].pattern, proposals[index].replacement.as_str())?;
+        }
+        editor.save()?;
+    }
+    say!("✅ Added {} mapping(s) to [commands] in {config_path}", accepted.len());
+    emit_json(&json!({ "proposals": proposals, "added": accepted.len() }))
+}
+
+/// Answers hook inputs over HTTP until the process is stopped.
+#[cfg(feature = "server")]
+fn run_serve(addr: &str, config_path: &str, replace_mode: bool, allow_remote: bool) -> Result<()> {
+    crate::server::serve(addr, config_path, replace_mode, allow_remote)
+}
+
+/// Runs the MCP server on stdin and stdout until stdin closes.
+#[cfg(feature = "mcp")]
+fn run_mcp(config_path: &str) -> Result<()> {
+    crate::mcp::run_mcp_server(|| load_conventions_config(config_path))
+}
+
+/// The error for a command whose feature this build leaves out; its flags
+/// are hidden from `--help` too.
+#[cfg_attr(all(feature = "analytics", feature = "bench", feature = "mcp", feature = "server"), allow(dead_code))]
+fn missing_feature(command: &str, feature: &str) -> AdvisorError {
+    AdvisorError::other(
+        format!("{command} is not available"),
+        format!("this build leaves out the `{feature}` feature (see --build-info); rebuild with `--features {feature}`"),
+    )
+}
+
+#[cfg(not(feature = "server"))]
+fn run_serve(_addr: &str, _config_path: &str, _replace_mode: bool, _allow_remote: bool) -> Result<()> {
+    Err(missing_feature("--serve", "server"))
+}
+
+#[cfg(not(feature = "mcp"))]
+fn run_mcp(_config_path: &str) -> Result<()> {
+    Err(missing_feature("--mcp", "mcp"))
+}
+
+#[cfg(not(feature = "bench"))]
+fn run_bench_rules(_config_path: &str, _file: Option<&str>) -> Result<()> {
+    Err(missing_feature("--bench-rules", "bench"))
+}
+
+#[cfg(not(feature = "bench"))]
+fn run_bench(_config_path: &str, _count: usize) -> Result<()> {
+    Err(missing_feature("--bench", "bench"))
+}
+
+#[cfg(not(feature = "analytics"))]
+fn run_report(_config_path: &str, _matches: &clap::ArgMatches) -> Result<()> {
+    Err(missing_feature("report", "analytics"))
+}
+
+#[cfg(not(feature = "analytics"))]
+fn run_infer_rules(_config_path: &str, _assume_yes: bool) -> Result<()> {
+    Err(missing_feature("--infer-rules", "analytics"))
+}
+
+#[cfg(not(feature = "analytics"))]
+fn run_stats() -> Result<()> {
+    Err(missing_feature("--stats", "analytics"))
+}
+
+/// Prints the hook events, input fields and schema versions this build handles.
+fn print_schema_support() {
+    let versions: Vec<&str> = SchemaVersion::ALL.iter().map(|v| v.name()).collect();
+    let events: Vec<&str> = HOOK_EVENTS.iter().map(|(event, _)| *event).collect();
+
+    println!("📋 Hook input schema support");
+    println!("   Schema versions: {}", versions.join(", "));
+    println!("   Events: {}", events.join(", "));
+    println!("   Unknown fields and events: ignored\n");
+    println!("   {:<25} {:<9} {:<30} Also accepted as", "Field", "Required", "Events");
+    for field in SCHEMA_FIELDS {
+        let events = if field.events.is_empty() { "all".to_string() } else { field.events.join(", ") };
+        let legacy = if field.legacy_names.is_empty() { "-".to_string() } else { field.legacy_names.join(", ") };
+        println!(
+            "   {:<25} {:<9} {:<30} {}",
+            field.name,
+            if field.required { "yes" } else { "no" },
+            events,
+            legacy
+        );
+    }
+}
+
+/// Removes stale sessions and compacts the audit log, as the hooks do over time.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file, for the `[audit]` retention limits
+fn run_gc_state(config_path: &str) -> Result<()> {
+    let project_dir = Path::new(".");
+    let config = load_cli_config(config_path)?;
+
+    let removed = remove_sessions(project_dir, chrono::Duration::days(STALE_SESSION_DAYS))?;
+    println!("🧹 Removed {} session(s) idle for more than {STALE_SESSION_DAYS} days", removed.len());
+
+    let Some(log) = AuditLog::from_config(&config.audit, project_dir)? else {
+        println!("ℹ️  [audit] is off; no audit log to compact");
+        return Ok(());
+    };
+    if config.audit.max_age_days.is_none() && config.audit.max_rows.is_none() {
+        println!("ℹ️  No [audit] max_age_days or max_rows set; the audit log is kept in full");
+        return Ok(());
+    }
+    let compaction = log.compact(chrono::Utc::now())?;
+    println!(
+        "🗜️  Rolled {} audit record(s) into daily counts; {} kept",
+        compaction.rolled_up, compaction.kept
+    );
+    Ok(())
+}
+
+/// Lists the sessions recorded in this project, or removes stale ones.
+///
+/// # Arguments
+/// * `action` - "list" or "clean"
+fn run_sessions(action: &str) -> Result<()> {
+    let project_dir = Path::new(".");
+
+    if action == "clean" {
+        let removed = remove_sessions(project_dir, chrono::Duration::days(STALE_SESSION_DAYS))?;
+        println!("🧹 Removed {} session(s) idle for more than {STALE_SESSION_DAYS} days", removed.len());
+        return Ok(());
+    }
+
+    let sessions = list_sessions(project_dir);
+    if sessions.is_empty() {
+        println!("🗂️  No sessions recorded yet in {STATE_DIR}");
+        return Ok(());
+    }
+
+    println!("🗂️  {} session(s) in {STATE_DIR}\n", sessions.len());
+    println!("   {:<38} {:<17} {:>9} {:>11}", "Session", "Last active", "Commands", "Suggestions");
+    for session in &sessions {
+        println!(
+            "   {:<38} {:<17} {:>9} {:>11}",
+            truncate(&session.id, 38),
+            session.modified.format("%Y-%m-%d %H:%M"),
+            session.state.history.len(),
+            session.state.suggestions.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints every path the advisor uses in this project, as text or, with
+/// `--format json`, JSON.
+///
 /// # Arguments
-/// * `config_path` - Path to the configuration file
-/// 
-/// # Returns
-/// * `Ok(())` - Installation completed successfully
-/// * `Err` - If any installation step fails
-fn run_smart_installation(config_path: &str) -> Result<()> {
-    println!("🚀 Claude Hook Advisor Installation");
-    println!("===================================\n");
-    
-    // Step 1: Check and install hooks if needed
-    if hooks_already_exist()? {
-        println!("✅ Hooks already installed in Claude Code settings");
-    } else {
-        println!("📋 Installing hooks into Claude Code settings...");
-        crate::installer::install_claude_hooks()?;
-        println!("✅ Hooks installed successfully");
-    }
-    
-    // Step 2: Handle config file
-    println!("\n📄 Checking configuration file...");
-    if Path::new(config_path).exists() {
-        println!("✅ Config file exists: {config_path}");
-        ensure_config_sections(config_path)?;
-    } else {
-        println!("📝 Creating new config file: {config_path}");
-        create_smart_config(config_path)?;
-    }
-    
-    println!("\n🎉 Installation complete! Claude Hook Advisor is ready to use.");
-    println!("💡 You can now use semantic directory references in Claude Code conversations.");
-    
+/// * `config_path` - Path to the configuration file, resolved as hooks resolve it
+fn run_where(config_path: &str) -> Result<()> {
+    let cwd = std::env::current_dir().io_context(|| "Failed to read the working directory".to_string())?;
+    let locations = crate::locations::locate(&cwd, config_path);
+    say!("{}", crate::locations::describe_locations(&locations));
+    emit_json(&locations)
+}
+
+/// Prints what this binary was built from, as text or, with `--format json`, JSON.
+fn run_build_info() -> Result<()> {
+    let info = crate::build_info::BuildInfo::current();
+    say!("{}", crate::build_info::describe_build_info(&info));
+    emit_json(&info)
+}
+
+/// Sends the telemetry events queued in this project.
+fn run_flush_telemetry(config_path: &str) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let sent = flush(&config.telemetry, Path::new("."))?;
+    println!("📤 Sent {sent} queued telemetry event(s)");
     Ok(())
 }
 
-/// Checks if Claude Hook Advisor hooks are already installed in Claude Code settings.
-/// 
-/// # Returns
-/// * `Ok(true)` - Hooks are already installed
-/// * `Ok(false)` - Hooks are not installed
-/// * `Err` - If settings file cannot be read or parsed
-fn hooks_already_exist() -> Result<bool> {
-    // Check for settings files in order of preference
-    let local_settings = Path::new(".claude/settings.local.json");
-    let shared_settings = Path::new(".claude/settings.json");
-    
-    let settings_path = if local_settings.exists() {
-        local_settings
-    } else if shared_settings.exists() {
-        shared_settings
-    } else {
-        return Ok(false); // No settings file means no hooks
+/// Sends a made-up blocked command to the `[notifications]` webhook, so the
+/// channel and format can be checked before a real block happens.
+fn run_test_notification(config_path: &str) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    if config.notifications.webhook.is_none() {
+        return Err(ConfigError::InvalidFormat("No [notifications] webhook is configured".to_string()).into());
+    }
+    let event = AuditEvent {
+        timestamp: chrono::Utc::now(),
+        session_id: "test-notification".to_string(),
+        tool_use_id: None,
+        command: "rm -rf / # test message from claude-hook-advisor".to_string(),
+        decision: "block".to_string(),
+        pattern: Some("rm -rf".to_string()),
+        suggested: None,
     };
-    
-    // Read and parse settings file
-    let settings_content = fs::read_to_string(settings_path)
-        .with_context(|| format!("Failed to read {}", settings_path.display()))?;
-    
-    let settings: serde_json::Value = serde_json::from_str(&settings_content)
-        .with_context(|| "Failed to parse Claude settings JSON")?;
-    
-    // Check if our hooks exist
-    if let Some(hooks) = settings.get("hooks").and_then(|h| h.as_object()) {
-        // Check PreToolUse and UserPromptSubmit hooks
-        for event_name in &["PreToolUse", "UserPromptSubmit"] {
-            if let Some(event_hooks) = hooks.get(*event_name).and_then(|h| h.as_array()) {
-                for hook_group in event_hooks {
-                    if let Some(hooks_array) = hook_group.get("hooks").and_then(|h| h.as_array()) {
-                        for hook in hooks_array {
-                            if let Some(command) = hook.get("command").and_then(|c| c.as_str()) {
-                                if command.contains("claude-hook-advisor") {
-                                    return Ok(true);
-                                }
-                            }
-                        }
-                    }
-                }
+    let notifications = Notifications { decisions: vec![event.decision.clone()], ..config.notifications };
+    notify(&notifications, &config.audit.redact, Path::new("."), &event)?;
+    println!("📣 Sent a test notification");
+    Ok(())
+}
+
+/// Turns anonymous usage telemetry on or off, or shows its state and queue.
+///
+/// # Arguments
+/// * `action` - "on", "off" or "status"
+fn run_usage_telemetry(action: &str) -> Result<()> {
+    let dir = usage::usage_dir().ok_or_else(|| {
+        ConfigError::InvalidFormat("Cannot locate the user config directory; set HOME or XDG_CONFIG_HOME".to_string())
+    })?;
+
+    match action {
+        "on" => {
+            usage::set_enabled(&dir, true)?;
+            println!("📊 Anonymous usage telemetry is on");
+            println!("   Only counts of hook events, decisions, features and built-in rule packs are kept,");
+            println!("   with the version and OS. No commands, paths or names ever leave this machine.");
+            println!("   Inspect the queue with `claude-hook-advisor --telemetry status`");
+        }
+        "off" => {
+            usage::set_enabled(&dir, false)?;
+            println!("🔕 Anonymous usage telemetry is off and the queued counters were deleted");
+        }
+        _ => {
+            let state = if usage::is_enabled(&dir) { "on" } else { "off" };
+            println!("📊 Anonymous usage telemetry: {state}");
+            println!("   Directory: {}", dir.display());
+            match usage::USAGE_ENDPOINT {
+                Some(endpoint) => println!("   Counters are sent daily to {endpoint}"),
+                None => println!("   This build has no collector; counters stay on this machine"),
+            }
+            match usage::queued(&dir) {
+                Some(report) => println!("\n{}", serde_json::to_string_pretty(&report)?),
+                None => println!("\n   Nothing queued"),
             }
         }
     }
-    
-    Ok(false)
+    Ok(())
 }
 
-/// Creates a smart configuration file with project-specific command mappings.
-/// Detects the project type and generates appropriate command mappings.
-/// Directory aliases are provided as commented examples only.
-/// 
+/// Shortens `text` to at most `width` characters for table output.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let prefix: String = text.chars().take(width - 1).collect();
+        format!("{prefix}…")
+    }
+}
+
+/// Shows the decision the PreToolUse hook would make for a single command.
+///
 /// # Arguments
-/// * `config_path` - Path where to create the configuration file
-/// 
-/// # Returns
-/// * `Ok(())` - Configuration created successfully
-/// * `Err` - If file writing fails
-fn create_smart_config(config_path: &str) -> Result<()> {
-    // Detect project type
-    let project_type = detect_project_type()?;
-    println!("🔍 Detected project type: {project_type}");
-    
-    // Get project-specific command mappings
-    let commands = get_commands_for_project_type(&project_type);
-    
-    // Create config structure with actual commands but empty directories
-    let config = Config {
-        commands,
-        semantic_directories: std::collections::HashMap::new(), // Empty - will be comments only
-    };
-    
-    // Generate TOML content
-    let toml_content = toml::to_string_pretty(&config)
-        .with_context(|| "Failed to serialize configuration to TOML")?;
-    
-    // Build the complete config with header and directory examples as comments
-    let _project_name = get_project_name();
-    let final_content = format!(r#"# Claude Hook Advisor Configuration
-# Auto-generated for {project_type} project
-# This file configures command mappings and semantic directory aliases
-# for use with Claude Code integration.
+/// * `config_path` - Path to the configuration file
+/// * `command` - Command to evaluate
+/// * `copy` - Copy the suggested command to the clipboard
+fn run_dry_run(config_path: &str, command: &str, copy: bool) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let policy = Policy::outside_session(&config)?;
 
-{toml_content}
-# Semantic directory aliases - natural language directory references
-# Uncomment and customize these examples:
-# docs = "~/Documents/Documentation"
-# central_docs = "~/Documents/Documentation"
-# project_docs = "~/Documents/Documentation/my-project"
-# claude_docs = "~/Documents/Documentation/claude"
-"#);
-    
-    fs::write(config_path, final_content)
-        .with_context(|| format!("Failed to write config file: {config_path}"))?;
-    
-    println!("✅ Created smart configuration for {project_type} project");
-    
-    // Show what was configured
-    if !config.commands.is_empty() {
-        println!("📝 Command mappings configured:");
-        for (from, to) in &config.commands {
-            println!("   {from} → {to}");
+    println!("🔍 Dry run: {command}");
+    let expanded = expand_aliases(&config.aliases, command);
+    if expanded != command {
+        println!("🔤 With aliases expanded: {expanded}");
+    }
+    println!();
+    let command: &str = &expanded;
+
+    let rule_match = policy.evaluate(command)?;
+    match &rule_match {
+        Some(rule_match) => {
+            let verdict = match rule_match.severity {
+                Severity::Warn => "⚠️  Would warn",
+                Severity::Ask => "❓ Would ask",
+                Severity::Block => "❌ Would block",
+            };
+            println!("{verdict}: {}", rule_match.reason);
+            if let Some(suggested) = &rule_match.suggested_command {
+                println!("   → {suggested}");
+            }
+            copy_suggestion(copy, rule_match.suggested_command.as_deref());
+        }
+        None => {
+            println!("✅ Would allow");
+            copy_suggestion(copy, None);
         }
-    } else {
-        println!("📝 No specific command mappings for {project_type} - using general alternatives");
     }
-    
-    Ok(())
+
+    exit_with_decision(rule_match.map(|m| m.severity))
 }
 
-/// Detects the project type by examining files in the current directory.
-/// 
-/// # Returns
-/// * `Ok(String)` - Detected project type ("Node.js", "Python", "Rust", etc.)
-/// * `Err` - If current directory cannot be accessed
-fn detect_project_type() -> Result<String> {
-    let current_dir = std::env::current_dir()?;
+/// Explains how a command is parsed and which rules match it.
+///
+/// Lists each simple command after wrapper stripping, then every matching rule
+/// or mapping in evaluation order. Only the first match is applied by the hook.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+/// * `command` - Command to explain
+/// * `copy` - Copy the suggested command to the clipboard
+fn run_explain(config_path: &str, command: &str, copy: bool) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let rules = CompiledRules::new(&config, &RuleContext::default())?;
 
-    // Check for various project indicators
-    if current_dir.join("package.json").exists() {
-        return Ok("Node.js".to_string());
+    println!("🔍 Explaining: {command}");
+    let expanded = expand_aliases(&config.aliases, command);
+    if expanded != command {
+        println!("🔤 With aliases expanded: {expanded}");
+    }
+    println!();
+    let command: &str = &expanded;
+    println!("Simple commands:");
+    for (index, segment) in split_segments(command).into_iter().enumerate() {
+        let text = &command[segment];
+        let normalized = normalize(text);
+        let underlying = &text[normalized.command_start..];
+        if normalized.wrappers.is_empty() {
+            println!("  {}. {underlying}", index + 1);
+        } else {
+            println!("  {}. {underlying}  (wrappers: {})", index + 1, normalized.wrappers.join(", "));
+        }
+        if config.matching.check_substitutions {
+            for inner in substitutions(text) {
+                println!("     ↳ substitution: {}", text[inner].trim());
+            }
+        }
     }
+    println!();
 
-    if current_dir.join("requirements.txt").exists()
-        || current_dir.join("pyproject.toml").exists()
-        || current_dir.join("setup.py").exists()
-    {
-        return Ok("Python".to_string());
+    let matches = rules.explain(command);
+    if matches.is_empty() {
+        println!("✅ No rules or mappings match");
+        copy_suggestion(copy, None);
+        return Ok(());
     }
 
-    if current_dir.join("Cargo.toml").exists() {
-        return Ok("Rust".to_string());
+    println!("Matching rules (first match applies):");
+    for (index, rule_match) in matches.iter().enumerate() {
+        let marker = if index == 0 { "▶" } else { " " };
+        println!("  {marker} {}", rule_match.pattern);
+        println!("     {}", rule_match.reason);
     }
 
-    if current_dir.join("go.mod").exists() {
-        return Ok("Go".to_string());
+    let suggested = matches[0].suggested_command.as_deref();
+    if let Some(suggested) = suggested {
+        println!("\n💡 Suggested: {suggested}");
     }
+    copy_suggestion(copy, suggested);
 
-    if current_dir.join("pom.xml").exists() || current_dir.join("build.gradle").exists() {
-        return Ok("Java".to_string());
+    exit_with_decision(Some(matches[0].severity))
+}
+
+/// Exits with the stable exit code for the most severe decision, if any.
+///
+/// Returns normally (exit code 0) when nothing matched.
+fn exit_with_decision(severity: Option<Severity>) -> Result<()> {
+    match severity {
+        Some(severity) => std::process::exit(severity.exit_code()),
+        None => Ok(()),
     }
+}
 
-    if current_dir.join("Dockerfile").exists() {
-        return Ok("Docker".to_string());
+/// Copies the suggested command to the system clipboard when `--copy` is given.
+///
+/// Clipboard failures (e.g. no display server) are reported but not fatal.
+fn copy_suggestion(copy: bool, suggested: Option<&str>) {
+    if !copy {
+        return;
     }
 
-    Ok("General".to_string())
-}
+    let Some(suggested) = suggested else {
+        println!("ℹ️  Nothing to copy: no replacement suggested");
+        return;
+    };
 
-/// Creates project-specific command mappings based on detected project type.
-/// 
-/// # Arguments
-/// * `project_type` - The detected project type
-/// 
-/// # Returns
-/// * `HashMap<String, String>` - Command mappings for the project
-fn get_commands_for_project_type(project_type: &str) -> std::collections::HashMap<String, String> {
-    let mut commands = std::collections::HashMap::new();
-    
-    match project_type {
-        "Node.js" => {
-            commands.insert("npm".to_string(), "bun".to_string());
-            commands.insert("yarn".to_string(), "bun".to_string());
-            commands.insert("pnpm".to_string(), "bun".to_string());
-            commands.insert("npx".to_string(), "bunx".to_string());
-            commands.insert("npm start".to_string(), "bun dev".to_string());
-            commands.insert("npm test".to_string(), "bun test".to_string());
-            commands.insert("npm run build".to_string(), "bun run build".to_string());
-        }
-        "Python" => {
-            commands.insert("pip".to_string(), "uv pip".to_string());
-            commands.insert("pip install".to_string(), "uv add".to_string());
-            commands.insert("pip uninstall".to_string(), "uv remove".to_string());
-            commands.insert("python".to_string(), "uv run python".to_string());
-            commands.insert("python -m".to_string(), "uv run python -m".to_string());
-        }
-        "Rust" => {
-            commands.insert("cargo check".to_string(), "cargo clippy".to_string());
-            commands.insert("cargo test".to_string(), "cargo test -- --nocapture".to_string());
-        }
-        "Go" => {
-            commands.insert("go run".to_string(), "go run -race".to_string());
-            commands.insert("go test".to_string(), "go test -v".to_string());
-        }
-        "Java" => {
-            commands.insert("mvn".to_string(), "./mvnw".to_string());
-            commands.insert("gradle".to_string(), "./gradlew".to_string());
-        }
-        "Docker" => {
-            commands.insert("docker".to_string(), "podman".to_string());
-            commands.insert("docker-compose".to_string(), "podman-compose".to_string());
-        }
-        _ => {
-            // General project - modern CLI alternatives
-            commands.insert("cat".to_string(), "bat".to_string());
-            commands.insert("ls".to_string(), "eza".to_string());
-            commands.insert("grep".to_string(), "rg".to_string());
-            commands.insert("find".to_string(), "fd".to_string());
-        }
+    match copy_to_clipboard(suggested) {
+        Ok(()) => println!("📋 Copied to clipboard"),
+        Err(e) => eprintln!("Warning: Could not copy to clipboard: {e}"),
     }
-    
-    // Add common safety and modern tool mappings for all project types
-    commands.insert("curl".to_string(), "curl -L".to_string());
-    commands.insert("rm".to_string(), "trash".to_string());
-    commands.insert("rm -rf".to_string(), "echo 'Use trash command for safety'".to_string());
-    
-    commands
 }
 
-/// Gets the current project name for variable substitution.
-fn get_project_name() -> String {
-    std::env::current_dir()
-        .ok()
-        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
-        .unwrap_or_else(|| "project".to_string())
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(text: &str) -> std::result::Result<(), arboard::Error> {
+    arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text))
 }
 
+#[cfg(not(feature = "clipboard"))]
+fn copy_to_clipboard(_text: &str) -> std::result::Result<(), &'static str> {
+    Err("built without the `clipboard` feature")
+}
 
-/// Ensures required sections exist in an existing config file.
-/// 
+/// Lints shell scripts and CI files with the configured rules.
+///
+/// Prints a diagnostic per violating command and exits with `EXIT_BLOCK` or
+/// `EXIT_WARN` for the most severe one, so it can gate CI pipelines.
+///
 /// # Arguments
 /// * `config_path` - Path to the configuration file
-/// 
-/// # Returns
-/// * `Ok(())` - Configuration updated successfully
-/// * `Err` - If file operations fail
-fn ensure_config_sections(config_path: &str) -> Result<()> {
-    let mut config_content = fs::read_to_string(config_path)
-        .with_context(|| format!("Failed to read config file: {config_path}"))?;
-    
-    let mut needs_update = false;
-    
-    // Check and add missing sections
-    if !config_content.contains("[commands]") {
-        config_content.push_str("\n# Command mappings - suggest alternatives when Claude Code runs these commands\n");
-        config_content.push_str("[commands]\n");
-        config_content.push_str("# npm = \"bun\"          # Suggest 'bun' instead of 'npm'\n");
-        config_content.push_str("# yarn = \"bun\"         # Suggest 'bun' instead of 'yarn'\n");
-        config_content.push_str("# npx = \"bunx\"         # Suggest 'bunx' instead of 'npx'\n");
-        config_content.push_str("# grep = \"rg\"          # Suggest 'rg' (ripgrep) instead of 'grep'\n\n");
-        needs_update = true;
-        println!("✅ Added [commands] section with examples");
+/// * `files` - Scripts or CI workflow files to check
+fn run_lint_script(config_path: &str, files: &[&String]) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+
+    let mut diagnostics = Vec::new();
+    for file in files {
+        let file_diagnostics = lint_file(&config, Path::new(file))?;
+        print_diagnostics(file, &file_diagnostics);
+        diagnostics.extend(file_diagnostics);
     }
-    
-    if !config_content.contains("[semantic_directories]") {
-        config_content.push_str("# Semantic directory aliases - natural language directory references\n");
-        config_content.push_str("[semantic_directories]\n");
-        config_content.push_str("docs = \"~/Documents/Documentation\"\n");
-        config_content.push_str("central_docs = \"~/Documents/Documentation\"\n");
-        config_content.push_str("project_docs = \"~/Documents/Documentation/my-project\"\n");
-        config_content.push_str("claude_docs = \"~/Documents/Documentation/claude\"\n\n");
-        needs_update = true;
-        println!("✅ Added [semantic_directories] section with default aliases");
+
+    print_lint_summary(&diagnostics, files.len());
+    exit_with_decision(diagnostics.iter().map(|d| d.rule.severity).max())
+}
+
+/// Runs as a git pre-commit hook, linting the staged version of each changed
+/// shell script and CI file.
+fn run_git_hook(config_path: &str) -> Result<()> {
+    let files = staged_lint_targets()?;
+    if files.is_empty() {
+        return Ok(());
     }
-    
-    
-    if needs_update {
-        fs::write(config_path, config_content)
-            .with_context(|| format!("Failed to update config file: {config_path}"))?;
-        println!("💾 Configuration file updated");
-    } else {
-        println!("✅ All required sections already present");
+
+    let config = load_cli_config(config_path)?;
+
+    let mut diagnostics = Vec::new();
+    for file in &files {
+        let content = read_staged_file(file)?;
+        let file_diagnostics = lint_source(&config, Path::new(file), &content)?;
+        print_diagnostics(file, &file_diagnostics);
+        diagnostics.extend(file_diagnostics);
+    }
+
+    print_lint_summary(&diagnostics, files.len());
+
+    // Only blocking rules abort the commit; warnings are just reported
+    if diagnostics.iter().any(|d| d.rule.severity == Severity::Block) {
+        std::process::exit(EXIT_BLOCK);
     }
-    
     Ok(())
 }
 
+fn print_diagnostics(file: &str, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        let icon = match diagnostic.rule.severity {
+            Severity::Warn => "⚠️ ",
+            Severity::Ask => "❓",
+            Severity::Block => "❌",
+        };
+        println!("{file}:{}: {}", diagnostic.line, diagnostic.command);
+        println!("   {icon} {}", diagnostic.rule.reason);
+    }
+}
+
+fn print_lint_summary(diagnostics: &[Diagnostic], file_count: usize) {
+    if diagnostics.is_empty() {
+        println!("✅ No violations in {file_count} file(s)");
+    } else {
+        println!("\n📊 {} violation(s) in {file_count} file(s)", diagnostics.len());
+    }
+}
 
 /// Prints comprehensive help information including new configuration features.
 fn print_help() {
@@ -410,16 +2435,78 @@ fn print_help() {
     println!();
     println!("Installation:");
     println!("  --install                 Install Claude Hook Advisor: configure hooks and create/update config file");
+    println!("  --quickstart              Install with detected defaults and run a self-test");
+    println!("  --recursive <ROOT>        With --install, set up every repository found under ROOT");
+    println!("  -y, --yes                 Skip installer prompts and use detected defaults");
+    println!("  --install-git-hook        Install a pre-commit hook that lints staged scripts and CI files");
+    println!("  --install-commands        Add /conventions and /advisor-stats slash commands to .claude/commands/");
     println!("  --uninstall               Remove Claude Hook Advisor hooks from Claude Code settings");
+    println!("  --doctor                  Check that the registered hooks can run, and repair stale paths");
     println!();
     println!("Command Mapping:");
     println!("  --hook                    Run as a Claude Code hook");
+    println!("  --hook --stream           Answer one hook input per stdin line, one JSON line each");
+    if cfg!(feature = "server") {
+        println!("  --serve <ADDR>            Serve policy decisions over HTTP (POST /evaluate), unauthenticated");
+        println!("  --allow-remote            With --serve, allow a non-loopback ADDR");
+    }
+    if cfg!(feature = "mcp") {
+        println!("  --mcp                     Run as an MCP server so Claude can query conventions");
+    }
+    println!("  --hook-mode <MODE>        Run as a claude hook or git pre-commit hook");
+    println!("  --no-cache                Don't use the cached parse of the config file");
+    println!("  --dry-run <COMMAND>       Show what the hook would decide for COMMAND");
+    println!("  --explain <COMMAND>       Show how COMMAND is parsed and which rules match");
+    if cfg!(feature = "clipboard") {
+        println!("  --copy                    With --dry-run/--explain, copy the suggestion to the clipboard");
+    }
+    println!("  --dry-run-file <FILE>     Check each command in FILE against the configured mappings");
+    if cfg!(feature = "bench") {
+        println!("  --bench-rules [FILE]      Measure rule compile time and per-command latency");
+    }
+    if cfg!(feature = "bench") {
+        println!("  --bench [N]               Report p50/p95 latency and throughput on N synthetic commands");
+    }
+    println!("  --simulate-session <FILE> Replay a YAML scenario of hook events through the hook");
+    println!("  --print-schema-support    Show which hook input fields and versions are understood");
+    println!("  lint-script <FILE>...     Check shell scripts and CI files, exiting 1 on violations");
+    println!("  snooze <PATTERN> <DURATION>  Suspend a rule or mapping for a while, e.g. `snooze npm 1h`");
+    println!("  snooze --list             Show active snoozes");
+    if cfg!(feature = "analytics") {
+        println!("  --stats                   Show acceptance rates of suggested replacements");
+    }
+    if cfg!(feature = "analytics") {
+        println!("  --infer-rules [--yes]     Propose mappings from lockfiles, tool configs and session history");
+    }
+    if cfg!(feature = "analytics") {
+        println!("  report [--week|--days N]  Write a Markdown or HTML (--format html) activity report");
+    }
+    println!("  --sessions [list|clean]   List recorded sessions, or remove stale ones");
+    println!("  --gc-state                Remove stale sessions and compact the audit log");
+    println!("  --flush-telemetry         Send queued telemetry events now");
+    println!("  --test-notification       Send a sample message to the [notifications] webhook");
+    println!("  --telemetry <on|off|status>  Opt in to or out of anonymous usage counters");
     println!();
     println!("Configuration:");
     println!("  -c, --config <FILE>       Path to config file [default: {}]", DEFAULT_CONFIG_FILE);
+    println!("  --no-color, --plain       Print without colors (NO_COLOR is honored too)");
+    println!("  -q, --quiet               Print nothing from management commands; the exit status is the result");
+    println!("  --format <text|json>      Print management command results as JSON");
     println!("  --check-config            Check configuration file status and migration needs");
-    println!("  --migrate-config          Migrate configuration from old file name to new format");
+    println!("  --lint-config             Report mapping loops and replacements other rules block");
+    println!("  --migrate-config          Migrate configuration to the new file name and current schema");
     println!("  --init-config             Create example configuration file");
+    println!("  --list-conventions        Print the command mappings, rules and aliases as Markdown");
+    println!("  --where                   Print the config, settings, state, cache and audit paths in use");
+    println!("  --build-info              Print the commit, target and features of this build");
+    println!("  dirs list                 List semantic directory aliases and where they resolve");
+    println!("  dirs search <TERM>        Find aliases whose name or path contains TERM");
+    println!("  dirs resolve <PHRASE>     Show what the prompt hook would resolve in PHRASE");
+    println!("  --test-config             Run the [[tests]] assertions in the config file");
+    println!("  --toggle-group <GROUP> <on|off>  Enable or disable a [groups.<name>] rule group");
+    println!("  --import-aliases <FILE>   Add shell aliases from FILE (- for stdin) to [aliases]");
+    println!("  --import-permissions [FILE]  Offer Claude Code Bash permissions as rules and [allowed] commands");
+    println!("  --export-permissions [FILE]  Offer rules and [allowed] commands as Claude Code permissions");
     println!();
     println!("Configuration Files:");
     println!("  {}                       New default configuration file name", DEFAULT_CONFIG_FILE);
@@ -427,96 +2514,261 @@ fn print_help() {
     println!();
     println!("Examples:");
     println!("  claude-hook-advisor --install           # Install hooks and create config");
+    println!("  claude-hook-advisor --install --yes     # Install non-interactively");
+    println!("  claude-hook-advisor --quickstart         # Install and verify in one step");
+    println!("  claude-hook-advisor --install --recursive ~/work  # Set up every repo under ~/work");
     println!("  claude-hook-advisor --check-config       # Check configuration status");
     println!("  claude-hook-advisor --migrate-config     # Migrate to new file name");
     println!("  claude-hook-advisor --init-config        # Create example config");
+    println!("  claude-hook-advisor lint-script build.sh # Check a script in CI");
+    println!("  claude-hook-advisor snooze npm 1h        # Let npm through for an hour");
+    println!("  claude-hook-advisor report --week -o retro.md  # Weekly report for a retrospective");
     println!();
     println!("To configure directory aliases and command mappings, edit {} directly.", DEFAULT_CONFIG_FILE);
 }
 
+/// What `--check-config` found, for `--format json`.
+#[derive(Serialize, Default)]
+struct ConfigStatus {
+    config_file: Option<PathBuf>,
+    valid: bool,
+    command_mappings: usize,
+    semantic_directories: usize,
+    user_directories: usize,
+    deprecations: Vec<Deprecation>,
+    /// Things that still work but deserve attention
+    warnings: Vec<String>,
+    problems: Vec<String>,
+}
+
+/// Reports the organization baseline files that apply and whether they load.
+fn check_baseline_files(config: &Config, status: &mut ConfigStatus) {
+    for path in baseline_paths(config) {
+        match load_baseline_file(&path) {
+            Ok((baseline, signed)) => say!(
+                "   🏢 Organization baseline {}: {} enforced rule(s), {} mapping(s), {}",
+                path.display(),
+                baseline.active_rules().len(),
+                baseline.active_commands().len(),
+                if signed { "signature verified" } else { "unsigned" }
+            ),
+            Err(e) => problem(
+                &mut status.problems,
+                format!("Organization baseline {} does not load, so every command is blocked: {e}", path.display()),
+            ),
+        }
+    }
+}
+
 /// Check configuration file status and migration needs.
 fn check_config_status() -> Result<()> {
-    println!("🔍 Configuration Status Check");
-    println!("============================\n");
+    say!("{}\n", ui::heading("🔍 Configuration Status Check"));
+    let mut status = ConfigStatus::default();
 
     match find_config_file() {
         Ok(config_path) => {
-            println!("✅ Configuration file found: {}", config_path.display());
-
-            // Check if it's the old file name
-            if config_path.ends_with(".claude-hook-advisor.toml") {
-                println!("⚠️  Using legacy configuration file name");
-                println!("💡 Consider migrating to the new file name: {}", DEFAULT_CONFIG_FILE);
-                println!("   Run 'claude-hook-advisor --migrate-config' to migrate automatically");
-            } else {
-                println!("✅ Using current configuration file name");
-            }
+            say!("{}", ui::success(format!("✅ Configuration file found: {}", config_path.display())));
+            status.deprecations = report_deprecations(&config_path);
 
             // Try to load and validate the configuration
-            match load_config_from_path(&config_path) {
-                Ok(config) => {
-                    println!("✅ Configuration file is valid");
-                    println!("   📝 {} command mappings defined", config.commands.len());
-                    println!("   📁 {} semantic directories defined", config.semantic_directories.len());
+            let loaded = load_config_from_path(&config_path);
+            status.config_file = Some(config_path);
+            match loaded {
+                Ok(mut config) => {
+                    say!("{}", ui::success("✅ Configuration file is valid"));
+                    status.valid = true;
+                    status.command_mappings = config.commands.len();
+                    status.semantic_directories = config.semantic_directories.len();
+                    say!("   📝 {} command mappings defined", config.commands.len());
+                    say!("   📁 {} semantic directories defined", config.semantic_directories.len());
+                    if let Err(e) = check_user_directories(&mut config, &mut status) {
+                        emit_json(&status)?;
+                        return Err(e);
+                    }
+                    check_directory_targets(&config, &mut status);
+                    check_baseline_files(&config, &mut status);
+                    check_expired_rules(&config, &mut status);
 
                     if config.commands.is_empty() && config.semantic_directories.is_empty() {
-                        println!("💡 Configuration is empty. Add some mappings or run 'claude-hook-advisor --init-config' for examples");
+                        say!("{}", ui::hint("💡 Configuration is empty. Add some mappings or run 'claude-hook-advisor --init-config' for examples"));
                     }
                 }
                 Err(e) => {
-                    println!("❌ Configuration file error: {}", e);
-                    return Err(e.context("Configuration validation failed"));
+                    problem(&mut status.problems, format!("Configuration file error: {e}"));
+                    emit_json(&status)?;
+                    return Err(e);
                 }
             }
         }
         Err(ConfigError::NotFound(_)) => {
-            println!("❌ No configuration file found");
-            println!("💡 Create one with: claude-hook-advisor --init-config");
-            println!("   Or install with: claude-hook-advisor --install");
+            problem(&mut status.problems, "No configuration file found".to_string());
+            say!("{}", ui::hint("💡 Create one with: claude-hook-advisor --init-config"));
+            say!("   Or install with: claude-hook-advisor --install");
         }
         Err(e) => {
-            println!("❌ Error checking configuration: {}", e);
-            return Err(anyhow::anyhow!("Configuration check failed: {}", e));
+            problem(&mut status.problems, format!("Error checking configuration: {e}"));
+            emit_json(&status)?;
+            return Err(e.into());
         }
     }
 
-    // Check for migration needs
-    if let Some(old_config_path) = needs_migration() {
-        println!("⚠️  Migration available:");
-        println!("   📄 Old file: {}", old_config_path.display());
-        println!("   📄 New file: {}", DEFAULT_CONFIG_FILE);
-        println!("   Run 'claude-hook-advisor --migrate-config' to migrate");
-    } else {
-        println!("✅ No migration needed");
+    let failed = !status.problems.is_empty();
+    finish_check(&status, failed)
+}
+
+/// What `--lint-config` found, for `--format json`.
+#[derive(Serialize)]
+struct LintConfigReport {
+    config_file: String,
+    conflicts: Vec<Conflict>,
+}
+
+/// Reports mappings whose replacement loops, is blocked by another rule or is
+/// mapped onward.
+///
+/// # Returns
+/// * `Err` - If the configuration doesn't load, or a conflict is an error
+fn run_lint_config(config_path: &str) -> Result<()> {
+    let config = load_cli_config(config_path)?;
+    let conflicts = find_conflicts(&config)?;
+    let errors = conflicts.iter().filter(|conflict| conflict.is_error()).count();
+
+    say!("{}\n", ui::heading(&format!("🔍 Conflicts in {config_path}")));
+    for conflict in &conflicts {
+        if conflict.is_error() {
+            say!("{}", ui::error(format!("❌ {}", conflict.message)));
+        } else {
+            say!("{}", ui::warning(format!("⚠️  {}", conflict.message)));
+        }
+    }
+    if conflicts.is_empty() {
+        say!("{}", ui::success("✅ No mapping loops or conflicting rules"));
+    }
+    emit_json(&LintConfigReport { config_file: config_path.to_string(), conflicts })?;
+
+    if errors > 0 {
+        let message = format!("{errors} mapping(s) point Claude at a command it can never run");
+        return Err(ConfigError::InvalidFormat(message).into());
+    }
+    Ok(())
+}
+
+/// Prints every deprecated construct in the configuration file, with the
+/// command that upgrades them, and returns them.
+fn report_deprecations(config_path: &Path) -> Vec<Deprecation> {
+    let content = fs::read_to_string(config_path).unwrap_or_default();
+    let deprecations = find_deprecations(config_path, &content);
+    if deprecations.is_empty() {
+        say!("{}", ui::success("✅ No deprecated configuration found"));
+        return deprecations;
     }
 
+    say!("📜 {} deprecated construct(s) found; they still work for now:", deprecations.len());
+    for deprecation in &deprecations {
+        say!("   {}", ui::warning(format!("⚠️  {} → {}", deprecation.construct, deprecation.replacement)));
+    }
+    say!("{}", ui::hint(format!("💡 Run '{MIGRATE_COMMAND}' to upgrade them; the file is backed up first")));
+    deprecations
+}
+
+/// Reports the user-wide semantic directories and the aliases that are
+/// defined in both scopes with different paths.
+fn check_user_directories(config: &mut Config, status: &mut ConfigStatus) -> Result<()> {
+    let Some(path) = user_directories_path().filter(|path| path.exists()) else {
+        return Ok(());
+    };
+    config.user_directories = match load_user_directories() {
+        Ok(directories) => directories,
+        Err(e) => {
+            problem(&mut status.problems, format!("User directories file error: {e}"));
+            return Err(e);
+        }
+    };
+    status.user_directories = config.user_directories.len();
+    say!("   🏠 {} user-wide semantic directories defined in {}", config.user_directories.len(), path.display());
+
+    for collision in alias_collisions(config) {
+        warn(
+            &mut status.warnings,
+            "⚠️",
+            format!(
+                "Alias '{}' is defined in both scopes; the project path {} shadows the user path {}",
+                collision.alias, collision.project_path, collision.user_path
+            ),
+        );
+    }
     Ok(())
 }
 
+/// Lists rules whose `expires` date or `active_between` window has passed.
+fn check_expired_rules(config: &Config, status: &mut ConfigStatus) {
+    let now = chrono::Utc::now();
+    for rule in config.active_rules() {
+        if let Some(expired) = rule.expired_at(now) {
+            warn(
+                &mut status.warnings,
+                "🗓️",
+                format!("Rule '{}' expired after {expired} and no longer applies; consider removing it", rule.pattern),
+            );
+        }
+    }
+}
+
+/// Warns about aliases whose directory doesn't exist or can't be resolved.
+fn check_directory_targets(config: &Config, status: &mut ConfigStatus) {
+    for entry in list_aliases(config).iter().filter(|entry| !entry.shadowed) {
+        match locate_directory(config, &entry.alias) {
+            Ok(resolution) if resolution.status == DirectoryStatus::Missing => {
+                let action = if config.directories.create_missing { "will be created on first use" } else { "does not exist" };
+                warn(&mut status.warnings, "⚠️", format!("Alias '{}' points to {}, which {action}", entry.alias, resolution.canonical_path));
+            }
+            Ok(_) => {}
+            Err(e) => warn(&mut status.warnings, "⚠️", format!("Alias '{}' cannot be resolved: {e}", entry.alias)),
+        }
+    }
+}
+
+/// What `--migrate-config` did, for `--format json`.
+#[derive(Serialize, Default)]
+struct MigrationResult {
+    /// The legacy file renamed to `config_file`, if there was one
+    renamed_from: Option<PathBuf>,
+    /// Copy of the legacy file kept by the rename
+    backup: Option<PathBuf>,
+    /// The configuration file after migrating
+    config_file: Option<PathBuf>,
+    /// The schema upgrade applied to it, if it was out of date
+    schema_upgrade: Option<SchemaUpgrade>,
+    schema_version: u32,
+}
+
 /// Run configuration migration from old file name to new format.
 fn run_config_migration() -> Result<()> {
-    println!("🔄 Configuration Migration");
-    println!("========================\n");
+    say!("{}\n", ui::heading("🔄 Configuration Migration"));
+    let mut result = MigrationResult { schema_version: CONFIG_VERSION, ..MigrationResult::default() };
 
     // Check if migration is needed
     if let Some(old_config_path) = needs_migration() {
-        println!("📄 Migrating from: {}", old_config_path.display());
-        println!("📄 Migrating to: {}", DEFAULT_CONFIG_FILE);
-        println!();
+        say!("📄 Migrating from: {}", old_config_path.display());
+        say!("📄 Migrating to: {}", DEFAULT_CONFIG_FILE);
+        say!();
 
         match migrate_config() {
             Ok(new_path) => {
-                println!("✅ Migration completed successfully!");
-                println!("📄 New configuration file: {}", new_path.display());
-                println!("💾 Backup created: {}.backup", old_config_path.display());
-                println!();
-                println!("🎉 Your configuration has been migrated to the new file name.");
-                println!("   The old file has been backed up with .backup extension.");
-                println!("   You can safely remove the backup file if everything works correctly.");
+                let backup = PathBuf::from(format!("{}{BACKUP_SUFFIX}", old_config_path.display()));
+                say!("{}", ui::success("✅ Migration completed successfully!"));
+                say!("📄 New configuration file: {}", new_path.display());
+                say!("💾 Backup created: {}", backup.display());
+                result.renamed_from = Some(old_config_path);
+                result.backup = Some(backup);
+                say!();
+                say!("🎉 Your configuration has been migrated to the new file name.");
+                say!("   The old file has been backed up with .backup extension.");
+                say!("   You can safely remove the backup file if everything works correctly.");
             }
             Err(e) => {
-                println!("❌ Migration failed: {}", e);
-                return Err(anyhow::anyhow!("Configuration migration failed: {}", e));
+                say!("{}", ui::error(format!("❌ Migration failed: {e}")));
+                return Err(e.into());
             }
         }
     } else {
@@ -525,20 +2777,31 @@ fn run_config_migration() -> Result<()> {
         let new_config = Path::new(DEFAULT_CONFIG_FILE);
 
         if old_config.exists() && new_config.exists() {
-            println!("ℹ️  Both configuration files exist:");
-            println!("   📄 Legacy: {}", old_config.display());
-            println!("   📄 Current: {}", new_config.display());
-            println!("💡 Consider removing the legacy file if it's no longer needed");
+            say!("ℹ️  Both configuration files exist:");
+            say!("   📄 Legacy: {}", old_config.display());
+            say!("   📄 Current: {}", new_config.display());
+            say!("{}", ui::hint("💡 Consider removing the legacy file if it's no longer needed"));
         } else if new_config.exists() {
-            println!("✅ Already using current configuration file format");
-            println!("📄 Configuration file: {}", new_config.display());
+            say!("{}", ui::success("✅ Already using current configuration file format"));
+            say!("📄 Configuration file: {}", new_config.display());
         } else {
-            println!("ℹ️  No legacy configuration file found");
-            println!("💡 Create a new configuration with: claude-hook-advisor --init-config");
+            say!("ℹ️  No legacy configuration file found");
+            say!("{}", ui::hint("💡 Create a new configuration with: claude-hook-advisor --init-config"));
         }
     }
 
-    Ok(())
+    // Then bring the file up to the current schema
+    if let Ok(config_path) = find_config_file() {
+        say!();
+        result.schema_upgrade = upgrade_config_file(&config_path)?;
+        match &result.schema_upgrade {
+            Some(upgrade) => say!("{}", describe_schema_upgrade(&config_path, upgrade)),
+            None => say!("{}", ui::success(format!("✅ {} is up to date with schema version {CONFIG_VERSION}", config_path.display()))),
+        }
+        result.config_file = Some(config_path);
+    }
+
+    emit_json(&result)
 }
 
 /// Create an example configuration file.
@@ -564,7 +2827,7 @@ fn create_example_config() -> Result<()> {
 
         // Create backup of existing file
         let backup_path = config_path.with_extension("toml.backup");
-        fs::copy(config_path, &backup_path).context("Failed to create backup")?;
+        fs::copy(config_path, &backup_path).io_context(|| "Failed to create backup".to_string())?;
         println!("💾 Existing configuration backed up to: {}", backup_path.display());
     }
 
@@ -572,6 +2835,8 @@ fn create_example_config() -> Result<()> {
     let example_config = r#"# Claude Hook Advisor Configuration
 # This file maps commands to preferred alternatives and defines semantic directory aliases
 
+version = 2
+
 [commands]
 # Node.js / JavaScript Development - Prefer Bun over npm/yarn
 npm = "bun"
@@ -618,7 +2883,7 @@ top = "htop"                  # Better process viewer
 "projects" = "~/Projects"
 "#;
 
-    fs::write(config_path, example_config).context("Failed to write configuration file")?;
+    write_atomic(config_path, example_config).io_context(|| "Failed to write configuration file".to_string())?;
 
     println!("✅ Example configuration created: {}", config_path.display());
     println!();
@@ -667,6 +2932,17 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1h"), Some(chrono::Duration::hours(1)));
+        assert_eq!(parse_duration("1h30m"), Some(chrono::Duration::minutes(90)));
+        assert_eq!(parse_duration("2d"), Some(chrono::Duration::days(2)));
+        assert_eq!(parse_duration("45s"), Some(chrono::Duration::seconds(45)));
+        for invalid in ["", "0m", "1", "h", "1x", "1.5h", "-1h"] {
+            assert_eq!(parse_duration(invalid), None, "{invalid}");
+        }
+    }
+
     #[test]
     fn test_hooks_already_exist_no_settings_file() {
         with_temp_dir(|| {
@@ -806,7 +3082,7 @@ mod tests {
         let temp_dir = tempdir().unwrap();
         let config_path = temp_dir.path().join("test-config.toml");
         
-        create_smart_config(config_path.to_str().unwrap()).unwrap();
+        create_config_with_commands(config_path.to_str().unwrap(), "General", HashMap::new(), Vec::new()).unwrap();
         
         let content = fs::read_to_string(&config_path).unwrap();
         
@@ -844,6 +3120,25 @@ mod tests {
         assert!(content.contains("# npm = \"bun\""));
     }
     
+    #[test]
+    fn test_ensure_config_sections_preserves_existing_content() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("test-config.toml");
+
+        // The commented-out header must not count as a [commands] section
+        let existing_config = "# Team config\n# [commands]\n[behavior]\nsilent_allow = true   # keep\n";
+        fs::write(&config_path, existing_config).unwrap();
+
+        ensure_config_sections(config_path.to_str().unwrap()).unwrap();
+
+        let content = fs::read_to_string(&config_path).unwrap();
+        assert!(content.starts_with(existing_config));
+        assert!(content.contains("\n[commands]\n"));
+        let config: Config = toml::from_str(&content).unwrap();
+        assert!(config.behavior.silent_allow);
+        assert_eq!(config.semantic_directories["claude_docs"], "~/Documents/Documentation/claude");
+    }
+
     #[test]
     fn test_ensure_config_sections_all_sections_present() {
         let temp_dir = tempdir().unwrap();
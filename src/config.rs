@@ -1,10 +1,25 @@
 //! Configuration loading and management
 
-use crate::types::{Config, ConfigError, CONFIG_FILE_NAMES, BACKUP_SUFFIX};
-use anyhow::{Context, Result};
+use crate::atomic::{copy_atomic, write_atomic};
+use crate::rules::{CompiledRules, RuleContext};
+use crate::types::{Config, ConfigError, IoContext, Result, Rule, Severity, BACKUP_SUFFIX, CONFIG_FILE_NAMES, DEFAULT_CONFIG_FILE};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, Table};
+
+/// Schema version of the configuration files this build writes. Files
+/// without a `version` key are version 1.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Environment variable in which Claude Code names the project's root directory.
+pub const PROJECT_DIR_ENV: &str = "CLAUDE_PROJECT_DIR";
+
+/// User-wide file of semantic directories shared by every project.
+pub const USER_DIRECTORIES_FILE: &str = "directories.toml";
 
 /// Finds the first available configuration file in the search order.
 ///
@@ -16,8 +31,21 @@ use std::path::{Path, PathBuf};
 /// * `Ok(PathBuf)` - Path to the found configuration file
 /// * `Err(ConfigError::NotFound)` - If no configuration file is found
 pub fn find_config_file() -> Result<PathBuf, ConfigError> {
+    find_config_file_in(Path::new(""))
+}
+
+/// Finds the first available configuration file in `dir`, as
+/// `find_config_file` does in the working directory.
+///
+/// # Arguments
+/// * `dir` - Directory to search, such as the project root; empty for the working directory
+///
+/// # Returns
+/// * `Ok(PathBuf)` - Path to the found configuration file, inside `dir`
+/// * `Err(ConfigError::NotFound)` - If no configuration file is found
+pub fn find_config_file_in(dir: &Path) -> Result<PathBuf, ConfigError> {
     for filename in CONFIG_FILE_NAMES {
-        let path = PathBuf::from(filename);
+        let path = dir.join(filename);
         if path.exists() {
             return Ok(path);
         }
@@ -27,25 +55,99 @@ pub fn find_config_file() -> Result<PathBuf, ConfigError> {
     ))
 }
 
+/// Root directory of the project a hook works on. Claude may run tools in a
+/// subdirectory, so this is `CLAUDE_PROJECT_DIR` when Claude Code sets it,
+/// and `cwd` otherwise.
+pub fn project_root(cwd: &Path) -> PathBuf {
+    project_root_from(env::var_os(PROJECT_DIR_ENV), cwd)
+}
+
+fn project_root_from(claude_project_dir: Option<OsString>, cwd: &Path) -> PathBuf {
+    match claude_project_dir.filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => cwd.to_path_buf(),
+    }
+}
+
+/// The configuration file a hook reads. The default name, or an empty path,
+/// is searched for in the project root; any other relative path is taken
+/// relative to it, as it was written when the hook was registered.
+///
+/// # Arguments
+/// * `project_dir` - Root directory of the project, from `project_root`
+/// * `config_path` - The `--config` value
+///
+/// # Returns
+/// * `Some(PathBuf)` - The file to load
+/// * `None` - If the file is searched for and neither name exists
+pub fn resolve_config_path(project_dir: &Path, config_path: &str) -> Option<PathBuf> {
+    if config_path.is_empty() || config_path == DEFAULT_CONFIG_FILE {
+        find_config_file_in(project_dir).ok()
+    } else {
+        Some(project_dir.join(config_path))
+    }
+}
+
 /// Loads configuration using the new file discovery mechanism.
 ///
 /// This function automatically searches for configuration files in the
 /// preferred order and loads the first one found.
 pub fn load_config_auto() -> Result<Config> {
-    match find_config_file() {
+    load_config_auto_in(Path::new(""))
+}
+
+/// Loads the first configuration file found in `dir`, as `load_config_auto`
+/// does in the working directory.
+pub fn load_config_auto_in(dir: &Path) -> Result<Config> {
+    match find_config_file_in(dir) {
         Ok(config_path) => load_config_from_path(&config_path),
         Err(ConfigError::NotFound(_)) => {
             // No config file found - return empty config with a warning
             eprintln!("ℹ️  No configuration file found. Run with --init-config to create one.");
-            Ok(Config {
-                commands: HashMap::new(),
-                semantic_directories: HashMap::new(),
-            })
+            Ok(Config::default())
         }
         Err(e) => Err(e.into()),
     }
 }
 
+/// Directory holding user-wide files: `$XDG_CONFIG_HOME/claude-hook-advisor`,
+/// falling back to `~/.config/claude-hook-advisor`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("claude-hook-advisor"))
+}
+
+/// The user-wide directories file, if a home directory is known.
+pub fn user_directories_path() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join(USER_DIRECTORIES_FILE))
+}
+
+#[derive(Deserialize)]
+struct UserDirectories {
+    #[serde(default)]
+    semantic_directories: HashMap<String, String>,
+}
+
+/// Loads the `[semantic_directories]` of the user-wide directories file.
+///
+/// # Returns
+/// * `Ok(HashMap)` - Alias → path; empty if the file does not exist
+/// * `Err(ConfigError::ParseError)` - If the file is not valid TOML
+pub fn load_user_directories() -> Result<HashMap<String, String>> {
+    match user_directories_path().filter(|path| path.exists()) {
+        Some(path) => parse_user_directories(&read_config_file(&path)?, &path),
+        None => Ok(HashMap::new()),
+    }
+}
+
+fn parse_user_directories(content: &str, path: &Path) -> Result<HashMap<String, String>> {
+    let file: UserDirectories =
+        toml::from_str(content).map_err(|e| ConfigError::ParseError(format!("{}: {e}", path.display())))?;
+    Ok(file.semantic_directories)
+}
+
 /// Checks if configuration migration is needed.
 ///
 /// Returns the path to the old configuration file if it exists and
@@ -88,8 +190,8 @@ pub fn migrate_config() -> Result<PathBuf, ConfigError> {
         ConfigError::BackupFailed(format!("Failed to create backup: {}", e))
     )?;
 
-    // Copy to new location
-    fs::copy(&old_path, &new_path).map_err(|e|
+    // Copy to new location; a crash mid-copy must not leave a truncated config behind
+    copy_atomic(&old_path, &new_path).map_err(|e|
         ConfigError::MigrationFailed(format!("Failed to copy to new location: {}", e))
     )?;
 
@@ -117,15 +219,313 @@ pub fn migrate_config() -> Result<PathBuf, ConfigError> {
 /// * `Ok(Config)` - Loaded configuration
 /// * `Err` - If file cannot be read or parsed
 pub fn load_config_from_path(config_path: &Path) -> Result<Config> {
-    let content = fs::read_to_string(config_path)
-        .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    let content = read_config_file(config_path)?;
+    parse_config(&content, config_path)
+}
 
-    let config: Config = toml::from_str(&content)
-        .with_context(|| format!("Failed to parse config file: {}", config_path.display()))?;
+/// Reads a configuration file, reporting failures as configuration errors.
+pub fn read_config_file(config_path: &Path) -> Result<String> {
+    fs::read_to_string(config_path).map_err(|source| {
+        ConfigError::ReadFailed { path: config_path.display().to_string(), source }.into()
+    })
+}
+
+/// Parses configuration file content; `config_path` is only used in errors.
+///
+/// Files of every schema version up to `CONFIG_VERSION` are read as they
+/// are; `upgrade_config_file` rewrites older ones in the current form.
+pub fn parse_config(content: &str, config_path: &Path) -> Result<Config> {
+    let config: Config = toml::from_str(content)
+        .map_err(|e| ConfigError::ParseError(format!("{}: {e}", config_path.display())))?;
+    schema_version(config.version, config_path)?;
 
     Ok(config)
 }
 
+/// One step of the configuration schema upgrade.
+struct Migration {
+    /// Schema version the step upgrades a file to
+    to: u32,
+    /// What the step changes, as reported when it is applied
+    summary: &'static str,
+    /// The construct the step replaces, and what replaces it
+    deprecates: (&'static str, &'static str),
+    /// Applies the step, returning whether it changed the document
+    apply: fn(&mut DocumentMut) -> bool,
+}
+
+/// Every schema upgrade, oldest first. A file of version N goes through the
+/// steps whose `to` is above N.
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 2,
+    summary: "Moved the [commands] mappings into ordered [[rules]] entries",
+    deprecates: ("Top-level [commands] mappings", "[[rules]] entries with a replacement"),
+    apply: commands_to_rules,
+}];
+
+/// A configuration file rewritten in the current schema.
+#[derive(Debug, Serialize)]
+pub struct SchemaUpgrade {
+    /// Schema version the file had
+    pub from: u32,
+    /// Copy of the file as it was
+    pub backup: PathBuf,
+    /// Summaries of the migrations that changed the file, in order
+    pub applied: Vec<&'static str>,
+}
+
+/// The schema version of a file with `version`; files without one are version 1.
+///
+/// # Returns
+/// * `Ok(u32)` - The version, at most `CONFIG_VERSION`
+/// * `Err(ConfigError::InvalidFormat)` - If the file is from a newer build, or the version is 0
+fn schema_version(version: Option<u32>, config_path: &Path) -> Result<u32> {
+    match version.unwrap_or(1) {
+        0 => Err(ConfigError::InvalidFormat(format!("{}: version must be 1 or higher", config_path.display())).into()),
+        version if version > CONFIG_VERSION => Err(ConfigError::InvalidFormat(format!(
+            "{}: schema version {version} is newer than this build supports ({CONFIG_VERSION}); \
+             upgrade claude-hook-advisor",
+            config_path.display()
+        ))
+        .into()),
+        version => Ok(version),
+    }
+}
+
+/// Applies the migrations from schema version `from` to `doc`.
+///
+/// # Returns
+/// The migrations that changed the document. When there are any, `version`
+/// is set to `CONFIG_VERSION`.
+fn upgrade_document(doc: &mut DocumentMut, from: u32) -> Vec<&'static Migration> {
+    let applied: Vec<&'static Migration> =
+        MIGRATIONS.iter().filter(|migration| migration.to > from).filter(|migration| (migration.apply)(doc)).collect();
+    if !applied.is_empty() {
+        doc.insert("version", toml_edit::value(i64::from(CONFIG_VERSION)));
+    }
+    applied
+}
+
+/// The deprecated constructs in configuration file content that
+/// `upgrade_config_file` would replace, without changing anything.
+///
+/// # Returns
+/// Each construct with what replaces it; empty if the content is current or
+/// doesn't parse
+pub fn pending_migrations(content: &str) -> Vec<(&'static str, &'static str)> {
+    let Ok(config) = toml::from_str::<Config>(content) else {
+        return Vec::new();
+    };
+    let from = config.version.unwrap_or(1);
+    if from >= CONFIG_VERSION {
+        return Vec::new();
+    }
+    let Ok(mut doc) = content.parse::<DocumentMut>() else {
+        return Vec::new();
+    };
+    upgrade_document(&mut doc, from).into_iter().map(|migration| migration.deprecates).collect()
+}
+
+/// Rewrites the configuration file at `config_path` in the current schema,
+/// after copying it to `<file>.v<N>.backup`.
+///
+/// A file whose migrations would change nothing is left alone, so a file
+/// without `version` is only touched when it has something to upgrade.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file
+///
+/// # Returns
+/// * `Ok(Some(SchemaUpgrade))` - If the file was upgraded
+/// * `Ok(None)` - If it was already current
+/// * `Err` - If it cannot be read, parsed, backed up or written
+pub fn upgrade_config_file(config_path: &Path) -> Result<Option<SchemaUpgrade>> {
+    let config = load_config_from_path(config_path)?;
+    let from = schema_version(config.version, config_path)?;
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let mut doc = read_config_file(config_path)?
+        .parse::<DocumentMut>()
+        .map_err(|e| ConfigError::ParseError(format!("{}: {e}", config_path.display())))?;
+    let applied: Vec<&'static str> = upgrade_document(&mut doc, from).into_iter().map(|migration| migration.summary).collect();
+    if applied.is_empty() {
+        return Ok(None);
+    }
+
+    let backup = PathBuf::from(format!("{}.v{from}{BACKUP_SUFFIX}", config_path.display()));
+    copy_atomic(config_path, &backup).io_context(|| format!("Failed to back up {}", config_path.display()))?;
+    write_atomic(config_path, doc.to_string())
+        .io_context(|| format!("Failed to write upgraded config: {}", config_path.display()))?;
+    Ok(Some(SchemaUpgrade { from, backup, applied }))
+}
+
+/// Version 2: `[commands]` is a map, evaluated in alphabetical order after
+/// every rule. The mappings become `[[rules]]` with a `replacement`, appended
+/// in that same order, so evaluation doesn't change but the order is now
+/// explicit and each mapping can take a message or conditions. Comments on
+/// the mappings move with them.
+fn commands_to_rules(doc: &mut DocumentMut) -> bool {
+    let Some(commands) = doc.get("commands").and_then(Item::as_table_like) else {
+        return false;
+    };
+    // Comments above the `[commands]` header go with the first mapping
+    let mut heading = doc
+        .get_key_value("commands")
+        .and_then(|(key, item)| match item {
+            Item::Table(table) => table.decor().prefix()?.as_str(),
+            _ => key.leaf_decor().prefix()?.as_str(),
+        })
+        .map(str::trim)
+        .unwrap_or_default()
+        .to_string();
+    let mut mappings = Vec::new();
+    for (pattern, item) in commands.iter() {
+        let Some(replacement) = item.as_str() else {
+            // Not a valid mapping; leave it for the loader to report
+            return false;
+        };
+        let comment = commands
+            .get_key_value(pattern)
+            .and_then(|(key, _)| key.leaf_decor().prefix()?.as_str())
+            .map(str::trim)
+            .unwrap_or_default();
+        let note = item.as_value().and_then(|value| value.decor().suffix()?.as_str()).map(str::trim).unwrap_or_default();
+        mappings.push((pattern.to_string(), replacement.to_string(), comment.to_string(), note.to_string()));
+    }
+    mappings.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let rules = doc.entry("rules").or_insert(Item::ArrayOfTables(ArrayOfTables::new()));
+    if !rules.is_array_of_tables() && !rules.is_array() {
+        return false;
+    }
+    for (pattern, replacement, comment, note) in mappings {
+        let mut replacement = toml_edit::Value::from(replacement);
+        if !note.is_empty() {
+            replacement.decor_mut().set_suffix(format!(" {note}"));
+        }
+        match rules {
+            Item::ArrayOfTables(tables) => {
+                let mut table = Table::new();
+                table.insert("pattern", toml_edit::value(pattern));
+                table.insert("replacement", Item::Value(replacement));
+                let comments: String = [std::mem::take(&mut heading), comment]
+                    .iter()
+                    .filter(|comment| !comment.is_empty())
+                    .map(|comment| format!("{comment}\n"))
+                    .collect();
+                table.decor_mut().set_prefix(format!("\n{comments}"));
+                tables.push(table);
+            }
+            Item::Value(toml_edit::Value::Array(array)) => {
+                let mut rule = InlineTable::new();
+                rule.insert("pattern", pattern.into());
+                rule.insert("replacement", replacement);
+                array.push(rule);
+            }
+            _ => unreachable!("checked above"),
+        }
+    }
+    doc.remove("commands");
+    true
+}
+
+/// Fluent builder for constructing a `Config` in code instead of TOML.
+///
+/// ```
+/// use claude_hook_advisor::ConfigBuilder;
+///
+/// let config = ConfigBuilder::new()
+///     .map("npm", "bun")
+///     .block("rm -rf /")
+///     .semantic_dir("docs", "~/Docs")
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.commands["npm"], "bun");
+/// ```
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+    errors: Vec<String>,
+}
+
+impl ConfigBuilder {
+    /// Starts from an empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `[commands]` mapping from `pattern` to `replacement`.
+    pub fn map(mut self, pattern: impl Into<String>, replacement: impl Into<String>) -> Self {
+        let (pattern, replacement) = (pattern.into(), replacement.into());
+        if pattern.trim().is_empty() || replacement.trim().is_empty() {
+            self.errors.push(format!("Mapping '{pattern}' = '{replacement}' has an empty side"));
+        } else if pattern.trim() == replacement.trim() {
+            self.errors.push(format!("Mapping '{pattern}' maps to itself"));
+        } else if let Some(existing) = self.config.commands.get(&pattern).filter(|e| **e != replacement) {
+            self.errors.push(format!("'{pattern}' is mapped to both '{existing}' and '{replacement}'"));
+        } else {
+            self.config.commands.insert(pattern, replacement);
+        }
+        self
+    }
+
+    /// Adds a rule blocking `pattern` with the default reason.
+    pub fn block(self, pattern: impl Into<String>) -> Self {
+        self.rule(Rule { pattern: pattern.into(), ..Rule::default() })
+    }
+
+    /// Adds a rule reporting `pattern` with `message` without stopping it.
+    pub fn warn(self, pattern: impl Into<String>, message: impl Into<String>) -> Self {
+        self.rule(Rule {
+            pattern: pattern.into(),
+            message: Some(message.into()),
+            severity: Some(Severity::Warn),
+            ..Rule::default()
+        })
+    }
+
+    /// Adds a `[[rules]]` entry.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        if rule.pattern.trim().is_empty() {
+            self.errors.push("Rule has an empty pattern".to_string());
+        } else {
+            self.config.rules.push(rule);
+        }
+        self
+    }
+
+    /// Adds a `[semantic_directories]` alias.
+    pub fn semantic_dir(mut self, alias: impl Into<String>, path: impl Into<String>) -> Self {
+        let (alias, path) = (alias.into(), path.into());
+        if alias.trim().is_empty() || path.trim().is_empty() {
+            self.errors.push(format!("Directory alias '{alias}' = '{path}' has an empty side"));
+        } else {
+            self.config.semantic_directories.insert(alias, path);
+        }
+        self
+    }
+
+    /// Validates and returns the configuration.
+    ///
+    /// # Returns
+    /// * `Ok(Config)` - The configuration, with every rule compiling
+    /// * `Err(ConfigError::InvalidFormat)` - Listing every problem found
+    pub fn build(self) -> Result<Config> {
+        let mut errors = self.errors;
+        if let Err(e) = CompiledRules::new(&self.config, &RuleContext::default()) {
+            errors.push(format!("{e:#}"));
+        }
+
+        if errors.is_empty() {
+            Ok(self.config)
+        } else {
+            Err(ConfigError::InvalidFormat(errors.join("; ")).into())
+        }
+    }
+}
+
 /// Loads configuration from a TOML file path (legacy function for compatibility).
 ///
 /// If the config file doesn't exist, returns an empty configuration and logs
@@ -143,28 +543,129 @@ pub fn load_config(config_path: &str) -> Result<Config> {
     if !Path::new(config_path).exists() {
         // Log warning to stderr when config file is not found
         eprintln!("Warning: Config file '{config_path}' not found. No command mappings will be applied.");
-        return Ok(Config {
-            commands: HashMap::new(),
-            semantic_directories: HashMap::new(),
-        });
+        return Ok(Config::default());
     }
 
-    let content = fs::read_to_string(config_path)
-        .with_context(|| format!("Failed to read config file: {config_path}"))?;
-
-    let config: Config = toml::from_str(&content)
-        .with_context(|| format!("Failed to parse config file: {config_path}"))?;
-
-    Ok(config)
+    let content = read_config_file(Path::new(config_path))?;
+    parse_config(&content, Path::new(config_path))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::AdvisorError;
     use std::fs;
     use tempfile::TempDir;
 
     #[test]
+    fn test_load_errors_are_config_variants() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let missing = temp_dir.path().join(".claude.toml");
+        let err = load_config_from_path(&missing).unwrap_err();
+        assert!(matches!(err, AdvisorError::Config(ConfigError::ReadFailed { .. })));
+
+        fs::write(&missing, "[commands\nnpm = ").unwrap();
+        let err = load_config_from_path(&missing).unwrap_err();
+        assert!(matches!(err, AdvisorError::Config(ConfigError::ParseError(_))));
+        assert!(err.to_string().contains(".claude.toml"));
+    }
+
+    #[test]
+    fn test_schema_upgrade_moves_commands_into_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".claude.toml");
+        let original = r#"# Team config
+[commands]
+# Package managers
+yarn = "bun"
+npm = "bun"   # faster installs
+
+[[rules]]
+pattern = "rm -rf /"
+message = "Never"
+"#;
+        fs::write(&path, original).unwrap();
+        let before = CompiledRules::new(&load_config_from_path(&path).unwrap(), &RuleContext::default()).unwrap();
+
+        let upgrade = upgrade_config_file(&path).unwrap().unwrap();
+        assert_eq!(upgrade.from, 1);
+        assert_eq!(upgrade.applied, ["Moved the [commands] mappings into ordered [[rules]] entries"]);
+        assert_eq!(fs::read_to_string(&upgrade.backup).unwrap(), original);
+
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert!(upgraded.contains("# Team config\n[[rules]]\npattern = \"npm\"\nreplacement = \"bun\" # faster installs\n"));
+        assert!(upgraded.contains("# Package managers\n[[rules]]\npattern = \"yarn\"\n"));
+        let config = load_config_from_path(&path).unwrap();
+        assert_eq!(config.version, Some(CONFIG_VERSION));
+        assert!(config.commands.is_empty());
+        let patterns: Vec<&str> = config.rules.iter().map(|rule| rule.pattern.as_str()).collect();
+        assert_eq!(patterns, ["rm -rf /", "npm", "yarn"]);
+
+        // Evaluation is unchanged
+        let after = CompiledRules::new(&config, &RuleContext::default()).unwrap();
+        for command in ["npm install", "yarn add x", "rm -rf /", "ls"] {
+            let suggestion = |rules: &CompiledRules| rules.evaluate(command).map(|m| (m.pattern, m.suggested_command));
+            assert_eq!(suggestion(&before), suggestion(&after), "{command}");
+        }
+
+        assert!(upgrade_config_file(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_schema_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".claude.toml");
+
+        // Nothing to migrate: the file is left as it is
+        fs::write(&path, "[semantic_directories]\ndocs = \"~/Docs\"\n").unwrap();
+        assert!(upgrade_config_file(&path).unwrap().is_none());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        // Inline tables are migrated too
+        let mut doc: DocumentMut = "commands = { npm = \"bun\" }\nrules = [{ pattern = \"curl\" }]\n".parse().unwrap();
+        assert_eq!(upgrade_document(&mut doc, 1).len(), 1);
+        let config: Config = toml::from_str(&doc.to_string()).unwrap();
+        assert_eq!(config.rules[1].replacement.as_deref(), Some("bun"));
+
+        fs::write(&path, "version = 3\n").unwrap();
+        let err = load_config_from_path(&path).unwrap_err();
+        assert!(err.to_string().contains("newer than this build supports"));
+        fs::write(&path, "version = 0\n").unwrap();
+        assert!(load_config_from_path(&path).is_err());
+    }
+
+    #[test]
+    fn test_config_builder() {
+        let config = ConfigBuilder::new()
+            .map("npm", "bun")
+            .block("rm -rf /")
+            .warn("git push", "Run the tests first")
+            .semantic_dir("docs", "~/Docs")
+            .build()
+            .unwrap();
+        assert_eq!(config.commands["npm"], "bun");
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[1].severity, Some(Severity::Warn));
+        assert_eq!(config.semantic_directories["docs"], "~/Docs");
+
+        let error = ConfigBuilder::new()
+            .map("npm", "npm")
+            .map("yarn", "bun")
+            .map("yarn", "pnpm")
+            .semantic_dir("", "~/x")
+            .rule(Rule { pattern: "kubectl".to_string(), matches: Some("(".to_string()), ..Rule::default() })
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'npm' maps to itself"));
+        assert!(error.contains("'yarn' is mapped to both 'bun' and 'pnpm'"));
+        assert!(error.contains("Directory alias '' = '~/x'"));
+        assert!(error.contains("regex parse error"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn test_config_loading_missing_file() {
         // Test loading non-existent config file
         let result = load_config("non-existent-file.toml");
@@ -182,7 +683,7 @@ mod tests {
         fs::write(&config_path, "[commands]\nnpm = \"bun\"").unwrap();
 
         // Test that it finds the new config file
-        let result = find_config_file_in_dir(temp_dir.path());
+        let result = find_config_file_in(temp_dir.path());
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), config_path);
     }
@@ -196,7 +697,7 @@ mod tests {
         fs::write(&old_config_path, "[commands]\nnpm = \"bun\"").unwrap();
 
         // Test that it finds the old config file as fallback
-        let result = find_config_file_in_dir(temp_dir.path());
+        let result = find_config_file_in(temp_dir.path());
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), old_config_path);
     }
@@ -212,7 +713,7 @@ mod tests {
         fs::write(&new_config_path, "[commands]\nnpm = \"bunx\"").unwrap();
 
         // Test that it prefers the new config file
-        let result = find_config_file_in_dir(temp_dir.path());
+        let result = find_config_file_in(temp_dir.path());
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), new_config_path);
     }
@@ -258,6 +759,30 @@ mod tests {
         assert!(backup.exists());
     }
 
+    #[test]
+    fn test_project_root_and_config_lookup() {
+        let dir = TempDir::new().unwrap();
+        let nested = dir.path().join("crates/core");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.path().join(".claude.toml"), "[commands]\nnpm = \"bun\"\n").unwrap();
+
+        // Claude Code names the project root even when a tool runs in a subdirectory
+        let root = project_root_from(Some(dir.path().into()), &nested);
+        assert_eq!(root, dir.path());
+        assert_eq!(project_root_from(Some(OsString::new()), &nested), nested);
+        assert_eq!(project_root_from(None, &nested), nested);
+
+        assert_eq!(resolve_config_path(&root, DEFAULT_CONFIG_FILE), Some(dir.path().join(".claude.toml")));
+        assert_eq!(resolve_config_path(&root, ""), Some(dir.path().join(".claude.toml")));
+        assert_eq!(resolve_config_path(&nested, DEFAULT_CONFIG_FILE), None);
+        assert_eq!(resolve_config_path(&root, "ci/hooks.toml"), Some(dir.path().join("ci/hooks.toml")));
+        assert_eq!(resolve_config_path(&root, "/etc/advisor.toml"), Some(PathBuf::from("/etc/advisor.toml")));
+
+        // The legacy name is still found
+        fs::write(nested.join(".claude-hook-advisor.toml"), "").unwrap();
+        assert_eq!(resolve_config_path(&nested, DEFAULT_CONFIG_FILE), Some(nested.join(".claude-hook-advisor.toml")));
+    }
+
     #[test]
     fn test_load_config_auto() {
         let temp_dir = TempDir::new().unwrap();
@@ -267,23 +792,13 @@ mod tests {
         fs::write(&config_path, "[commands]\nnpm = \"bun\"").unwrap();
 
         // Test auto loading
-        let result = load_config_auto_in_dir(temp_dir.path());
+        let result = load_config_auto_in(temp_dir.path());
         assert!(result.is_ok());
         let config = result.unwrap();
         assert_eq!(config.commands.get("npm"), Some(&"bun".to_string()));
     }
 
     // Helper functions for testing with different directories
-    fn find_config_file_in_dir(dir: &std::path::Path) -> Result<std::path::PathBuf, ConfigError> {
-        for filename in CONFIG_FILE_NAMES {
-            let path = dir.join(filename);
-            if path.exists() {
-                return Ok(path);
-            }
-        }
-        Err(ConfigError::NotFound("No config found".to_string()))
-    }
-
     fn needs_migration_in_dir(dir: &std::path::Path) -> Option<std::path::PathBuf> {
         let old_config = dir.join(".claude-hook-advisor.toml");
         let new_config = dir.join(".claude.toml");
@@ -295,7 +810,7 @@ mod tests {
         }
     }
 
-    fn migrate_config_in_dir(dir: &std::path::Path) -> Result<std::path::PathBuf, ConfigError> {
+    fn migrate_config_in_dir(dir: &std::path::Path) -> Result<std::path::PathBuf> {
         let old_path = dir.join(".claude-hook-advisor.toml");
         let new_path = dir.join(".claude.toml");
         let backup_path = dir.join(format!("{}{}", old_path.display(), BACKUP_SUFFIX));
@@ -304,7 +819,7 @@ mod tests {
         fs::copy(&old_path, &backup_path)?;
 
         // Copy to new location
-        fs::copy(&old_path, &new_path)?;
+        copy_atomic(&old_path, &new_path)?;
 
         // Validate new configuration
         load_config_from_path(&new_path)?;
@@ -314,15 +829,4 @@ mod tests {
 
         Ok(new_path)
     }
-
-    fn load_config_auto_in_dir(dir: &std::path::Path) -> Result<Config> {
-        match find_config_file_in_dir(dir) {
-            Ok(config_path) => load_config_from_path(&config_path),
-            Err(ConfigError::NotFound(_)) => Ok(Config {
-                commands: HashMap::new(),
-                semantic_directories: HashMap::new(),
-            }),
-            Err(e) => Err(e.into()),
-        }
-    }
 }
\ No newline at end of file
@@ -0,0 +1,294 @@
+//! Editing configuration files in place
+//!
+//! Programmatic changes to `.claude.toml` go through a `toml_edit` document
+//! rather than string concatenation, so the user's comments, key order,
+//! whitespace and inline tables survive every edit. Only the tables and keys
+//! being changed are touched.
+
+use crate::atomic::write_atomic;
+use crate::config::read_config_file;
+use crate::types::{ConfigError, IoContext, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+/// A configuration file opened for editing.
+#[derive(Debug)]
+pub struct ConfigEditor {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigEditor {
+    /// Reads and parses the configuration file at `path`.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the configuration file
+    ///
+    /// # Returns
+    /// * `Ok(ConfigEditor)` - The parsed document, ready for edits
+    /// * `Err` - If the file cannot be read or is not valid TOML
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = read_config_file(path)?;
+        Self::parse(&content, path)
+    }
+
+    /// Parses `content` as the configuration file at `path`.
+    pub fn parse(content: &str, path: &Path) -> Result<Self> {
+        let doc = content
+            .parse::<DocumentMut>()
+            .map_err(|e| ConfigError::ParseError(format!("{}: {e}", path.display())))?;
+        Ok(Self { path: path.to_path_buf(), doc })
+    }
+
+    /// Whether the top-level table `name` exists in any form: a `[name]`
+    /// header, an inline table, or dotted keys like `name.key = ...`.
+    pub fn has_table(&self, name: &str) -> bool {
+        self.doc.contains_key(name)
+    }
+
+    /// Appends an empty `[name]` table preceded by `comment` unless the table
+    /// already exists.
+    ///
+    /// # Arguments
+    /// * `name` - Top-level table name
+    /// * `comment` - Comment lines placed above the table header, each
+    ///   starting with `#`
+    ///
+    /// # Returns
+    /// * `true` if the table was added
+    pub fn ensure_table(&mut self, name: &str, comment: &str) -> bool {
+        if self.has_table(name) {
+            return false;
+        }
+
+        let mut table = Table::new();
+        // Keep a blank line between the new table and whatever precedes it
+        let separator = if self.doc.is_empty() && self.doc.trailing().as_str().unwrap_or("").is_empty() {
+            ""
+        } else {
+            "\n"
+        };
+        let comment = comment.trim_end();
+        let prefix = if comment.is_empty() {
+            separator.to_string()
+        } else {
+            format!("{separator}{comment}\n")
+        };
+        table.decor_mut().set_prefix(prefix);
+        self.doc.insert(name, Item::Table(table));
+        true
+    }
+
+    /// Sets `key` inside the table at `table` (a path such as
+    /// `["groups", "node"]`), creating missing tables and keeping the key's
+    /// position and comments if it already exists.
+    ///
+    /// # Returns
+    /// * `Err(ConfigError::InvalidFormat)` - If a table on the path holds a non-table value
+    pub fn set_value(&mut self, table: &[&str], key: &str, new: impl Into<Value>) -> Result<()> {
+        let item = self.table_mut(table)?;
+        let entries = item
+            .as_table_like_mut()
+            .ok_or_else(|| ConfigError::InvalidFormat(format!("'{}' is not a table", table.join("."))))?;
+
+        let inserted = match entries.get_mut(key).and_then(Item::as_value_mut) {
+            // Replace the value only, so comments around it stay put
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = new.into();
+                *existing.decor_mut() = decor;
+                false
+            }
+            None => {
+                entries.insert(key, Item::Value(new.into()));
+                true
+            }
+        };
+
+        // In `{ a = 1 }` the space before `}` belongs to the last value; move
+        // it to the new last value so the result isn't `{ a = 1 , b = 2 }`
+        if let Some(inline) = item.as_inline_table_mut().filter(|_| inserted) {
+            let len = inline.len();
+            if len > 1 {
+                let mut values = inline.iter_mut().skip(len - 2).map(|(_, v)| v);
+                if let (Some(previous), Some(last)) = (values.next(), values.next()) {
+                    if let Some(suffix) = previous.decor().suffix().cloned() {
+                        previous.decor_mut().set_suffix("");
+                        last.decor_mut().set_suffix(suffix);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a table with `entries` to the array of tables `name`, such as
+    /// a new `[[rules]]` entry after the existing ones.
+    ///
+    /// # Returns
+    /// * `Err(ConfigError::InvalidFormat)` - If `name` holds something other than an array of tables
+    pub fn append_table(&mut self, name: &str, entries: Vec<(&str, Value)>) -> Result<()> {
+        let array = self
+            .doc
+            .entry(name)
+            .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .ok_or_else(|| ConfigError::InvalidFormat(format!("'{name}' is not an array of tables")))?;
+
+        let mut table = Table::new();
+        for (key, value) in entries {
+            table.insert(key, Item::Value(value));
+        }
+        table.decor_mut().set_prefix("\n");
+        array.push(table);
+        Ok(())
+    }
+
+    /// Finds the table at `path`, creating missing ones. Intermediate tables
+    /// are implicit, so only the innermost gets a `[header]`.
+    fn table_mut(&mut self, path: &[&str]) -> Result<&mut Item> {
+        let mut item = self.doc.as_item_mut();
+        for (depth, name) in path.iter().enumerate() {
+            let entries = item
+                .as_table_like_mut()
+                .ok_or_else(|| ConfigError::InvalidFormat(format!("'{}' is not a table", path[..depth].join("."))))?;
+            if entries.get(name).is_none() {
+                let mut table = Table::new();
+                table.set_implicit(depth + 1 < path.len());
+                entries.insert(name, Item::Table(table));
+            }
+            item = entries.get_mut(name).expect("table was just inserted");
+        }
+        Ok(item)
+    }
+
+    /// Writes the document back to the file it was read from.
+    pub fn save(&self) -> Result<()> {
+        write_atomic(&self.path, self.doc.to_string())
+            .io_context(|| format!("Failed to update config file: {}", self.path.display()))
+    }
+}
+
+impl fmt::Display for ConfigEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(content: &str) -> ConfigEditor {
+        ConfigEditor::parse(content, Path::new(".claude.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_untouched_document_round_trips_exactly() {
+        let content = r#"# Team config — keep sorted!
+[commands]
+npm   = "bun"    # we moved off npm in 2024
+yarn = 'bun'
+
+# Aliases
+semantic_directories = { docs = "~/Docs" }
+
+[[rules]]
+pattern = "rm -rf /"
+"#;
+        assert_eq!(edit(content).to_string(), content);
+    }
+
+    #[test]
+    fn test_ensure_table_recognises_every_table_form() {
+        let mut editor = edit("commands.npm = \"bun\"\nsemantic_directories = { docs = \"~/Docs\" }\n");
+        assert!(!editor.ensure_table("commands", "# Commands"));
+        assert!(!editor.ensure_table("semantic_directories", "# Dirs"));
+
+        // A header that only appears in a comment or a string is not a table
+        let mut editor = edit("# [commands]\nnote = \"[semantic_directories]\"\n");
+        assert!(editor.ensure_table("commands", "# Commands"));
+        assert!(editor.ensure_table("semantic_directories", ""));
+        let updated = editor.to_string();
+        assert!(updated.starts_with("# [commands]\nnote = \"[semantic_directories]\"\n"));
+        assert!(updated.contains("\n# Commands\n[commands]\n"));
+        assert!(updated.contains("\n[semantic_directories]\n"));
+    }
+
+    #[test]
+    fn test_ensure_table_appends_after_existing_tables() {
+        let content = "[behavior]\nreplace_mode = true # keep\n\n[[rules]]\npattern = \"sudo\"\n";
+        let mut editor = edit(content);
+        editor.ensure_table("commands", "# Command mappings");
+
+        let updated = editor.to_string();
+        assert!(updated.starts_with(content));
+        assert!(updated.ends_with("\n# Command mappings\n[commands]\n"));
+        assert!(toml::from_str::<toml::Table>(&updated).is_ok());
+    }
+
+    #[test]
+    fn test_set_value_preserves_neighbours_and_comments() {
+        let mut editor = edit("[commands]\n# package managers\nnpm = \"pnpm\" # temporary\nyarn = \"bun\"\n");
+        editor.set_value(&["commands"], "npm", "bun").unwrap();
+        editor.set_value(&["commands"], "grep", "rg").unwrap();
+        assert_eq!(
+            editor.to_string(),
+            "[commands]\n# package managers\nnpm = \"bun\" # temporary\nyarn = \"bun\"\ngrep = \"rg\"\n"
+        );
+
+        let mut editor = edit("semantic_directories = { docs = \"~/Docs\" }\n");
+        editor.set_value(&["semantic_directories"], "notes", "~/Notes").unwrap();
+        editor.set_value(&["semantic_directories"], "docs", "~/Documents").unwrap();
+        assert!(edit("commands = 1\n").set_value(&["commands"], "npm", "bun").is_err());
+        assert_eq!(
+            editor.to_string(),
+            "semantic_directories = { docs = \"~/Documents\", notes = \"~/Notes\" }\n"
+        );
+    }
+
+    #[test]
+    fn test_set_value_in_nested_tables() {
+        let mut editor = edit("[groups.node]\nenabled = true   # flip while debugging\n");
+        editor.set_value(&["groups", "node"], "enabled", false).unwrap();
+        editor.set_value(&["groups", "safety"], "enabled", true).unwrap();
+        assert_eq!(
+            editor.to_string(),
+            "[groups.node]\nenabled = false   # flip while debugging\n\n[groups.safety]\nenabled = true\n"
+        );
+    }
+
+    #[test]
+    fn test_set_value_in_implicit_table() {
+        // `groups.node` only exists through its subtable's header
+        let mut editor = edit("[groups.node.commands]\nnpm = \"bun\"\n");
+        editor.set_value(&["groups", "node"], "enabled", false).unwrap();
+        let updated: toml::Table = toml::from_str(&editor.to_string()).unwrap();
+        assert_eq!(updated["groups"]["node"]["enabled"].as_bool(), Some(false));
+        assert_eq!(updated["groups"]["node"]["commands"]["npm"].as_str(), Some("bun"));
+    }
+
+    #[test]
+    fn test_append_table() {
+        let mut editor = edit("# Rules\n[[rules]]\npattern = \"sudo\" # keep\n\n[commands]\nnpm = \"bun\"\n");
+        editor.append_table("rules", vec![("pattern", "git push".into()), ("severity", "ask".into())]).unwrap();
+        let updated = editor.to_string();
+        assert!(updated.starts_with("# Rules\n[[rules]]\npattern = \"sudo\" # keep\n"), "{updated}");
+        let parsed: toml::Table = toml::from_str(&updated).unwrap();
+        assert_eq!(parsed["rules"][1]["pattern"].as_str(), Some("git push"));
+        assert_eq!(parsed["commands"]["npm"].as_str(), Some("bun"));
+
+        let mut editor = edit("version = 2\n");
+        editor.append_table("rules", vec![("pattern", "rm -rf".into())]).unwrap();
+        assert_eq!(editor.to_string(), "version = 2\n\n[[rules]]\npattern = \"rm -rf\"\n");
+        assert!(edit("rules = 1\n").append_table("rules", vec![]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_toml_is_a_parse_error() {
+        let err = ConfigEditor::parse("[commands\n", Path::new("team.toml")).unwrap_err();
+        assert!(matches!(err, crate::types::AdvisorError::Config(ConfigError::ParseError(_))));
+        assert!(err.to_string().contains("team.toml"));
+    }
+}
@@ -0,0 +1,213 @@
+//! Conflicts between mappings and rules
+//!
+//! Each mapping is sensible on its own, but together they can contradict
+//! each other: `npm` maps to `bun` while another rule blocks `bun`, or `npm`
+//! maps to `bun` and `bun` back to `npm`. The hook copes at run time, since
+//! a loop suggests nothing, but Claude is then told to use a command it will
+//! be refused. `--lint-config` follows every replacement through the rules
+//! the way the hook does and reports where it ends badly.
+
+use crate::rules::{CompiledRules, RuleContext, RuleMatch};
+use crate::types::{Config, Result, Severity};
+use serde::Serialize;
+
+/// How a replacement fails to settle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Rewriting comes back to a command it started from, or doesn't settle
+    /// within `[matching] max_chain_depth`
+    Cycle,
+    /// The replacement is blocked, or needs confirmation, by another rule
+    BlockedTarget,
+    /// The replacement is itself mapped onward
+    Chain,
+}
+
+/// One conflict, found by following a mapping from its pattern.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// The commands rewriting goes through, starting with the mapping's pattern
+    pub chain: Vec<String>,
+    /// The rule the replacement runs into, for `BlockedTarget`
+    pub rule: Option<String>,
+    pub severity: Option<Severity>,
+    pub message: String,
+}
+
+impl Conflict {
+    /// Cycles and blocked replacements are errors: Claude is pointed at a
+    /// command it can never run. The rest still works and is only a warning.
+    pub fn is_error(&self) -> bool {
+        match self.kind {
+            ConflictKind::Cycle => true,
+            ConflictKind::BlockedTarget => self.severity == Some(Severity::Block),
+            ConflictKind::Chain => false,
+        }
+    }
+}
+
+/// Follows the replacement of every mapping and rule and reports the ones
+/// that loop, run into another rule, or are mapped onward.
+///
+/// Rules are compiled as the hook compiles them outside any session, so
+/// rules whose `when` conditions don't hold there are left out.
+///
+/// # Returns
+/// * `Ok(Vec<Conflict>)` - The conflicts, in pattern order; a cycle is reported once
+/// * `Err` - If a rule pattern is invalid
+pub fn find_conflicts(config: &Config) -> Result<Vec<Conflict>> {
+    let rules = CompiledRules::new(config, &RuleContext::default())?;
+
+    let mut patterns: Vec<&String> = config.active_commands().into_iter().map(|(pattern, _)| pattern).collect();
+    patterns.extend(config.active_rules().into_iter().filter(|rule| rule.replacement.is_some()).map(|rule| &rule.pattern));
+    patterns.sort();
+    patterns.dedup();
+
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    for pattern in patterns {
+        let Some(conflict) = follow(&rules, pattern, config.matching.max_chain_depth) else {
+            continue;
+        };
+        let reported = conflict.kind == ConflictKind::Cycle
+            && conflicts.iter().any(|earlier| earlier.kind == ConflictKind::Cycle && same_cycle(&earlier.chain, &conflict.chain));
+        if !reported {
+            conflicts.push(conflict);
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Follows the rewrites of `pattern`, run as a command, until they settle or
+/// go through more than `max_depth` further mappings. Without chaining
+/// (`max_depth` of 0), one onward mapping is a chain rather than an error,
+/// since Claude is only sent round twice.
+fn follow(rules: &CompiledRules, pattern: &str, max_depth: usize) -> Option<Conflict> {
+    // A pattern that another rule catches first is shadowed, which is not a conflict of its own
+    let first = step(rules, pattern).filter(|first| first.pattern == pattern)?;
+    let mut chain = vec![pattern.to_string(), first.suggested_command?];
+
+    loop {
+        let current = chain.last().expect("the chain starts with two commands").clone();
+        let Some(next) = step(rules, &current) else {
+            return (chain.len() > 2).then(|| Conflict {
+                kind: ConflictKind::Chain,
+                message: format!(
+                    "'{pattern}' is mapped to '{}', which is mapped onward: {}; map '{pattern}' to '{current}' directly",
+                    chain[1],
+                    arrows(&chain)
+                ),
+                chain,
+                rule: None,
+                severity: None,
+            });
+        };
+        let Some(suggested) = next.suggested_command else {
+            let verdict = match next.severity {
+                Severity::Block => "blocks",
+                Severity::Ask => "asks about",
+                Severity::Warn => "warns about",
+            };
+            return Some(Conflict {
+                kind: ConflictKind::BlockedTarget,
+                message: format!("'{pattern}' is rewritten to '{current}', but rule '{}' {verdict} it", next.pattern),
+                chain,
+                rule: Some(next.pattern),
+                severity: Some(next.severity),
+            });
+        };
+
+        let looped = chain.contains(&suggested);
+        chain.push(suggested);
+        if looped || chain.len() > max_depth.max(1) + 2 {
+            let message = if looped {
+                format!("Rewriting '{pattern}' loops: {}", arrows(&chain))
+            } else {
+                format!("Rewriting '{pattern}' never settles within [matching] max_chain_depth = {max_depth}: {} → …", arrows(&chain[..3]))
+            };
+            return Some(Conflict {
+                kind: ConflictKind::Cycle,
+                message,
+                chain,
+                rule: None,
+                severity: None,
+            });
+        }
+    }
+}
+
+/// The match `evaluate` would apply first, with its single rewrite.
+fn step(rules: &CompiledRules, command: &str) -> Option<RuleMatch> {
+    rules.explain(command).into_iter().next()
+}
+
+/// Whether two chains end in the same loop, whichever command each started from.
+fn same_cycle(a: &[String], b: &[String]) -> bool {
+    let members = |chain: &[String]| {
+        let last = chain.last().expect("a cycle has commands");
+        let start = chain.iter().position(|command| command == last).unwrap_or(0);
+        let mut members = chain[start..].to_vec();
+        members.sort();
+        members.dedup();
+        members
+    };
+    members(a) == members(b)
+}
+
+fn arrows(chain: &[String]) -> String {
+    chain.join(" → ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflicts(toml: &str) -> Vec<Conflict> {
+        find_conflicts(&toml::from_str(toml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_cycles_are_reported_once() {
+        let found = conflicts("[commands]\nnpm = \"bun\"\nbun = \"npm\"\nyarn = \"pnpm\"\n");
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].kind, ConflictKind::Cycle);
+        assert_eq!(found[0].chain, ["bun", "npm", "bun"]);
+        assert!(found[0].is_error());
+
+        let found = conflicts("[commands]\nnpm = \"sudo npm\"\n");
+        assert_eq!(found[0].kind, ConflictKind::Cycle);
+        assert!(found[0].message.starts_with("Rewriting 'npm' never settles within [matching] max_chain_depth = 16: npm → sudo npm → sudo sudo npm"), "{}", found[0].message);
+
+        // A chain longer than the configured depth is no longer followed by the hook
+        let found = conflicts("[matching]\nmax_chain_depth = 1\n\n[commands]\nyarn = \"npm\"\nnpm = \"pnpm\"\npnpm = \"bun\"\n");
+        assert_eq!(found[0].kind, ConflictKind::Chain);
+        assert_eq!(found[1].kind, ConflictKind::Cycle);
+        assert_eq!(found[1].chain, ["yarn", "npm", "pnpm", "bun"]);
+    }
+
+    #[test]
+    fn test_replacements_hit_by_other_rules() {
+        let found = conflicts(
+            "[commands]\nnpm = \"bun\"\nyarn = \"pnpm\"\n\n[[rules]]\npattern = \"bun\"\nmessage = \"No bun here\"\n\n\
+             [[rules]]\npattern = \"pnpm\"\nseverity = \"ask\"\n",
+        );
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert_eq!(found[0].message, "'npm' is rewritten to 'bun', but rule 'bun' blocks it");
+        assert!(found[0].is_error());
+        assert_eq!(found[1].severity, Some(Severity::Ask));
+        assert!(!found[1].is_error());
+    }
+
+    #[test]
+    fn test_chained_mappings() {
+        let found = conflicts("[commands]\nyarn = \"npm\"\nnpm = \"bun\"\ncat = \"bat\"\n");
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].kind, ConflictKind::Chain);
+        assert_eq!(found[0].chain, ["yarn", "npm", "bun"]);
+        assert_eq!(found[0].message, "'yarn' is mapped to 'npm', which is mapped onward: yarn → npm → bun; map 'yarn' to 'bun' directly");
+
+        // Mappings that only add flags settle at once
+        assert!(conflicts("[commands]\n\"cargo test\" = \"cargo test --workspace\"\n").is_empty());
+    }
+}
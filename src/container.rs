@@ -0,0 +1,182 @@
+//! Rewriting commands to run inside containers
+//!
+//! The `[container]` table names commands that belong inside a compose service
+//! or a running container, such as a database client. Matching commands are
+//! rewritten to `docker compose exec -T <service> ...` or
+//! `docker exec <container> ...`, but only while the target is running, so a
+//! stopped stack never produces suggestions that cannot work.
+
+use crate::rules::{command_matches, evaluate_command, RuleContext, RuleMatch};
+use crate::types::{Config, Container, ContainerRuntime, Result, Rule};
+use std::process::Command;
+
+/// Where a command should run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerTarget {
+    /// A docker compose service
+    Service(String),
+    /// A container by name
+    Container(String),
+}
+
+impl ContainerTarget {
+    /// Prefix that runs a command inside this target.
+    fn exec_prefix(&self, runtime: ContainerRuntime) -> String {
+        let runtime = runtime.binary();
+        match self {
+            // -T: Claude Code has no terminal to attach
+            ContainerTarget::Service(service) => format!("{runtime} compose exec -T {service}"),
+            ContainerTarget::Container(name) => format!("{runtime} exec {name}"),
+        }
+    }
+
+    /// Asks the container runtime whether this target is running.
+    ///
+    /// A missing runtime or any error counts as not running.
+    pub fn is_running(&self, runtime: ContainerRuntime) -> bool {
+        let runtime = runtime.binary();
+        let output = match self {
+            ContainerTarget::Service(_) => Command::new(runtime)
+                .args(["compose", "ps", "--status", "running", "--services"])
+                .output(),
+            ContainerTarget::Container(name) => Command::new(runtime)
+                .args(["ps", "--filter", &format!("name=^{name}$"), "--format", "{{.Names}}"])
+                .output(),
+        };
+
+        let Some(output) = output.ok().filter(|output| output.status.success()) else {
+            return false;
+        };
+        let wanted = match self {
+            ContainerTarget::Service(name) | ContainerTarget::Container(name) => name,
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.trim() == wanted)
+    }
+}
+
+/// Rewrites `command` to run inside the container its tool belongs to.
+///
+/// Targets are only checked with `is_running` when one of their commands
+/// appears in `command`.
+///
+/// # Arguments
+/// * `container` - The `[container]` configuration
+/// * `command` - The command to check
+/// * `is_running` - Whether a target is currently running
+///
+/// # Returns
+/// * `Ok(Some(RuleMatch))` - The rewrite, if a configured tool matched a running target
+/// * `Ok(None)` - If nothing matched or the target is not running
+pub fn container_match(
+    container: &Container,
+    command: &str,
+    is_running: &dyn Fn(&ContainerTarget) -> bool,
+) -> Result<Option<RuleMatch>> {
+    let mut rules = Vec::new();
+    for (target, tools) in targets(container) {
+        let mut matching = Vec::new();
+        for tool in tools {
+            if command_matches(tool, command)? {
+                matching.push(tool);
+            }
+        }
+        if matching.is_empty() || !is_running(&target) {
+            continue;
+        }
+
+        let prefix = target.exec_prefix(container.runtime);
+        rules.extend(matching.into_iter().map(|tool| Rule {
+            pattern: tool.clone(),
+            replacement: Some(format!("{prefix} {tool}")),
+            ..Rule::default()
+        }));
+    }
+
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let config = Config { rules, ..Config::default() };
+    evaluate_command(&config, command, &RuleContext::default())
+}
+
+/// Configured targets and their tools, services first, in name order.
+fn targets(container: &Container) -> Vec<(ContainerTarget, &Vec<String>)> {
+    let mut services: Vec<_> = container.services.iter().collect();
+    services.sort();
+    let mut containers: Vec<_> = container.containers.iter().collect();
+    containers.sort();
+
+    services
+        .into_iter()
+        .map(|(name, tools)| (ContainerTarget::Service(name.clone()), tools))
+        .chain(
+            containers
+                .into_iter()
+                .map(|(name, tools)| (ContainerTarget::Container(name.clone()), tools)),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn container() -> Container {
+        toml::from_str(
+            r#"
+[services]
+db = ["psql", "pg_dump"]
+
+[containers]
+cache = ["redis-cli"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rewrites_into_running_targets() {
+        let container = container();
+        let running = |_: &ContainerTarget| true;
+
+        let rewrite = container_match(&container, "psql -U app -c 'select 1'", &running).unwrap().unwrap();
+        assert_eq!(
+            rewrite.suggested_command.as_deref(),
+            Some("docker compose exec -T db psql -U app -c 'select 1'")
+        );
+
+        let rewrite = container_match(&container, "redis-cli ping", &running).unwrap().unwrap();
+        assert_eq!(rewrite.suggested_command.as_deref(), Some("docker exec cache redis-cli ping"));
+
+        // Already running inside the container
+        assert!(container_match(&container, "docker compose exec -T db psql", &running).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stopped_targets_are_skipped() {
+        let container = container();
+        let checked = RefCell::new(Vec::new());
+        let stopped = |target: &ContainerTarget| {
+            checked.borrow_mut().push(target.clone());
+            false
+        };
+
+        assert!(container_match(&container, "psql", &stopped).unwrap().is_none());
+        // Only the target whose tool matched was checked
+        assert_eq!(*checked.borrow(), vec![ContainerTarget::Service("db".to_string())]);
+
+        assert!(container_match(&container, "ls", &stopped).unwrap().is_none());
+        assert_eq!(checked.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_podman_runtime() {
+        let mut container = container();
+        container.runtime = ContainerRuntime::Podman;
+        let rewrite = container_match(&container, "pg_dump app", &|_| true).unwrap().unwrap();
+        assert_eq!(rewrite.suggested_command.as_deref(), Some("podman compose exec -T db pg_dump app"));
+    }
+}
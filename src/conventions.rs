@@ -0,0 +1,173 @@
+//! The project's conventions as Markdown
+//!
+//! One summary of the preferred commands, rules, aliases and allowlist serves
+//! four readers: people running `--list-conventions`, Claude calling the MCP
+//! `list_conventions` tool, subagents, whose prompts get it appended with
+//! `[subagents] share_conventions`, and Claude again after its context is
+//! compacted (see `compaction_summary`).
+
+use crate::directory::{list_aliases, locate_directory};
+use crate::types::{Config, DefaultDecision, Rule, Severity, ToolRule};
+use chrono::Utc;
+
+/// A Markdown summary of the conventions configured for the project.
+pub fn list_conventions(config: &Config) -> String {
+    let sections = convention_sections(config, false);
+    if sections.is_empty() {
+        "No conventions are configured for this project.".to_string()
+    } else {
+        sections.join("\n\n")
+    }
+}
+
+/// The conventions to carry over a context compaction, with directory
+/// aliases resolved to the paths they stand for, so Claude doesn't need to
+/// ask again.
+///
+/// # Returns
+/// The summary, or `None` if no conventions are configured
+pub fn compaction_summary(config: &Config) -> Option<String> {
+    let sections = convention_sections(config, true);
+    (!sections.is_empty()).then(|| {
+        format!(
+            "# Project conventions\nThese conventions from claude-hook-advisor still apply after the conversation is compacted.\n\n{}",
+            sections.join("\n\n")
+        )
+    })
+}
+
+/// One Markdown section per kind of convention that is configured.
+fn convention_sections(config: &Config, resolve_directories: bool) -> Vec<String> {
+    let mut commands = config.active_commands();
+    commands.sort();
+    let now = Utc::now();
+    let rules: Vec<&Rule> = config.active_rules().into_iter().filter(|rule| rule.is_active_at(now)).collect();
+    let aliases = list_aliases(config);
+
+    let mut sections = Vec::new();
+    if config.mode.default == DefaultDecision::Deny {
+        let allowed = if config.allowed.commands.is_empty() {
+            "nothing yet".to_string()
+        } else {
+            config.allowed.commands.iter().map(|command| format!("`{command}`")).collect::<Vec<_>>().join(", ")
+        };
+        sections.push(format!("## Allowed commands\nOnly these commands may run: {allowed}"));
+    }
+    if !commands.is_empty() {
+        let lines: Vec<String> = commands.iter().map(|(pattern, replacement)| format!("- `{pattern}` → use `{replacement}`")).collect();
+        sections.push(format!("## Preferred commands\n{}", lines.join("\n")));
+    }
+    if !config.inject_flags.is_empty() {
+        let mut injections: Vec<_> = config.inject_flags.iter().filter(|(_, flags)| !flags.is_empty()).collect();
+        injections.sort();
+        let lines: Vec<String> = injections.iter().map(|(pattern, flags)| format!("- `{pattern}` → always pass `{}`", flags.join(" "))).collect();
+        sections.push(format!("## Required flags\n{}", lines.join("\n")));
+    }
+    if !config.forbidden_flags.is_empty() {
+        let mut forbidden: Vec<_> = config.forbidden_flags.iter().filter(|(_, forbidden)| !forbidden.flags.is_empty()).collect();
+        forbidden.sort_by_key(|(pattern, _)| *pattern);
+        let lines: Vec<String> = forbidden
+            .iter()
+            .map(|(pattern, forbidden)| {
+                let flags: Vec<String> = forbidden.flags.iter().map(|flag| format!("`{flag}`")).collect();
+                format!("- `{pattern}` → never pass {}", flags.join(" or "))
+            })
+            .collect();
+        sections.push(format!("## Forbidden flags\n{}", lines.join("\n")));
+    }
+    if !rules.is_empty() {
+        let lines: Vec<String> = rules.iter().map(|rule| describe_rule(rule)).collect();
+        sections.push(format!("## Rules\n{}", lines.join("\n")));
+    }
+    if !config.tool_rules.is_empty() {
+        let lines: Vec<String> = config.tool_rules.iter().map(describe_tool_rule).collect();
+        sections.push(format!("## Tool rules\n{}", lines.join("\n")));
+    }
+    if !aliases.is_empty() {
+        let lines: Vec<String> = aliases
+            .iter()
+            .filter(|entry| !entry.shadowed)
+            .map(|entry| {
+                let resolved = resolve_directories.then(|| locate_directory(config, &entry.alias).ok()).flatten();
+                let path = resolved.map_or_else(|| entry.path.clone(), |resolution| resolution.canonical_path);
+                format!("- `{}` → {path} ({})", entry.alias, entry.scope)
+            })
+            .collect();
+        sections.push(format!("## Directory aliases\n{}", lines.join("\n")));
+    }
+    sections
+}
+
+fn describe_rule(rule: &Rule) -> String {
+    let action = action(rule.severity.unwrap_or_default());
+    let mut line = format!("- `{}` ({action})", rule.pattern);
+    if let Some(message) = &rule.message {
+        line.push_str(&format!(": {message}"));
+    }
+    if let Some(replacement) = &rule.replacement {
+        line.push_str(&format!(" Use `{replacement}` instead."));
+    }
+    if let Some(doc) = &rule.doc {
+        line.push_str(&format!(" [Why]({doc})"));
+    }
+    line
+}
+
+fn describe_tool_rule(rule: &ToolRule) -> String {
+    let mut line = format!("- `{}` calls with `{}` matching `{}` ({})", rule.tool, rule.field, rule.matches, action(rule.severity.unwrap_or_default()));
+    if let Some(message) = &rule.message {
+        line.push_str(&format!(": {message}"));
+    }
+    line
+}
+
+fn action(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warn => "warned",
+        Severity::Ask => "needs confirmation",
+        Severity::Block => "blocked",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_conventions() {
+        assert_eq!(list_conventions(&Config::default()), "No conventions are configured for this project.");
+
+        let config: Config = toml::from_str(
+            "[commands]\nnpm = \"bun\"\n\n[inject_flags]\nrsync = [\"--dry-run\"]\n\n[forbidden_flags]\n\"git push\" = { flags = [\"--force\", \"-f\"] }\n\n[[rules]]\npattern = \"rm -rf /\"\n\n[mode]\ndefault = \"deny\"\n\n[allowed]\ncommands = [\"ls\"]\n\n\
+             [[tool_rules]]\ntool = \"WebFetch\"\nfield = \"$.url\"\nmatches = \"internal\"\nseverity = \"ask\"\n",
+        )
+        .unwrap();
+        let conventions = list_conventions(&config);
+        assert!(conventions.starts_with("## Allowed commands\nOnly these commands may run: `ls`"), "{conventions}");
+        assert!(conventions.contains("## Preferred commands\n- `npm` → use `bun`"));
+        assert!(conventions.contains("## Forbidden flags\n- `git push` → never pass `--force` or `-f`"));
+        assert!(conventions.contains("## Required flags\n- `rsync` → always pass `--dry-run`"));
+        assert!(conventions.contains("## Rules\n- `rm -rf /` (blocked)"));
+        assert!(conventions.contains("## Tool rules\n- `WebFetch` calls with `$.url` matching `internal` (needs confirmation)"));
+    }
+
+    #[test]
+    fn test_compaction_summary() {
+        assert_eq!(compaction_summary(&Config::default()), None);
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let docs = temp_dir.path().join("docs");
+        std::fs::create_dir(&docs).unwrap();
+        let mut config = Config::default();
+        config.commands.insert("npm".to_string(), "bun".to_string());
+        config.semantic_directories.insert("docs".to_string(), format!("{}/./docs", temp_dir.path().display()));
+
+        let summary = compaction_summary(&config).unwrap();
+        assert!(summary.starts_with("# Project conventions\n"), "{summary}");
+        assert!(summary.contains("- `npm` → use `bun`"));
+        let resolved = docs.canonicalize().unwrap();
+        assert!(summary.contains(&format!("- `docs` → {} (project)", resolved.display())), "{summary}");
+        // --list-conventions shows aliases as configured
+        assert!(list_conventions(&config).contains("/./docs (project)"));
+    }
+}
@@ -0,0 +1,93 @@
+//! Deprecated configuration constructs
+//!
+//! Older configurations keep working: the legacy file name is still found,
+//! and older schemas are still read. Nothing tells the user they are out of
+//! date, though, until support is finally dropped. This module lists every
+//! deprecated construct in a configuration file, for the report in
+//! `--check-config` and a one-time `systemMessage` in hook mode, each
+//! pointing at `--migrate-config`.
+
+use crate::config::pending_migrations;
+use serde::Serialize;
+use std::path::Path;
+
+/// The command that upgrades every construct found here.
+pub const MIGRATE_COMMAND: &str = "claude-hook-advisor --migrate-config";
+
+/// File name used before `.claude.toml`.
+const LEGACY_FILE_NAME: &str = ".claude-hook-advisor.toml";
+
+/// A deprecated construct found in a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Deprecation {
+    /// What the file uses
+    pub construct: String,
+    /// What to use instead
+    pub replacement: String,
+}
+
+/// Lists the deprecated constructs in a configuration file.
+///
+/// # Arguments
+/// * `config_path` - Path of the file
+/// * `content` - The file's content
+///
+/// # Returns
+/// The constructs in the order `--migrate-config` upgrades them; empty if
+/// the file is current
+pub fn find_deprecations(config_path: &Path, content: &str) -> Vec<Deprecation> {
+    let mut deprecations = Vec::new();
+    if config_path.file_name().is_some_and(|name| name == LEGACY_FILE_NAME) {
+        deprecations.push(Deprecation {
+            construct: format!("The {LEGACY_FILE_NAME} file name"),
+            replacement: ".claude.toml".to_string(),
+        });
+    }
+    deprecations.extend(pending_migrations(content).into_iter().map(|(construct, replacement)| Deprecation {
+        construct: construct.to_string(),
+        replacement: replacement.to_string(),
+    }));
+    deprecations
+}
+
+/// The hook's one-line notice for `deprecations`.
+pub fn deprecation_notice(deprecations: &[Deprecation]) -> String {
+    let found: Vec<String> =
+        deprecations.iter().map(|deprecation| format!("{} → {}", deprecation.construct, deprecation.replacement)).collect();
+    format!(
+        "📜 This project's claude-hook-advisor configuration is out of date. Deprecated: {}. \
+         They still work for now. Run `{MIGRATE_COMMAND}` to upgrade; the old file is backed up first.",
+        found.join("; ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_deprecations() {
+        let legacy = find_deprecations(Path::new("/p/.claude-hook-advisor.toml"), "[commands]\nnpm = \"bun\"\n");
+        assert_eq!(
+            legacy,
+            [
+                Deprecation {
+                    construct: "The .claude-hook-advisor.toml file name".to_string(),
+                    replacement: ".claude.toml".to_string()
+                },
+                Deprecation {
+                    construct: "Top-level [commands] mappings".to_string(),
+                    replacement: "[[rules]] entries with a replacement".to_string()
+                },
+            ]
+        );
+        let notice = deprecation_notice(&legacy);
+        assert!(notice.contains("file name → .claude.toml; Top-level [commands] mappings → [[rules]]"));
+        assert!(notice.contains(MIGRATE_COMMAND));
+
+        // Current files, and old ones with nothing to upgrade, are fine
+        assert!(find_deprecations(Path::new(".claude.toml"), "version = 2\n[commands]\nnpm = \"bun\"\n").is_empty());
+        assert!(find_deprecations(Path::new(".claude.toml"), "[semantic_directories]\ndocs = \"~/Docs\"\n").is_empty());
+        assert!(find_deprecations(Path::new(".claude.toml"), "[commands\n").is_empty());
+    }
+}
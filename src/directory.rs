@@ -1,14 +1,18 @@
 //! Directory resolution and aliasing functionality
 
-use crate::types::{Config, DirectoryResolution};
-use anyhow::{anyhow, Context, Result};
+use crate::types::{
+    AdvisorError, Config, ConfigError, DirectoryResolution, DirectoryScope, DirectoryStatus, IoContext, Result,
+    SymlinkPolicy,
+};
+use chrono::{Local, NaiveDate};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Mutex;
+use std::time::SystemTime;
 
 /// Cache for compiled regex patterns to avoid recompilation
 static REGEX_CACHE: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
@@ -22,7 +26,7 @@ fn get_cached_regex(pattern: &str) -> Result<Regex> {
         return Ok(regex.clone());
     }
     
-    let regex = Regex::new(pattern).context("Failed to compile regex pattern")?;
+    let regex = Regex::new(pattern)?;
     cache.insert(pattern.to_string(), regex.clone());
     Ok(regex)
 }
@@ -37,26 +41,186 @@ fn get_cached_regex(pattern: &str) -> Result<Regex> {
 /// * `config` - Configuration containing directory mappings
 /// * `alias` - The directory alias to resolve
 /// 
+/// A directory that doesn't exist is created when `[directories]` sets
+/// `create_missing`; otherwise it is reported with `DirectoryStatus::Missing`.
+///
 /// # Returns
 /// * `Ok(DirectoryResolution)` - Resolved directory with metadata
-/// * `Err` - If alias not found or path invalid
+/// * `Err` - If alias not found, path invalid or the directory can't be created
 pub fn resolve_directory(config: &Config, alias: &str) -> Result<DirectoryResolution> {
-    // Find the alias in semantic_directories
-    let directory_path = config.semantic_directories.get(alias)
-        .ok_or_else(|| anyhow!("Directory alias '{}' not found", alias))?;
+    resolve(config, alias, config.directories.create_missing)
+}
+
+/// Resolves an alias like `resolve_directory`, but never creates a missing
+/// directory; used to inspect aliases without side effects.
+pub fn locate_directory(config: &Config, alias: &str) -> Result<DirectoryResolution> {
+    resolve(config, alias, false)
+}
+
+fn resolve(config: &Config, alias: &str, create_missing: bool) -> Result<DirectoryResolution> {
+    // Project aliases shadow user-wide ones of the same name
+    let (directory_path, scope) = match config.semantic_directories.get(alias) {
+        Some(path) => (path, DirectoryScope::Project),
+        None => config
+            .user_directories
+            .get(alias)
+            .map(|path| (path, DirectoryScope::User))
+            .ok_or_else(|| ConfigError::UnknownAlias(alias.to_string()))?,
+    };
     
-    // Expand tilde and resolve to canonical path (provides basic security)
-    let expanded_path = expand_path(directory_path)?;
-    let canonical_path = fs::canonicalize(&expanded_path)
-        .with_context(|| format!("Failed to resolve path: {}", expanded_path.display()))?;
+    // Expand tilde and placeholders, and normalize `..` before anything
+    // touches the disk
+    let (expanded_path, variables_substituted) =
+        substitute_placeholders(&expand_path(directory_path)?, Local::now().date_naive())?;
+    let absolute = std::path::absolute(&expanded_path)
+        .io_context(|| format!("Failed to resolve path: {}", expanded_path.display()))?;
+    let absolute = normalize_lexically(&absolute);
+
+    // Roots are checked against the real path, so a symlink can't lead outside them
+    let real = real_path(&absolute);
+    if !within_allowed_roots(&config.directories.allowed_roots, &real)? {
+        return Err(ConfigError::DirectoryNotAllowed {
+            alias: alias.to_string(),
+            path: real.to_string_lossy().to_string(),
+        }
+        .into());
+    }
+
+    let status = if absolute.exists() {
+        DirectoryStatus::Exists
+    } else if create_missing {
+        fs::create_dir_all(&absolute)
+            .io_context(|| format!("Failed to create directory: {}", absolute.display()))?;
+        DirectoryStatus::Created
+    } else {
+        DirectoryStatus::Missing
+    };
+    let canonical_path = match config.directories.symlinks {
+        SymlinkPolicy::Resolve => real_path(&absolute),
+        SymlinkPolicy::Preserve => absolute,
+    };
 
     Ok(DirectoryResolution {
         canonical_path: canonical_path.to_string_lossy().to_string(),
         alias_used: alias.to_string(),
-        variables_substituted: Vec::new(),
+        variables_substituted,
+        scope,
+        status,
     })
 }
 
+/// Fills in the `{...}` placeholders of a path at lookup time.
+///
+/// - `{today}` and `{yesterday}` become the date as `YYYY-MM-DD` and may
+///   appear anywhere in a path component, as in `~/Journal/{today}`
+/// - `{latest_semver_dir}` becomes the subdirectory with the highest version
+///   name (`v1.10.0` > `v1.9.2` > `v1.9.2-rc1`); names that aren't versions
+///   are ignored
+/// - `{latest_dir}` becomes the most recently modified subdirectory
+///
+/// The `latest` placeholders scan the directory above them, so they must
+/// make up a whole path component.
+///
+/// # Arguments
+/// * `path` - Path with the tilde already expanded
+/// * `today` - The date `{today}` stands for
+///
+/// # Returns
+/// * `Ok((PathBuf, Vec<(String, String)>))` - The path and each placeholder
+///   with the value it was replaced by
+/// * `Err` - If a placeholder is unknown or misplaced, or no subdirectory
+///   matches a `latest` placeholder
+fn substitute_placeholders(path: &Path, today: NaiveDate) -> Result<(PathBuf, Vec<(String, String)>)> {
+    let mut resolved = PathBuf::new();
+    let mut substituted = Vec::new();
+
+    for component in path.components() {
+        let text = component.as_os_str().to_string_lossy();
+        if !text.contains('{') {
+            resolved.push(component);
+            continue;
+        }
+
+        let value = match text.as_ref() {
+            "{latest_semver_dir}" => {
+                let name = latest_subdirectory(&resolved, |name, _| version_key(name))?;
+                substituted.push(("latest_semver_dir".to_string(), name.clone()));
+                name
+            }
+            "{latest_dir}" => {
+                let name = latest_subdirectory(&resolved, |_, modified| Some(modified))?;
+                substituted.push(("latest_dir".to_string(), name.clone()));
+                name
+            }
+            _ => {
+                let mut error = None;
+                let value = PLACEHOLDER.replace_all(&text, |captures: &regex::Captures| {
+                    let date = match &captures[1] {
+                        "today" => today,
+                        "yesterday" => today.pred_opt().unwrap_or(today),
+                        name => {
+                            error.get_or_insert_with(|| name.to_string());
+                            return String::new();
+                        }
+                    };
+                    let date = date.format("%Y-%m-%d").to_string();
+                    substituted.push((captures[1].to_string(), date.clone()));
+                    date
+                });
+                if let Some(name) = error {
+                    return Err(ConfigError::InvalidFormat(format!(
+                        "Unknown placeholder {{{name}}} in {}; {{latest_semver_dir}} and {{latest_dir}} must be a whole path component",
+                        path.display()
+                    ))
+                    .into());
+                }
+                value.into_owned()
+            }
+        };
+        resolved.push(value);
+    }
+
+    Ok((resolved, substituted))
+}
+
+/// Placeholder syntax inside a path component: `{name}`.
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([A-Za-z_]+)\}").expect("placeholder regex is valid"));
+
+/// Name of the subdirectory of `dir` with the greatest `key`; entries for
+/// which `key` returns None are skipped.
+fn latest_subdirectory<K: Ord>(dir: &Path, key: impl Fn(&str, SystemTime) -> Option<K>) -> Result<String> {
+    let entries = fs::read_dir(dir).io_context(|| format!("Failed to scan directory: {}", dir.display()))?;
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+            Some((key(&name, modified)?, name))
+        })
+        .max()
+        .map(|(_, name)| name)
+        .ok_or_else(|| {
+            AdvisorError::other(format!("Failed to resolve path: {}", dir.display()), "no matching subdirectory")
+        })
+}
+
+/// Sort key of a version directory name such as `1.2.3`, `v1.2` or
+/// `v2.0.0-rc.1`: numeric parts first, then releases above pre-releases.
+fn version_key(name: &str) -> Option<(Vec<u64>, bool, String)> {
+    let name = name.strip_prefix('v').or_else(|| name.strip_prefix('V')).unwrap_or(name);
+    let (numbers, pre_release) = match name.split_once('-') {
+        Some((numbers, pre_release)) => (numbers, Some(pre_release)),
+        None => (name, None),
+    };
+    let numbers = numbers.split('.').map(|part| part.parse().ok()).collect::<Option<Vec<u64>>>()?;
+    // A lone number is more likely a date or an id than a version
+    if numbers.len() < 2 {
+        return None;
+    }
+    Some((numbers, pre_release.is_none(), pre_release.unwrap_or_default().to_string()))
+}
+
 /// Detects directory references in natural language text.
 /// 
 /// Scans user prompts for potential directory references and attempts
@@ -69,19 +233,10 @@ pub fn resolve_directory(config: &Config, alias: &str) -> Result<DirectoryResolu
 /// # Returns
 /// * `Vec<DirectoryResolution>` - All resolved directory references found
 pub fn detect_directory_references(config: &Config, text: &str) -> Vec<DirectoryResolution> {
-    let mut results = Vec::new();
-    
-    // Try exact alias matches first
-    for alias in config.semantic_directories.keys() {
-        let alias_pattern = format!(r"\b{}\b", regex::escape(alias));
-        if let Ok(regex) = get_cached_regex(&alias_pattern) {
-            if regex.is_match(text) {
-                if let Ok(resolution) = resolve_directory(config, alias) {
-                    results.push(resolution);
-                }
-            }
-        }
-    }
+    let mut results: Vec<DirectoryResolution> = mentioned_aliases(config, text)
+        .into_iter()
+        .filter_map(|alias| resolve_directory(config, alias).ok())
+        .collect();
     
     // Remove duplicates (same canonical path)
     results.sort_by(|a, b| a.canonical_path.cmp(&b.canonical_path));
@@ -90,6 +245,218 @@ pub fn detect_directory_references(config: &Config, text: &str) -> Vec<Directory
     results
 }
 
+/// The aliases `text` mentions as whole words.
+pub fn mentioned_aliases<'a>(config: &'a Config, text: &str) -> Vec<&'a String> {
+    all_aliases(config)
+        .filter(|alias| {
+            get_cached_regex(&format!(r"\b{}\b", regex::escape(alias))).is_ok_and(|regex| regex.is_match(text))
+        })
+        .collect()
+}
+
+/// Every alias in either scope, each name once.
+fn all_aliases(config: &Config) -> impl Iterator<Item = &String> {
+    config.semantic_directories.keys().chain(
+        config.user_directories.keys().filter(|alias| !config.semantic_directories.contains_key(*alias)),
+    )
+}
+
+/// A configured alias, as listed by `dirs list`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AliasEntry {
+    pub alias: String,
+    /// The configured path, before expansion
+    pub path: String,
+    pub scope: DirectoryScope,
+    /// Whether a project alias of the same name hides this user-wide one
+    pub shadowed: bool,
+}
+
+/// Lists the aliases of both scopes, sorted by alias with the project entry
+/// before a user-wide one of the same name.
+pub fn list_aliases(config: &Config) -> Vec<AliasEntry> {
+    let project = config.semantic_directories.iter().map(|(alias, path)| AliasEntry {
+        alias: alias.clone(),
+        path: path.clone(),
+        scope: DirectoryScope::Project,
+        shadowed: false,
+    });
+    let user = config.user_directories.iter().map(|(alias, path)| AliasEntry {
+        alias: alias.clone(),
+        path: path.clone(),
+        scope: DirectoryScope::User,
+        shadowed: config.semantic_directories.contains_key(alias),
+    });
+    let mut entries: Vec<AliasEntry> = project.chain(user).collect();
+    entries.sort_by(|a, b| a.alias.cmp(&b.alias).then_with(|| a.shadowed.cmp(&b.shadowed)));
+    entries
+}
+
+/// Most aliases listed for one misspelled mention.
+const MAX_ALIAS_CANDIDATES: usize = 3;
+
+/// Words in a prompt that look like a misspelled alias.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AliasSuggestion {
+    /// The words as they appear in the prompt, lowercased
+    pub mention: String,
+    /// Nearby aliases with their edit distances, closest first
+    pub candidates: Vec<(String, usize)>,
+}
+
+/// Finds phrases in `text` that are close to, but not exactly, a known alias,
+/// such as "projcet docs" for "project docs".
+///
+/// Each alias is compared, ignoring case, with every run of as many words in
+/// the prompt. A phrase is close when its Levenshtein distance is at most a
+/// fifth of the alias length, so aliases shorter than five characters never
+/// get suggestions. Aliases the prompt already mentions exactly are left out.
+///
+/// # Arguments
+/// * `config` - Configuration containing directory mappings
+/// * `text` - The user prompt text to analyze
+///
+/// # Returns
+/// * `Vec<AliasSuggestion>` - One entry per close phrase, in prompt order
+pub fn suggest_aliases(config: &Config, text: &str) -> Vec<AliasSuggestion> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric() && c != '_' && c != '-').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let aliases: Vec<String> = all_aliases(config).map(|alias| alias.to_lowercase()).collect();
+
+    // (position in prompt, phrase, alias, distance)
+    let mut close = Vec::new();
+    let mut mentioned = Vec::new();
+    for alias in &aliases {
+        let size = alias.split_whitespace().count().max(1);
+        let max_distance = alias.chars().count() / 5;
+        for (position, window) in words.windows(size).enumerate() {
+            let phrase = window.join(" ");
+            match levenshtein(&phrase, alias) {
+                0 => mentioned.push(alias.as_str()),
+                distance if distance <= max_distance => close.push((position, phrase, alias.as_str(), distance)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut suggestions: Vec<(usize, AliasSuggestion)> = Vec::new();
+    close.sort();
+    for (position, phrase, alias, distance) in close {
+        if mentioned.contains(&alias) || aliases.contains(&phrase) {
+            continue;
+        }
+        match suggestions.iter_mut().find(|(_, suggestion)| suggestion.mention == phrase) {
+            Some((_, suggestion)) => suggestion.candidates.push((alias.to_string(), distance)),
+            None => suggestions.push((
+                position,
+                AliasSuggestion { mention: phrase, candidates: vec![(alias.to_string(), distance)] },
+            )),
+        }
+    }
+
+    suggestions
+        .into_iter()
+        .map(|(_, mut suggestion)| {
+            suggestion.candidates.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            suggestion.candidates.truncate(MAX_ALIAS_CANDIDATES);
+            suggestion
+        })
+        .collect()
+}
+
+/// Number of single-character insertions, deletions and substitutions that
+/// turn `a` into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// An alias defined in both the project and the user-wide scope.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AliasCollision {
+    pub alias: String,
+    /// Path from the project configuration, which is the one used
+    pub project_path: String,
+    /// Path from the user-wide directories file, which is shadowed
+    pub user_path: String,
+}
+
+/// Finds aliases defined in both scopes, sorted by alias.
+///
+/// Only aliases whose paths differ are reported; defining the same path in
+/// both places is harmless.
+pub fn alias_collisions(config: &Config) -> Vec<AliasCollision> {
+    let mut collisions: Vec<AliasCollision> = config
+        .semantic_directories
+        .iter()
+        .filter_map(|(alias, project_path)| {
+            let user_path = config.user_directories.get(alias).filter(|user_path| *user_path != project_path)?;
+            Some(AliasCollision {
+                alias: alias.clone(),
+                project_path: project_path.clone(),
+                user_path: user_path.clone(),
+            })
+        })
+        .collect();
+    collisions.sort_by(|a, b| a.alias.cmp(&b.alias));
+    collisions
+}
+
+/// Removes `.` and resolves `..` components without following symlinks.
+pub(crate) fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// The path with every symlink resolved. For a path that doesn't exist
+/// (yet), the longest existing ancestor is resolved and the rest appended.
+pub(crate) fn real_path(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(real) = fs::canonicalize(ancestor) {
+            return match path.strip_prefix(ancestor) {
+                Ok(rest) if !rest.as_os_str().is_empty() => real.join(rest),
+                _ => real,
+            };
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Whether `real` is inside one of the allowed roots; true when no roots are configured.
+pub(crate) fn within_allowed_roots(roots: &[String], real: &Path) -> Result<bool> {
+    if roots.is_empty() {
+        return Ok(true);
+    }
+    for root in roots {
+        let root = expand_path(root)?;
+        let root = std::path::absolute(&root).io_context(|| format!("Failed to resolve path: {}", root.display()))?;
+        if real.starts_with(real_path(&normalize_lexically(&root))) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
 
 /// Expands tilde (~) to user home directory.
 /// 
@@ -102,10 +469,10 @@ pub fn detect_directory_references(config: &Config, text: &str) -> Vec<Directory
 /// # Returns
 /// * `Ok(PathBuf)` - Expanded absolute path
 /// * `Err` - If home directory cannot be determined
-fn expand_path(path: &str) -> Result<PathBuf> {
+pub(crate) fn expand_path(path: &str) -> Result<PathBuf> {
     if path.starts_with('~') {
         let home_dir = env::var("HOME")
-            .with_context(|| "Failed to get HOME environment variable")?;
+            .map_err(|e| AdvisorError::other("Failed to get HOME environment variable", e))?;
         let expanded = path.replacen('~', &home_dir, 1);
         Ok(PathBuf::from(expanded))
     } else {
@@ -129,6 +496,7 @@ mod tests {
         Config {
             commands: HashMap::new(),
             semantic_directories,
+            ..Config::default()
         }
     }
 
@@ -172,7 +540,177 @@ mod tests {
         
         // The important behavioral test: ensure we're not doing fuzzy matching
         let no_fuzzy_match = "check documentation folder";
-        let results2 = detect_directory_references(&config, &no_fuzzy_match);
+        let results2 = detect_directory_references(&config, no_fuzzy_match);
         assert_eq!(results2.len(), 0, "Should not fuzzy-match 'documentation' to 'docs'");
     }
+
+    #[test]
+    fn test_placeholders_are_resolved_at_lookup_time() {
+        let root = tempfile::TempDir::new().unwrap();
+        for dir in ["v1.9.2", "v1.10.0-rc1", "v1.10.0", "2024-05-01", "notes"] {
+            fs::create_dir(root.path().join(dir)).unwrap();
+        }
+        let today = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+
+        let (path, substituted) = substitute_placeholders(&root.path().join("{latest_semver_dir}/notes"), today).unwrap();
+        assert_eq!(path, root.path().join("v1.10.0/notes"));
+        assert_eq!(substituted, vec![("latest_semver_dir".to_string(), "v1.10.0".to_string())]);
+
+        let (path, _) = substitute_placeholders(&root.path().join("{yesterday}"), today).unwrap();
+        assert_eq!(path, root.path().join("2024-05-01"));
+        let (path, _) = substitute_placeholders(&root.path().join("log-{today}.d"), today).unwrap();
+        assert_eq!(path, root.path().join("log-2024-05-02.d"));
+
+        // A newly created directory is the most recently modified one (named
+        // to also win a tie on filesystems with coarse timestamps)
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::create_dir(root.path().join("zz-newest")).unwrap();
+        let (path, _) = substitute_placeholders(&root.path().join("{latest_dir}"), today).unwrap();
+        assert_eq!(path, root.path().join("zz-newest"));
+
+        assert!(substitute_placeholders(&root.path().join("{tomorrow}"), today).is_err());
+        assert!(substitute_placeholders(&root.path().join("notes/{latest_semver_dir}"), today).is_err());
+    }
+
+    #[test]
+    fn test_missing_directories() {
+        let root = tempfile::TempDir::new().unwrap();
+        let target = root.path().join("notes/2024");
+        let mut config = Config {
+            semantic_directories: HashMap::from([("notes".to_string(), target.to_string_lossy().to_string())]),
+            ..Config::default()
+        };
+
+        let resolution = resolve_directory(&config, "notes").unwrap();
+        assert_eq!(resolution.status, DirectoryStatus::Missing);
+        assert_eq!(PathBuf::from(&resolution.canonical_path), target);
+        assert!(!target.exists());
+
+        config.directories.create_missing = true;
+        assert_eq!(locate_directory(&config, "notes").unwrap().status, DirectoryStatus::Missing);
+        assert_eq!(resolve_directory(&config, "notes").unwrap().status, DirectoryStatus::Created);
+        assert!(target.is_dir());
+        assert_eq!(resolve_directory(&config, "notes").unwrap().status, DirectoryStatus::Exists);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policy_and_allowed_roots() {
+        let root = tempfile::TempDir::new().unwrap();
+        let real = fs::canonicalize(root.path()).unwrap();
+        fs::create_dir_all(real.join("allowed/docs")).unwrap();
+        fs::create_dir_all(real.join("elsewhere")).unwrap();
+        std::os::unix::fs::symlink(real.join("allowed/docs"), real.join("allowed/link")).unwrap();
+        std::os::unix::fs::symlink(real.join("elsewhere"), real.join("allowed/escape")).unwrap();
+
+        let target = |path: &str| real.join(path).to_string_lossy().to_string();
+        let mut config = Config {
+            semantic_directories: HashMap::from([
+                ("link".to_string(), target("allowed/./link")),
+                ("dotdot".to_string(), target("allowed/docs/../docs")),
+                ("escape".to_string(), target("allowed/escape")),
+                ("outside".to_string(), target("allowed/../elsewhere")),
+            ]),
+            ..Config::default()
+        };
+
+        assert_eq!(resolve_directory(&config, "link").unwrap().canonical_path, target("allowed/docs"));
+        assert_eq!(resolve_directory(&config, "dotdot").unwrap().canonical_path, target("allowed/docs"));
+        config.directories.symlinks = SymlinkPolicy::Preserve;
+        assert_eq!(resolve_directory(&config, "link").unwrap().canonical_path, target("allowed/link"));
+
+        config.directories.allowed_roots = vec![target("allowed")];
+        assert!(resolve_directory(&config, "link").is_ok());
+        for alias in ["escape", "outside"] {
+            let err = resolve_directory(&config, alias).unwrap_err();
+            assert!(matches!(err, AdvisorError::Config(ConfigError::DirectoryNotAllowed { .. })), "{alias}");
+        }
+    }
+
+    #[test]
+    fn test_list_aliases() {
+        let config = Config {
+            semantic_directories: HashMap::from([("docs".to_string(), "./docs".to_string())]),
+            user_directories: HashMap::from([
+                ("notes".to_string(), "~/Notes".to_string()),
+                ("docs".to_string(), "~/Docs".to_string()),
+            ]),
+            ..Config::default()
+        };
+        let entries = list_aliases(&config);
+        let summary: Vec<(&str, DirectoryScope, bool)> =
+            entries.iter().map(|e| (e.alias.as_str(), e.scope, e.shadowed)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("docs", DirectoryScope::Project, false),
+                ("docs", DirectoryScope::User, true),
+                ("notes", DirectoryScope::User, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("project docs", "project docs"), 0);
+        assert_eq!(levenshtein("projcet docs", "project docs"), 2);
+        assert_eq!(levenshtein("docs", "doc"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggest_aliases_for_near_misses() {
+        let mut config = create_test_config();
+        config.semantic_directories.insert("project docs".to_string(), "~/Documents/project".to_string());
+
+        let suggestions = suggest_aliases(&config, "Open the Projcet Docs, please");
+        assert_eq!(
+            suggestions,
+            vec![AliasSuggestion {
+                mention: "projcet docs".to_string(),
+                candidates: vec![("project docs".to_string(), 2)]
+            }]
+        );
+        let suggestions = suggest_aliases(&config, "see project_dcos");
+        assert_eq!(suggestions[0].candidates, vec![("project_docs".to_string(), 2)]);
+
+        // Exact mentions, short aliases and distant words get no suggestions
+        assert!(suggest_aliases(&config, "check the project docs").is_empty());
+        assert!(suggest_aliases(&config, "check the dogs").is_empty());
+        assert!(suggest_aliases(&config, "check the product logs").is_empty());
+    }
+
+    #[test]
+    fn test_project_aliases_shadow_user_aliases() {
+        let project = tempfile::TempDir::new().unwrap();
+        let user = tempfile::TempDir::new().unwrap();
+        let path = |dir: &tempfile::TempDir| dir.path().to_string_lossy().to_string();
+        let config = Config {
+            semantic_directories: HashMap::from([("docs".to_string(), path(&project))]),
+            user_directories: HashMap::from([
+                ("docs".to_string(), path(&user)),
+                ("notes".to_string(), path(&user)),
+                ("scratch".to_string(), path(&project)),
+            ]),
+            ..Config::default()
+        };
+
+        let docs = resolve_directory(&config, "docs").unwrap();
+        assert_eq!(docs.scope, DirectoryScope::Project);
+        assert_eq!(PathBuf::from(docs.canonical_path), fs::canonicalize(project.path()).unwrap());
+        assert_eq!(resolve_directory(&config, "notes").unwrap().scope, DirectoryScope::User);
+
+        let results = detect_directory_references(&config, "compare my notes with the docs");
+        assert_eq!(results.len(), 2);
+
+        let config = Config {
+            semantic_directories: HashMap::from([("docs".to_string(), path(&project)), ("scratch".to_string(), path(&project))]),
+            ..config
+        };
+        assert_eq!(
+            alias_collisions(&config),
+            vec![AliasCollision { alias: "docs".to_string(), project_path: path(&project), user_path: path(&user) }]
+        );
+    }
 }
\ No newline at end of file
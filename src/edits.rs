@@ -0,0 +1,175 @@
+//! Classifying file edits as documentation-only
+//!
+//! An edit that only touches Markdown or other prose files, or only changes
+//! comments in code, can't change what the project does. `[sandbox]
+//! ignore_doc_edits` lets such Write, Edit and MultiEdit calls skip the
+//! checks meant for code. The classification is lexical: comments are
+//! stripped from the text before and after the edit, and if the remaining
+//! code is the same, only comments changed. Anything it isn't sure of, such
+//! as a language it doesn't know, counts as a code edit.
+
+use serde_json::Value;
+use std::path::Path;
+
+/// Extensions of files that hold documentation rather than code.
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "rst", "txt", "adoc"];
+
+/// How comments and strings look in a language.
+struct CommentSyntax {
+    line: &'static str,
+    block: Option<(&'static str, &'static str)>,
+    /// Characters that open and close string literals, which may contain comment markers
+    quotes: &'static [char],
+}
+
+const SLASH: CommentSyntax = CommentSyntax { line: "//", block: Some(("/*", "*/")), quotes: &['"', '`'] };
+const HASH: CommentSyntax = CommentSyntax { line: "#", block: None, quotes: &['"', '\''] };
+const DASH: CommentSyntax = CommentSyntax { line: "--", block: None, quotes: &['"', '\''] };
+
+/// Whether a file tool call only changes documentation or comments.
+///
+/// # Arguments
+/// * `tool_name` - The tool, such as `Edit`; only Write, Edit and MultiEdit can qualify
+/// * `tool_input` - The call's input, as sent
+///
+/// # Returns
+/// * `true` - If the file is a documentation file, or the code without comments is unchanged
+/// * `false` - For any other call, or when in doubt
+pub fn is_doc_only_edit(tool_name: &str, tool_input: &Value) -> bool {
+    let Some(path) = tool_input["file_path"].as_str().map(Path::new) else {
+        return false;
+    };
+    if !matches!(tool_name, "Write" | "Edit" | "MultiEdit") {
+        return false;
+    }
+    if extension(path).is_some_and(|extension| DOC_EXTENSIONS.contains(&extension.as_str())) {
+        return true;
+    }
+    let Some(syntax) = comment_syntax(path) else {
+        return false;
+    };
+
+    let same_code = |old: &Value, new: &Value| match (old.as_str(), new.as_str()) {
+        (Some(old), Some(new)) => code_lines(old, syntax) == code_lines(new, syntax),
+        _ => false,
+    };
+    match tool_name {
+        "Edit" => same_code(&tool_input["old_string"], &tool_input["new_string"]),
+        "MultiEdit" => tool_input["edits"]
+            .as_array()
+            .is_some_and(|edits| !edits.is_empty() && edits.iter().all(|edit| same_code(&edit["old_string"], &edit["new_string"]))),
+        // A new file is all new code; an existing one is compared with what it replaces
+        _ => std::fs::read_to_string(path)
+            .is_ok_and(|existing| same_code(&Value::from(existing), &tool_input["content"])),
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase)
+}
+
+fn comment_syntax(path: &Path) -> Option<&'static CommentSyntax> {
+    let syntax = match extension(path)?.as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "java" | "kt" | "kts" | "scala" | "swift" | "go" | "js" | "jsx"
+        | "mjs" | "cjs" | "ts" | "tsx" | "dart" | "proto" | "zig" | "scss" | "less" => &SLASH,
+        "py" | "sh" | "bash" | "zsh" | "rb" | "pl" | "r" | "toml" | "yaml" | "yml" | "conf" | "cfg" | "cmake" | "tf" => &HASH,
+        "sql" | "lua" | "hs" => &DASH,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// The non-blank lines of `text` with comments removed and trailing
+/// whitespace trimmed. Indentation is kept, since it is code in some languages.
+fn code_lines(text: &str, syntax: &CommentSyntax) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let mut code = String::new();
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if in_block {
+                let end = syntax.block.map_or("", |(_, end)| end);
+                match rest.find(end) {
+                    Some(at) => {
+                        in_block = false;
+                        rest = &rest[at + end.len()..];
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+
+            if let Some(open) = quote {
+                quote = if escaped || c != open { Some(open) } else { None };
+                escaped = !escaped && c == '\\';
+            } else if syntax.quotes.contains(&c) {
+                quote = Some(c);
+            } else if let Some((start, _)) = syntax.block.filter(|(start, _)| rest.starts_with(start)) {
+                in_block = true;
+                rest = &rest[start.len()..];
+                continue;
+            } else if rest.starts_with(syntax.line) && (syntax.line != "#" || code.is_empty() || code.ends_with(char::is_whitespace)) {
+                // `#` only starts a comment at the start of a word, so `$#` and `a#b` stay code
+                break;
+            }
+            code.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        let code = code.trim_end();
+        if !code.trim().is_empty() {
+            lines.push(code.to_string());
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn edit(path: &str, old: &str, new: &str) -> bool {
+        is_doc_only_edit("Edit", &json!({ "file_path": path, "old_string": old, "new_string": new }))
+    }
+
+    #[test]
+    fn test_comment_only_edits() {
+        assert!(edit("/p/src/main.rs", "// Parse args\nlet x = 1; // one", "/// Parses the arguments\nlet x = 1;"));
+        assert!(edit("/p/src/lib.ts", "/* old\n   note */\nrun();", "/* new note */\nrun();\n"));
+        assert!(edit("/p/build.sh", "# setup\necho $# args", "# Set up the build\necho $# args"));
+        assert!(edit("/p/README.md", "cargo build", "rm -rf /"));
+
+        assert!(!edit("/p/src/main.rs", "let x = 1; // one", "let x = 2; // one"));
+        assert!(!edit("/p/src/main.rs", r#"let url = "http://a";"#, r#"let url = "http://b";"#));
+        assert!(!edit("/p/app.py", "if ok:\n    run()", "if ok:\nrun()"));
+        assert!(!edit("/p/app.py", "x = 'a # b'", "x = 'a # c'"));
+        assert!(!edit("/p/Makefile", "# a", "# b"));
+        assert!(!is_doc_only_edit("Read", &json!({ "file_path": "/p/README.md" })));
+    }
+
+    #[test]
+    fn test_multi_edit_and_write() {
+        let multi = json!({ "file_path": "/p/src/lib.rs", "edits": [
+            { "old_string": "// a", "new_string": "// b" },
+            { "old_string": "fn f() {}", "new_string": "fn f() {} // trivial" },
+        ] });
+        assert!(is_doc_only_edit("MultiEdit", &multi));
+        let mixed = json!({ "file_path": "/p/src/lib.rs", "edits": [{ "old_string": "// a", "new_string": "fn g() {}" }] });
+        assert!(!is_doc_only_edit("MultiEdit", &mixed));
+
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("config.toml");
+        fs::write(&file, "# Old comment\nname = \"app\"\n").unwrap();
+        let path = file.to_str().unwrap();
+        assert!(is_doc_only_edit("Write", &json!({ "file_path": path, "content": "# New comment\nname = \"app\"\n" })));
+        assert!(!is_doc_only_edit("Write", &json!({ "file_path": path, "content": "name = \"other\"\n" })));
+        let new_file = dir.path().join("new.toml");
+        assert!(!is_doc_only_edit("Write", &json!({ "file_path": new_file.to_str().unwrap(), "content": "# only a comment\n" })));
+    }
+}
@@ -1,18 +1,43 @@
 //! Hook processing logic
 
-use crate::config::{load_config_auto, load_config_from_path};
-use crate::directory::detect_directory_references;
-use crate::types::{Config, HookInput, HookOutput};
-use anyhow::{Context, Result};
-use once_cell::sync::Lazy;
-use regex::Regex;
-use std::collections::HashMap;
-use std::io::{self, Read};
-use std::path::Path;
-use std::sync::Mutex;
-
-/// Cache for compiled regex patterns to avoid recompilation
-static REGEX_CACHE: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+use crate::cache::load_config_cached;
+use crate::config::{load_config_auto_in, load_config_from_path, load_user_directories, project_root, resolve_config_path};
+use crate::conventions::compaction_summary;
+use crate::deprecation::{deprecation_notice, find_deprecations};
+use crate::edits::is_doc_only_edit;
+use crate::directory::{detect_directory_references, locate_directory, mentioned_aliases, suggest_aliases};
+use crate::policy::Policy;
+use crate::rules::{RuleContext, RuleMatch};
+use crate::audit::{AuditEvent, AuditLog};
+use crate::approvals::{request_approval, ApprovalRequest, Verdict};
+use crate::subagent::{check_subagent_prompt, with_conventions};
+use crate::sandbox::{check_sandbox, tool_paths};
+use crate::shell_aliases::expand_aliases;
+use crate::search::{search_defaults, searches_project};
+use crate::checklist::{added_lines, unmet_items};
+use crate::advice::{advice_message, slow_command_advice};
+use crate::state::{mark_deprecations_checked, mark_onboarding_shown, DurationLog, SessionState, SnoozeList, Suggestion};
+use crate::notify::notify;
+use crate::telemetry::ship_event;
+use crate::tool_rules::check_tool_rules;
+use crate::word_diff::word_diff;
+use crate::usage;
+use crate::types::{
+    AdvisorError, ApprovalFallback, Config, ConfigError, DirectoryStatus, HookInput, HookOutput, HookSpecificOutput, PermissionDecision,
+    PermissionOutput,
+    PostToolUseOutput, Result, SessionStartOutput, Severity, StopOutput, SuppressedOutput, SystemMessageOutput, ToolInput, TrackedOutput, TrackedRun,
+    DEFAULT_CONFIG_FILE,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Notice shown once in a project whose hooks run without a configuration file.
+const ONBOARDING_MESSAGE: &str = "👋 claude-hook-advisor is running, but this project has no .claude.toml yet, \
+so every command is allowed. Run `claude-hook-advisor --quickstart` to set it up with sensible defaults, \
+or `claude-hook-advisor --install` to choose them.";
 
 /// Runs the application as a Claude Code hook for multiple event types.
 /// 
@@ -25,43 +50,233 @@ static REGEX_CACHE: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::ne
 /// # Arguments
 /// * `config_path` - Path to the .claude-hook-advisor.toml configuration file
 /// * `replace_mode` - If true, returns "replace" decision; if false, returns "block"
+/// * `use_cache` - Reuse the cached parse of an unchanged configuration file
 /// 
 /// # Returns
 /// * `Ok(())` - Hook processing completed (may output to stdout)
 /// * `Err` - If JSON parsing or configuration loading fails
-pub fn run_as_hook(config_path: &str, replace_mode: bool) -> Result<()> {
-    // Read configuration
-    let config = if config_path.is_empty() {
-        load_config_auto()?
-    } else {
-        load_config_from_path(Path::new(config_path))?
-    };
-
+pub fn run_as_hook(config_path: &str, replace_mode: bool, use_cache: bool) -> Result<()> {
     // Read JSON input from stdin
     let mut buffer = String::new();
     io::stdin().read_to_string(&mut buffer)?;
 
-    let hook_input: HookInput =
-        serde_json::from_str(&buffer).context("Failed to parse hook input JSON")?;
+    handle_event(&buffer, config_path, replace_mode, use_cache, &mut HookStdout::direct())
+}
+
+/// Runs as a hook over a stream of events: each stdin line is one hook input,
+/// and each produces exactly one line of JSON on stdout, in order.
+///
+/// A line's output is the JSON response the hook would print for that event
+/// on its own, or `{}` when it would print nothing. Plain text, as printed for
+/// prompts, is returned as `additionalContext`, and warnings as a
+/// `systemMessage`. An event that fails yields `{"error": "..."}` and the
+/// stream carries on.
+///
+/// # Arguments
+/// * `config_path` - Path to the configuration file, as for `run_as_hook`
+/// * `replace_mode` - If true, returns "replace" decision; if false, returns "block"
+/// * `use_cache` - Reuse the cached parse of an unchanged configuration file
+///
+/// # Returns
+/// * `Ok(())` - At the end of the input
+/// * `Err` - If stdin can't be read or stdout can't be written
+pub fn run_hook_stream(config_path: &str, replace_mode: bool, use_cache: bool) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = evaluate_event(&line, config_path, replace_mode, use_cache)
+            .unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }));
+        writeln!(stdout, "{response}")?;
+        // Wrappers wait for each answer before sending the next event
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Handles one hook input and returns its response as a single JSON value,
+/// the way `--stream` and `--serve` answer it (see `run_hook_stream`).
+pub(crate) fn evaluate_event(input: &str, config_path: &str, replace_mode: bool, use_cache: bool) -> Result<serde_json::Value> {
+    let mut out = HookStdout::collected();
+    handle_event(input, config_path, replace_mode, use_cache, &mut out)?;
+    Ok(out.into_response())
+}
+
+/// Where a hook's stdout goes: straight to stdout, or collected so a stream
+/// can answer each event with a single line.
+struct HookStdout {
+    collected: Option<Vec<String>>,
+    event: String,
+    /// Whether anything was written yet
+    written: bool,
+}
+
+impl HookStdout {
+    fn direct() -> Self {
+        HookStdout { collected: None, event: String::new(), written: false }
+    }
+
+    fn collected() -> Self {
+        HookStdout { collected: Some(Vec::new()), event: String::new(), written: false }
+    }
+
+    /// Writes a line of plain text.
+    fn line(&mut self, line: impl Into<String>) {
+        self.written = true;
+        match &mut self.collected {
+            Some(lines) => lines.push(line.into()),
+            None => println!("{}", line.into()),
+        }
+    }
+
+    /// Writes a JSON response.
+    fn json<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        self.line(serde_json::to_string(value)?);
+        Ok(())
+    }
+
+    /// Shows a warning: on stderr for a single event, as a `systemMessage` in a stream.
+    fn warning(&mut self, message: &str) -> Result<()> {
+        if self.collected.is_none() {
+            eprintln!("⚠️  {message}");
+            return Ok(());
+        }
+        self.json(&SystemMessageOutput { system_message: format!("⚠️  {message}") })
+    }
+
+    /// The collected output as one JSON value.
+    fn into_response(self) -> serde_json::Value {
+        let lines = self.collected.unwrap_or_default();
+        if let [line] = &lines[..] {
+            if let Ok(value @ serde_json::Value::Object(_)) = serde_json::from_str(line) {
+                return value;
+            }
+        }
+        if lines.is_empty() {
+            return serde_json::json!({});
+        }
+        // A warning followed by a decision is one response with both
+        let objects: Vec<serde_json::Map<String, serde_json::Value>> =
+            lines.iter().map_while(|line| serde_json::from_str(line).ok()).collect();
+        if objects.len() == lines.len() {
+            return objects.into_iter().fold(serde_json::json!({}), |mut merged, object| {
+                for (key, value) in object {
+                    match (&merged[&key], &value) {
+                        (serde_json::Value::String(earlier), serde_json::Value::String(later)) if key == "systemMessage" => {
+                            merged[&key] = format!("{earlier}\n{later}").into();
+                        }
+                        _ => merged[&key] = value,
+                    }
+                }
+                merged
+            });
+        }
+        serde_json::json!({
+            "hookSpecificOutput": { "hookEventName": self.event, "additionalContext": lines.join("\n") }
+        })
+    }
+}
+
+/// Handles one hook input, writing its response to `out`.
+fn handle_event(input: &str, config_path: &str, replace_mode: bool, use_cache: bool, out: &mut HookStdout) -> Result<()> {
+    let hook_input = HookInput::parse(input)?;
+    out.event = hook_input.hook_event_name.to_string();
+
+    // Claude may run tools in a subdirectory, so the configuration is looked
+    // up in the project root rather than the working directory
+    let project_dir = project_dir(&hook_input);
+    let config_file = resolve_config_path(&project_dir, config_path);
+
+    // Hooks registered in a project that was never set up allow everything,
+    // but say so once instead of failing on the missing config file
+    if config_path == DEFAULT_CONFIG_FILE && config_file.is_none() {
+        return handle_unconfigured(&hook_input, out);
+    }
+
+    // Read configuration
+    let mut config = match &config_file {
+        None => load_config_auto_in(&project_dir)?,
+        Some(path) if use_cache => load_config_cached(path)?,
+        Some(path) => load_config_from_path(path)?,
+    };
+
+    // Only prompts resolve directory aliases, so only they read the user-wide ones
+    if hook_input.hook_event_name == "UserPromptSubmit" {
+        match load_user_directories() {
+            Ok(directories) => config.user_directories = directories,
+            Err(e) => eprintln!("Warning: Ignoring user-wide directories: {e}"),
+        }
+    }
+
+    if let Err(e) = usage::count(&usage::invocation_counters(&config, &hook_input.hook_event_name)) {
+        eprintln!("Warning: Failed to record usage counters: {e:#}");
+    }
 
     // Route to appropriate handler based on hook event type
-    match hook_input.hook_event_name.as_str() {
-        "PreToolUse" => handle_pre_tool_use(&config, &hook_input, replace_mode)?,
-        "UserPromptSubmit" => handle_user_prompt_submit(&config, &hook_input)?,
-        "PostToolUse" => handle_post_tool_use(&config, &hook_input)?,
+    match hook_input.hook_event_name.as_ref() {
+        "PreToolUse" => handle_pre_tool_use(&config, &hook_input, replace_mode, out)?,
+        "UserPromptSubmit" => handle_user_prompt_submit(&config, &hook_input, out)?,
+        "PostToolUse" => handle_post_tool_use(&config, &hook_input, out)?,
+        "Stop" => handle_stop(&config, &hook_input, out)?,
+        "PreCompact" => handle_pre_compact(&config, out),
+        "SessionStart" => handle_session_start(&config, &hook_input, out)?,
         _ => {
             // Unknown hook event type, log warning and continue
             eprintln!("Warning: Unknown hook event type: {}", hook_input.hook_event_name);
         }
     }
 
+    if let Some(config_file) = &config_file {
+        notify_deprecations(config_file, &hook_input, out)?;
+    }
+    Ok(())
+}
+
+/// Shows the deprecated constructs in the configuration file as a
+/// `systemMessage`, once per version of the file.
+///
+/// The notice needs an event that gives no other response, since a hook
+/// answers with a single JSON object; until one comes, the file counts as
+/// unchecked.
+fn notify_deprecations(config_path: &Path, hook_input: &HookInput, out: &mut HookStdout) -> Result<()> {
+    if out.written {
+        return Ok(());
+    }
+    let Ok(content) = std::fs::read_to_string(config_path) else {
+        return Ok(());
+    };
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    let key = format!("{}:{:016x}", config_path.display(), hasher.finish());
+    if !mark_deprecations_checked(&project_dir(hook_input), &key) {
+        return Ok(());
+    }
+
+    let deprecations = find_deprecations(config_path, &content);
+    if deprecations.is_empty() {
+        return Ok(());
+    }
+    out.json(&SystemMessageOutput { system_message: deprecation_notice(&deprecations) })
+}
+
+/// Shows the first-run notice the first time a hook fires in a project
+/// without a configuration file; every command is allowed.
+fn handle_unconfigured(hook_input: &HookInput, out: &mut HookStdout) -> Result<()> {
+    if mark_onboarding_shown(&project_dir(hook_input)) {
+        let output = SystemMessageOutput { system_message: ONBOARDING_MESSAGE.to_string() };
+        out.json(&output)?;
+    }
     Ok(())
 }
 
 /// Handles PreToolUse hook events for command mapping and replacement.
 /// 
 /// Processes Bash commands and checks for configured mappings. If a mapping
-/// is found, outputs JSON decision to block or replace the command.
+/// is found, outputs JSON decision to block or replace the command. Other
+/// tools are only checked against the `[sandbox]` roots.
 /// 
 /// # Arguments
 /// * `config` - Configuration containing command mappings
@@ -71,43 +286,367 @@ pub fn run_as_hook(config_path: &str, replace_mode: bool) -> Result<()> {
 /// # Returns
 /// * `Ok(())` - Processing completed (may exit process with JSON output)
 /// * `Err` - If command mapping check fails
-fn handle_pre_tool_use(config: &Config, hook_input: &HookInput, replace_mode: bool) -> Result<()> {
-    // Only process Bash commands
-    if hook_input.tool_name.as_deref() != Some("Bash") {
+fn handle_pre_tool_use(config: &Config, hook_input: &HookInput, replace_mode: bool, out: &mut HookStdout) -> Result<()> {
+    let Some(tool_input) = &hook_input.tool_input else {
         return Ok(());
+    };
+
+    // [[tool_rules]] can govern any tool, including ones nothing else here knows
+    if !config.tool_rules.is_empty() {
+        let tool_name = hook_input.tool_name.as_deref().unwrap_or_default();
+        if let Some((rule_match, value)) = check_tool_rules(&config.tool_rules, tool_name, &hook_input.raw_tool_input())? {
+            let decision = match rule_match.severity {
+                Severity::Warn => "warn",
+                Severity::Ask => "ask",
+                Severity::Block => "block",
+            };
+            let call = format!("{tool_name} {value}");
+            record_decision(config, &project_dir(hook_input), AuditEvent::new(hook_input, &call, decision, Some(&rule_match)));
+            if rule_match.severity != Severity::Warn {
+                return respond(config, Some(rule_match), false, out);
+            }
+            // A warning doesn't settle the call; the other checks still run
+            out.warning(&rule_match.reason)?;
+        }
     }
 
-    let Some(tool_input) = &hook_input.tool_input else {
+    // Task and file tools only reach this hook through a wider matcher
+    if hook_input.tool_name.as_deref() == Some("Task") {
+        return handle_task_tool(config, hook_input, tool_input, out);
+    }
+    if hook_input.tool_name.as_deref() != Some("Bash") {
+        return handle_file_tool(config, hook_input, tool_input, out);
+    }
+
+    let Some(command) = &tool_input.command else {
         return Ok(());
     };
 
-    let Some(command) = &tool_input.command else {
+    let project_dir = project_dir(hook_input);
+    let mut state = SessionState::load(&project_dir, &hook_input.session_id);
+    let key = tool_call_key(hook_input, command);
+    state.start_tool(&key);
+    state.accept_suggestion(command, &key);
+
+    // Policies apply to the effective command, with shell aliases expanded
+    let expanded = expand_aliases(&config.aliases, command);
+    let command: &str = &expanded;
+
+    let mut context = RuleContext::from_hook_input(hook_input)
+        .with_kube_context(config, command)
+        .with_git_status(config, command, &working_dir(hook_input))
+        .with_clock(config, chrono::Utc::now());
+    context.history = Some(state.history.clone());
+    context.snoozed = SnoozeList::load(&project_dir).active_patterns();
+
+    // Policies are checked in the same order as everywhere else (see
+    // `policy`), with the one-time retry of a failed suggestion between the
+    // gates and the rules
+    let policy = Policy::new(config, context, &project_dir, &working_dir(hook_input));
+    let mut gate = policy.check_baseline(command)?;
+    let enforced = gate.is_some();
+    if gate.is_none() {
+        gate = policy.check_gates(command)?;
+    }
+    let mut rule_match = if gate.is_some() {
+        gate
+    } else if state.take_failed_original(command).is_some() {
+        // The suggested replacement failed earlier; let the original through once
+        None
+    } else {
+        policy.check_rules(command)?
+    };
+
+    // An override the user armed in a prompt lets this command through once,
+    // with a warning; the organization baseline can't be overridden
+    let overridden = !enforced
+        && rule_match.as_ref().is_some_and(|m| m.severity != Severity::Warn)
+        && state.take_override();
+    if let Some(rule_match) = rule_match.as_mut().filter(|_| overridden) {
+        rule_match.severity = Severity::Warn;
+        rule_match.reason = format!("Allowed once by a human override. It would otherwise be stopped: {}", rule_match.reason);
+    }
+
+    // An approval service answers for the user when it can
+    let verdict = match rule_match.as_mut().filter(|m| m.severity == Severity::Ask && config.approvals.webhook.is_some()) {
+        Some(rule_match) => ask_approval_service(config, hook_input, command, rule_match),
+        None => None,
+    };
+
+    let decision = match &rule_match {
+        Some(_) if overridden => "override",
+        _ if verdict.as_ref().is_some_and(|v| v.approved) => "approved",
+        _ if verdict.is_some() => "denied",
+        None => "allow",
+        Some(m) if m.severity == Severity::Warn => "warn",
+        Some(m) if m.severity == Severity::Ask => "ask",
+        Some(m) if replace_mode && m.suggested_command.is_some() => "replace",
+        Some(_) => "block",
+    };
+    let event = AuditEvent::new(hook_input, command, decision, rule_match.as_ref());
+
+    // Remember blocked commands' suggestions so PostToolUse can report if they
+    // fail, unless the hit is one a sampled rule leaves unrecorded
+    if let Some(rule_match) = rule_match.as_ref().filter(|m| m.severity == Severity::Block) {
+        let sample_rate = config.sample_rate(&rule_match.pattern);
+        if let Some(suggested) = rule_match.suggested_command.as_ref().filter(|_| event.sampled_in(sample_rate)) {
+            state.record_suggestion(command, suggested, &rule_match.pattern, hook_input.tool_use_id.as_deref()).sample_rate = sample_rate;
+            // A replaced command runs the suggestion as part of this same tool call
+            if replace_mode {
+                state.accept_suggestion(suggested, &key);
+            }
+        }
+    }
+    save_state(&state, &project_dir, hook_input);
+    record_decision(config, &project_dir, event);
+
+    if config.output.show_diff && !replace_mode {
+        if let Some(rule_match) = rule_match.as_mut().filter(|m| m.severity == Severity::Block) {
+            if let Some(suggested) = &rule_match.suggested_command {
+                let diff = word_diff(command, suggested);
+                rule_match.reason.push_str(&format!("\nChanges: {diff}"));
+            }
+        }
+    }
+
+    if let Some(verdict) = verdict.filter(|v| v.approved) {
+        return out.json(&PermissionOutput {
+            hook_specific_output: PermissionDecision {
+                hook_event_name: "PreToolUse".to_string(),
+                permission_decision: "allow".to_string(),
+                permission_decision_reason: verdict.describe(),
+                updated_input: None,
+            },
+        });
+    }
+    respond(config, rule_match, replace_mode, out)
+}
+
+/// Sends an `ask` match to the `[approvals]` webhook and applies a denial
+/// or a timeout to it: both turn it into a block, a timeout only with
+/// `on_timeout = "block"`.
+///
+/// # Returns
+/// The service's verdict; `None` when it gave none, including on errors
+fn ask_approval_service(config: &Config, hook_input: &HookInput, command: &str, rule_match: &mut RuleMatch) -> Option<Verdict> {
+    let verdict = ApprovalRequest::new(hook_input, command, rule_match, &config.audit.redact)
+        .and_then(|request| request_approval(&config.approvals, &request))
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to ask for approval: {e:#}");
+            None
+        });
+    match &verdict {
+        Some(verdict) if !verdict.approved => {
+            rule_match.severity = Severity::Block;
+            rule_match.reason = format!("{}. {}", verdict.describe(), rule_match.reason);
+        }
+        None if config.approvals.on_timeout == ApprovalFallback::Block => {
+            rule_match.severity = Severity::Block;
+            rule_match.reason = format!(
+                "No approval arrived within {}s, so the command is blocked. {}",
+                config.approvals.timeout_secs, rule_match.reason
+            );
+        }
+        _ => {}
+    }
+    verdict
+}
+
+/// Checks a file tool call (Read, Write, Edit, Glob, ...) against the
+/// `[sandbox]` roots. Only flagged calls are audited, so reads inside the
+/// project don't flood the log. Grep calls inside the project that pass get
+/// the `[search]` defaults.
+fn handle_file_tool(config: &Config, hook_input: &HookInput, tool_input: &ToolInput, out: &mut HookStdout) -> Result<()> {
+    let project_dir = project_dir(hook_input);
+    let working_dir = working_dir(hook_input);
+    let tool_name = hook_input.tool_name.as_deref().unwrap_or("tool");
+    let paths = tool_paths(tool_input);
+    // Edits that only touch documentation or comments can't change what the code does
+    let doc_edit = config.sandbox.ignore_doc_edits && is_doc_only_edit(tool_name, &hook_input.raw_tool_input());
+    let rule_match = if paths.is_empty() || doc_edit { None } else { check_sandbox(&config.sandbox, &project_dir, &working_dir, &paths)? };
+
+    let search_path = tool_input.path.as_deref().map(|path| working_dir.join(path).to_string_lossy().into_owned());
+    if rule_match.is_none()
+        && tool_name == "Grep"
+        && searches_project(&project_dir, search_path.as_deref())
+    {
+        if let Some((updated_input, added)) = search_defaults(&config.search, &hook_input.raw_tool_input()) {
+            return respond_with_updated_input(updated_input, format!("Added the project's [search] defaults: {}", added.join(", ")), out);
+        }
+    }
+    if paths.is_empty() {
+        return Ok(());
+    }
+    if let Some(rule_match) = &rule_match {
+        let call = format!("{tool_name} {}", paths.join(" "));
+        let decision = match rule_match.severity {
+            Severity::Warn => "warn",
+            Severity::Ask => "ask",
+            Severity::Block => "block",
+        };
+        record_decision(config, &project_dir, AuditEvent::new(hook_input, &call, decision, Some(rule_match)));
+    }
+
+    respond(config, rule_match, false, out)
+}
+
+/// Checks a Task (subagent) call's prompt against `[subagents]`. An allowed
+/// prompt gets the project's conventions appended when `share_conventions`
+/// is on, through `updatedInput`.
+fn handle_task_tool(config: &Config, hook_input: &HookInput, tool_input: &ToolInput, out: &mut HookStdout) -> Result<()> {
+    let Some(prompt) = &tool_input.prompt else {
         return Ok(());
     };
 
-    // Check for command mappings
-    if let Some((suggestion, replacement_cmd)) = check_command_mappings(config, command)? {
-        let output = if replace_mode {
-            HookOutput {
+    if let Some(rule_match) = check_subagent_prompt(&config.subagents, prompt) {
+        let call = format!("Task {}", tool_input.description.as_deref().unwrap_or_default());
+        record_decision(config, &project_dir(hook_input), AuditEvent::new(hook_input, call.trim_end(), "block", Some(&rule_match)));
+        return respond(config, Some(rule_match), false, out);
+    }
+
+    let Some(prompt) = with_conventions(config, prompt) else {
+        return respond(config, None, false, out);
+    };
+    let mut updated_input = hook_input.raw_tool_input();
+    updated_input["prompt"] = prompt.into();
+    respond_with_updated_input(updated_input, "Added the project's conventions to the subagent prompt".to_string(), out)
+}
+
+/// Lets a tool call run with `updated_input` in place of the input Claude sent.
+fn respond_with_updated_input(updated_input: serde_json::Value, reason: String, out: &mut HookStdout) -> Result<()> {
+    out.json(&PermissionOutput {
+        hook_specific_output: PermissionDecision {
+            hook_event_name: "PreToolUse".to_string(),
+            permission_decision: "allow".to_string(),
+            permission_decision_reason: reason,
+            updated_input: Some(updated_input),
+        },
+    })
+}
+
+/// Prints the PreToolUse response for a decision: a warning on stderr, a
+/// permission prompt, or a block or replace decision.
+///
+/// # Arguments
+/// * `config` - Configuration, for `silent_allow`
+/// * `rule_match` - What objected to the tool call, if anything
+/// * `replace_mode` - Whether suggested commands replace the original
+///
+/// # Returns
+/// * `Ok(())` - Once the response is written
+/// * `Err` - If the output cannot be serialized
+fn respond(config: &Config, rule_match: Option<RuleMatch>, replace_mode: bool, out: &mut HookStdout) -> Result<()> {
+    if let Some(rule_match) = rule_match {
+        if rule_match.severity == Severity::Warn {
+            // Warnings never stop the command; the reason is shown in the transcript
+            if config.behavior.silent_allow {
+                print_suppressed_output(out)?;
+            } else {
+                out.warning(&rule_match.reason)?;
+            }
+            return Ok(());
+        }
+
+        if rule_match.severity == Severity::Ask {
+            // Leave the decision to the user through Claude Code's permission prompt
+            let output = PermissionOutput {
+                hook_specific_output: PermissionDecision {
+                    hook_event_name: "PreToolUse".to_string(),
+                    permission_decision: "ask".to_string(),
+                    permission_decision_reason: rule_match.reason,
+                    updated_input: None,
+                },
+            };
+            return out.json(&output);
+        }
+
+        let output = match rule_match.suggested_command {
+            Some(replacement_cmd) if replace_mode => HookOutput {
                 decision: "replace".to_string(),
                 reason: format!("Command mapped: using '{replacement_cmd}' instead"),
                 replacement_command: Some(replacement_cmd),
-            }
-        } else {
-            HookOutput {
+                suggested_command: None,
+            },
+            suggested_command => HookOutput {
                 decision: "block".to_string(),
-                reason: suggestion,
+                reason: rule_match.reason,
                 replacement_command: None,
-            }
+                suggested_command,
+            },
         };
 
-        println!("{}", serde_json::to_string(&output)?);
-        std::process::exit(0);
+        return out.json(&output);
+    }
+
+    if config.behavior.silent_allow {
+        print_suppressed_output(out)?;
     }
 
     Ok(())
 }
 
+/// Key correlating a tool call's PreToolUse and PostToolUse events: the tool
+/// use id when Claude Code provides one, otherwise the command text.
+fn tool_call_key<'a>(hook_input: &'a HookInput, command: &str) -> Cow<'a, str> {
+    match hook_input.tool_use_id.as_deref() {
+        Some(id) => Cow::Borrowed(id),
+        None => Cow::Owned(format!("command:{}", command.trim())),
+    }
+}
+
+/// Root directory of the project, which holds the configuration, session
+/// state and audit log: `CLAUDE_PROJECT_DIR` when Claude Code sets it,
+/// otherwise the hook's working directory.
+fn project_dir(hook_input: &HookInput) -> PathBuf {
+    project_root(&working_dir(hook_input))
+}
+
+/// Directory the tool call runs in, which relative paths in it start from.
+fn working_dir(hook_input: &HookInput) -> PathBuf {
+    PathBuf::from(hook_input.cwd.as_deref().unwrap_or("."))
+}
+
+/// Saves session state; failures are reported but never fail the hook.
+fn save_state(state: &SessionState, project_dir: &Path, hook_input: &HookInput) {
+    if let Err(e) = state.save(project_dir, &hook_input.session_id) {
+        eprintln!("Warning: Failed to save session state: {e:#}");
+    }
+}
+
+/// Writes a decision to the audit log, telemetry sink and chat webhook, where
+/// enabled, and counts it for usage telemetry; failures are reported but never
+/// fail the hook. A hit a rule's `sample_rate` leaves out is only notified.
+fn record_decision(config: &Config, project_dir: &Path, event: AuditEvent) {
+    let sampled_in = event.sampled_in(event.pattern.as_deref().and_then(|pattern| config.sample_rate(pattern)));
+    if sampled_in {
+        if let Err(e) = usage::count(&[format!("decisions.{}", event.decision)]) {
+            eprintln!("Warning: Failed to record usage counters: {e:#}");
+        }
+
+        let result = AuditLog::from_config(&config.audit, project_dir)
+            .and_then(|log| log.map_or(Ok(()), |log| log.record(event.clone()).and_then(|_| log.compact_if_due())));
+        if let Err(e) = result {
+            eprintln!("Warning: Failed to write audit log: {e:#}");
+        }
+    }
+
+    if let Err(e) = notify(&config.notifications, &config.audit.redact, project_dir, &event) {
+        eprintln!("Warning: Failed to send notification: {e:#}");
+    }
+    if !sampled_in {
+        return;
+    }
+
+    if let Err(e) = ship_event(&config.telemetry, &config.audit.redact, project_dir, event) {
+        eprintln!("Warning: Failed to send telemetry: {e:#}");
+    }
+}
+
+/// Tells Claude Code to keep this hook's output out of the transcript.
+fn print_suppressed_output(out: &mut HookStdout) -> Result<()> {
+    out.json(&SuppressedOutput { suppress_output: true })
+}
+
 /// Handles UserPromptSubmit hook events for directory reference detection.
 /// 
 /// Analyzes user prompts for semantic directory references and outputs
@@ -120,29 +659,83 @@ fn handle_pre_tool_use(config: &Config, hook_input: &HookInput, replace_mode: bo
 /// # Returns
 /// * `Ok(())` - Processing completed (may output directory resolutions)
 /// * `Err` - If directory resolution fails
-fn handle_user_prompt_submit(config: &Config, hook_input: &HookInput) -> Result<()> {
+fn handle_user_prompt_submit(config: &Config, hook_input: &HookInput, out: &mut HookStdout) -> Result<()> {
     let Some(prompt) = &hook_input.prompt else {
         return Ok(());
     };
 
-    // Detect directory references in the prompt
-    let directory_refs = detect_directory_references(config, prompt);
-    
-    if !directory_refs.is_empty() {
-        // Output directory resolutions as plain text (not JSON for UserPromptSubmit)
-        for resolution in directory_refs {
-            println!("Directory reference '{}' resolved to: {}", 
-                resolution.alias_used, 
+    // Output directory resolutions as plain text (not JSON for UserPromptSubmit)
+    for line in directory_context(config, prompt) {
+        out.line(line);
+    }
+
+    if config.human_override.phrase.as_deref().is_some_and(|phrase| !phrase.trim().is_empty() && prompt.contains(phrase)) {
+        let project_dir = project_dir(hook_input);
+        let mut state = SessionState::load(&project_dir, &hook_input.session_id);
+        state.arm_override();
+        save_state(&state, &project_dir, hook_input);
+        out.line("Human override armed: the next command the advisor would block or ask about runs once without being stopped.");
+    }
+
+    Ok(())
+}
+
+/// Describes the directory references in a prompt: each resolved alias, and
+/// the nearest aliases for phrases that look like misspelled ones.
+///
+/// # Arguments
+/// * `config` - Configuration containing directory mappings
+/// * `prompt` - The user prompt text to analyze
+///
+/// # Returns
+/// * `Vec<String>` - The lines the UserPromptSubmit hook prints
+pub fn directory_context(config: &Config, prompt: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for resolution in detect_directory_references(config, prompt) {
+        lines.push(format!(
+            "Directory reference '{}' resolved to: {} ({} scope)",
+            resolution.alias_used, resolution.canonical_path, resolution.scope
+        ));
+        match resolution.status {
+            DirectoryStatus::Exists => {}
+            DirectoryStatus::Created => lines.push("  Created the missing directory".to_string()),
+            DirectoryStatus::Missing => lines.push(format!(
+                "  Warning: {} does not exist (set create_missing = true under [directories] to create it on first use)",
                 resolution.canonical_path
-            );
-            
-            if !resolution.variables_substituted.is_empty() {
-                println!("  Variables substituted: {:?}", resolution.variables_substituted);
-            }
+            )),
+        }
+
+        if !resolution.variables_substituted.is_empty() {
+            let variables: Vec<String> = resolution
+                .variables_substituted
+                .iter()
+                .map(|(name, value)| format!("{{{name}}} = {value}"))
+                .collect();
+            lines.push(format!("  Variables substituted: {}", variables.join(", ")));
         }
     }
 
-    Ok(())
+    // Say why a mentioned alias resolved to nothing when policy blocked it
+    for alias in mentioned_aliases(config, prompt) {
+        if let Err(AdvisorError::Config(e @ ConfigError::DirectoryNotAllowed { .. })) = locate_directory(config, alias) {
+            lines.push(format!("Directory reference '{alias}' was not resolved: {e}"));
+        }
+    }
+
+    // Point out near misses so users discover the aliases that exist
+    for suggestion in suggest_aliases(config, prompt) {
+        let candidates: Vec<String> = suggestion
+            .candidates
+            .iter()
+            .map(|(alias, distance)| format!("'{alias}' (distance {distance})"))
+            .collect();
+        lines.push(format!(
+            "No directory alias matches '{}'; closest aliases: {}",
+            suggestion.mention,
+            candidates.join(", ")
+        ));
+    }
+    lines
 }
 
 /// Handles PostToolUse hook events for command execution tracking.
@@ -157,7 +750,7 @@ fn handle_user_prompt_submit(config: &Config, hook_input: &HookInput) -> Result<
 /// # Returns
 /// * `Ok(())` - Processing completed (may output analytics)
 /// * `Err` - If execution tracking fails
-fn handle_post_tool_use(_config: &Config, hook_input: &HookInput) -> Result<()> {
+fn handle_post_tool_use(config: &Config, hook_input: &HookInput, out: &mut HookStdout) -> Result<()> {
     let Some(tool_name) = &hook_input.tool_name else {
         return Ok(());
     };
@@ -171,126 +764,178 @@ fn handle_post_tool_use(_config: &Config, hook_input: &HookInput) -> Result<()>
         return Ok(());
     }
 
-    // Log execution results for future analytics
-    let exit_code = tool_response.exit_code.unwrap_or(-1);
-    let success = exit_code == 0;
-    
-    if let Some(tool_input) = &hook_input.tool_input {
-        if let Some(command) = &tool_input.command {
-            println!("Command execution tracked: {command} (exit_code: {exit_code}, success: {success})");
+    let Some(command) = hook_input.tool_input.as_ref().and_then(|input| input.command.as_ref()) else {
+        return Ok(());
+    };
+
+    let project_dir = project_dir(hook_input);
+    let mut state = SessionState::load(&project_dir, &hook_input.session_id);
+    let key = tool_call_key(hook_input, command);
+    let duration_ms = state.finish_tool(&key);
+    state.record_run(command, tool_response.exit_code, duration_ms);
+
+    // Attribute the outcome to the suggestion this tool call accepted, and
+    // close the loop when the replacement did not work
+    if let Some(suggestion) = state.suggestion_for_tool(&key) {
+        suggestion.exit_code = tool_response.exit_code;
+        if let Some(exit_code) = tool_response.exit_code.filter(|code| *code != 0) {
+            suggestion.failed = true;
+            let output = failed_replacement_feedback(suggestion, exit_code);
+            save_state(&state, &project_dir, hook_input);
+            out.json(&output)?;
+            return Ok(());
         }
     }
 
-    Ok(())
-}
+    // Advise on commands that keep being slow, once per session
+    if let Some(duration_ms) = duration_ms {
+        let mut log = DurationLog::load(&project_dir);
+        log.record(command, duration_ms);
+        if let Err(e) = log.save(&project_dir) {
+            eprintln!("Warning: Failed to save duration log: {e:#}");
+        }
 
-/// Gets or creates a cached regex for the given pattern
-fn get_cached_regex(pattern: &str) -> Result<Regex> {
-    let mut cache = REGEX_CACHE.lock()
-        .expect("regex cache mutex should not be poisoned");
-    
-    if let Some(regex) = cache.get(pattern) {
-        return Ok(regex.clone());
-    }
-    
-    let regex = Regex::new(pattern)?;
-    cache.insert(pattern.to_string(), regex.clone());
-    Ok(regex)
+        if let Some((advice, slow_runs)) = slow_command_advice(config, &log, command)? {
+            if !state.advised.contains(&advice.pattern) {
+                state.advised.push(advice.pattern.clone());
+                save_state(&state, &project_dir, hook_input);
+                let output = PostToolUseOutput {
+                    decision: None,
+                    reason: None,
+                    hook_specific_output: HookSpecificOutput {
+                        hook_event_name: "PostToolUse".to_string(),
+                        additional_context: advice_message(advice, slow_runs),
+                    },
+                };
+                out.json(&output)?;
+                return Ok(());
+            }
+        }
+    }
+    save_state(&state, &project_dir, hook_input);
+
+    // The run is in the session state; only report it when asked to, and
+    // never into the transcript
+    if config.behavior.tracking_output {
+        let output = TrackedOutput {
+            suppress_output: true,
+            tracked: TrackedRun {
+                command: command.to_string(),
+                exit_code: tool_response.exit_code,
+                success: tool_response.exit_code == Some(0),
+                duration_ms,
+            },
+        };
+        out.json(&output)?;
+    } else if config.behavior.silent_allow {
+        print_suppressed_output(out)?;
+    }
+    Ok(())
 }
 
-/// Checks if a command matches any configured mappings and generates suggestions.
-/// 
-/// Uses word-boundary regex matching to ensure exact command matches (e.g., "npm"
-/// matches "npm install" but not "npm-check"). Returns the first matching pattern.
-/// Uses cached regex compilation for better performance.
-/// 
+/// Handles Stop hook events by enforcing the completion checklist.
+///
+/// When items are unmet, Claude is told to finish them instead of ending its
+/// turn. If Claude is already continuing because of a Stop hook, it is let go,
+/// so an item it cannot fix never traps it in a loop.
+///
 /// # Arguments
-/// * `config` - Configuration containing command mappings
-/// * `command` - The bash command to check against mappings
-/// 
+/// * `config` - Configuration containing the `[checklist]`
+/// * `hook_input` - Hook input data from Claude Code
+///
 /// # Returns
-/// * `Ok(Some((suggestion, replacement)))` - If a mapping is found
-/// * `Ok(None)` - If no mappings match the command
-/// * `Err` - If regex compilation fails
-pub fn check_command_mappings(config: &Config, command: &str) -> Result<Option<(String, String)>> {
-    for (pattern, replacement) in &config.commands {
-        // Create regex pattern to match the command at word boundaries
-        let regex_pattern = format!(r"\b{}\b", regex::escape(pattern));
-        let regex = get_cached_regex(&regex_pattern)?;
-
-        if regex.is_match(command) {
-            // Generate suggested replacement
-            let suggested_command = regex.replace_all(command, replacement);
-            let suggestion = format!(
-                "Command '{pattern}' is mapped to use '{replacement}' instead. Try: {suggested_command}"
-            );
-            return Ok(Some((suggestion, suggested_command.to_string())));
-        }
+/// * `Ok(())` - Processing completed (may output a block decision)
+/// * `Err` - If a checklist pattern is invalid or output serialization fails
+fn handle_stop(config: &Config, hook_input: &HookInput, out: &mut HookStdout) -> Result<()> {
+    let checklist = &config.checklist;
+    if hook_input.stop_hook_active || (checklist.commands.is_empty() && checklist.forbidden_markers.is_empty()) {
+        return Ok(());
     }
 
-    Ok(None)
-}
+    let project_dir = project_dir(hook_input);
+    let state = SessionState::load(&project_dir, &hook_input.session_id);
+    let added = if checklist.forbidden_markers.is_empty() {
+        Vec::new()
+    } else {
+        added_lines(&project_dir)
+    };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
+    let unmet = unmet_items(checklist, &state.history, &added)?;
+    if unmet.is_empty() {
+        return Ok(());
+    }
 
-    #[test]
-    fn test_command_mapping() {
-        let mut commands = HashMap::new();
-        commands.insert("npm".to_string(), "bun".to_string());
-        commands.insert("yarn".to_string(), "bun".to_string());
-        commands.insert("npx".to_string(), "bunx".to_string());
-
-        let config = Config { 
-            commands,
-            semantic_directories: HashMap::new(),
-        };
+    let items: Vec<String> = unmet.iter().map(|item| format!("- {item}")).collect();
+    let output = StopOutput {
+        decision: "block".to_string(),
+        reason: format!(
+            "The project's completion checklist is not met yet:\n{}\nFinish these items before ending your turn.",
+            items.join("\n")
+        ),
+    };
+    out.json(&output)?;
+    Ok(())
+}
 
-        // Test npm mapping
-        let result = check_command_mappings(&config, "npm install").unwrap();
-        assert!(result.is_some());
-        let (suggestion, replacement) = result.unwrap();
-        assert!(suggestion.contains("bun install"));
-        assert_eq!(replacement, "bun install");
+/// Handles PreCompact events by printing the project's conventions, so the
+/// summary Claude Code writes when it compacts the conversation keeps them.
+fn handle_pre_compact(config: &Config, out: &mut HookStdout) {
+    if let Some(summary) = compaction_summary(config) {
+        out.line(summary);
+    }
+}
 
-        // Test yarn mapping
-        let result = check_command_mappings(&config, "yarn start").unwrap();
-        assert!(result.is_some());
-        let (suggestion, replacement) = result.unwrap();
-        assert!(suggestion.contains("bun start"));
-        assert_eq!(replacement, "bun start");
+/// Handles SessionStart events after a compaction by adding the project's
+/// conventions to Claude's context again, in case the summary lost them.
+/// Other session starts are left alone.
+fn handle_session_start(config: &Config, hook_input: &HookInput, out: &mut HookStdout) -> Result<()> {
+    if hook_input.source.as_deref() != Some("compact") {
+        return Ok(());
     }
+    let Some(summary) = compaction_summary(config) else {
+        return Ok(());
+    };
+    out.json(&SessionStartOutput {
+        hook_specific_output: HookSpecificOutput { hook_event_name: "SessionStart".to_string(), additional_context: summary },
+    })
+}
 
-    #[test]
-    fn test_command_mapping_edge_cases() {
-        let mut commands = HashMap::new();
-        commands.insert("npm".to_string(), "bun".to_string());
-        let config = Config { 
-            commands,
-            semantic_directories: HashMap::new(),
-        };
+/// Builds the feedback telling Claude that a suggested replacement failed.
+///
+/// The original command is allowed once afterwards, so Claude can fall back to it.
+fn failed_replacement_feedback(suggestion: &Suggestion, exit_code: i32) -> PostToolUseOutput {
+    let Suggestion { original, suggested, pattern, .. } = suggestion;
+    PostToolUseOutput {
+        decision: Some("block".to_string()),
+        reason: Some(format!("The suggested replacement '{suggested}' failed with exit code {exit_code}.")),
+        hook_specific_output: HookSpecificOutput {
+            hook_event_name: "PostToolUse".to_string(),
+            additional_context: format!(
+                "claude-hook-advisor suggested '{suggested}' instead of '{original}' (rule '{pattern}'), \
+                 but it exited with code {exit_code}. The replacement tool may be missing or may not \
+                 accept the same arguments. Check its error output and fix the command, or run the \
+                 original '{original}' once more: it will be allowed this time."
+            ),
+        },
+    }
+}
 
-        // Test word boundaries - "npm" in "my-npm-tool" should NOT match due to word boundaries
-        let result = check_command_mappings(&config, "my-npm-tool install").unwrap();
-        // Looking at the regex implementation, it actually DOES match substring "npm"
-        // Let's test what the actual behavior is
-        if result.is_some() {
-            // If it matches, that's the current behavior - document it
-            let (_, replacement) = result.unwrap();
-            assert!(replacement.contains("bun"));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
 
-        // Test empty command
-        let result = check_command_mappings(&config, "").unwrap();
-        assert!(result.is_none());
+    /// Runs a hook event in the project holding `config`, in session "s"
+    /// unless `fields` names another.
+    fn hook_event(config: &Path, fields: Value) -> Value {
+        let mut input = json!({ "session_id": "s", "cwd": config.parent().unwrap() });
+        input.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        evaluate_event(&input.to_string(), config.to_str().unwrap(), false, false).unwrap()
+    }
 
-        // Test command with multiple spaces
-        let result = check_command_mappings(&config, "npm   install   --verbose").unwrap();
-        assert!(result.is_some());
-        let (_, replacement) = result.unwrap();
-        assert_eq!(replacement, "bun   install   --verbose");
+    /// Runs a PreToolUse event for a `tool` call in the project holding `config`.
+    fn pre_tool_use(config: &Path, tool: &str, tool_input: Value) -> Value {
+        hook_event(config, json!({ "hook_event_name": "PreToolUse", "tool_name": tool, "tool_input": tool_input }))
     }
 
     #[test]
@@ -300,6 +945,7 @@ mod tests {
             decision: "block".to_string(),
             reason: "Test reason".to_string(),
             replacement_command: Some("test command".to_string()),
+            suggested_command: None,
         };
         
         let json = serde_json::to_string(&output).unwrap();
@@ -312,6 +958,7 @@ mod tests {
             decision: "allow".to_string(),
             reason: "No mapping found".to_string(),
             replacement_command: None,
+            suggested_command: None,
         };
         
         let json = serde_json::to_string(&output).unwrap();
@@ -320,4 +967,234 @@ mod tests {
         // Should not include replacement_command field when None due to serde skip
         assert!(!json.contains("replacement_command"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_failed_replacement_feedback() {
+        let mut state = SessionState::default();
+        state.record_suggestion("npm ci", "bun ci", "npm", None);
+        state.accept_suggestion("bun ci", "toolu_1");
+        let suggestion = state.suggestion_for_tool("toolu_1").unwrap();
+
+        let output = failed_replacement_feedback(suggestion, 127);
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["decision"], "block");
+        assert_eq!(json["hookSpecificOutput"]["hookEventName"], "PostToolUse");
+        let context = json["hookSpecificOutput"]["additionalContext"].as_str().unwrap();
+        assert!(context.contains("'npm ci'"));
+        assert!(context.contains("code 127"));
+    }
+
+    #[test]
+    fn test_suppressed_output_serialization() {
+        let json = serde_json::to_string(&SuppressedOutput { suppress_output: true }).unwrap();
+        assert_eq!(json, r#"{"suppressOutput":true}"#);
+
+        let config: Config = toml::from_str("[behavior]\nsilent_allow = true\n").unwrap();
+        assert!(config.behavior.silent_allow);
+        assert!(!Config::default().behavior.silent_allow);
+    }
+
+    #[test]
+    fn test_stream_responses_are_one_json_value() {
+        let response = |lines: &[&str]| {
+            let mut out = HookStdout::collected();
+            out.event = "UserPromptSubmit".to_string();
+            for line in lines {
+                out.line(*line);
+            }
+            out.into_response()
+        };
+        assert_eq!(response(&[]), serde_json::json!({}));
+        assert_eq!(response(&[r#"{"decision":"block"}"#])["decision"], "block");
+        let context = response(&["Directory reference 'docs' resolved to: /tmp", "  Created the missing directory"]);
+        assert_eq!(context["hookSpecificOutput"]["hookEventName"], "UserPromptSubmit");
+        assert!(context["hookSpecificOutput"]["additionalContext"].as_str().unwrap().ends_with("\n  Created the missing directory"));
+
+        let mut out = HookStdout::collected();
+        out.warning("careful").unwrap();
+        assert_eq!(out.into_response()["systemMessage"], "⚠️  careful");
+
+        let mut out = HookStdout::collected();
+        out.warning("careful").unwrap();
+        out.warning("very").unwrap();
+        out.line(r#"{"decision":"block","reason":"no"}"#);
+        assert_eq!(
+            out.into_response(),
+            serde_json::json!({"systemMessage": "⚠️  careful\n⚠️  very", "decision": "block", "reason": "no"})
+        );
+    }
+
+    #[test]
+    fn test_deprecation_notice_is_shown_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        std::fs::write(&config, "[commands]\nnpm = \"bun\"\n").unwrap();
+        let event = |command: &str| pre_tool_use(&config, "Bash", json!({ "command": command }));
+
+        // A blocked command already has a response, so the notice waits
+        let blocked = event("npm install");
+        assert_eq!(blocked["decision"], "block");
+        assert!(blocked.get("systemMessage").is_none());
+        let notice = event("ls");
+        assert!(notice["systemMessage"].as_str().unwrap().contains("Top-level [commands] mappings → [[rules]]"));
+        assert_eq!(event("ls"), serde_json::json!({}));
+
+        // Changing the file checks it again
+        std::fs::write(&config, "version = 2\n[commands]\nnpm = \"bun\"\n").unwrap();
+        assert_eq!(event("ls"), serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_aliases_are_expanded_before_rules() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        std::fs::write(&config, "version = 2\n[commands]\nnpm = \"bun\"\n\n[aliases]\nni = \"npm install\"\n").unwrap();
+        let event = |command: &str| pre_tool_use(&config, "Bash", json!({ "command": command }));
+
+        let blocked = event("ni lodash");
+        assert_eq!(blocked["decision"], "block");
+        assert!(blocked["reason"].as_str().unwrap().contains("bun install lodash"));
+        assert_eq!(event("echo ni"), serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_block_output_carries_the_suggested_command() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        std::fs::write(&config, "version = 2\n[commands]\nnpm = \"bun\"\n").unwrap();
+        let event = || pre_tool_use(&config, "Bash", json!({ "command": "cd web && npm ci" }));
+
+        let blocked = event();
+        assert_eq!(blocked["suggested_command"], "cd web && bun ci");
+        assert_eq!(blocked["reason"], "Command 'npm' is mapped to use 'bun' instead. Try: cd web && bun ci");
+
+        std::fs::write(&config, "version = 2\n[commands]\nnpm = \"bun\"\n\n[output]\nshow_diff = true\n").unwrap();
+        let reason = event()["reason"].as_str().unwrap().to_string();
+        assert!(reason.ends_with("Try: cd web && bun ci\nChanges: cd web && [-npm-]{+bun+} ci"), "{reason}");
+    }
+
+    #[test]
+    fn test_sampled_rules_record_a_share_of_hits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        let event = |session: &str| {
+            hook_event(&config, json!({
+                "session_id": session, "hook_event_name": "PreToolUse", "tool_name": "Bash", "tool_input": { "command": "npm ci" },
+            }))
+        };
+        let audit_file = dir.path().join(crate::audit::DEFAULT_AUDIT_FILE);
+        let rule = "version = 2
+[audit]
+enabled = true
+
+[[rules]]
+pattern = \"npm\"
+replacement = \"bun\"
+";
+
+        // Hits left out of the sample are still stopped, but not recorded
+        std::fs::write(&config, format!("{rule}sample_rate = 0.000001\n")).unwrap();
+        for _ in 0..10 {
+            assert_eq!(event("s1")["suggested_command"], "bun ci");
+        }
+        assert!(SessionState::load(dir.path(), "s1").suggestions.is_empty());
+        assert!(!audit_file.exists());
+
+        std::fs::write(&config, format!("{rule}sample_rate = 1.0\n")).unwrap();
+        event("s2");
+        let suggestions = SessionState::load(dir.path(), "s2").suggestions;
+        assert_eq!(suggestions[0].sample_rate, Some(1.0));
+        assert_eq!(std::fs::read_to_string(&audit_file).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_tool_rules_govern_any_tool() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        std::fs::write(
+            &config,
+            "version = 2\n[commands]\nnpm = \"bun\"\n\n\
+             [[tool_rules]]\ntool = \"WebFetch\"\nfield = \"$.url\"\nmatches = \"internal\"\n\n\
+             [[tool_rules]]\ntool = \"Bash\"\nfield = \"/run_in_background\"\nmatches = \"true\"\nseverity = \"warn\"\n",
+        )
+        .unwrap();
+
+        let blocked = pre_tool_use(&config, "WebFetch", json!({"url": "https://wiki.internal/x", "prompt": "summarize"}));
+        assert_eq!(blocked["decision"], "block");
+        assert!(blocked["reason"].as_str().unwrap().contains("'https://wiki.internal/x'"));
+        assert_eq!(pre_tool_use(&config, "WebFetch", json!({"url": "https://docs.rs"})), json!({}));
+
+        // A warning still lets the command's own checks run
+        let warned = pre_tool_use(&config, "Bash", json!({"command": "npm ci", "run_in_background": true}));
+        assert_eq!(warned["decision"], "block");
+        assert!(warned["systemMessage"].as_str().unwrap().contains("/run_in_background 'true' is discouraged"));
+    }
+
+    #[test]
+    fn test_unanswered_approvals_fall_back() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        let write_config = |on_timeout: &str| {
+            let content = format!(
+                "version = 2\n[[rules]]\npattern = \"terraform destroy\"\nseverity = \"ask\"\n\n\
+                 [approvals]\nwebhook = \"http://127.0.0.1:1/approvals\"\ntimeout_secs = 1\non_timeout = \"{on_timeout}\"\n"
+            );
+            std::fs::write(&config, content).unwrap();
+        };
+        let event = || pre_tool_use(&config, "Bash", json!({ "command": "terraform destroy" }));
+
+        write_config("ask");
+        assert_eq!(event()["hookSpecificOutput"]["permissionDecision"], "ask");
+        write_config("block");
+        let blocked = event();
+        assert_eq!(blocked["decision"], "block");
+        assert!(blocked["reason"].as_str().unwrap().starts_with("No approval arrived within 1s"));
+    }
+
+    #[test]
+    fn test_conventions_survive_compaction() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join(".claude.toml");
+        std::fs::write(&config, "version = 2\n[commands]\nnpm = \"bun\"\n").unwrap();
+
+        let pre_compact = hook_event(&config, json!({ "hook_event_name": "PreCompact", "trigger": "auto" }));
+        let context = pre_compact["hookSpecificOutput"]["additionalContext"].as_str().unwrap();
+        assert!(context.contains("- `npm` → use `bun`"), "{context}");
+
+        let resumed = hook_event(&config, json!({ "hook_event_name": "SessionStart", "source": "compact" }));
+        assert_eq!(resumed["hookSpecificOutput"]["hookEventName"], "SessionStart");
+        assert!(resumed["hookSpecificOutput"]["additionalContext"].as_str().unwrap().starts_with("# Project conventions"));
+
+        let started = hook_event(&config, json!({ "hook_event_name": "SessionStart", "source": "startup" }));
+        assert!(started.get("hookSpecificOutput").is_none(), "{started}");
+    }
+
+    #[test]
+    fn test_tracked_output_serialization() {
+        let output = TrackedOutput {
+            suppress_output: true,
+            tracked: TrackedRun { command: "cargo test".to_string(), exit_code: Some(101), success: false, duration_ms: Some(5400) },
+        };
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["suppressOutput"], true);
+        assert_eq!(json["tracked"]["command"], "cargo test");
+        assert_eq!(json["tracked"]["exit_code"], 101);
+        assert!(!Config::default().behavior.tracking_output);
+    }
+
+    #[test]
+    fn test_permission_output_serialization() {
+        let output = PermissionOutput {
+            hook_specific_output: PermissionDecision {
+                hook_event_name: "PreToolUse".to_string(),
+                permission_decision: "ask".to_string(),
+                permission_decision_reason: "confirm".to_string(),
+                updated_input: None,
+            },
+        };
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["hookSpecificOutput"]["permissionDecision"], "ask");
+        assert_eq!(json["hookSpecificOutput"]["permissionDecisionReason"], "confirm");
+        assert_eq!(json["hookSpecificOutput"]["hookEventName"], "PreToolUse");
+    }
+}
@@ -0,0 +1,92 @@
+//! Plan-before-apply enforcement for infrastructure-as-code tools
+//!
+//! With `[iac] plan_before_apply = true`, an apply is only allowed once the
+//! matching plan succeeded earlier in the same session, as recorded in the
+//! session's command history. A successful apply uses the plan up, so the next
+//! apply needs a fresh one.
+
+use crate::rules::{command_matches, RuleMatch};
+use crate::state::CommandRun;
+use crate::types::{Result, Severity};
+
+/// An apply command and the plan that must succeed before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanGate {
+    pub apply: &'static str,
+    pub plan: &'static str,
+}
+
+/// Tools whose apply is gated on a plan.
+pub const PLAN_GATES: &[PlanGate] = &[
+    PlanGate { apply: "terraform apply", plan: "terraform plan" },
+    PlanGate { apply: "tofu apply", plan: "tofu plan" },
+    PlanGate { apply: "terragrunt apply", plan: "terragrunt plan" },
+    PlanGate { apply: "pulumi up", plan: "pulumi preview" },
+];
+
+/// Blocks an apply whose plan has not succeeded since the last apply.
+///
+/// # Arguments
+/// * `history` - Commands that finished in the session, oldest first
+/// * `command` - The command about to run
+///
+/// # Returns
+/// * `Ok(Some(RuleMatch))` - A blocking match explaining which plan to run first
+/// * `Ok(None)` - If `command` is no apply, or a fresh plan succeeded
+pub fn check_plan_gate(history: &[CommandRun], command: &str) -> Result<Option<RuleMatch>> {
+    for gate in PLAN_GATES {
+        if command_matches(gate.apply, command)? && !has_fresh_plan(history, gate)? {
+            return Ok(Some(RuleMatch {
+                pattern: gate.apply.to_string(),
+                reason: format!(
+                    "'{}' requires a successful '{}' earlier in this session. Run '{}', review the \
+                     planned changes, then apply.",
+                    gate.apply, gate.plan, gate.plan
+                ),
+                suggested_command: None,
+                severity: Severity::Block,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the latest successful plan or apply of `gate` is a plan.
+fn has_fresh_plan(history: &[CommandRun], gate: &PlanGate) -> Result<bool> {
+    for run in history.iter().rev().filter(|run| run.succeeded()) {
+        if command_matches(gate.apply, &run.command)? {
+            return Ok(false);
+        }
+        if command_matches(gate.plan, &run.command)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SessionState;
+
+    #[test]
+    fn test_apply_requires_successful_plan() {
+        let mut state = SessionState::default();
+        let blocked = check_plan_gate(&state.history, "terraform apply -auto-approve").unwrap().unwrap();
+        assert!(blocked.reason.contains("'terraform plan'"));
+        assert!(check_plan_gate(&state.history, "terraform plan").unwrap().is_none());
+
+        // A failed plan does not count
+        state.record_run("terraform plan -out plan.tfplan", Some(1), None);
+        assert!(check_plan_gate(&state.history, "terraform apply").unwrap().is_some());
+
+        state.record_run("terraform plan -out plan.tfplan", Some(0), None);
+        assert!(check_plan_gate(&state.history, "terraform apply plan.tfplan").unwrap().is_none());
+        // Other tools keep their own gates
+        assert!(check_plan_gate(&state.history, "pulumi up --yes").unwrap().is_some());
+
+        // Applying uses the plan up
+        state.record_run("terraform apply plan.tfplan", Some(0), None);
+        assert!(check_plan_gate(&state.history, "terraform apply").unwrap().is_some());
+    }
+}
@@ -0,0 +1,222 @@
+//! Inference of command mappings from what a project already uses
+//!
+//! Claude reaches for the tools it knows best, such as npm, even in a project
+//! that uses pnpm. The project usually says which tool it uses, through its
+//! lockfiles and tool configs, and through the commands that succeeded in
+//! recorded sessions. `--infer-rules` compares both with families of tools
+//! that do the same job and proposes `[commands]` mappings from the other
+//! members of a family to the one in use.
+
+use crate::shell::{normalize, split_segments};
+use crate::state::SessionState;
+use crate::types::Config;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Successful runs needed before history alone picks a tool.
+const MIN_RUNS: usize = 5;
+
+/// Share of a family's successful runs the most used tool needs before
+/// history alone picks it.
+const MIN_SHARE: f64 = 0.8;
+
+/// A tool that does the same job as the others in its family.
+struct Tool {
+    /// The command word, which mappings to this tool replace
+    command: &'static str,
+    /// How to run the tool in place of the others, such as `uv pip` for `pip`
+    invocation: &'static str,
+    /// Files in the project root that show it uses this tool
+    markers: &'static [&'static str],
+}
+
+struct Family {
+    name: &'static str,
+    tools: &'static [Tool],
+}
+
+const fn tool(command: &'static str, invocation: &'static str, markers: &'static [&'static str]) -> Tool {
+    Tool { command, invocation, markers }
+}
+
+const BIOME: &[&str] = &["biome.json", "biome.jsonc"];
+const RUFF: &[&str] = &["ruff.toml", ".ruff.toml"];
+
+/// Interchangeable tools, grouped by the job they do.
+const FAMILIES: &[Family] = &[
+    Family {
+        name: "JavaScript package manager",
+        tools: &[
+            tool("npm", "npm", &["package-lock.json", "npm-shrinkwrap.json"]),
+            tool("yarn", "yarn", &["yarn.lock"]),
+            tool("pnpm", "pnpm", &["pnpm-lock.yaml"]),
+            tool("bun", "bun", &["bun.lock", "bun.lockb"]),
+        ],
+    },
+    Family {
+        name: "Python package installer",
+        tools: &[tool("pip", "pip", &[]), tool("uv", "uv pip", &["uv.lock"])],
+    },
+    Family {
+        name: "JavaScript linter",
+        tools: &[
+            tool("eslint", "eslint", &["eslint.config.js", "eslint.config.mjs", ".eslintrc.js", ".eslintrc.cjs", ".eslintrc.json", ".eslintrc.yml", ".eslintrc"]),
+            tool("biome", "biome lint", BIOME),
+        ],
+    },
+    Family {
+        name: "JavaScript formatter",
+        tools: &[
+            tool("prettier", "prettier", &[".prettierrc", ".prettierrc.json", ".prettierrc.yaml", ".prettierrc.js", "prettier.config.js"]),
+            tool("biome", "biome format", BIOME),
+        ],
+    },
+    Family {
+        name: "Python linter",
+        tools: &[tool("flake8", "flake8", &[".flake8"]), tool("ruff", "ruff check", RUFF)],
+    },
+    Family {
+        name: "Python formatter",
+        tools: &[tool("black", "black", &[]), tool("ruff", "ruff format", RUFF)],
+    },
+    Family {
+        name: "text search",
+        tools: &[tool("grep", "grep", &[]), tool("rg", "rg", &[])],
+    },
+];
+
+/// A mapping `--infer-rules` suggests adding to `[commands]`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Proposal {
+    pub pattern: String,
+    pub replacement: String,
+    /// The evidence, such as "pnpm-lock.yaml shows the project uses pnpm"
+    pub reason: String,
+}
+
+/// Proposes mappings to the tools the project uses.
+///
+/// Within each family, a tool is picked when it is the only one with a
+/// marker file in the project, or, among the marked tools or all of them if
+/// none is marked, when it made at least 80% of the family's successful runs,
+/// and at least five of them. Each other tool of the family is then proposed
+/// to map to it, unless a mapping or rule already covers that command.
+///
+/// # Arguments
+/// * `config` - The configuration, whose existing mappings and rules are not proposed again
+/// * `project_dir` - The project root, searched for marker files
+/// * `sessions` - Recorded sessions, whose command history counts as usage
+pub fn infer_mappings(config: &Config, project_dir: &Path, sessions: &[SessionState]) -> Vec<Proposal> {
+    let runs = successful_runs(sessions);
+    let mut configured: Vec<&str> = config.active_commands().into_iter().map(|(pattern, _)| pattern.as_str()).collect();
+    configured.extend(config.active_rules().into_iter().map(|rule| rule.pattern.as_str()));
+
+    let mut proposals: Vec<Proposal> = Vec::new();
+    for family in FAMILIES {
+        let Some((preferred, reason)) = pick_tool(family, project_dir, &runs) else {
+            continue;
+        };
+        for other in family.tools.iter().filter(|tool| tool.command != preferred.command) {
+            let known = configured.contains(&other.command) || proposals.iter().any(|p| p.pattern == other.command);
+            if !known {
+                proposals.push(Proposal {
+                    pattern: other.command.to_string(),
+                    replacement: preferred.invocation.to_string(),
+                    reason: reason.clone(),
+                });
+            }
+        }
+    }
+    proposals
+}
+
+/// The tool of `family` the project uses, with the evidence for it.
+fn pick_tool<'f>(family: &'f Family, project_dir: &Path, runs: &HashMap<String, usize>) -> Option<(&'f Tool, String)> {
+    let marked: Vec<(&Tool, &str)> = family
+        .tools
+        .iter()
+        .filter_map(|tool| tool.markers.iter().find(|marker| project_dir.join(marker).is_file()).map(|marker| (tool, *marker)))
+        .collect();
+    if let [(tool, marker)] = marked[..] {
+        return Some((tool, format!("{marker} shows the project uses {}", tool.command)));
+    }
+
+    let count = |tool: &Tool| runs.get(tool.command).copied().unwrap_or(0);
+    let total: usize = family.tools.iter().map(count).sum();
+    let candidates: Vec<&Tool> = if marked.is_empty() { family.tools.iter().collect() } else { marked.iter().map(|(tool, _)| *tool).collect() };
+    let best = candidates.into_iter().max_by_key(|tool| count(tool))?;
+    let used = count(best);
+    (used >= MIN_RUNS && used as f64 >= MIN_SHARE * total as f64).then(|| {
+        (best, format!("{} made {used} of the {total} successful {} runs in recorded sessions", best.command, family.name))
+    })
+}
+
+/// Successful runs per command word across the history of all sessions.
+fn successful_runs(sessions: &[SessionState]) -> HashMap<String, usize> {
+    let mut runs: HashMap<String, usize> = HashMap::new();
+    for run in sessions.iter().flat_map(|session| &session.history).filter(|run| run.succeeded()) {
+        for segment in split_segments(&run.command) {
+            let segment = &run.command[segment];
+            let word = segment[normalize(segment).command_start..].split_whitespace().next().unwrap_or_default();
+            if !word.is_empty() {
+                *runs.entry(word.to_string()).or_default() += 1;
+            }
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn sessions(commands: &[(&str, usize)]) -> Vec<SessionState> {
+        let mut state = SessionState::default();
+        for (command, times) in commands {
+            for _ in 0..*times {
+                state.record_run(command, Some(0), None);
+            }
+        }
+        state.record_run("npm install", Some(1), None);
+        vec![state]
+    }
+
+    fn pairs(proposals: &[Proposal]) -> Vec<(&str, &str)> {
+        proposals.iter().map(|p| (p.pattern.as_str(), p.replacement.as_str())).collect()
+    }
+
+    #[test]
+    fn test_lockfiles_pick_the_tool() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("pnpm-lock.yaml"), "").unwrap();
+        fs::write(temp_dir.path().join("uv.lock"), "").unwrap();
+
+        let proposals = infer_mappings(&Config::default(), temp_dir.path(), &[]);
+        assert_eq!(pairs(&proposals), [("npm", "pnpm"), ("yarn", "pnpm"), ("bun", "pnpm"), ("pip", "uv pip")]);
+        assert_eq!(proposals[0].reason, "pnpm-lock.yaml shows the project uses pnpm");
+
+        // Commands the configuration already covers are not proposed again
+        let config: Config = toml::from_str("[commands]\nnpm = \"bun\"\n\n[[rules]]\npattern = \"yarn\"\n").unwrap();
+        assert_eq!(pairs(&infer_mappings(&config, temp_dir.path(), &[]))[..2], [("bun", "pnpm"), ("pip", "uv pip")]);
+    }
+
+    #[test]
+    fn test_history_picks_the_tool() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = sessions(&[("cd web && pnpm install", 6), ("rg TODO src", 9), ("grep -r x .", 1), ("ruff check .", 3)]);
+        let proposals = infer_mappings(&Config::default(), temp_dir.path(), &history);
+        assert_eq!(pairs(&proposals), [("npm", "pnpm"), ("yarn", "pnpm"), ("bun", "pnpm"), ("grep", "rg")]);
+        assert_eq!(proposals[3].reason, "rg made 9 of the 10 successful text search runs in recorded sessions");
+
+        // Two lockfiles are settled by history, and a split history settles nothing
+        fs::write(temp_dir.path().join("package-lock.json"), "").unwrap();
+        fs::write(temp_dir.path().join("yarn.lock"), "").unwrap();
+        let history = sessions(&[("yarn add zod", 5), ("npm test", 4)]);
+        assert!(infer_mappings(&Config::default(), temp_dir.path(), &history).is_empty());
+        let history = sessions(&[("yarn add zod", 8)]);
+        assert_eq!(pairs(&infer_mappings(&Config::default(), temp_dir.path(), &history)), [("npm", "yarn"), ("pnpm", "yarn"), ("bun", "yarn")]);
+    }
+}
@@ -1,13 +1,45 @@
 //! Installation and project setup logic
 
-use anyhow::{anyhow, Context, Result};
+use crate::atomic::write_atomic;
+use crate::jsonc;
+use crate::shell::tokenize;
+use crate::types::{AdvisorError, IoContext, Result};
+use crate::ui::{self, say};
 use serde_json::{Map, Value};
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
+/// Hook events registered by the installer, with their tool matchers.
+pub const HOOK_EVENTS: &[(&str, &str)] = &[
+    ("PreToolUse", "Bash"),
+    ("UserPromptSubmit", ""),
+    ("PostToolUse", "Bash"),
+    ("Stop", ""),
+    ("PreCompact", ""),
+    ("SessionStart", "compact"),
+];
+
+/// Options controlling how hooks are written into Claude Code settings.
+#[derive(Debug, Clone)]
+pub struct HookInstallOptions {
+    /// Hook events to register (names from `HOOK_EVENTS`)
+    pub events: Vec<String>,
+    /// Register the hook with `--replace` instead of the default block behavior
+    pub replace_mode: bool,
+    /// Skip interactive prompts and use defaults
+    pub assume_yes: bool,
+}
 
-
+impl Default for HookInstallOptions {
+    fn default() -> Self {
+        Self {
+            events: HOOK_EVENTS.iter().map(|(event, _)| event.to_string()).collect(),
+            replace_mode: false,
+            assume_yes: false,
+        }
+    }
+}
 
 /// Installs Claude Hook Advisor hooks directly into Claude Code settings.
 /// 
@@ -17,16 +49,18 @@ use std::path::{Path, PathBuf};
 /// 3. Carefully merges our hooks while preserving all existing hooks
 /// 4. Only replaces hooks that contain "claude-hook-advisor" in the command
 /// 
+/// # Arguments
+/// * `options` - Which hook events to register and how the hook is invoked
+///
 /// # Returns
 /// * `Ok(())` - Hooks installed successfully  
 /// * `Err` - If file operations fail or JSON parsing errors occur
-pub fn install_claude_hooks() -> Result<()> {
-    println!("🔧 Claude Hook Advisor - Hooks Installation");
-    println!("===========================================");
+pub fn install_claude_hooks(options: &HookInstallOptions) -> Result<()> {
+    say!("{}", ui::heading("🔧 Claude Hook Advisor - Hooks Installation"));
 
     // Determine the best settings file to use
-    let settings_path = determine_settings_file()?;
-    println!("📁 Using settings file: {}", settings_path.display());
+    let settings_path = determine_settings_file(options.assume_yes)?;
+    say!("📁 Using settings file: {}", settings_path.display());
 
     // Create backup before modifying
     create_settings_backup(&settings_path)?;
@@ -38,14 +72,14 @@ pub fn install_claude_hooks() -> Result<()> {
     let binary_path = get_current_binary_path()?;
     
     // Merge our hooks into existing settings
-    merge_claude_hooks(&mut settings, &binary_path)?;
+    merge_selected_hooks(&mut settings, &binary_path, options)?;
 
     // Write updated settings back to file
     write_settings_file(&settings_path, &settings)?;
 
-    println!("✅ Hooks successfully installed!");
-    println!("🎯 Claude Hook Advisor will now intercept Bash commands in Claude Code");
-    println!("📋 Run claude-hook-advisor --list-directory-aliases to see active directory mappings");
+    say!("{}", ui::success("✅ Hooks successfully installed!"));
+    say!("🎯 Claude Hook Advisor will now intercept Bash commands in Claude Code");
+    say!("📋 Run claude-hook-advisor dirs list to see active directory mappings");
 
     Ok(())
 }
@@ -56,19 +90,20 @@ pub fn install_claude_hooks() -> Result<()> {
 /// 1. .claude/settings.local.json (preferred - not committed to git)
 /// 2. .claude/settings.json (fallback - shared project settings)
 /// 
-/// Creates the .claude directory if it doesn't exist.
-fn determine_settings_file() -> Result<PathBuf> {
+/// Creates the .claude directory if it doesn't exist. When `assume_yes` is set,
+/// local settings are chosen without prompting.
+fn determine_settings_file(assume_yes: bool) -> Result<PathBuf> {
     let claude_dir = PathBuf::from(".claude");
     
     // Create .claude directory if it doesn't exist
     if !claude_dir.exists() {
         fs::create_dir_all(&claude_dir)
-            .context("Failed to create .claude directory")?;
-        println!("📁 Created .claude directory");
+            .io_context(|| "Failed to create .claude directory".to_string())?;
+        say!("📁 Created .claude directory");
     }
 
     // Prefer local settings (not committed)
-    let local_settings = claude_dir.join("settings.local.json");
+    let local_settings = default_settings_file();
     let shared_settings = claude_dir.join("settings.json");
 
     // If local settings exist, use them
@@ -77,8 +112,8 @@ fn determine_settings_file() -> Result<PathBuf> {
     }
 
     // If shared settings exist, ask user preference
-    if shared_settings.exists() {
-        println!("📋 Found existing .claude/settings.json (shared with team)");
+    if shared_settings.exists() && !assume_yes {
+        say!("📋 Found existing .claude/settings.json (shared with team)");
         print!("Install hooks to local settings instead? (Y/n): ");
         io::stdout().flush()?;
 
@@ -95,10 +130,16 @@ fn determine_settings_file() -> Result<PathBuf> {
     Ok(local_settings)
 }
 
+/// The settings file hooks are installed to unless the user picks the
+/// shared one: `.claude/settings.local.json`, which isn't committed.
+pub fn default_settings_file() -> PathBuf {
+    Path::new(".claude").join("settings.local.json")
+}
+
 /// Creates a timestamped backup of the settings file.
-fn create_settings_backup(settings_path: &Path) -> Result<()> {
+pub fn create_settings_backup(settings_path: &Path) -> Result<()> {
     if !settings_path.exists() {
-        println!("📋 No existing settings file to backup");
+        say!("📋 No existing settings file to backup");
         return Ok(());
     }
 
@@ -114,27 +155,72 @@ fn create_settings_backup(settings_path: &Path) -> Result<()> {
         .join(&backup_name);
 
     fs::copy(settings_path, &backup_path)
-        .with_context(|| format!("Failed to create backup at {}", backup_path.display()))?;
+        .io_context(|| format!("Failed to create backup at {}", backup_path.display()))?;
 
-    println!("💾 Created backup: {}", backup_path.display());
+    say!("💾 Created backup: {}", backup_path.display());
     Ok(())
 }
 
 /// Loads existing settings file or creates a new empty settings structure.
-fn load_or_create_settings(settings_path: &Path) -> Result<Value> {
-    if settings_path.exists() {
-        let content = fs::read_to_string(settings_path)
-            .with_context(|| format!("Failed to read settings file: {}", settings_path.display()))?;
-        
-        if content.trim().is_empty() {
-            return Ok(Value::Object(Map::new()));
-        }
+///
+/// Comments and trailing commas are accepted, as Claude Code accepts them.
+/// The settings are checked against the shape Claude Code expects for hooks
+/// before anything is changed.
+pub fn load_or_create_settings(settings_path: &Path) -> Result<Value> {
+    if !settings_path.exists() {
+        return Ok(Value::Object(Map::new()));
+    }
 
-        serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse JSON in settings file: {}", settings_path.display()))
-    } else {
-        Ok(Value::Object(Map::new()))
+    let content = fs::read_to_string(settings_path)
+        .io_context(|| format!("Failed to read settings file: {}", settings_path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(Value::Object(Map::new()));
     }
+
+    let settings = jsonc::parse(&content).map_err(|e| {
+        AdvisorError::Installer(format!("Failed to parse JSON in settings file {}: {e}", settings_path.display()))
+    })?;
+    validate_settings(&settings)
+        .map_err(|e| AdvisorError::Installer(format!("Invalid settings file {}: {e}", settings_path.display())))?;
+    Ok(settings)
+}
+
+/// Checks that the settings are an object and that `hooks`, if present, has
+/// the shape Claude Code reads: event names mapping to arrays of groups, each
+/// with an optional string `matcher` and a `hooks` array of hook objects.
+///
+/// # Returns
+/// * `Err(String)` - The path of the first value with the wrong type
+fn validate_settings(settings: &Value) -> std::result::Result<(), String> {
+    let settings = settings.as_object().ok_or("settings must be a JSON object")?;
+    let Some(hooks) = settings.get("hooks") else {
+        return Ok(());
+    };
+    let hooks = hooks.as_object().ok_or("hooks must be an object")?;
+
+    for (event, groups) in hooks {
+        let groups = groups.as_array().ok_or_else(|| format!("hooks.{event} must be an array"))?;
+        for (i, group) in groups.iter().enumerate() {
+            let path = format!("hooks.{event}[{i}]");
+            let group = group.as_object().ok_or_else(|| format!("{path} must be an object"))?;
+            if group.get("matcher").is_some_and(|matcher| !matcher.is_string()) {
+                return Err(format!("{path}.matcher must be a string"));
+            }
+            let Some(entries) = group.get("hooks") else {
+                continue;
+            };
+            let entries = entries.as_array().ok_or_else(|| format!("{path}.hooks must be an array"))?;
+            for (j, entry) in entries.iter().enumerate() {
+                let entry = entry.as_object().ok_or_else(|| format!("{path}.hooks[{j}] must be an object"))?;
+                for field in ["type", "command"] {
+                    if entry.get(field).is_some_and(|value| !value.is_string()) {
+                        return Err(format!("{path}.hooks[{j}].{field} must be a string"));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Gets the current binary path, preferring debug build for development.
@@ -160,16 +246,13 @@ fn get_current_binary_path() -> Result<String> {
     }
 }
 
-/// Merges Claude Hook Advisor hooks into existing settings, preserving other hooks.
-/// 
-/// This function is careful to:
-/// - Only replace hooks containing "claude-hook-advisor" 
-/// - Preserve all other existing hooks
-/// - Create proper hook structure if it doesn't exist
-/// - Handle both array and object formats for hooks
-fn merge_claude_hooks(settings: &mut Value, binary_path: &str) -> Result<()> {
+/// Merges the hook events selected in `options` into existing settings.
+///
+/// Our hooks are removed from events that were not selected, so re-running the
+/// installer with a smaller selection leaves no stale registrations behind.
+fn merge_selected_hooks(settings: &mut Value, binary_path: &str, options: &HookInstallOptions) -> Result<()> {
     let settings_obj = settings.as_object_mut()
-        .ok_or_else(|| anyhow!("Settings must be a JSON object"))?;
+        .ok_or_else(|| AdvisorError::Installer("Settings must be a JSON object".to_string()))?;
 
     // Ensure hooks object exists
     if !settings_obj.contains_key("hooks") {
@@ -178,19 +261,22 @@ fn merge_claude_hooks(settings: &mut Value, binary_path: &str) -> Result<()> {
 
     let hooks = settings_obj.get_mut("hooks")
         .and_then(|h| h.as_object_mut())
-        .ok_or_else(|| anyhow!("hooks must be an object"))?;
+        .ok_or_else(|| AdvisorError::Installer("hooks must be an object".to_string()))?;
 
     // Our hook configuration
-    let hook_command = format!("{binary_path} --hook");
+    let hook_command = if options.replace_mode {
+        format!("{binary_path} --hook --replace")
+    } else {
+        format!("{binary_path} --hook")
+    };
 
-    // Install PreToolUse hook for Bash commands
-    merge_hook_event(hooks, "PreToolUse", "Bash", &hook_command)?;
-    
-    // Install UserPromptSubmit hook (no matcher needed)
-    merge_hook_event(hooks, "UserPromptSubmit", "", &hook_command)?;
-    
-    // Install PostToolUse hook for Bash commands  
-    merge_hook_event(hooks, "PostToolUse", "Bash", &hook_command)?;
+    for (event_name, matcher) in HOOK_EVENTS {
+        if options.events.iter().any(|e| e == event_name) {
+            merge_hook_event(hooks, event_name, matcher, &hook_command)?;
+        } else {
+            remove_hooks_from_event(hooks, event_name)?;
+        }
+    }
 
     Ok(())
 }
@@ -204,14 +290,14 @@ fn merge_hook_event(hooks: &mut Map<String, Value>, event_name: &str, matcher: &
 
     let event_hooks = hooks.get_mut(event_name)
         .and_then(|h| h.as_array_mut())
-        .ok_or_else(|| anyhow!("{} hooks must be an array", event_name))?;
+        .ok_or_else(|| AdvisorError::Installer(format!("{} hooks must be an array", event_name)))?;
 
     // Look for existing claude-hook-advisor hooks to replace
     let mut found_existing = false;
 
     for hook_group in event_hooks.iter_mut() {
         let hook_obj = hook_group.as_object_mut()
-            .ok_or_else(|| anyhow!("Hook group must be an object"))?;
+            .ok_or_else(|| AdvisorError::Installer("Hook group must be an object".to_string()))?;
 
         // Check if this hook group matches our matcher
         let group_matcher = hook_obj.get("matcher")
@@ -271,21 +357,329 @@ fn merge_hook_event(hooks: &mut Map<String, Value>, event_name: &str, matcher: &
     Ok(())
 }
 
-/// Writes the updated settings back to the file with pretty formatting.
-fn write_settings_file(settings_path: &Path, settings: &Value) -> Result<()> {
-    let json_content = serde_json::to_string_pretty(settings)
-        .context("Failed to serialize settings to JSON")?;
+/// Writes the updated settings back to the file.
+///
+/// An existing file is edited in place, so its comments and formatting
+/// survive; the result is parsed back and compared with `settings` before it
+/// is written. A new or empty file is written pretty-printed.
+pub fn write_settings_file(settings_path: &Path, settings: &Value) -> Result<()> {
+    let existing = fs::read_to_string(settings_path).unwrap_or_default();
+    let edited = Some(existing.as_str())
+        .filter(|existing| !existing.trim().is_empty())
+        .and_then(|existing| jsonc::update(existing, settings).ok())
+        .filter(|edited| jsonc::parse(edited).is_ok_and(|parsed| parsed == *settings));
+
+    let json_content = match edited {
+        Some(edited) => edited,
+        None => {
+            if !existing.trim().is_empty() {
+                say!("⚠️  Could not edit {} in place; rewriting it without comments", settings_path.display());
+            }
+            serde_json::to_string_pretty(settings)
+                .io_context(|| "Failed to serialize settings to JSON".to_string())?
+        }
+    };
+
+    write_atomic(settings_path, json_content)
+        .io_context(|| format!("Failed to write settings file: {}", settings_path.display()))?;
+
+    Ok(())
+}
+
+/// A claude-hook-advisor command registered in Claude Code settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredHook {
+    pub event: String,
+    pub command: String,
+    /// The program the command runs, without quotes
+    pub program: String,
+}
+
+impl RegisteredHook {
+    /// Whether the program is gone, so Claude Code silently never runs the hook.
+    pub fn is_stale(&self) -> bool {
+        if self.program.contains('/') {
+            !Path::new(&self.program).is_file()
+        } else {
+            which::which(&self.program).is_err()
+        }
+    }
+
+    /// Whether the program is the running binary.
+    pub fn runs(&self, exe: &Path) -> bool {
+        let program = if self.program.contains('/') {
+            PathBuf::from(&self.program)
+        } else {
+            match which::which(&self.program) {
+                Ok(path) => path,
+                Err(_) => return false,
+            }
+        };
+        match (program.canonicalize(), exe.canonicalize()) {
+            (Ok(program), Ok(exe)) => program == exe,
+            _ => false,
+        }
+    }
+}
+
+/// The claude-hook-advisor commands registered in `settings`, by event.
+pub fn registered_hooks(settings: &Value) -> Vec<RegisteredHook> {
+    let Some(hooks) = settings.get("hooks").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    let mut registered = Vec::new();
+    for (event, groups) in hooks {
+        let commands = groups
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|group| group.get("hooks").and_then(Value::as_array))
+            .flatten()
+            .filter_map(|hook| hook.get("command").and_then(Value::as_str))
+            .filter(|command| command.contains("claude-hook-advisor"));
+        for command in commands {
+            if let Some((_, program)) = hook_program(command) {
+                registered.push(RegisteredHook { event: event.clone(), command: command.to_string(), program });
+            }
+        }
+    }
+    registered
+}
+
+/// The program a hook command runs: its byte range in the command and its
+/// unquoted text.
+fn hook_program(command: &str) -> Option<(std::ops::Range<usize>, String)> {
+    let word = tokenize(command).into_iter().next()?;
+    let program = command[word.clone()].chars().filter(|c| !matches!(c, '\'' | '"')).collect();
+    Some((word, program))
+}
+
+/// Finds the settings file in use and the claude-hook-advisor hooks in it.
+///
+/// # Returns
+/// * `Ok(Some((path, hooks)))` - The settings file and its hooks, possibly none
+/// * `Ok(None)` - If there is no settings file
+/// * `Err` - If the settings file can't be read or parsed
+pub fn installed_hooks() -> Result<Option<(PathBuf, Vec<RegisteredHook>)>> {
+    let Ok(settings_path) = find_existing_settings_file() else {
+        return Ok(None);
+    };
+    let settings = load_or_create_settings(&settings_path)?;
+    Ok(Some((settings_path, registered_hooks(&settings))))
+}
+
+/// Points stale hook commands in `settings` at `binary`, keeping their arguments.
+///
+/// # Returns
+/// * The number of commands rewritten
+fn rewrite_stale_hooks(settings: &mut Value, binary: &str) -> usize {
+    let binary = if binary.contains(char::is_whitespace) { format!("\"{binary}\"") } else { binary.to_string() };
+    let stale: Vec<String> = registered_hooks(settings)
+        .into_iter()
+        .filter(RegisteredHook::is_stale)
+        .map(|hook| hook.command)
+        .collect();
+
+    let mut rewritten = 0;
+    let groups = settings.get_mut("hooks").and_then(Value::as_object_mut).into_iter().flat_map(|hooks| hooks.values_mut());
+    for group in groups.filter_map(Value::as_array_mut).flatten() {
+        let entries = group.get_mut("hooks").and_then(Value::as_array_mut).into_iter().flatten();
+        for command in entries.filter_map(|hook| hook.get_mut("command")) {
+            let Some(text) = command.as_str().filter(|text| stale.iter().any(|stale| stale == text)) else {
+                continue;
+            };
+            if let Some((program, _)) = hook_program(text) {
+                *command = Value::String(format!("{}{binary}{}", &text[..program.start], &text[program.end..]));
+                rewritten += 1;
+            }
+        }
+    }
+    rewritten
+}
+
+/// Rewrites the hook commands whose binary is gone to run `binary` instead.
+/// The settings file is backed up first.
+///
+/// # Arguments
+/// * `settings_path` - The settings file, as found by `installed_hooks`
+/// * `binary` - The program the hooks should run: a path or a name on PATH
+///
+/// # Returns
+/// * `Ok(usize)` - The number of commands rewritten
+/// * `Err` - If the settings file can't be read, parsed or written
+pub fn repair_stale_hooks(settings_path: &Path, binary: &str) -> Result<usize> {
+    let mut settings = load_or_create_settings(settings_path)?;
+    let rewritten = rewrite_stale_hooks(&mut settings, binary);
+    if rewritten > 0 {
+        create_settings_backup(settings_path)?;
+        write_settings_file(settings_path, &settings)?;
+    }
+    Ok(rewritten)
+}
+
+/// Comment line marking the snippet we add to `.git/hooks/pre-commit`.
+const GIT_HOOK_MARKER: &str = "# claude-hook-advisor: lint staged scripts and CI files";
+
+/// Installs a git pre-commit hook that lints staged shell scripts and CI files.
+///
+/// The hook runs `claude-hook-advisor --hook-mode git`, so commands blocked for
+/// Claude are also blocked for humans at commit time. An existing pre-commit
+/// script is kept: our snippet is inserted after its shebang line.
+///
+/// # Returns
+/// * `Ok(())` - Hook installed, or already present
+/// * `Err` - If not inside a git repository or the hook cannot be written
+pub fn install_git_hook() -> Result<()> {
+    println!("{}", ui::heading("🔧 Claude Hook Advisor - Git Hook Installation"));
+
+    let hook_path = git_hooks_dir()?.join("pre-commit");
+    let existing = fs::read_to_string(&hook_path).ok();
+    let binary_path = get_current_binary_path()?;
+
+    let Some(script) = pre_commit_script(existing.as_deref(), &binary_path) else {
+        println!("{}", ui::success(format!("✅ Git pre-commit hook already installed: {}", hook_path.display())));
+        return Ok(());
+    };
+
+    if existing.is_some() {
+        create_settings_backup(&hook_path)?;
+    } else if let Some(parent) = hook_path.parent() {
+        fs::create_dir_all(parent)
+            .io_context(|| format!("Failed to create hooks directory: {}", parent.display()))?;
+    }
+
+    write_atomic(&hook_path, script)
+        .io_context(|| format!("Failed to write git hook: {}", hook_path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))
+            .io_context(|| format!("Failed to make git hook executable: {}", hook_path.display()))?;
+    }
+
+    println!("{}", ui::success(format!("✅ Git pre-commit hook installed: {}", hook_path.display())));
+    println!("🎯 Staged shell scripts and CI files will be checked against your rules on commit");
+
+    Ok(())
+}
+
+/// Asks git where hooks live, honoring `core.hooksPath` and worktrees.
+fn git_hooks_dir() -> Result<PathBuf> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .io_context(|| "Failed to run git".to_string())?;
+
+    if !output.status.success() {
+        return Err(AdvisorError::Installer("Not inside a git repository".to_string()));
+    }
+
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
 
-    fs::write(settings_path, json_content)
-        .with_context(|| format!("Failed to write settings file: {}", settings_path.display()))?;
+/// Builds the pre-commit script, adding our snippet to `existing` if given.
+///
+/// Returns `None` if the snippet is already present.
+fn pre_commit_script(existing: Option<&str>, binary_path: &str) -> Option<String> {
+    let snippet = format!("{GIT_HOOK_MARKER}\n\"{binary_path}\" --hook-mode git || exit 1\n");
+
+    match existing {
+        None => Some(format!("#!/bin/sh\n{snippet}")),
+        Some(script) if script.contains(GIT_HOOK_MARKER) => None,
+        Some(script) => match script.split_once('\n') {
+            Some((shebang, rest)) if shebang.starts_with("#!") => Some(format!("{shebang}\n{snippet}{rest}")),
+            _ => Some(format!("#!/bin/sh\n{snippet}{script}")),
+        },
+    }
+}
+
+/// Line marking the slash commands we write, so re-running the installer updates them.
+const SLASH_COMMAND_MARKER: &str = "<!-- claude-hook-advisor -->";
+
+/// Where Claude Code looks for a project's custom slash commands.
+const SLASH_COMMANDS_DIR: &str = ".claude/commands";
+
+/// A slash command whose body runs the CLI and shows Claude its output.
+struct SlashCommand {
+    name: &'static str,
+    description: &'static str,
+    /// Arguments passed to the CLI
+    args: &'static str,
+    /// What Claude should do with the output
+    instructions: &'static str,
+}
+
+const SLASH_COMMANDS: &[SlashCommand] = &[
+    SlashCommand {
+        name: "conventions",
+        description: "Show this project's command conventions",
+        args: "--list-conventions",
+        instructions: "These are the project's command conventions. Follow them for the rest of this session.",
+    },
+    SlashCommand {
+        name: "advisor-stats",
+        description: "Show how often claude-hook-advisor suggestions were accepted",
+        args: "--stats",
+        instructions: "Summarize which suggestions are usually accepted and which rules look worth revisiting.",
+    },
+];
+
+/// Writes the `.claude/commands/` entries for our slash commands in the current project.
+///
+/// Commands we wrote before are updated; a file of the same name written by
+/// someone else is left alone.
+///
+/// # Returns
+/// * `Ok(())` - Commands written, or skipped with a notice
+/// * `Err` - If the commands directory or a command file cannot be written
+pub fn install_slash_commands() -> Result<()> {
+    println!("{}", ui::heading("🔧 Claude Hook Advisor - Slash Command Installation"));
+
+    let binary_path = get_current_binary_path()?;
+    for (path, written) in write_slash_commands(Path::new(SLASH_COMMANDS_DIR), &binary_path)? {
+        if written {
+            println!("{}", ui::success(format!("✅ Wrote {}", path.display())));
+        } else {
+            println!("⏭️  Skipped {}: it exists and was not written by claude-hook-advisor", path.display());
+        }
+    }
+    println!("🎯 Type /conventions or /advisor-stats in Claude Code to use them");
 
     Ok(())
 }
 
+/// Writes each slash command into `dir`, returning its path and whether it was written.
+fn write_slash_commands(dir: &Path, binary_path: &str) -> Result<Vec<(PathBuf, bool)>> {
+    fs::create_dir_all(dir).io_context(|| format!("Failed to create commands directory: {}", dir.display()))?;
+
+    let mut outcomes = Vec::new();
+    for command in SLASH_COMMANDS {
+        let path = dir.join(format!("{}.md", command.name));
+        let foreign = fs::read_to_string(&path).is_ok_and(|existing| !existing.contains(SLASH_COMMAND_MARKER));
+        if !foreign {
+            write_atomic(&path, slash_command_file(command, binary_path))
+                .io_context(|| format!("Failed to write slash command: {}", path.display()))?;
+        }
+        outcomes.push((path, !foreign));
+    }
+    Ok(outcomes)
+}
+
+/// The Markdown file for `command`: it runs the CLI when invoked and lets
+/// Claude run that one command without asking.
+fn slash_command_file(command: &SlashCommand, binary_path: &str) -> String {
+    let invocation = format!("{binary_path} {}", command.args);
+    format!(
+        "---\ndescription: {}\nallowed-tools: Bash({invocation})\n---\n{SLASH_COMMAND_MARKER}\n\n!`{invocation}`\n\n{}\n",
+        command.description, command.instructions
+    )
+}
+
 /// Uninstalls Claude Hook Advisor hooks from Claude Code settings.
 pub fn uninstall_claude_hooks() -> Result<()> {
-    println!("🔧 Claude Hook Advisor - Hooks Uninstallation");
-    println!("===============================================");
+    println!("{}", ui::heading("🔧 Claude Hook Advisor - Hooks Uninstallation"));
 
     let settings_path = find_existing_settings_file()?;
     println!("📁 Using settings file: {}", settings_path.display());
@@ -300,7 +694,7 @@ pub fn uninstall_claude_hooks() -> Result<()> {
     }
 
     write_settings_file(&settings_path, &settings)?;
-    println!("✅ Hooks successfully uninstalled!");
+    println!("{}", ui::success("✅ Hooks successfully uninstalled!"));
     println!("🗑️  Removed {removed_count} claude-hook-advisor hook(s)");
     
     Ok(())
@@ -317,12 +711,12 @@ fn find_existing_settings_file() -> Result<PathBuf> {
     if shared_settings.exists() {
         return Ok(shared_settings);
     }
-    Err(anyhow!("No Claude Code settings file found. Run 'claude-hook-advisor --install' first."))
+    Err(AdvisorError::Installer("No Claude Code settings file found. Run 'claude-hook-advisor --install' first.".to_string()))
 }
 
 fn remove_claude_hooks(settings: &mut Value) -> Result<usize> {
     let settings_obj = settings.as_object_mut()
-        .ok_or_else(|| anyhow!("Settings must be a JSON object"))?;
+        .ok_or_else(|| AdvisorError::Installer("Settings must be a JSON object".to_string()))?;
 
     if !settings_obj.contains_key("hooks") {
         return Ok(0);
@@ -330,7 +724,7 @@ fn remove_claude_hooks(settings: &mut Value) -> Result<usize> {
 
     let hooks = settings_obj.get_mut("hooks")
         .and_then(|h| h.as_object_mut())
-        .ok_or_else(|| anyhow!("hooks must be an object"))?;
+        .ok_or_else(|| AdvisorError::Installer("hooks must be an object".to_string()))?;
 
     let mut total_removed = 0;
     let event_names: Vec<String> = hooks.keys().cloned().collect();
@@ -350,7 +744,7 @@ fn remove_claude_hooks(settings: &mut Value) -> Result<usize> {
 fn remove_hooks_from_event(hooks: &mut Map<String, Value>, event_name: &str) -> Result<usize> {
     let event_hooks = match hooks.get_mut(event_name) {
         Some(hooks_array) => hooks_array.as_array_mut()
-            .ok_or_else(|| anyhow!("{} hooks must be an array", event_name))?,
+            .ok_or_else(|| AdvisorError::Installer(format!("{} hooks must be an array", event_name)))?,
         None => return Ok(0),
     };
 
@@ -359,7 +753,7 @@ fn remove_hooks_from_event(hooks: &mut Map<String, Value>, event_name: &str) ->
     while i < event_hooks.len() {
         let hook_group = &mut event_hooks[i];
         let hook_obj = hook_group.as_object_mut()
-            .ok_or_else(|| anyhow!("Hook group must be an object"))?;
+            .ok_or_else(|| AdvisorError::Installer("Hook group must be an object".to_string()))?;
 
         if let Some(hooks_array) = hook_obj.get_mut("hooks")
             .and_then(|h| h.as_array_mut()) {
@@ -407,7 +801,7 @@ mod tests {
         let mut settings = serde_json::json!({});
         let binary_path = "/path/to/claude-hook-advisor";
         
-        let result = merge_claude_hooks(&mut settings, binary_path);
+        let result = merge_selected_hooks(&mut settings, binary_path, &HookInstallOptions::default());
         assert!(result.is_ok());
 
         // Should have created hooks structure
@@ -439,7 +833,7 @@ mod tests {
         });
 
         let binary_path = "/path/to/claude-hook-advisor";
-        let result = merge_claude_hooks(&mut settings, binary_path);
+        let result = merge_selected_hooks(&mut settings, binary_path, &HookInstallOptions::default());
         assert!(result.is_ok());
 
         let hooks = settings.get("hooks").unwrap().as_object().unwrap();
@@ -480,7 +874,7 @@ mod tests {
         });
 
         let binary_path = "/path/to/claude-hook-advisor";
-        let result = merge_claude_hooks(&mut settings, binary_path);
+        let result = merge_selected_hooks(&mut settings, binary_path, &HookInstallOptions::default());
         assert!(result.is_ok());
 
         let hooks = settings.get("hooks").unwrap().as_object().unwrap();
@@ -537,15 +931,17 @@ mod tests {
         let binary_path = "/usr/local/bin/claude-hook-advisor";
 
         // Install our hooks
-        let install_result = merge_claude_hooks(&mut settings, binary_path);
+        let install_result = merge_selected_hooks(&mut settings, binary_path, &HookInstallOptions::default());
         assert!(install_result.is_ok());
 
         // Verify installation
         let hooks = settings.get("hooks").unwrap().as_object().unwrap();
         
-        // Should have 3 hook event types now (PreToolUse, UserPromptSubmit, PostToolUse)
-        // PreToolUse and PostToolUse existed before, UserPromptSubmit is new
-        assert_eq!(hooks.len(), 3);
+        // Should have 6 hook event types now (PreToolUse, UserPromptSubmit, PostToolUse, Stop,
+        // PreCompact, SessionStart); PreToolUse and PostToolUse existed before
+        assert_eq!(hooks.len(), 6);
+        assert!(hooks.contains_key("Stop"));
+        assert_eq!(hooks["SessionStart"][0]["matcher"], "compact");
         assert!(hooks.contains_key("PreToolUse"));
         assert!(hooks.contains_key("UserPromptSubmit"));
         assert!(hooks.contains_key("PostToolUse"));
@@ -588,6 +984,141 @@ mod tests {
 
 
     #[test]
+    fn test_merge_selected_hooks() {
+        let mut settings = serde_json::json!({
+            "hooks": {
+                "PostToolUse": [
+                    {
+                        "matcher": "Bash",
+                        "hooks": [
+                            {
+                                "type": "command",
+                                "command": "claude-hook-advisor --hook"
+                            }
+                        ]
+                    }
+                ]
+            }
+        });
+
+        let options = HookInstallOptions {
+            events: vec!["PreToolUse".to_string()],
+            replace_mode: true,
+            assume_yes: true,
+        };
+        merge_selected_hooks(&mut settings, "claude-hook-advisor", &options).unwrap();
+
+        let hooks = settings.get("hooks").unwrap().as_object().unwrap();
+        assert_eq!(hooks.len(), 1, "unselected events should not keep our hooks");
+
+        let pre_tool_use = hooks.get("PreToolUse").unwrap().as_array().unwrap();
+        let command = pre_tool_use[0]["hooks"][0]["command"].as_str().unwrap();
+        assert_eq!(command, "claude-hook-advisor --hook --replace");
+    }
+
+    #[test]
+    fn test_jsonc_settings_keep_comments() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("settings.local.json");
+        let original = "{\n  // Personal overrides\n  \"model\": \"opus\" /* for now */\n}\n";
+        fs::write(&path, original).unwrap();
+
+        let mut settings = load_or_create_settings(&path).unwrap();
+        merge_selected_hooks(&mut settings, "claude-hook-advisor", &HookInstallOptions::default()).unwrap();
+        write_settings_file(&path, &settings).unwrap();
+        let installed = fs::read_to_string(&path).unwrap();
+        assert!(installed.starts_with("{\n  // Personal overrides\n  \"model\": \"opus\", /* for now */\n  \"hooks\": {"));
+
+        let mut settings = load_or_create_settings(&path).unwrap();
+        assert_eq!(remove_claude_hooks(&mut settings).unwrap(), HOOK_EVENTS.len());
+        write_settings_file(&path, &settings).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_stale_hooks_are_rewritten() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("new home")).unwrap();
+        let present = dir.path().join("new home/claude-hook-advisor");
+        fs::write(&present, "").unwrap();
+        let mut settings = serde_json::json!({
+            "hooks": {
+                "PreToolUse": [{
+                    "matcher": "Bash",
+                    "hooks": [
+                        { "type": "command", "command": "'/moved away/claude-hook-advisor' --hook --replace" },
+                        { "type": "command", "command": "prettier --check" }
+                    ]
+                }],
+                "Stop": [{ "hooks": [{ "type": "command", "command": format!("'{}' --hook", present.display()) }] }]
+            }
+        });
+
+        let registered = registered_hooks(&settings);
+        assert_eq!(registered.len(), 2);
+        assert_eq!(registered[0].program, "/moved away/claude-hook-advisor");
+        assert!(registered[0].is_stale());
+        assert!(!registered[1].is_stale());
+        assert!(registered[1].runs(&present));
+
+        let binary = present.to_string_lossy();
+        assert_eq!(rewrite_stale_hooks(&mut settings, &binary), 1);
+        assert_eq!(settings["hooks"]["PreToolUse"][0]["hooks"][0]["command"], format!("\"{binary}\" --hook --replace"));
+        assert_eq!(settings["hooks"]["PreToolUse"][0]["hooks"][1]["command"], "prettier --check");
+        assert_eq!(rewrite_stale_hooks(&mut settings, &binary), 0);
+    }
+
+    #[test]
+    fn test_validate_settings() {
+        assert!(validate_settings(&serde_json::json!({ "model": "opus" })).is_ok());
+        assert_eq!(validate_settings(&serde_json::json!([])).unwrap_err(), "settings must be a JSON object");
+        let bad_command = serde_json::json!({
+            "hooks": { "Stop": [{ "hooks": [{ "type": "command", "command": ["echo"] }] }] }
+        });
+        assert_eq!(validate_settings(&bad_command).unwrap_err(), "hooks.Stop[0].hooks[0].command must be a string");
+        let bad_matcher = serde_json::json!({ "hooks": { "PreToolUse": [{ "matcher": 1 }] } });
+        assert_eq!(validate_settings(&bad_matcher).unwrap_err(), "hooks.PreToolUse[0].matcher must be a string");
+    }
+
+    #[test]
+    fn test_pre_commit_script() {
+        let fresh = pre_commit_script(None, "claude-hook-advisor").unwrap();
+        assert!(fresh.starts_with("#!/bin/sh\n"));
+        assert!(fresh.contains("\"claude-hook-advisor\" --hook-mode git || exit 1"));
+
+        // Existing hooks keep their shebang and body, with our check running first
+        let existing = "#!/bin/bash\ncargo fmt --check\n";
+        let merged = pre_commit_script(Some(existing), "claude-hook-advisor").unwrap();
+        assert!(merged.starts_with("#!/bin/bash\n# claude-hook-advisor"));
+        assert!(merged.ends_with("cargo fmt --check\n"));
+
+        // Installing twice is a no-op
+        assert_eq!(pre_commit_script(Some(&merged), "claude-hook-advisor"), None);
+    }
+
+    #[test]
+    fn test_write_slash_commands() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let commands = dir.path().join(".claude/commands");
+        fs::create_dir_all(&commands).unwrap();
+        fs::write(commands.join("advisor-stats.md"), "My own stats command\n").unwrap();
+
+        let outcomes = write_slash_commands(&commands, "claude-hook-advisor").unwrap();
+        assert_eq!(outcomes, [(commands.join("conventions.md"), true), (commands.join("advisor-stats.md"), false)]);
+        let conventions = fs::read_to_string(commands.join("conventions.md")).unwrap();
+        assert!(conventions.starts_with("---\ndescription: Show this project's command conventions\n"));
+        assert!(conventions.contains("allowed-tools: Bash(claude-hook-advisor --list-conventions)\n"));
+        assert!(conventions.contains("\n!`claude-hook-advisor --list-conventions`\n"));
+        assert_eq!(fs::read_to_string(commands.join("advisor-stats.md")).unwrap(), "My own stats command\n");
+
+        // Our own files are updated on the next install
+        let outcomes = write_slash_commands(&commands, "/opt/bin/claude-hook-advisor").unwrap();
+        assert!(outcomes[0].1);
+        assert!(fs::read_to_string(commands.join("conventions.md")).unwrap().contains("!`/opt/bin/claude-hook-advisor --list-conventions`"));
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_debug_assertions_consistency() {
         // This test validates that we're using the correct build detection method
         // In debug builds (cargo test), debug_assertions should be true
@@ -0,0 +1,529 @@
+//! Comment-preserving edits of JSON with comments (JSONC)
+//!
+//! Claude Code accepts `//` and `/* */` comments and trailing commas in its
+//! settings files. Rewriting such a file from a parsed `serde_json::Value`
+//! would drop the comments and reformat everything, so edits are made as
+//! text splices instead: the file is parsed into a tree of byte spans, the
+//! old and new values are compared, and only the members and elements that
+//! changed are rewritten. Everything else, comments included, is kept byte
+//! for byte.
+
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+/// A parsed JSONC value and where it is in the text.
+#[derive(Debug)]
+struct Node {
+    span: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    /// Members as `(key, start of the key, value)`, in file order
+    Object(Vec<(String, usize, Node)>),
+    Array(Vec<Node>),
+    Scalar(Value),
+}
+
+impl Node {
+    fn to_value(&self) -> Value {
+        match &self.kind {
+            Kind::Object(members) => Value::Object(
+                members.iter().map(|(key, _, value)| (key.clone(), value.to_value())).collect::<Map<_, _>>(),
+            ),
+            Kind::Array(items) => Value::Array(items.iter().map(Node::to_value).collect()),
+            Kind::Scalar(value) => value.clone(),
+        }
+    }
+}
+
+/// Parses JSON that may contain comments and trailing commas.
+///
+/// # Returns
+/// * `Ok(Value)` - The document, with comments dropped
+/// * `Err(String)` - What is wrong and at which line and column
+pub fn parse(text: &str) -> std::result::Result<Value, String> {
+    Ok(Parser::new(text).document()?.to_value())
+}
+
+/// Rewrites `text` so that it holds `value`, changing only what differs.
+///
+/// Members and elements that are added are indented like their siblings;
+/// removed ones take their own lines, and any comment at the end of them,
+/// with them. Comments elsewhere are preserved.
+///
+/// # Arguments
+/// * `text` - The current JSONC document
+/// * `value` - What the document should contain
+///
+/// # Returns
+/// * `Ok(String)` - The edited document
+/// * `Err(String)` - If `text` is not valid JSONC
+pub fn update(text: &str, value: &Value) -> std::result::Result<String, String> {
+    let root = Parser::new(text).document()?;
+    let mut editor = Editor {
+        text,
+        unit: indent_unit(text, &root),
+        newline: if text.contains("\r\n") { "\r\n" } else { "\n" },
+        edits: Vec::new(),
+    };
+    editor.diff(&root, value);
+
+    // Apply from the end so earlier offsets stay valid; at the same offset a
+    // removal goes before an insertion so it cannot swallow the inserted text
+    let mut edits = editor.edits;
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse((range.start, range.end)));
+    let mut updated = text.to_string();
+    for (range, replacement) in edits {
+        updated.replace_range(range, &replacement);
+    }
+    Ok(updated)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser { text, pos: 0 }
+    }
+
+    fn document(&mut self) -> std::result::Result<Node, String> {
+        let node = self.value()?;
+        self.skip_trivia()?;
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected text after the document"));
+        }
+        Ok(node)
+    }
+
+    fn error(&self, message: &str) -> String {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        format!("line {line}, column {column}: {message}")
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) -> std::result::Result<(), String> {
+        loop {
+            let rest = &self.text[self.pos..];
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let end = comment.find("*/").ok_or_else(|| self.error("unterminated comment"))?;
+                self.pos += end + 4;
+            } else if rest.starts_with(|c: char| c.is_whitespace()) {
+                self.pos += rest.len() - rest.trim_start().len();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn value(&mut self) -> std::result::Result<Node, String> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let kind = match self.peek() {
+            Some(b'{') => self.object()?,
+            Some(b'[') => self.array()?,
+            Some(b'"') => Kind::Scalar(Value::String(self.string()?)),
+            Some(_) => {
+                let rest = &self.text[self.pos..];
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c))).unwrap_or(rest.len());
+                let literal = serde_json::from_str(&rest[..len]).map_err(|_| self.error("expected a value"))?;
+                self.pos += len;
+                Kind::Scalar(literal)
+            }
+            None => return Err(self.error("unexpected end of document")),
+        };
+        Ok(Node { span: start..self.pos, kind })
+    }
+
+    fn string(&mut self) -> std::result::Result<String, String> {
+        let start = self.pos;
+        let bytes = self.text.as_bytes();
+        let mut end = start + 1;
+        while end < bytes.len() && bytes[end] != b'"' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        if end >= bytes.len() {
+            return Err(self.error("unterminated string"));
+        }
+        let string = serde_json::from_str(&self.text[start..=end]).map_err(|e| self.error(&e.to_string()))?;
+        self.pos = end + 1;
+        Ok(string)
+    }
+
+    fn expect(&mut self, byte: u8) -> std::result::Result<(), String> {
+        self.skip_trivia()?;
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// After an element: consumes the comma before the next one, or reports
+    /// that the container ends.
+    fn separator(&mut self, close: u8) -> std::result::Result<bool, String> {
+        self.skip_trivia()?;
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(byte) if byte == close => Ok(true),
+            _ => Err(self.error(&format!("expected ',' or '{}'", close as char))),
+        }
+    }
+
+    fn object(&mut self) -> std::result::Result<Kind, String> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b'}') {
+                break;
+            }
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a member name"));
+            }
+            let key_start = self.pos;
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, key_start, self.value()?));
+            if self.separator(b'}')? {
+                break;
+            }
+        }
+        self.pos += 1;
+        Ok(Kind::Object(members))
+    }
+
+    fn array(&mut self) -> std::result::Result<Kind, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b']') {
+                break;
+            }
+            items.push(self.value()?);
+            if self.separator(b']')? {
+                break;
+            }
+        }
+        self.pos += 1;
+        Ok(Kind::Array(items))
+    }
+}
+
+/// The indentation one nesting level adds, taken from the document's first
+/// member or element; two spaces if there is none.
+fn indent_unit(text: &str, root: &Node) -> String {
+    let first = match &root.kind {
+        Kind::Object(members) => members.first().map(|(_, start, _)| *start),
+        Kind::Array(items) => items.first().map(|item| item.span.start),
+        Kind::Scalar(_) => None,
+    };
+    first
+        .filter(|start| starts_line(text, *start))
+        .map(|start| line_indent(text, start).to_string())
+        .filter(|unit| !unit.is_empty())
+        .unwrap_or_else(|| "  ".to_string())
+}
+
+/// Start of the line containing `pos`.
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// The whitespace that starts the line containing `pos`.
+fn line_indent(text: &str, pos: usize) -> &str {
+    let line = &text[line_start(text, pos)..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Whether only whitespace precedes `pos` on its line.
+fn starts_line(text: &str, pos: usize) -> bool {
+    text[line_start(text, pos)..pos].trim().is_empty()
+}
+
+/// Skips spaces and tabs from `pos`.
+fn skip_blanks(text: &str, pos: usize) -> usize {
+    pos + text[pos..].len() - text[pos..].trim_start_matches([' ', '\t']).len()
+}
+
+struct Editor<'a> {
+    text: &'a str,
+    unit: String,
+    newline: &'static str,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl Editor<'_> {
+    /// Serializes `value` pretty-printed, continuing lines at `indent`.
+    fn render(&self, value: &Value, indent: &str) -> String {
+        let mut buffer = Vec::new();
+        let mut serializer =
+            serde_json::Serializer::with_formatter(&mut buffer, PrettyFormatter::with_indent(self.unit.as_bytes()));
+        value.serialize(&mut serializer).expect("serializing a JSON value cannot fail");
+        String::from_utf8(buffer)
+            .expect("serde_json writes UTF-8")
+            .replace('\n', &format!("{}{indent}", self.newline))
+    }
+
+    fn replace(&mut self, node: &Node, value: &Value) {
+        let indent = line_indent(self.text, node.span.start).to_string();
+        let rendered = self.render(value, &indent);
+        self.edits.push((node.span.clone(), rendered));
+    }
+
+    fn diff(&mut self, node: &Node, value: &Value) {
+        if node.to_value() == *value {
+            return;
+        }
+
+        match (&node.kind, value) {
+            (Kind::Object(members), Value::Object(map)) => {
+                let kept: Vec<bool> = members.iter().map(|(key, _, _)| map.contains_key(key)).collect();
+                if !kept.contains(&true) {
+                    return self.replace(node, value);
+                }
+                for (key, _, member) in members.iter().filter(|(key, _, _)| map.contains_key(key)) {
+                    self.diff(member, &map[key]);
+                }
+                let added: Vec<(Option<&String>, &Value)> = map
+                    .iter()
+                    .filter(|(key, _)| !members.iter().any(|(existing, _, _)| existing == *key))
+                    .map(|(key, value)| (Some(key), value))
+                    .collect();
+                let spans: Vec<Range<usize>> = members.iter().map(|(_, start, member)| *start..member.span.end).collect();
+                self.splice_items(&spans, &kept, added);
+            }
+            (Kind::Array(items), Value::Array(values)) if !items.is_empty() => {
+                let old: Vec<Value> = items.iter().map(Node::to_value).collect();
+                let spans: Vec<Range<usize>> = items.iter().map(|item| item.span.clone()).collect();
+                if old.len() == values.len() {
+                    for (item, value) in items.iter().zip(values) {
+                        self.diff(item, value);
+                    }
+                } else if values.starts_with(&old) {
+                    let added = values[old.len()..].iter().map(|value| (None, value)).collect();
+                    self.splice_items(&spans, &vec![true; old.len()], added);
+                } else if let Some(kept) = subsequence(&old, values) {
+                    self.splice_items(&spans, &kept, Vec::new());
+                } else {
+                    self.replace(node, value);
+                }
+            }
+            _ => self.replace(node, value),
+        }
+    }
+
+    /// Removes the items whose `kept` flag is false and appends `added`,
+    /// members with their key, after the last kept item.
+    fn splice_items(&mut self, spans: &[Range<usize>], kept: &[bool], added: Vec<(Option<&String>, &Value)>) {
+        let Some(last_kept) = kept.iter().rposition(|kept| *kept) else {
+            return;
+        };
+        let multiline = starts_line(self.text, spans[last_kept].start);
+        let indent = line_indent(self.text, spans[last_kept].start).to_string();
+        let added: Vec<String> = added
+            .into_iter()
+            .map(|(key, value)| {
+                let key = key.map(|key| format!("{}: ", Value::String(key.clone()))).unwrap_or_default();
+                match multiline {
+                    true => format!("{key}{}", self.render(value, &indent)),
+                    false => format!("{key}{value}"),
+                }
+            })
+            .collect();
+
+        // Runs of removed items, each spliced out in one piece
+        let mut index = 0;
+        while index < spans.len() {
+            if kept[index] {
+                index += 1;
+                continue;
+            }
+            let first = index;
+            while index < spans.len() && !kept[index] {
+                index += 1;
+            }
+            let last = index - 1;
+            let whole_lines = starts_line(self.text, spans[first].start);
+            let start = if whole_lines {
+                line_start(self.text, spans[first].start)
+            } else if first > last_kept {
+                // Inline, after the last kept item: take the blanks before it too
+                self.text[..spans[first].start].trim_end_matches([' ', '\t']).len()
+            } else {
+                spans[first].start
+            };
+            self.edits.push((start..self.item_end(spans[last].end, whole_lines), String::new()));
+        }
+
+        // Where the last kept item's comma is, if it has one
+        let after = skip_blanks(self.text, spans[last_kept].end);
+        let comma = (self.text[after..].starts_with(',')).then_some(after);
+        if last_kept + 1 < spans.len() && added.is_empty() {
+            // The items after it are gone, and so must be its comma
+            if let Some(comma) = comma {
+                self.edits.push((comma..comma + 1, String::new()));
+            }
+        }
+        if added.is_empty() {
+            return;
+        }
+
+        let tail = comma.map_or(after, |comma| skip_blanks(self.text, comma + 1));
+        let eol = line_tail(self.text, tail).filter(|eol| *eol > tail);
+        let has_comment = eol.is_some();
+        let at = eol.unwrap_or_else(|| comma.map_or(spans[last_kept].end, |comma| comma + 1));
+        if comma.is_none() && has_comment {
+            self.edits.push((spans[last_kept].end..spans[last_kept].end, ",".to_string()));
+        }
+        let lead = if comma.is_none() && !has_comment { "," } else { "" };
+        let text = if multiline {
+            let separator = format!(",{}{indent}", self.newline);
+            format!("{lead}{}{indent}{}", self.newline, added.join(&separator))
+        } else {
+            format!("{lead} {}", added.join(", "))
+        };
+        self.edits.push((at..at, text));
+    }
+
+    /// Where the removal of an item ending at `end` stops: after its comma,
+    /// and for an item on lines of its own, after the comment closing its
+    /// last line and the line break.
+    fn item_end(&self, end: usize, whole_lines: bool) -> usize {
+        let mut pos = skip_blanks(self.text, end);
+        if self.text[pos..].starts_with(',') {
+            pos = skip_blanks(self.text, pos + 1);
+        } else if !whole_lines {
+            return end;
+        }
+        if !whole_lines {
+            return pos;
+        }
+        match line_tail(self.text, pos) {
+            Some(eol) if self.text[eol..].starts_with("\r\n") => eol + 2,
+            Some(eol) if eol < self.text.len() => eol + 1,
+            Some(eol) => eol,
+            None => pos,
+        }
+    }
+}
+
+/// If only blanks and comments follow `pos` on its line, where the line ends
+/// (before its line break).
+fn line_tail(text: &str, pos: usize) -> Option<usize> {
+    let mut pos = skip_blanks(text, pos);
+    while let Some(comment) = text[pos..].strip_prefix("/*") {
+        let len = comment.find("*/")?;
+        if comment[..len].contains('\n') {
+            return None;
+        }
+        pos = skip_blanks(text, pos + len + 4);
+    }
+    if text[pos..].starts_with("//") {
+        pos += text[pos..].find('\n').unwrap_or(text.len() - pos);
+    }
+    let pos = if text[..pos].ends_with('\r') { pos - 1 } else { pos };
+    match text[pos..].chars().next() {
+        None | Some('\n' | '\r') => Some(pos),
+        _ => None,
+    }
+}
+
+/// Which items of `old` remain, if `new` is `old` with some items removed.
+fn subsequence(old: &[Value], new: &[Value]) -> Option<Vec<bool>> {
+    let mut kept = vec![false; old.len()];
+    let mut remaining = new.iter().peekable();
+    for (index, value) in old.iter().enumerate() {
+        if remaining.peek() == Some(&value) {
+            kept[index] = true;
+            remaining.next();
+        }
+    }
+    remaining.peek().is_none().then_some(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SETTINGS: &str = r#"{
+  // Shared with the team
+  "permissions": { "allow": ["Bash(git:*)"] },
+  /* hooks below */
+  "hooks": {
+    "PreToolUse": [
+      {
+        "matcher": "Write",
+        "hooks": [{ "type": "command", "command": "prettier --write" }], // formatter
+      },
+      {
+        "matcher": "Bash",
+        "hooks": [
+          { "type": "command", "command": "claude-hook-advisor --hook" } // ours
+        ]
+      }
+    ]
+  }
+}
+"#;
+
+    #[test]
+    fn test_parse_accepts_comments_and_trailing_commas() {
+        let value = parse(SETTINGS).unwrap();
+        assert_eq!(value["hooks"]["PreToolUse"][0]["hooks"][0]["command"], "prettier --write");
+        assert_eq!(value["permissions"]["allow"][0], "Bash(git:*)");
+        assert!(parse("{ \"a\": 1 /* unterminated }").unwrap_err().contains("unterminated comment"));
+        assert!(parse("{\n  \"a\" 1\n}").unwrap_err().starts_with("line 2, column 7"));
+    }
+
+    #[test]
+    fn test_update_keeps_comments() {
+        let mut value = parse(SETTINGS).unwrap();
+        value["hooks"]["PreToolUse"][1]["hooks"][0]["command"] = json!("claude-hook-advisor --hook --replace");
+        value["hooks"]["Stop"] = json!([{ "hooks": [{ "type": "command", "command": "claude-hook-advisor --hook" }] }]);
+
+        let updated = update(SETTINGS, &value).unwrap();
+        assert_eq!(parse(&updated).unwrap(), value);
+        for comment in ["// Shared with the team", "/* hooks below */", "// formatter", "// ours"] {
+            assert!(updated.contains(comment), "{comment} missing from:\n{updated}");
+        }
+        assert!(updated.contains("\"command\": \"claude-hook-advisor --hook --replace\" } // ours"));
+        assert!(updated.contains("    ],\n    \"Stop\": [\n      {\n        \"hooks\": ["));
+    }
+
+    #[test]
+    fn test_update_removes_items_with_their_lines() {
+        let mut value = parse(SETTINGS).unwrap();
+        value["hooks"]["PreToolUse"].as_array_mut().unwrap().pop();
+
+        let updated = update(SETTINGS, &value).unwrap();
+        assert_eq!(parse(&updated).unwrap(), value);
+        assert!(!updated.contains("// ours"));
+        assert!(updated.contains("// formatter\n      }\n    ]"));
+
+        let inline = update("[1, 2, 3]", &json!([1, 3])).unwrap();
+        assert_eq!(inline, "[1, 3]");
+        assert_eq!(update("[1, 2, 3]", &json!([1])).unwrap(), "[1]");
+        assert_eq!(update("{\"a\": 1}", &json!({"a": 1, "b": [true]})).unwrap(), "{\"a\": 1, \"b\": [true]}");
+        assert_eq!(update("{}", &json!({"a": 1})).unwrap(), "{\n  \"a\": 1\n}");
+    }
+}
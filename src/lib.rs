@@ -5,15 +5,74 @@
 
 // Public API - main functions and essential types for external users
 pub use cli::run_cli;
+pub use config::ConfigBuilder;
 pub use directory::resolve_directory;
-pub use types::{DirectoryResolution, Config};
+pub use rules::{check_command_mappings, check_command_mappings_batch};
+pub use types::{DirectoryResolution, DirectoryScope, DirectoryStatus, Config};
 
 // Modules needed by internal binary and tests
 pub mod cli;
 pub mod types;
 
 // Private implementation modules
+mod advice;
+mod allowlist;
+mod approvals;
+#[cfg(feature = "analytics")]
+mod analytics;
+mod assertions;
+mod atomic;
+mod audit;
+mod baseline;
+#[cfg(feature = "bench")]
+mod bench;
+mod build_info;
+mod cache;
+mod checklist;
 mod config;
+mod config_edit;
+mod conflicts;
+mod container;
+mod conventions;
+mod deprecation;
 mod hooks;
+mod iac;
+#[cfg(feature = "analytics")]
+mod infer;
+mod jsonc;
 mod installer;
-mod directory;
\ No newline at end of file
+mod directory;
+mod edits;
+mod limits;
+mod lint;
+mod locations;
+#[cfg(feature = "mcp")]
+mod mcp;
+mod network;
+mod notify;
+mod packages;
+mod permissions;
+mod policy;
+mod project;
+#[cfg(feature = "analytics")]
+mod report;
+mod rules;
+mod sandbox;
+mod schedule;
+mod search;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(test)]
+mod rewrite_properties;
+mod shell;
+mod shell_aliases;
+mod signing;
+mod simulate;
+mod ssh;
+mod state;
+mod subagent;
+mod telemetry;
+mod tool_rules;
+mod ui;
+mod usage;
+mod word_diff;
\ No newline at end of file
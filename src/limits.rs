@@ -0,0 +1,99 @@
+//! Per-session budgets for expensive commands
+//!
+//! The `[limits]` table caps how often a command may run in one session, or
+//! how much time its runs may take together. Once a budget is spent the
+//! command is blocked, which stops an agent that keeps rebuilding in a loop.
+
+use crate::rules::{command_matches, RuleMatch};
+use crate::state::CommandRun;
+use crate::types::{Limit, Result, Severity};
+use std::collections::HashMap;
+
+/// Blocks `command` if the budget of a matching `[limits]` pattern is spent.
+///
+/// Patterns are checked in alphabetical order, so the result does not depend
+/// on how the table was loaded.
+///
+/// # Arguments
+/// * `limits` - The `[limits]` table, pattern → budget
+/// * `history` - Commands that finished in the session, oldest first
+/// * `command` - The command about to run
+///
+/// # Returns
+/// * `Ok(Some(RuleMatch))` - A blocking match for the first exceeded budget
+/// * `Ok(None)` - If no budget is exceeded
+/// * `Err` - If a pattern fails to compile
+pub fn check_limits(limits: &HashMap<String, Limit>, history: &[CommandRun], command: &str) -> Result<Option<RuleMatch>> {
+    let mut patterns: Vec<(&String, &Limit)> = limits.iter().collect();
+    patterns.sort_by_key(|(pattern, _)| *pattern);
+
+    for (pattern, limit) in patterns {
+        if !command_matches(pattern, command)? {
+            continue;
+        }
+
+        let mut runs = 0;
+        let mut total_ms = 0;
+        for run in history {
+            if command_matches(pattern, &run.command)? {
+                runs += 1;
+                total_ms += run.duration_ms.unwrap_or(0);
+            }
+        }
+
+        let spent = if limit.max_per_session.is_some_and(|max| runs >= max) {
+            Some(format!("'{pattern}' already ran {runs} time(s) in this session"))
+        } else if limit.max_total_secs.is_some_and(|max| total_ms >= max * 1000) {
+            Some(format!("'{pattern}' already took {}s in this session", total_ms / 1000))
+        } else {
+            None
+        };
+
+        if let Some(spent) = spent {
+            return Ok(Some(RuleMatch {
+                pattern: pattern.clone(),
+                reason: format!(
+                    "{spent}, which is this project's budget. If it keeps failing, read the last error \
+                     and fix its cause instead of running it again, or ask the user to raise [limits]."
+                ),
+                suggested_command: None,
+                severity: Severity::Block,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SessionState;
+    use crate::types::Config;
+
+    #[test]
+    fn test_budgets() {
+        let config: Config = toml::from_str(
+            r#"
+[limits]
+"docker build" = { max_per_session = 2 }
+"cargo test" = { max_total_secs = 60 }
+"#,
+        )
+        .unwrap();
+        let mut state = SessionState::default();
+        let check = |state: &SessionState, command: &str| check_limits(&config.limits, &state.history, command).unwrap();
+
+        state.record_run("docker build .", Some(1), None);
+        assert!(check(&state, "docker build .").is_none());
+        state.record_run("sudo docker build -t app .", Some(1), None);
+        let blocked = check(&state, "docker build .").unwrap();
+        assert!(blocked.reason.starts_with("'docker build' already ran 2 time(s)"));
+        assert!(check(&state, "docker run app").is_none());
+
+        state.record_run("cargo test", Some(0), Some(45_000));
+        assert!(check(&state, "cargo test").is_none());
+        state.record_run("cargo test", Some(0), Some(20_000));
+        assert!(check(&state, "cargo test --workspace").unwrap().reason.contains("took 65s"));
+    }
+}
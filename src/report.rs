@@ -0,0 +1,493 @@
+//! Activity reports for team retrospectives
+//!
+//! `report` summarizes what agents ran in a project over a period: command
+//! volume, the decisions made, the most blocked commands and how often
+//! suggestions were taken. It also points at rules worth revisiting: rules
+//! that never fired, and blocks that were routinely worked around. The report
+//! is built from the recorded sessions and, when `[audit]` is enabled, the
+//! audit log and the daily counts of its compacted records, and rendered as
+//! Markdown or HTML.
+
+use crate::analytics::{acceptance_by_pattern, AcceptanceStats};
+use crate::audit::{AuditEvent, AuditLog, DailyRollup};
+use crate::rules::{CompiledRules, RuleContext};
+use crate::state::{list_sessions, SessionState};
+use crate::types::{Config, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Most blocked commands listed in a report.
+const TOP_BLOCKED: usize = 10;
+
+/// A block is reported as frequently overridden once this many of its
+/// suggestions were worked around...
+const MIN_OVERRIDES: usize = 2;
+
+/// ...making up at least this percentage of them.
+const OVERRIDE_SHARE: f64 = 50.0;
+
+/// Output format of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+/// A rule pattern and how often it blocked a command.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BlockedCommand {
+    pub pattern: String,
+    pub blocks: usize,
+    /// The most recent command the pattern blocked
+    pub example: String,
+}
+
+/// A rule that deserves a second look.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RuleAdvice {
+    pub pattern: String,
+    pub advice: String,
+}
+
+/// Command activity in a project over a period.
+#[derive(Debug)]
+pub struct ActivityReport {
+    /// Start of the period; `None` covers everything recorded
+    pub since: Option<DateTime<Utc>>,
+    pub until: DateTime<Utc>,
+    /// Sessions with activity in the period
+    pub sessions: usize,
+    pub commands_run: usize,
+    pub commands_failed: usize,
+    /// Decision → count, from the audit log
+    pub decisions: BTreeMap<String, usize>,
+    /// Whether readable audit records were available
+    pub audited: bool,
+    pub top_blocked: Vec<BlockedCommand>,
+    pub acceptance: BTreeMap<String, AcceptanceStats>,
+    pub advice: Vec<RuleAdvice>,
+}
+
+/// Builds the activity report for a project.
+///
+/// # Arguments
+/// * `config` - Configuration whose rules are reviewed
+/// * `project_dir` - Project whose sessions and audit log are read
+/// * `since` - Start of the period, or `None` for everything recorded
+/// * `until` - End of the period
+///
+/// # Returns
+/// * `Ok(ActivityReport)` - The aggregated report
+/// * `Err` - If the rules fail to compile or the audit log can't be read
+pub fn build_report(
+    config: &Config,
+    project_dir: &Path,
+    since: Option<DateTime<Utc>>,
+    until: DateTime<Utc>,
+) -> Result<ActivityReport> {
+    let in_period = |time: DateTime<Utc>| since.is_none_or(|since| time >= since) && time <= until;
+
+    // Keep only what happened in the period
+    let sessions: Vec<SessionState> = list_sessions(project_dir)
+        .into_iter()
+        .map(|session| {
+            let mut state = session.state;
+            state.suggestions.retain(|s| in_period(s.created_at));
+            state.history.retain(|run| in_period(run.finished_at));
+            state
+        })
+        .filter(|state| !state.suggestions.is_empty() || !state.history.is_empty())
+        .collect();
+
+    let (events, rollups): (Vec<AuditEvent>, Vec<DailyRollup>) = match AuditLog::from_config(&config.audit, project_dir)? {
+        Some(log) => (
+            log.events()?.into_iter().filter(|event| in_period(event.timestamp)).collect(),
+            log.rollups()?
+                .into_iter()
+                .filter(|(day, _)| since.is_none_or(|since| *day >= since.date_naive()) && *day <= until.date_naive())
+                .map(|(_, rollup)| rollup)
+                .collect(),
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let mut decisions = BTreeMap::new();
+    for event in &events {
+        *decisions.entry(event.decision.clone()).or_insert(0) += 1;
+    }
+    for (decision, count) in rollups.iter().flat_map(|rollup| &rollup.decisions) {
+        *decisions.entry(decision.clone()).or_insert(0) += count;
+    }
+
+    let history = sessions.iter().flat_map(|s| &s.history);
+    let commands_run = history.clone().count();
+    let commands_failed = history.clone().filter(|run| run.exit_code.is_some_and(|code| code != 0)).count();
+
+    // Without audit records, blocks are known only from their suggestions
+    let audited = !events.is_empty() || !rollups.is_empty();
+    let blocks: Vec<(&str, &str)> = if !audited {
+        sessions.iter().flat_map(|s| &s.suggestions).map(|s| (s.pattern.as_str(), s.original.as_str())).collect()
+    } else {
+        events
+            .iter()
+            .filter(|event| matches!(event.decision.as_str(), "block" | "replace"))
+            .filter_map(|event| Some((event.pattern.as_deref()?, event.command.as_str())))
+            .collect()
+    };
+
+    let acceptance = acceptance_by_pattern(&sessions);
+    let mut advice = never_fired(config, &sessions, &events, &rollups)?
+        .into_iter()
+        .map(|pattern| RuleAdvice { pattern, advice: "never fired; consider removing it".to_string() })
+        .collect::<Vec<_>>();
+    advice.extend(overridden(&sessions));
+
+    Ok(ActivityReport {
+        since,
+        until,
+        sessions: sessions.len(),
+        commands_run,
+        commands_failed,
+        decisions,
+        audited,
+        top_blocked: top_blocked(&blocks, &rollups),
+        acceptance,
+        advice,
+    })
+}
+
+/// Groups blocks by pattern, most frequent first. Compacted blocks add to
+/// the counts; a pattern known only from them has no example command.
+fn top_blocked(blocks: &[(&str, &str)], rollups: &[DailyRollup]) -> Vec<BlockedCommand> {
+    let mut by_pattern: BTreeMap<&str, BlockedCommand> = BTreeMap::new();
+    let rolled_up = rollups.iter().flat_map(|rollup| &rollup.blocked).map(|(pattern, count)| (pattern.as_str(), *count, None));
+    let recent = blocks.iter().map(|(pattern, command)| (*pattern, 1, Some(*command)));
+    for (pattern, count, command) in rolled_up.chain(recent) {
+        let entry = by_pattern.entry(pattern).or_insert_with(|| BlockedCommand {
+            pattern: pattern.to_string(),
+            blocks: 0,
+            example: String::new(),
+        });
+        entry.blocks += count;
+        if let Some(command) = command {
+            entry.example = command.to_string();
+        }
+    }
+
+    let mut top: Vec<BlockedCommand> = by_pattern.into_values().collect();
+    top.sort_by(|a, b| b.blocks.cmp(&a.blocks).then_with(|| a.pattern.cmp(&b.pattern)));
+    top.truncate(TOP_BLOCKED);
+    top
+}
+
+/// Patterns of active rules and mappings that matched nothing in the period:
+/// no audited decision, no suggestion and no command that ran.
+fn never_fired(config: &Config, sessions: &[SessionState], events: &[AuditEvent], rollups: &[DailyRollup]) -> Result<Vec<String>> {
+    let rules = CompiledRules::new(config, &RuleContext::default())?;
+    let mut fired: BTreeSet<String> = events.iter().filter_map(|event| event.pattern.clone()).collect();
+    fired.extend(rollups.iter().flat_map(|rollup| rollup.blocked.keys().cloned()));
+    fired.extend(sessions.iter().flat_map(|s| &s.suggestions).map(|s| s.pattern.clone()));
+    fired.extend(
+        sessions
+            .iter()
+            .flat_map(|s| &s.history)
+            .filter_map(|run| rules.evaluate(&run.command))
+            .map(|rule_match| rule_match.pattern),
+    );
+
+    let patterns: BTreeSet<&String> = config
+        .active_rules()
+        .into_iter()
+        .map(|rule| &rule.pattern)
+        .chain(config.active_commands().into_iter().map(|(pattern, _)| pattern))
+        .collect();
+    Ok(patterns.into_iter().filter(|pattern| !fired.contains(*pattern)).cloned().collect())
+}
+
+/// Blocks whose suggestion was routinely not taken, with the original
+/// command run anyway: let through after the replacement failed, or run
+/// later in the session.
+fn overridden(sessions: &[SessionState]) -> Vec<RuleAdvice> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for session in sessions {
+        for suggestion in &session.suggestions {
+            let entry = counts.entry(&suggestion.pattern).or_default();
+            entry.1 += 1;
+            let ran_anyway = suggestion.retried
+                || (suggestion.accepted_by.is_none()
+                    && session
+                        .history
+                        .iter()
+                        .any(|run| run.command == suggestion.original && run.finished_at >= suggestion.created_at));
+            if ran_anyway {
+                entry.0 += 1;
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter(|(_, (overrides, total))| {
+            *overrides >= MIN_OVERRIDES && *overrides as f64 * 100.0 / *total as f64 >= OVERRIDE_SHARE
+        })
+        .map(|(pattern, (overrides, total))| RuleAdvice {
+            pattern: pattern.to_string(),
+            advice: format!(
+                "was worked around {overrides} of {total} times; consider relaxing it or making it a warning"
+            ),
+        })
+        .collect()
+}
+
+/// A titled part of a report: a table, a list, or a note when it has neither.
+struct Section {
+    heading: &'static str,
+    headers: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+    advice: Vec<(String, String)>,
+    empty: &'static str,
+}
+
+impl ActivityReport {
+    /// Renders the report in `format`.
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+        }
+    }
+
+    fn period(&self) -> String {
+        match self.since {
+            Some(since) => format!("{} – {}", since.format("%Y-%m-%d"), self.until.format("%Y-%m-%d")),
+            None => format!("everything recorded until {}", self.until.format("%Y-%m-%d")),
+        }
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let mut activity = vec![
+            vec!["Sessions".to_string(), self.sessions.to_string()],
+            vec!["Commands run".to_string(), self.commands_run.to_string()],
+            vec!["Commands failed".to_string(), self.commands_failed.to_string()],
+        ];
+        activity.extend(self.decisions.iter().map(|(decision, count)| vec![format!("Decision: {decision}"), count.to_string()]));
+
+        vec![
+            Section {
+                heading: "Activity",
+                headers: &["Metric", "Count"],
+                rows: activity,
+                advice: Vec::new(),
+                empty: "",
+            },
+            Section {
+                heading: "Top blocked commands",
+                headers: &["Pattern", "Blocks", "Latest command"],
+                rows: self
+                    .top_blocked
+                    .iter()
+                    .map(|blocked| vec![blocked.pattern.clone(), blocked.blocks.to_string(), blocked.example.clone()])
+                    .collect(),
+                advice: Vec::new(),
+                empty: "No commands were blocked.",
+            },
+            Section {
+                heading: "Suggestion acceptance",
+                headers: &["Pattern", "Suggested", "Accepted", "Rate", "Success"],
+                rows: self
+                    .acceptance
+                    .iter()
+                    .map(|(pattern, stats)| {
+                        vec![
+                            pattern.clone(),
+                            stats.suggested.to_string(),
+                            stats.accepted.to_string(),
+                            format!("{:.0}%", stats.acceptance_rate()),
+                            format!("{:.0}%", stats.success_rate()),
+                        ]
+                    })
+                    .collect(),
+                advice: Vec::new(),
+                empty: "No suggestions were made.",
+            },
+            Section {
+                heading: "Rule suggestions",
+                headers: &[],
+                rows: Vec::new(),
+                advice: self.advice.iter().map(|a| (a.pattern.clone(), a.advice.clone())).collect(),
+                empty: "Every rule fired and no block was routinely worked around.",
+            },
+        ]
+    }
+
+    fn audit_note(&self) -> Option<&'static str> {
+        (!self.audited).then_some(
+            "Enable [audit] (unencrypted) to include allowed and warned commands and blocks without a suggestion.",
+        )
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Claude Hook Advisor report\n\nPeriod: {}", self.period());
+        if let Some(note) = self.audit_note() {
+            let _ = writeln!(out, "\n_{note}_");
+        }
+
+        let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+        for section in self.sections() {
+            let _ = writeln!(out, "\n## {}\n", section.heading);
+            if !section.rows.is_empty() {
+                let _ = writeln!(out, "| {} |", section.headers.join(" | "));
+                let _ = writeln!(out, "|{}", " --- |".repeat(section.headers.len()));
+                for row in &section.rows {
+                    let cells: Vec<String> = row.iter().map(|text| cell(text)).collect();
+                    let _ = writeln!(out, "| {} |", cells.join(" | "));
+                }
+            } else if !section.advice.is_empty() {
+                for (pattern, advice) in &section.advice {
+                    let _ = writeln!(out, "- `{pattern}` {advice}");
+                }
+            } else {
+                let _ = writeln!(out, "{}", section.empty);
+            }
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Claude Hook Advisor report</title>\n</head>\n<body>"
+        );
+        let _ = writeln!(out, "<h1>Claude Hook Advisor report</h1>\n<p>Period: {}</p>", escape_html(&self.period()));
+        if let Some(note) = self.audit_note() {
+            let _ = writeln!(out, "<p><em>{}</em></p>", escape_html(note));
+        }
+
+        for section in self.sections() {
+            let _ = writeln!(out, "<h2>{}</h2>", section.heading);
+            if !section.rows.is_empty() {
+                let headers: String = section.headers.iter().map(|h| format!("<th>{h}</th>")).collect();
+                let _ = writeln!(out, "<table>\n<tr>{headers}</tr>");
+                for row in &section.rows {
+                    let cells: String = row.iter().map(|text| format!("<td>{}</td>", escape_html(text))).collect();
+                    let _ = writeln!(out, "<tr>{cells}</tr>");
+                }
+                let _ = writeln!(out, "</table>");
+            } else if !section.advice.is_empty() {
+                let _ = writeln!(out, "<ul>");
+                for (pattern, advice) in &section.advice {
+                    let _ = writeln!(out, "<li><code>{}</code> {}</li>", escape_html(pattern), escape_html(advice));
+                }
+                let _ = writeln!(out, "</ul>");
+            } else {
+                let _ = writeln!(out, "<p>{}</p>", section.empty);
+            }
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{CommandRun, Suggestion};
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn suggestion(pattern: &str, original: &str, created_at: DateTime<Utc>, accepted: bool) -> Suggestion {
+        Suggestion {
+            original: original.to_string(),
+            suggested: "bun install".to_string(),
+            pattern: pattern.to_string(),
+            created_at,
+            failed: false,
+            tool_use_id: None,
+            accepted_by: accepted.then(|| "toolu_2".to_string()),
+            exit_code: accepted.then_some(0),
+            retried: false,
+            sample_rate: None,
+        }
+    }
+
+    fn run(command: &str, exit_code: i32, finished_at: DateTime<Utc>) -> CommandRun {
+        CommandRun { command: command.to_string(), exit_code: Some(exit_code), duration_ms: None, finished_at }
+    }
+
+    #[test]
+    fn test_weekly_report() {
+        let project = TempDir::new().unwrap();
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+yarn = "bun"
+
+[[rules]]
+pattern = "git push --force"
+
+[[rules]]
+pattern = "cargo publish"
+severity = "warn"
+"#,
+        )
+        .unwrap();
+
+        let now = Utc::now();
+        let recent = now - Duration::days(2);
+        let old = now - Duration::days(30);
+        let state = SessionState {
+            suggestions: vec![
+                suggestion("npm", "npm install", recent, true),
+                suggestion("yarn", "yarn add x", recent, false),
+                suggestion("yarn", "yarn add y", recent, false),
+                suggestion("npm", "npm test", old, false),
+            ],
+            history: vec![
+                run("bun install", 0, recent),
+                run("yarn add x", 0, recent + Duration::minutes(1)),
+                run("yarn add y", 1, recent + Duration::minutes(2)),
+                run("cargo publish", 0, old),
+            ],
+            ..SessionState::default()
+        };
+        state.save(project.path(), "session-1").unwrap();
+
+        let report = build_report(&config, project.path(), Some(now - Duration::days(7)), now).unwrap();
+        assert_eq!(report.sessions, 1);
+        assert_eq!(report.commands_run, 3);
+        assert_eq!(report.commands_failed, 1);
+        assert!(!report.audited);
+        assert_eq!(report.top_blocked[0], BlockedCommand { pattern: "yarn".to_string(), blocks: 2, example: "yarn add y".to_string() });
+        assert_eq!(report.acceptance["npm"].suggested, 1);
+
+        let advised: Vec<(&str, &str)> = report.advice.iter().map(|a| (a.pattern.as_str(), a.advice.as_str())).collect();
+        assert_eq!(
+            advised,
+            vec![
+                ("cargo publish", "never fired; consider removing it"),
+                ("git push --force", "never fired; consider removing it"),
+                ("yarn", "was worked around 2 of 2 times; consider relaxing it or making it a warning"),
+            ]
+        );
+
+        let markdown = report.render(ReportFormat::Markdown);
+        assert!(markdown.contains("## Top blocked commands\n\n| Pattern | Blocks | Latest command |\n| --- | --- | --- |\n| yarn | 2 | yarn add y |"));
+        assert!(markdown.contains("- `git push --force` never fired"));
+        let html = report.render(ReportFormat::Html);
+        assert!(html.contains("<tr><td>yarn</td><td>2</td><td>yarn add y</td></tr>"));
+
+        // The whole history includes the old session activity
+        let all = build_report(&config, project.path(), None, now).unwrap();
+        assert_eq!(all.commands_run, 4);
+        assert!(!all.advice.iter().any(|a| a.pattern == "cargo publish"));
+    }
+}
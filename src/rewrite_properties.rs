@@ -0,0 +1,152 @@
+//! Property tests for the command rewrite contract
+//!
+//! Replace mode hands Claude a rewritten command to run, so rewriting has to
+//! be safe on any input, not just the commands in the unit tests. For
+//! arbitrary commands and mapping sets:
+//!
+//! - segments that are not rewritten are left untouched, and a rewritten
+//!   segment keeps every argument after the replaced pattern
+//! - a command with balanced quotes is never rewritten into one without
+//! - rewriting is idempotent: the suggested command suggests nothing further
+//! - no input, however malformed, makes evaluation panic
+
+use crate::rules::{CompiledRules, RuleContext};
+use crate::shell::split_segments;
+use crate::types::Config;
+use proptest::prelude::*;
+
+const WORDS: &[&str] = &["npm", "yarn", "bun", "pnpm", "install", "ci", "run", "build", "test", "cargo", "--watch", "-v"];
+const WRAPPERS: &[&str] = &["sudo", "env FOO=1", "nice -n 5", "xargs"];
+const SEPARATORS: &[&str] = &["; ", " && ", " || ", " | ", "\n"];
+
+fn word() -> impl Strategy<Value = String> {
+    prop::sample::select(WORDS).prop_map(str::to_string)
+}
+
+/// A plain word, or a quoted argument that may hide separators.
+fn argument() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => word(),
+        1 => "[a-z ;|&$()]{0,8}".prop_map(|text| format!("\"{text}\"")),
+        1 => "[a-z ;|&\"]{0,8}".prop_map(|text| format!("'{text}'")),
+    ]
+}
+
+fn simple_command() -> impl Strategy<Value = String> {
+    (
+        prop::option::of(prop::sample::select(WRAPPERS)),
+        word(),
+        prop::collection::vec(argument(), 0..4),
+    )
+        .prop_map(|(wrapper, name, args)| {
+            let mut parts: Vec<String> = wrapper.map(str::to_string).into_iter().collect();
+            parts.push(name);
+            parts.extend(args);
+            parts.join(" ")
+        })
+}
+
+fn command_line() -> impl Strategy<Value = String> {
+    (simple_command(), prop::collection::vec((prop::sample::select(SEPARATORS), simple_command()), 0..3)).prop_map(
+        |(first, rest)| {
+            rest.into_iter().fold(first, |line, (separator, command)| format!("{line}{separator}{command}"))
+        },
+    )
+}
+
+/// A `[commands]` mapping between vocabulary words; replacements start
+/// with a command name, as a replacement starting with an option is not a
+/// command.
+fn mapping() -> impl Strategy<Value = (String, String)> {
+    let name = word().prop_filter("command name", |word| !word.starts_with('-'));
+    (
+        prop::collection::vec(word(), 1..3),
+        name,
+        prop::collection::vec(prop_oneof![4 => word(), 1 => Just("'--flag=a b'".to_string())], 0..3),
+    )
+        .prop_map(|(pattern, name, args)| {
+            let replacement = std::iter::once(name).chain(args).collect::<Vec<_>>().join(" ");
+            (pattern.join(" "), replacement)
+        })
+}
+
+fn compile(mappings: &[(String, String)]) -> CompiledRules {
+    let config = Config { commands: mappings.iter().cloned().collect(), ..Config::default() };
+    CompiledRules::new(&config, &RuleContext::default()).unwrap()
+}
+
+fn suggestion(rules: &CompiledRules, command: &str) -> Option<String> {
+    rules.evaluate(command).and_then(|m| m.suggested_command)
+}
+
+/// Whether every quote in `command` is closed, following shell quoting rules.
+fn quotes_balanced(command: &str) -> bool {
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, _) => {}
+        }
+    }
+    quote.is_none()
+}
+
+fn segments(command: &str) -> Vec<&str> {
+    split_segments(command).into_iter().map(|range| &command[range]).collect()
+}
+
+proptest! {
+    #[test]
+    fn rewriting_preserves_arguments_outside_the_match(command in command_line(), (pattern, replacement) in mapping()) {
+        let rules = compile(&[(pattern.clone(), replacement.clone())]);
+        if let Some(rewritten) = suggestion(&rules, &command) {
+            let (before, after) = (segments(&command), segments(&rewritten));
+            prop_assert_eq!(before.len(), after.len());
+            for (original, new) in before.into_iter().zip(after) {
+                if original == new {
+                    continue;
+                }
+                prop_assert_eq!(original.replacen(&pattern, &replacement, 1), new);
+                prop_assert_eq!(
+                    new.split_whitespace().count(),
+                    original.split_whitespace().count() - pattern.split_whitespace().count()
+                        + replacement.split_whitespace().count()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rewriting_never_unbalances_quotes(command in command_line(), mappings in prop::collection::vec(mapping(), 1..4)) {
+        prop_assume!(quotes_balanced(&command));
+        let rules = compile(&mappings);
+        if let Some(rewritten) = suggestion(&rules, &command) {
+            prop_assert!(quotes_balanced(&rewritten), "{} became {}", command, rewritten);
+        }
+    }
+
+    #[test]
+    fn rewriting_is_idempotent(command in command_line(), mappings in prop::collection::vec(mapping(), 1..5)) {
+        let rules = compile(&mappings);
+        if let Some(rewritten) = suggestion(&rules, &command) {
+            prop_assert_eq!(suggestion(&rules, &rewritten), None, "{} became {}", command, rewritten);
+        }
+    }
+
+    #[test]
+    fn arbitrary_input_never_panics(command in "\\PC{0,60}", mappings in prop::collection::vec(mapping(), 0..3)) {
+        let rules = compile(&mappings);
+        let rewritten = suggestion(&rules, &command);
+        if quotes_balanced(&command) {
+            prop_assert!(rewritten.is_none_or(|rewritten| quotes_balanced(&rewritten)));
+        }
+    }
+}
@@ -0,0 +1,1720 @@
+//! Rule evaluation: matching commands against mappings and conditional rules
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::shell::{self, normalize, split_segments};
+use crate::schedule::{self, Schedule};
+use crate::state::CommandRun;
+use crate::types::{AdvisorError, Config, ForbiddenFlagAction, ForbiddenFlags, HookInput, Requirement, Result, RuleCondition, RuleSource, Severity};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Rule count from which evaluation is spread across threads.
+///
+/// Below this, thread coordination costs more than matching the rules inline.
+pub const PARALLEL_THRESHOLD: usize = 512;
+
+/// Cache for compiled regex patterns to avoid recompilation
+static REGEX_CACHE: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Hook context that rule conditions are evaluated against.
+#[derive(Debug, Clone, Default)]
+pub struct RuleContext {
+    pub permission_mode: Option<String>,
+    pub in_nix_shell: bool,
+    pub in_devcontainer: bool,
+    /// The current kubectl context, when a rule needs it (see `with_kube_context`)
+    pub kube_context: Option<String>,
+    /// Whether the working tree has uncommitted changes, when a rule needs it
+    /// (see `with_git_status`); None if unknown
+    pub git_dirty: Option<bool>,
+    /// The local date and time `cron` conditions are checked against (see
+    /// `with_clock`); None if unknown, where they don't hold
+    pub local_time: Option<NaiveDateTime>,
+    /// Commands that finished in the session; None outside a session, where
+    /// rules with `requires` do not apply
+    pub history: Option<Vec<CommandRun>>,
+    /// Rule and mapping patterns that are snoozed and do not apply
+    pub snoozed: Vec<String>,
+}
+
+impl RuleContext {
+    /// Builds the evaluation context from the fields of a hook event and the
+    /// environment Claude Code (and so the hook) runs in.
+    pub fn from_hook_input(hook_input: &HookInput) -> Self {
+        let env_set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        Self {
+            permission_mode: hook_input.permission_mode.as_deref().map(str::to_string),
+            in_nix_shell: env_set("IN_NIX_SHELL"),
+            in_devcontainer: ["REMOTE_CONTAINERS", "CODESPACES", "DEVCONTAINER"]
+                .iter()
+                .any(|name| env_set(name)),
+            kube_context: None,
+            git_dirty: None,
+            local_time: None,
+            history: None,
+            snoozed: Vec::new(),
+        }
+    }
+
+    /// Adds the current kubectl context when a rule has a `kube_context`
+    /// condition and `command` runs kubectl.
+    ///
+    /// Asking kubectl takes tens of milliseconds, so other commands skip it.
+    pub fn with_kube_context(mut self, config: &Config, command: &str) -> Self {
+        if needs_kube_context(config, command) {
+            self.kube_context = current_kube_context();
+        }
+        self
+    }
+
+    /// Adds whether the working tree in `dir` is dirty when a rule with a
+    /// `git_dirty` condition matches `command`.
+    ///
+    /// `git status` can take a while in a large repository, so commands no
+    /// such rule is about skip it.
+    pub fn with_git_status(mut self, config: &Config, command: &str, dir: &Path) -> Self {
+        let needed = config.active_rules().into_iter().any(|rule| {
+            rule.when.as_ref().is_some_and(|when| when.git_dirty.is_some()) && command_matches(&rule.pattern, command).unwrap_or(false)
+        });
+        if needed {
+            self.git_dirty = working_tree_dirty(dir);
+        }
+        self
+    }
+
+    /// Sets the local time to `now` in the `[time] timezone`; an unknown time
+    /// zone leaves it unknown.
+    pub fn with_clock(mut self, config: &Config, now: DateTime<Utc>) -> Self {
+        self.local_time = schedule::local_time(config.time.timezone.as_deref(), now).ok();
+        self
+    }
+
+    /// Whether tool calls are auto-approved or reviewed by the user.
+    pub fn source(&self) -> RuleSource {
+        match self.permission_mode.as_deref() {
+            Some("acceptEdits") | Some("bypassPermissions") => RuleSource::Agent,
+            _ => RuleSource::User,
+        }
+    }
+
+    /// Returns true if every condition set in `when` holds in this context.
+    pub fn satisfies(&self, when: &RuleCondition) -> bool {
+        let permission_mode_ok = when
+            .permission_mode
+            .as_ref()
+            .is_none_or(|mode| self.permission_mode.as_ref() == Some(mode));
+        let source_ok = when.source.is_none_or(|source| self.source() == source);
+        let nix_ok = when.in_nix_shell.is_none_or(|inside| self.in_nix_shell == inside);
+        let devcontainer_ok = when.in_devcontainer.is_none_or(|inside| self.in_devcontainer == inside);
+        // An unknown context never matches, so production rules fail closed only
+        // where a context is known to be production
+        let kube_context_ok = when.kube_context.as_ref().is_none_or(|pattern| {
+            self.kube_context
+                .as_ref()
+                .is_some_and(|context| get_cached_regex(pattern).is_ok_and(|regex| regex.is_match(context)))
+        });
+
+        // Outside a repository, or if git fails, neither `true` nor `false` holds
+        let git_dirty_ok = when.git_dirty.is_none_or(|dirty| self.git_dirty == Some(dirty));
+        let cron_ok = when.cron.as_ref().is_none_or(|spec| {
+            self.local_time
+                .is_some_and(|time| Schedule::parse(spec).is_ok_and(|schedule| schedule.contains(time)))
+        });
+
+        permission_mode_ok && source_ok && nix_ok && devcontainer_ok && kube_context_ok && git_dirty_ok && cron_ok
+    }
+}
+
+impl RuleContext {
+    /// Whether the prerequisite succeeded within the window it names.
+    ///
+    /// Without a session history nothing can be checked, so it counts as met.
+    pub fn requirement_met(&self, requirement: &Requirement) -> bool {
+        let Some(history) = &self.history else {
+            return true;
+        };
+        let window = requirement.within_commands.unwrap_or(history.len());
+        history.iter().rev().take(window).any(|run| {
+            run.succeeded() && command_matches(&requirement.before, &run.command).unwrap_or(false)
+        })
+    }
+}
+
+/// Whether `command` runs kubectl and an active rule, top-level or in an
+/// enabled group, has a `kube_context` condition.
+fn needs_kube_context(config: &Config, command: &str) -> bool {
+    config
+        .active_rules()
+        .into_iter()
+        .any(|rule| rule.when.as_ref().is_some_and(|when| when.kube_context.is_some()))
+        && command_matches("kubectl", command).unwrap_or(false)
+}
+
+/// Runs `kubectl config current-context`; None if kubectl is missing or has no context.
+fn current_kube_context() -> Option<String> {
+    let output = Command::new("kubectl").args(["config", "current-context"]).output();
+    output
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|context| !context.is_empty())
+}
+
+/// Runs `git status --porcelain` in `dir`; None outside a repository or if git is missing.
+fn working_tree_dirty(dir: &Path) -> Option<bool> {
+    let output = Command::new("git").args(["status", "--porcelain"]).current_dir(dir).output();
+    output
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| !output.stdout.iter().all(u8::is_ascii_whitespace))
+}
+
+/// A rule or mapping that matched a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    /// The pattern that matched
+    pub pattern: String,
+    /// Human-readable reason shown to Claude
+    pub reason: String,
+    /// The fully rewritten command, for rules that suggest a replacement
+    pub suggested_command: Option<String>,
+    /// Whether the command is blocked or only warned about
+    pub severity: Severity,
+}
+
+/// Gets or creates a cached regex for the given pattern
+fn get_cached_regex(pattern: &str) -> Result<Regex> {
+    let mut cache = REGEX_CACHE.lock()
+        .expect("regex cache mutex should not be poisoned");
+    
+    if let Some(regex) = cache.get(pattern) {
+        return Ok(regex.clone());
+    }
+    
+    let regex = Regex::new(pattern)?;
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+/// Checks if a command matches any configured mappings and generates suggestions.
+///
+/// The command line is split into simple commands and each one is normalized by
+/// stripping wrappers such as `sudo`, `env FOO=1`, `nice` or `xargs`, so that
+/// `sudo npm install` is matched against the `npm` mapping. Patterns must match at
+/// the start of the underlying command on a word boundary (e.g., "npm" matches
+/// "npm install" but not "npm-check"). Wrappers are preserved in the rewrite.
+/// Returns the first matching pattern that suggests a replacement, evaluated
+/// with a default (condition-free) context. Uses cached regex compilation for
+/// better performance.
+///
+/// # Arguments
+/// * `config` - Configuration containing command mappings
+/// * `command` - The bash command to check against mappings
+///
+/// # Returns
+/// * `Ok(Some((suggestion, replacement)))` - If a mapping is found
+/// * `Ok(None)` - If no mappings match the command
+/// * `Err` - If regex compilation fails
+pub fn check_command_mappings(config: &Config, command: &str) -> Result<Option<(String, String)>> {
+    Ok(check_command_mappings_batch(config, &[command])?.remove(0))
+}
+
+/// Checks many commands against the configured mappings in one pass.
+///
+/// The rule set is compiled once and reused for every command, which makes this
+/// suitable for linting scripts or CI files against the same policy as the agent.
+///
+/// # Arguments
+/// * `config` - Configuration containing command mappings
+/// * `commands` - The bash commands to check
+///
+/// # Returns
+/// * `Ok(Vec<Option<(suggestion, replacement)>>)` - One result per input command, in order
+/// * `Err` - If regex compilation fails
+pub fn check_command_mappings_batch(config: &Config, commands: &[&str]) -> Result<Vec<Option<(String, String)>>> {
+    let compiled = CompiledRules::new(config, &RuleContext::default())?;
+    Ok(commands
+        .iter()
+        .map(|command| {
+            compiled
+                .evaluate(command)
+                .and_then(|m| m.suggested_command.map(|suggested| (m.reason, suggested)))
+        })
+        .collect())
+}
+
+/// Whether `pattern` matches any simple command in `command`, using the same
+/// command-position matching as mappings.
+pub fn command_matches(pattern: &str, command: &str) -> Result<bool> {
+    let regex = command_regex(pattern)?;
+    Ok(underlying_commands(command, false)
+        .into_iter()
+        .any(|range| regex.is_match(&command[range])))
+}
+
+/// Compiles `pattern` to match at the start of a simple command.
+///
+/// The pattern ends at a word boundary, and a space in it matches any run of
+/// blanks and line continuations, so `npm install` also matches a command
+/// Claude split as `npm \` + newline + `  install`.
+fn command_regex(pattern: &str) -> Result<Regex> {
+    let words: Vec<String> = pattern.split(' ').filter(|word| !word.is_empty()).map(regex::escape).collect();
+    get_cached_regex(&format!(r"^{}\b", words.join(r"(?:[ \t]|\\\r?\n)+")))
+}
+
+/// Evaluates a command against `[[rules]]` and `[commands]` mappings.
+///
+/// When several patterns match, the winner is chosen by `priority`, then by
+/// pattern length (see `CompiledRules::new`).
+///
+/// Rules whose `when` conditions do not hold in `context` are skipped.
+///
+/// # Arguments
+/// * `config` - Configuration containing command mappings and rules
+/// * `command` - The bash command to evaluate
+/// * `context` - Hook context used to evaluate rule conditions
+///
+/// # Returns
+/// * `Ok(Some(RuleMatch))` - The first matching rule or mapping
+/// * `Ok(None)` - If nothing matches the command
+/// * `Err` - If regex compilation fails
+pub fn evaluate_command(config: &Config, command: &str, context: &RuleContext) -> Result<Option<RuleMatch>> {
+    Ok(CompiledRules::new(config, context)?.evaluate_with_alternatives(command, config.output.max_alternatives))
+}
+
+/// Rules and mappings that apply in one context, compiled for repeated evaluation.
+pub struct CompiledRules {
+    entries: Vec<CompiledRule>,
+    /// Whether commands inside substitutions are matched too
+    substitutions: bool,
+    /// Most mappings a suggestion is passed on through after the first (`[matching] max_chain_depth`)
+    max_chain_depth: usize,
+}
+
+struct CompiledRule {
+    pattern: String,
+    regex: Regex,
+    replacement: Option<String>,
+    message: Option<String>,
+    severity: Severity,
+    priority: i32,
+    specificity: Specificity,
+    /// Arguments a mapping like `cargo build` → `cargo build --workspace` adds;
+    /// commands that already have all of them are left alone
+    added_args: Vec<String>,
+    /// Flags an `[inject_flags]` entry makes sure the command has; only the
+    /// missing ones are added, right after the pattern
+    injected_flags: Vec<String>,
+    /// Flags a `[forbidden_flags]` entry objects to; commands without any are left alone
+    forbidden_flags: Vec<String>,
+    /// Link explaining why the rule exists (`doc`)
+    doc: Option<String>,
+    /// Regex the matched command must contain (`matches`)
+    required: Option<Regex>,
+    /// Regex that exempts the matched command (`unless`)
+    exempt: Option<Regex>,
+}
+
+/// How specific a rule is; more specific rules win over general ones without
+/// needing an explicit `priority`.
+///
+/// Compared field by field: a pattern with more words (`npm run build` over
+/// `npm`) wins first, then a rule with more `when` conditions, then the
+/// longer pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Specificity {
+    pub words: usize,
+    pub conditions: usize,
+    pub length: usize,
+}
+
+impl Specificity {
+    /// Scores a pattern and the number of conditions attached to it.
+    pub fn new(pattern: &str, conditions: usize) -> Self {
+        Self {
+            words: pattern.split_whitespace().count(),
+            conditions,
+            length: pattern.len(),
+        }
+    }
+}
+
+impl CompiledRules {
+    /// Compiles the rules whose conditions hold in `context` together with the
+    /// `[commands]` mappings, in evaluation order.
+    ///
+    /// The order is deterministic: higher `priority` first, then more specific
+    /// rules (see `Specificity`), then `[[rules]]` in file order before
+    /// `[commands]` mappings in alphabetical order.
+    pub fn new(config: &Config, context: &RuleContext) -> Result<Self> {
+        // Report invalid context patterns even when no context is known
+        let rules = config.active_rules();
+        for pattern in rules.iter().filter_map(|rule| rule.when.as_ref()?.kube_context.as_ref()) {
+            get_cached_regex(pattern)?;
+        }
+        for spec in rules.iter().filter_map(|rule| rule.when.as_ref()?.cron.as_ref()) {
+            Schedule::parse(spec)?;
+        }
+        if let Some(timezone) = config.time.timezone.as_deref() {
+            schedule::local_time(Some(timezone), Utc::now())?;
+        }
+        if let Some(rule) = rules.iter().find(|rule| rule.sample_rate.is_some_and(|rate| !(rate > 0.0 && rate <= 1.0))) {
+            return Err(AdvisorError::Rule(format!(
+                "Rule '{}' has sample_rate = {}; it must be more than 0 and at most 1",
+                rule.pattern,
+                rule.sample_rate.unwrap_or_default()
+            )));
+        }
+
+        let now = Utc::now();
+        let rules = rules
+            .into_iter()
+            .filter(|rule| rule.is_active_at(now))
+            .filter(|rule| !context.snoozed.contains(&rule.pattern))
+            .filter(|rule| rule.when.as_ref().is_none_or(|when| context.satisfies(when)))
+            .filter(|rule| rule.requires.as_ref().is_none_or(|requirement| !context.requirement_met(requirement)))
+            .map(|rule| {
+                let conditions = rule.when.as_ref().map_or(0, RuleCondition::count)
+                    + usize::from(rule.matches.is_some())
+                    + usize::from(rule.unless.is_some())
+                    + usize::from(rule.requires.is_some());
+                let message = rule.message.clone().or_else(|| {
+                    rule.requires.as_ref().map(|requirement| requirement_message(&rule.pattern, requirement))
+                });
+                CompiledRule::new(
+                    &rule.pattern,
+                    rule.replacement.as_ref(),
+                    message.as_ref(),
+                    rule.severity.unwrap_or_default(),
+                    rule.priority.unwrap_or_default(),
+                    conditions,
+                )?
+                .with_filters(rule.matches.as_deref(), rule.unless.as_deref())
+                .map(|entry| CompiledRule { doc: rule.doc.clone(), ..entry })
+            });
+
+        // HashMap iteration order is random, so fix an order before sorting;
+        // the sort is stable, so a top-level mapping stays ahead of a group's
+        let mut mappings = config.active_commands();
+        mappings.retain(|(pattern, _)| !context.snoozed.contains(pattern));
+        mappings.sort_by_key(|(pattern, _)| *pattern);
+        let mappings = mappings
+            .into_iter()
+            .map(|(pattern, replacement)| CompiledRule::new(pattern, Some(replacement), None, Severity::Block, 0, 0));
+
+        let mut injections: Vec<(&String, &Vec<String>)> = config.inject_flags.iter().filter(|(_, flags)| !flags.is_empty()).collect();
+        injections.retain(|(pattern, _)| !context.snoozed.contains(pattern));
+        injections.sort_by_key(|(pattern, _)| *pattern);
+        let injections = injections.into_iter().map(|(pattern, flags)| CompiledRule::injecting(pattern, flags));
+
+        let mut forbidden: Vec<(&String, &ForbiddenFlags)> =
+            config.forbidden_flags.iter().filter(|(_, forbidden)| !forbidden.flags.is_empty()).collect();
+        forbidden.retain(|(pattern, _)| !context.snoozed.contains(pattern));
+        forbidden.sort_by_key(|(pattern, _)| *pattern);
+        let forbidden = forbidden.into_iter().map(|(pattern, forbidden)| CompiledRule::forbidding(pattern, forbidden));
+
+        let mut entries = rules.chain(mappings).chain(injections).chain(forbidden).collect::<Result<Vec<_>>>()?;
+        // Stable sort keeps file order among rules of equal priority and specificity
+        entries.sort_by_key(|entry| (Reverse(entry.priority), Reverse(entry.specificity)));
+
+        Ok(Self {
+            entries,
+            substitutions: config.matching.check_substitutions,
+            max_chain_depth: config.matching.max_chain_depth,
+        })
+    }
+
+    /// Returns the first rule or mapping matching `command`.
+    ///
+    /// Large rule sets are evaluated in parallel; the result is still the first
+    /// match in evaluation order, and later rules stop being checked once an
+    /// earlier one has matched.
+    ///
+    /// A suggested command is itself re-evaluated, so chained mappings such as
+    /// `yarn` → `npm` → `bun` suggest the final command directly. The
+    /// suggestion is therefore a fixed point: evaluating it never suggests
+    /// another rewrite. Mappings that would rewrite forever (`a` → `b` → `a`,
+    /// or `npm` → `sudo npm`), or through more than `[matching]
+    /// max_chain_depth` further mappings, suggest nothing. With a depth of 0
+    /// the first rewrite is suggested as it is.
+    pub fn evaluate(&self, command: &str) -> Option<RuleMatch> {
+        let (entry, suggested, via) = self.resolve(command)?;
+        Some(entry.describe(suggested, &via, &[]))
+    }
+
+    /// The entry `evaluate` applies to `command`, its final rewrite, and the
+    /// replacements of the further mappings the rewrite went through.
+    fn resolve<'a>(&'a self, command: &str) -> Option<(&'a CompiledRule, Option<String>, Vec<String>)> {
+        let (entry, matches) = self.first_match(command)?;
+        let mut suggested = entry.rewrite(command, &matches);
+        let mut via: Vec<String> = Vec::new();
+        if self.max_chain_depth == 0 {
+            return Some((entry, suggested, via));
+        }
+
+        // The original command is only borrowed; each rewrite already owns its string
+        let mut seen: Vec<Cow<str>> = vec![Cow::Borrowed(command)];
+        while let Some(current) = suggested.take() {
+            let next = self.first_match(&current).and_then(|(next_entry, next_matches)| {
+                let rewritten = next_entry.rewrite(&current, &next_matches)?;
+                Some((next_entry, rewritten))
+            });
+            match next {
+                None => {
+                    suggested = Some(current);
+                    break;
+                }
+                Some((_, next)) if next == current || seen.iter().any(|earlier| *earlier == next) || via.len() >= self.max_chain_depth => {
+                    break
+                }
+                Some((next_entry, next)) => {
+                    seen.push(Cow::Owned(current));
+                    via.extend(next_entry.replacement.clone());
+                    suggested = Some(next);
+                }
+            }
+        }
+
+        Some((entry, suggested, via))
+    }
+
+    /// Like `evaluate`, but a blocking suggestion's reason also lists up to
+    /// `max` other commands that later matching rules suggest, so Claude can
+    /// pick one that suits the task. Suggestions the rules would block
+    /// themselves are left out.
+    pub fn evaluate_with_alternatives(&self, command: &str, max: usize) -> Option<RuleMatch> {
+        let (entry, suggested, via) = self.resolve(command)?;
+        let Some(preferred) = suggested.as_deref() else {
+            return Some(entry.describe(suggested, &via, &[]));
+        };
+        if max == 0 || entry.severity != Severity::Block {
+            return Some(entry.describe(suggested, &via, &[]));
+        }
+
+        let mut alternatives: Vec<String> = Vec::new();
+        for suggestion in self.explain(command).into_iter().skip(1).filter_map(|shadowed| shadowed.suggested_command) {
+            if alternatives.len() == max {
+                break;
+            }
+            let acceptable = self.evaluate(&suggestion).is_none_or(|m| m.severity != Severity::Block);
+            if acceptable && suggestion != preferred && suggestion != command && !alternatives.contains(&suggestion) {
+                alternatives.push(suggestion);
+            }
+        }
+        Some(entry.describe(suggested, &via, &alternatives))
+    }
+
+    /// Finds the first entry in evaluation order that matches `command`.
+    fn first_match<'a>(&'a self, command: &str) -> Option<(&'a CompiledRule, Vec<Range<usize>>)> {
+        let commands = underlying_commands(command, self.substitutions);
+        let try_entry = |entry: &'a CompiledRule| {
+            let matches = entry.find_matches(command, &commands);
+            (!matches.is_empty()).then_some((entry, matches))
+        };
+
+        if self.is_parallel() {
+            self.entries.par_iter().find_map_first(try_entry)
+        } else {
+            self.entries.iter().find_map(try_entry)
+        }
+    }
+
+    /// Number of compiled rules and mappings.
+    #[cfg_attr(not(feature = "bench"), allow(dead_code))]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether `evaluate` spreads the rules across threads.
+    pub fn is_parallel(&self) -> bool {
+        self.entries.len() >= PARALLEL_THRESHOLD
+    }
+
+    /// Returns every rule or mapping matching `command`, in evaluation order.
+    ///
+    /// The first entry is the one `evaluate` applies; the rest are shadowed.
+    pub fn explain(&self, command: &str) -> Vec<RuleMatch> {
+        let commands = underlying_commands(command, self.substitutions);
+
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let matches = entry.find_matches(command, &commands);
+                (!matches.is_empty()).then(|| entry.to_match(command, &matches))
+            })
+            .collect()
+    }
+}
+
+impl CompiledRule {
+    fn new(
+        pattern: &str,
+        replacement: Option<&String>,
+        message: Option<&String>,
+        severity: Severity,
+        priority: i32,
+        conditions: usize,
+    ) -> Result<Self> {
+        // Match the underlying command at word boundaries
+        let regex = command_regex(pattern)?;
+        let added_args = replacement
+            .and_then(|r| r.strip_prefix(pattern))
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(|rest| rest.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+            replacement: replacement.cloned(),
+            message: message.cloned(),
+            severity,
+            priority,
+            specificity: Specificity::new(pattern, conditions),
+            added_args,
+            injected_flags: Vec::new(),
+            forbidden_flags: Vec::new(),
+            doc: None,
+            required: None,
+            exempt: None,
+        })
+    }
+
+    /// An `[inject_flags]` entry: commands matching `pattern` that lack any of
+    /// `flags` are rewritten with the missing ones added.
+    fn injecting(pattern: &str, flags: &[String]) -> Result<Self> {
+        let replacement = format!("{pattern} {}", flags.join(" "));
+        let entry = Self::new(pattern, Some(&replacement), None, Severity::Block, 0, 0)?;
+        Ok(CompiledRule { injected_flags: flags.to_vec(), ..entry })
+    }
+
+    /// A `[forbidden_flags]` entry: commands matching `pattern` with any of its
+    /// flags are blocked, with the command without them as the suggestion
+    /// unless the action is `block`.
+    fn forbidding(pattern: &str, forbidden: &ForbiddenFlags) -> Result<Self> {
+        let message = forbidden
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("Command '{pattern}' must not be run with {}", forbidden.flags.join(" or ")));
+        let replacement = (forbidden.action == ForbiddenFlagAction::Strip).then(|| pattern.to_string());
+        let entry = Self::new(pattern, replacement.as_ref(), Some(&message), Severity::Block, 0, 0)?;
+        Ok(CompiledRule { forbidden_flags: forbidden.flags.clone(), ..entry })
+    }
+
+    /// Restricts the rule to commands that contain `matches` and do not contain `unless`.
+    fn with_filters(mut self, matches: Option<&str>, unless: Option<&str>) -> Result<Self> {
+        self.required = matches.map(get_cached_regex).transpose()?;
+        self.exempt = unless.map(get_cached_regex).transpose()?;
+        Ok(self)
+    }
+
+    /// Whether the `matches` and `unless` filters let this rule apply to `simple_command`.
+    fn passes_filters(&self, simple_command: &str) -> bool {
+        self.required.as_ref().is_none_or(|regex| regex.is_match(simple_command))
+            && !self.exempt.as_ref().is_some_and(|regex| regex.is_match(simple_command))
+    }
+
+    /// Whether `simple_command` already carries every argument this mapping adds.
+    fn already_applied(&self, simple_command: &str) -> bool {
+        if !self.injected_flags.is_empty() {
+            return self.missing_flags(simple_command).is_empty();
+        }
+        if !self.forbidden_flags.is_empty() {
+            return self.forbidden_tokens(simple_command).is_empty();
+        }
+        !self.added_args.is_empty()
+            && self
+                .added_args
+                .iter()
+                .all(|arg| simple_command.split_whitespace().any(|word| word == arg))
+    }
+
+    /// The injected flags `simple_command` lacks. Arguments after `--` are
+    /// passed on to another program and don't count.
+    fn missing_flags(&self, simple_command: &str) -> Vec<&str> {
+        let words: Vec<&str> = simple_command.split_whitespace().take_while(|word| *word != "--").collect();
+        self.injected_flags.iter().map(String::as_str).filter(|flag| !has_flag(&words, flag)).collect()
+    }
+
+    /// The words of `simple_command` that are forbidden flags, before any `--`.
+    fn forbidden_tokens(&self, simple_command: &str) -> Vec<Range<usize>> {
+        shell::tokenize(simple_command)
+            .into_iter()
+            .take_while(|token| &simple_command[token.clone()] != "--")
+            .filter(|token| {
+                let word = &simple_command[token.clone()];
+                self.forbidden_flags.iter().any(|flag| {
+                    word == flag || (flag.starts_with("--") && word.strip_prefix(flag.as_str()).is_some_and(|rest| rest.starts_with('=')))
+                })
+            })
+            .collect()
+    }
+
+    /// Finds where this rule matches at the start of the given underlying commands.
+    fn find_matches(&self, command: &str, commands: &[Range<usize>]) -> Vec<Range<usize>> {
+        commands
+            .iter()
+            .filter(|range| {
+                let simple_command = &command[(*range).clone()];
+                !self.already_applied(simple_command) && self.passes_filters(simple_command)
+            })
+            .filter_map(|range| {
+                self.regex
+                    .find(&command[range.clone()])
+                    .map(|m| range.start + m.start()..range.start + m.end())
+            })
+            .collect()
+    }
+
+    /// Rewrites the matched parts of `command`, for rules with a replacement.
+    fn rewrite(&self, command: &str, matches: &[Range<usize>]) -> Option<String> {
+        if !self.injected_flags.is_empty() {
+            return Some(self.inject(command, matches));
+        }
+        if !self.forbidden_flags.is_empty() && self.replacement.is_some() {
+            return Some(self.strip(command, matches));
+        }
+        // Keep wrappers and separators intact
+        self.replacement.as_ref().map(|replacement| splice(command, matches, replacement))
+    }
+
+    /// Adds the flags each matched simple command lacks right after the match.
+    fn inject(&self, command: &str, matches: &[Range<usize>]) -> String {
+        let commands = underlying_commands(command, true);
+        let mut result = String::with_capacity(command.len() + 16);
+        let mut last = 0;
+        for range in matches {
+            if range.start < last {
+                continue;
+            }
+            let end = simple_command_end(&commands, command, range.start);
+            result.push_str(&command[last..range.end]);
+            for flag in self.missing_flags(&command[range.start..end]) {
+                result.push(' ');
+                result.push_str(flag);
+            }
+            last = range.end;
+        }
+        result.push_str(&command[last..]);
+        result
+    }
+
+    /// Removes the forbidden flags from each matched simple command, with the
+    /// whitespace before them.
+    fn strip(&self, command: &str, matches: &[Range<usize>]) -> String {
+        let commands = underlying_commands(command, true);
+        let mut result = String::with_capacity(command.len());
+        let mut last = 0;
+        for range in matches {
+            if range.start < last {
+                continue;
+            }
+            let end = simple_command_end(&commands, command, range.start);
+            for token in self.forbidden_tokens(&command[range.start..end]) {
+                let start = range.start + token.start;
+                result.push_str(command[last..start].trim_end());
+                last = range.start + token.end;
+            }
+        }
+        result.push_str(&command[last..]);
+        result
+    }
+
+    fn to_match(&self, command: &str, matches: &[Range<usize>]) -> RuleMatch {
+        self.describe(self.rewrite(command, matches), &[], &[])
+    }
+
+    /// Builds the match report, given the final rewrite of the command, the
+    /// replacements of the further mappings it went through, and other
+    /// commands that are acceptable too. The rule's `doc` link ends the reason.
+    fn describe(&self, suggested_command: Option<String>, via: &[String], alternatives: &[String]) -> RuleMatch {
+        let mut rule_match = self.describe_match(suggested_command, via);
+        if !alternatives.is_empty() {
+            append_sentence(&mut rule_match.reason, &format!("Also acceptable: {}", alternatives.join("; ")));
+        }
+        if let Some(doc) = &self.doc {
+            append_sentence(&mut rule_match.reason, &format!("Why: {doc}"));
+        }
+        rule_match
+    }
+
+    fn describe_match(&self, suggested_command: Option<String>, via: &[String]) -> RuleMatch {
+        let pattern = &self.pattern;
+        match (&self.replacement, suggested_command) {
+            (Some(_), Some(suggested_command)) if !self.injected_flags.is_empty() && via.is_empty() => RuleMatch {
+                pattern: pattern.clone(),
+                reason: format!("Command '{pattern}' must be run with {}. Try: {suggested_command}", self.injected_flags.join(" ")),
+                suggested_command: Some(suggested_command),
+                severity: self.severity,
+            },
+            (Some(_), Some(suggested_command)) if !self.forbidden_flags.is_empty() && via.is_empty() => {
+                let mut reason = self.message.clone().unwrap_or_default();
+                append_sentence(&mut reason, &format!("Try: {suggested_command}"));
+                RuleMatch { pattern: pattern.clone(), reason, suggested_command: Some(suggested_command), severity: self.severity }
+            }
+            (Some(replacement), Some(suggested_command)) if !via.is_empty() => RuleMatch {
+                pattern: pattern.clone(),
+                reason: format!(
+                    "Command '{pattern}' is mapped to use '{replacement}' instead, which is mapped on to '{}'. Try: {suggested_command}",
+                    via.join("' and then '")
+                ),
+                suggested_command: Some(suggested_command),
+                severity: self.severity,
+            },
+            (Some(replacement), Some(suggested_command)) => RuleMatch {
+                pattern: pattern.clone(),
+                reason: format!("Command '{pattern}' is mapped to use '{replacement}' instead. Try: {suggested_command}"),
+                suggested_command: Some(suggested_command),
+                severity: self.severity,
+            },
+            (Some(replacement), None) => RuleMatch {
+                pattern: pattern.clone(),
+                reason: format!(
+                    "Command '{pattern}' is mapped to use '{replacement}' instead, but the mappings rewrite it in a loop or past [matching] max_chain_depth; fix the configuration"
+                ),
+                suggested_command: None,
+                severity: self.severity,
+            },
+            (None, _) => RuleMatch {
+                pattern: pattern.clone(),
+                reason: self
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| match self.severity {
+                        Severity::Warn => format!("Command '{pattern}' is discouraged by project policy"),
+                        Severity::Ask => format!("Command '{pattern}' needs confirmation by project policy"),
+                        Severity::Block => format!("Command '{pattern}' is blocked by project policy"),
+                    }),
+                suggested_command: None,
+                severity: self.severity,
+            },
+        }
+    }
+}
+
+/// End of the innermost simple command that starts at or before `start`
+/// and contains it; substitutions follow the command they are in.
+fn simple_command_end(commands: &[Range<usize>], command: &str, start: usize) -> usize {
+    commands.iter().rev().find(|c| c.start <= start && start < c.end).map_or(command.len(), |c| c.end)
+}
+
+/// Whether `words` already contain `flag`. A flag with a value, such as
+/// `--jobs=4` or `--features "a b"`, is present when its name is, with any value.
+fn has_flag(words: &[&str], flag: &str) -> bool {
+    let flag_words: Vec<&str> = flag.split_whitespace().collect();
+    let Some(name) = flag_words.first().map(|word| word.split_once('=').map_or(*word, |(name, _)| name)) else {
+        return true;
+    };
+    words.iter().any(|word| *word == name || (name.starts_with('-') && word.strip_prefix(name).is_some_and(|rest| rest.starts_with('='))))
+        || words.windows(flag_words.len()).any(|window| window == flag_words.as_slice())
+}
+
+/// Adds `sentence` to `reason`, separated by a full stop.
+fn append_sentence(reason: &mut String, sentence: &str) {
+    let trimmed = reason.trim_end().trim_end_matches('.').len();
+    reason.truncate(trimmed);
+    reason.push_str(". ");
+    reason.push_str(sentence);
+}
+
+/// Default reason for a rule whose prerequisite has not succeeded.
+fn requirement_message(pattern: &str, requirement: &Requirement) -> String {
+    let before = &requirement.before;
+    match requirement.within_commands {
+        Some(window) => format!(
+            "Command '{pattern}' requires a successful '{before}' within the last {window} commands. Run '{before}' first."
+        ),
+        None => format!("Command '{pattern}' requires a successful '{before}' earlier in this session. Run '{before}' first."),
+    }
+}
+
+/// Returns the byte ranges of each simple command with its wrappers stripped,
+/// in order of where they start.
+///
+/// With `substitutions`, the simple commands inside each `$(...)` or backtick
+/// substitution follow the command containing it, at any depth.
+fn underlying_commands(command: &str, substitutions: bool) -> Vec<Range<usize>> {
+    let mut commands = Vec::new();
+    collect_commands(command, 0..command.len(), substitutions, &mut commands);
+    commands
+}
+
+fn collect_commands(command: &str, range: Range<usize>, substitutions: bool, commands: &mut Vec<Range<usize>>) {
+    let text = &command[range.clone()];
+    for segment in split_segments(text) {
+        let start = range.start + segment.start;
+        let simple_command = &text[segment.clone()];
+        commands.push(start + normalize(simple_command).command_start..range.start + segment.end);
+        if substitutions {
+            for inner in shell::substitutions(simple_command) {
+                collect_commands(command, start + inner.start..start + inner.end, true, commands);
+            }
+        }
+    }
+}
+
+/// Replaces each of the given (ordered) byte ranges with `replacement`; a
+/// range overlapping the one before it is left alone.
+fn splice(command: &str, ranges: &[Range<usize>], replacement: &str) -> String {
+    let mut result = String::with_capacity(command.len());
+    let mut last = 0;
+    for range in ranges {
+        if range.start < last {
+            continue;
+        }
+        result.push_str(&command[last..range.start]);
+        result.push_str(replacement);
+        last = range.end;
+    }
+    result.push_str(&command[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Rule;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_command_mapping() {
+        let mut commands = HashMap::new();
+        commands.insert("npm".to_string(), "bun".to_string());
+        commands.insert("yarn".to_string(), "bun".to_string());
+        commands.insert("npx".to_string(), "bunx".to_string());
+
+        let config = Config { 
+            commands,
+            semantic_directories: HashMap::new(),
+            ..Config::default()
+        };
+
+        // Test npm mapping
+        let result = check_command_mappings(&config, "npm install").unwrap();
+        assert!(result.is_some());
+        let (suggestion, replacement) = result.unwrap();
+        assert!(suggestion.contains("bun install"));
+        assert_eq!(replacement, "bun install");
+
+        // Test yarn mapping
+        let result = check_command_mappings(&config, "yarn start").unwrap();
+        assert!(result.is_some());
+        let (suggestion, replacement) = result.unwrap();
+        assert!(suggestion.contains("bun start"));
+        assert_eq!(replacement, "bun start");
+    }
+
+    #[test]
+    fn test_command_mapping_edge_cases() {
+        let mut commands = HashMap::new();
+        commands.insert("npm".to_string(), "bun".to_string());
+        let config = Config { 
+            commands,
+            semantic_directories: HashMap::new(),
+            ..Config::default()
+        };
+
+        // Test word boundaries - "npm" in "my-npm-tool" should NOT match due to word boundaries
+        let result = check_command_mappings(&config, "my-npm-tool install").unwrap();
+        // Looking at the regex implementation, it actually DOES match substring "npm"
+        // Let's test what the actual behavior is
+        if let Some((_, replacement)) = result {
+            // If it matches, that's the current behavior - document it
+            assert!(replacement.contains("bun"));
+        }
+
+        // Test empty command
+        let result = check_command_mappings(&config, "").unwrap();
+        assert!(result.is_none());
+
+        // Test command with multiple spaces
+        let result = check_command_mappings(&config, "npm   install   --verbose").unwrap();
+        assert!(result.is_some());
+        let (_, replacement) = result.unwrap();
+        assert_eq!(replacement, "bun   install   --verbose");
+    }
+
+    #[test]
+    fn test_command_mapping_through_wrappers() {
+        let mut commands = HashMap::new();
+        commands.insert("npm".to_string(), "bun".to_string());
+        let config = Config {
+            commands,
+            semantic_directories: HashMap::new(),
+            ..Config::default()
+        };
+
+        let (_, replacement) = check_command_mappings(&config, "sudo npm install").unwrap().unwrap();
+        assert_eq!(replacement, "sudo bun install");
+
+        let (_, replacement) = check_command_mappings(&config, "env FOO=1 npm ci").unwrap().unwrap();
+        assert_eq!(replacement, "env FOO=1 bun ci");
+
+        let (_, replacement) = check_command_mappings(&config, "cat pkgs | xargs npm install").unwrap().unwrap();
+        assert_eq!(replacement, "cat pkgs | xargs bun install");
+
+        let (_, replacement) = check_command_mappings(&config, "cd app && npm ci && npm test").unwrap().unwrap();
+        assert_eq!(replacement, "cd app && bun ci && bun test");
+
+        // Only the command position is matched, not arguments of other commands
+        assert!(check_command_mappings(&config, "which npm").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_command_mapping_ignores_quoted_text() {
+        let mut commands = HashMap::new();
+        commands.insert("npm".to_string(), "bun".to_string());
+        let config = Config {
+            commands,
+            semantic_directories: HashMap::new(),
+            ..Config::default()
+        };
+
+        for command in [
+            r#"echo "please run npm install""#,
+            r#"git commit -m "fix npm scripts""#,
+            r#"git commit -m "bump deps; npm install again""#,
+            "grep -rn 'npm install' docs",
+            "sed -i 's/yarn; npm/bun/g' README.md",
+            r#"awk '/npm/ { n++ }; END { print n }' build.log"#,
+            "cat <<EOF > CONTRIBUTING.md\nnpm install\nEOF",
+        ] {
+            assert!(
+                check_command_mappings(&config, command).unwrap().is_none(),
+                "should not match: {command}"
+            );
+        }
+
+        // Real commands after quoted text are still matched
+        let (_, replacement) = check_command_mappings(&config, r#"echo "a; b" && npm ci"#).unwrap().unwrap();
+        assert_eq!(replacement, r#"echo "a; b" && bun ci"#);
+    }
+
+    #[test]
+    fn test_command_mapping_in_multi_line_commands() {
+        let mut commands = HashMap::new();
+        commands.insert("npm".to_string(), "bun".to_string());
+        commands.insert("yarn add".to_string(), "bun add".to_string());
+        let config = Config {
+            commands,
+            ..Config::default()
+        };
+        let rewrite = |command: &str| check_command_mappings(&config, command).unwrap().map(|(_, replacement)| replacement);
+
+        // Only the command parts are rewritten; heredoc bodies stay as written
+        assert_eq!(
+            rewrite("cat <<'EOF' > .npmrc && npm ci\nnpm install\nEOF\nnpm test").as_deref(),
+            Some("cat <<'EOF' > .npmrc && bun ci\nnpm install\nEOF\nbun test")
+        );
+        assert_eq!(rewrite("git commit -m \"$(cat <<'EOF'\nDon't \"npm install\"; yarn add x\nEOF\n)\""), None);
+
+        // Patterns match across line continuations
+        assert_eq!(rewrite("npm \\\n  install").as_deref(), Some("bun \\\n  install"));
+        assert_eq!(rewrite("yarn \\\n  add lodash").as_deref(), Some("bun add lodash"));
+        assert_eq!(rewrite("cd app && \\\n  sudo \\\n  npm ci").as_deref(), Some("cd app && \\\n  sudo \\\n  bun ci"));
+        assert!(command_matches("yarn add", "yarn  add x").unwrap());
+        assert!(!command_matches("yarn add", "yarn \\\n\n add x").unwrap());
+    }
+
+    #[test]
+    fn test_command_mapping_in_substitutions() {
+        let mut config: Config = toml::from_str("[commands]\nnpm = \"bun\"\n\n[[rules]]\npattern = \"rm -rf\"\n").unwrap();
+        let rewrite = |config: &Config, command: &str| {
+            CompiledRules::new(config, &RuleContext::default()).unwrap().evaluate(command).and_then(|m| m.suggested_command)
+        };
+
+        assert_eq!(rewrite(&config, "echo $(npm view pkg version)").as_deref(), Some("echo $(bun view pkg version)"));
+        assert_eq!(
+            rewrite(&config, "npm ci && echo \"v`npm -v`\" $(cd x; sudo npm root)").as_deref(),
+            Some("bun ci && echo \"v`bun -v`\" $(cd x; sudo bun root)")
+        );
+        assert_eq!(rewrite(&config, "ls $(dirname $(npm root -g))").as_deref(), Some("ls $(dirname $(bun root -g))"));
+        assert_eq!(rewrite(&config, "echo '$(npm ci)'"), None);
+        let rules = CompiledRules::new(&config, &RuleContext::default()).unwrap();
+        assert_eq!(rules.evaluate("echo $(rm -rf build)").unwrap().severity, Severity::Block);
+
+        // Switched off, substitutions are opaque again
+        config.matching.check_substitutions = false;
+        assert_eq!(rewrite(&config, "echo $(npm view pkg version)"), None);
+        assert_eq!(rewrite(&config, "npm ci $(npm root)").as_deref(), Some("bun ci $(npm root)"));
+    }
+
+    #[test]
+    fn test_command_mapping_batch() {
+        let mut commands = HashMap::new();
+        commands.insert("npm".to_string(), "bun".to_string());
+        let config = Config {
+            commands,
+            ..Config::default()
+        };
+
+        let results = check_command_mappings_batch(&config, &["npm ci", "ls -la", "sudo npm test"]).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().1, "bun ci");
+        assert!(results[1].is_none());
+        assert_eq!(results[2].as_ref().unwrap().1, "sudo bun test");
+
+        assert!(check_command_mappings_batch(&config, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rule_conditions() {
+        let config = Config {
+            rules: vec![
+                Rule {
+                    pattern: "git push --force".to_string(),
+                    message: Some("Force pushes need a human".to_string()),
+                    when: Some(RuleCondition {
+                        source: Some(RuleSource::Agent),
+                        ..RuleCondition::default()
+                    }),
+                    ..Rule::default()
+                },
+                Rule {
+                    pattern: "rm".to_string(),
+                    replacement: Some("trash".to_string()),
+                    when: Some(RuleCondition {
+                        permission_mode: Some("bypassPermissions".to_string()),
+                        ..RuleCondition::default()
+                    }),
+                    ..Rule::default()
+                },
+            ],
+            ..Config::default()
+        };
+
+        let user = RuleContext { permission_mode: Some("default".to_string()), ..RuleContext::default() };
+        let accept_edits = RuleContext { permission_mode: Some("acceptEdits".to_string()), ..RuleContext::default() };
+        let bypass = RuleContext { permission_mode: Some("bypassPermissions".to_string()), ..RuleContext::default() };
+
+        assert!(evaluate_command(&config, "git push --force", &user).unwrap().is_none());
+        let blocked = evaluate_command(&config, "git push --force", &accept_edits).unwrap().unwrap();
+        assert_eq!(blocked.reason, "Force pushes need a human");
+        assert!(blocked.suggested_command.is_none());
+
+        assert!(evaluate_command(&config, "rm -r build", &accept_edits).unwrap().is_none());
+        let mapped = evaluate_command(&config, "rm -r build", &bypass).unwrap().unwrap();
+        assert_eq!(mapped.suggested_command.as_deref(), Some("trash -r build"));
+    }
+
+    #[test]
+    fn test_environment_conditions() {
+        let config: Config = toml::from_str(
+            r#"
+[[rules]]
+pattern = "cargo"
+replacement = "nix develop -c cargo"
+when = { in_nix_shell = false }
+"#,
+        )
+        .unwrap();
+
+        let outside = RuleContext::default();
+        let inside = RuleContext { in_nix_shell: true, ..RuleContext::default() };
+        let wrapped = evaluate_command(&config, "cargo build", &outside).unwrap().unwrap();
+        assert_eq!(wrapped.suggested_command.as_deref(), Some("nix develop -c cargo build"));
+        assert!(evaluate_command(&config, "cargo build", &inside).unwrap().is_none());
+        // The wrapped command itself is not matched again
+        assert!(evaluate_command(&config, "nix develop -c cargo build", &outside).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_argument_filters_and_kube_context() {
+        let config: Config = toml::from_str(
+            r#"
+[[rules]]
+pattern = "kubectl"
+matches = "--context[= ]prod"
+message = "production"
+
+[[rules]]
+pattern = "kubectl"
+unless = "(^|\\s)(-n|--namespace)\\b"
+message = "namespace"
+
+[[rules]]
+pattern = "helm"
+when = { kube_context = "^prod" }
+"#,
+        )
+        .unwrap();
+        let context = RuleContext::default();
+        let reason = |command: &str, context: &RuleContext| {
+            evaluate_command(&config, command, context).unwrap().map(|m| m.reason)
+        };
+
+        assert_eq!(reason("kubectl get pods --context prod -n web", &context).as_deref(), Some("production"));
+        assert_eq!(reason("kubectl get pods", &context).as_deref(), Some("namespace"));
+        assert_eq!(reason("kubectl get pods -n web", &context), None);
+
+        let production = RuleContext { kube_context: Some("prod-eu".to_string()), ..RuleContext::default() };
+        assert!(reason("helm upgrade app", &context).is_none());
+        assert!(reason("helm upgrade app", &production).is_some());
+    }
+
+    #[test]
+    fn test_kube_context_rules_in_groups() {
+        let config: Config = toml::from_str(
+            "[groups.k8s]\nenabled = true\n\n[[groups.k8s.rules]]\npattern = \"kubectl delete\"\nwhen = { kube_context = \"^prod\" }\n",
+        )
+        .unwrap();
+        assert!(needs_kube_context(&config, "kubectl delete pod web"));
+        assert!(!needs_kube_context(&config, "ls"));
+
+        let disabled: Config = toml::from_str(
+            "[groups.k8s]\nenabled = false\n\n[[groups.k8s.rules]]\npattern = \"kubectl delete\"\nwhen = { kube_context = \"^prod\" }\n",
+        )
+        .unwrap();
+        assert!(!needs_kube_context(&disabled, "kubectl delete pod web"));
+
+        let production = RuleContext { kube_context: Some("prod-eu".to_string()), ..RuleContext::default() };
+        assert!(evaluate_command(&config, "kubectl delete pod web", &production).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_git_dirty_condition() {
+        let config: Config = toml::from_str(
+            r#"
+[[rules]]
+pattern = "git reset --hard"
+message = "This throws away uncommitted work"
+when = { git_dirty = true }
+
+[[rules]]
+pattern = "git stash drop"
+severity = "warn"
+when = { git_dirty = false }
+"#,
+        )
+        .unwrap();
+        let reason = |command: &str, context: &RuleContext| evaluate_command(&config, command, context).unwrap().map(|m| m.reason);
+
+        // Unknown state satisfies neither value
+        assert!(reason("git reset --hard", &RuleContext::default()).is_none());
+        assert!(reason("git stash drop", &RuleContext::default()).is_none());
+        let dirty = RuleContext { git_dirty: Some(true), ..RuleContext::default() };
+        assert_eq!(reason("git reset --hard", &dirty).as_deref(), Some("This throws away uncommitted work"));
+        assert!(reason("git stash drop", &dirty).is_none());
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git_init = Command::new("git").args(["init", "-q"]).current_dir(temp_dir.path()).status();
+        if !git_init.is_ok_and(|status| status.success()) {
+            return;
+        }
+        let status = |command: &str| RuleContext::default().with_git_status(&config, command, temp_dir.path()).git_dirty;
+        assert_eq!(status("git reset --hard HEAD~1"), Some(false));
+        // Commands no such rule matches skip git
+        assert_eq!(status("ls"), None);
+        std::fs::write(temp_dir.path().join("wip.rs"), "fn main() {}").unwrap();
+        assert_eq!(status("git reset --hard HEAD~1"), Some(true));
+    }
+
+    #[test]
+    fn test_cron_condition() {
+        let config: Config = toml::from_str(
+            r#"
+[time]
+timezone = "America/New_York"
+
+[[rules]]
+pattern = "./deploy.sh"
+message = "Deploys only happen during business hours"
+when = { cron = "!Mon-Fri 09:00-17:00" }
+"#,
+        )
+        .unwrap();
+        let blocked = |now: &str| {
+            let now = DateTime::parse_from_rfc3339(now).unwrap().with_timezone(&Utc);
+            let context = RuleContext::default().with_clock(&config, now);
+            evaluate_command(&config, "./deploy.sh prod", &context).unwrap().is_some()
+        };
+
+        // 14:00 UTC on a Wednesday is 10:00 in New York, 22:00 UTC is 18:00
+        assert!(!blocked("2025-06-18T14:00:00Z"));
+        assert!(blocked("2025-06-18T22:00:00Z"));
+        assert!(blocked("2025-06-21T14:00:00Z"));
+        // Without a clock the condition doesn't hold
+        assert!(evaluate_command(&config, "./deploy.sh", &RuleContext::default()).unwrap().is_none());
+
+        let invalid: Config = toml::from_str("[[rules]]\npattern = \"x\"\nwhen = { cron = \"Mon-Fry\" }\n").unwrap();
+        assert!(evaluate_command(&invalid, "x", &RuleContext::default()).is_err());
+        let invalid: Config = toml::from_str("[time]\ntimezone = \"Mars/Olympus\"\n").unwrap();
+        assert!(evaluate_command(&invalid, "x", &RuleContext::default()).is_err());
+    }
+
+    #[test]
+    fn test_sample_rate_is_validated() {
+        for rate in ["0.0", "1.5", "-0.1", "nan"] {
+            let config: Config = toml::from_str(&format!("[[rules]]\npattern = \"npm\"\nsample_rate = {rate}\n")).unwrap();
+            assert!(evaluate_command(&config, "npm ci", &RuleContext::default()).is_err(), "{rate}");
+        }
+        let config: Config = toml::from_str("[[rules]]\npattern = \"npm\"\nsample_rate = 0.1\n").unwrap();
+        assert!(evaluate_command(&config, "npm ci", &RuleContext::default()).unwrap().is_some());
+        assert_eq!(config.sample_rate("npm"), Some(0.1));
+    }
+
+    #[test]
+    fn test_prerequisite_commands() {
+        let config: Config = toml::from_str(
+            r#"
+[[rules]]
+pattern = "git commit"
+requires = { before = "cargo fmt", within_commands = 2 }
+"#,
+        )
+        .unwrap();
+        let mut state = crate::state::SessionState::default();
+        let evaluate = |state: &crate::state::SessionState| {
+            let context = RuleContext { history: Some(state.history.clone()), ..RuleContext::default() };
+            evaluate_command(&config, "git commit -m wip", &context).unwrap()
+        };
+
+        let blocked = evaluate(&state).unwrap();
+        assert!(blocked.reason.contains("'cargo fmt' within the last 2 commands"));
+
+        state.record_run("cargo fmt --all", Some(1), None);
+        assert!(evaluate(&state).is_some());
+        state.record_run("cargo fmt --all", Some(0), None);
+        assert!(evaluate(&state).is_none());
+
+        // The run falls out of the window
+        state.record_run("cargo test", Some(0), None);
+        state.record_run("git add .", Some(0), None);
+        assert!(evaluate(&state).is_some());
+
+        // Outside a session, prerequisites are not checked
+        assert!(evaluate_command(&config, "git commit", &RuleContext::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_explain_lists_shadowed_matches() {
+        let config = Config {
+            commands: HashMap::from([("npm".to_string(), "bun".to_string())]),
+            rules: vec![Rule {
+                pattern: "npm publish".to_string(),
+                message: Some("Publishing is done by CI".to_string()),
+                ..Rule::default()
+            }],
+            ..Config::default()
+        };
+        let rules = CompiledRules::new(&config, &RuleContext::default()).unwrap();
+
+        let matches = rules.explain("npm publish --dry-run");
+        let patterns: Vec<&str> = matches.iter().map(|m| m.pattern.as_str()).collect();
+        assert_eq!(patterns, vec!["npm publish", "npm"]);
+        assert_eq!(rules.evaluate("npm publish --dry-run").as_ref(), matches.first());
+        assert!(rules.explain("cargo build").is_empty());
+    }
+
+    #[test]
+    fn test_alternatives_in_block_reasons() {
+        let config: Config = toml::from_str(
+            r#"
+[output]
+max_alternatives = 2
+
+[[rules]]
+pattern = "npm"
+replacement = "bun"
+priority = 10
+
+[[rules]]
+pattern = "npm"
+replacement = "pnpm"
+priority = 5
+
+[[rules]]
+pattern = "npm"
+replacement = "yarn"
+
+[[rules]]
+pattern = "npm install"
+replacement = "npm ci"
+priority = -1
+
+[commands]
+yarn = "bun"
+"#,
+        )
+        .unwrap();
+
+        // yarn is mapped away itself, so it isn't offered
+        let mapped = evaluate_command(&config, "npm install", &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(mapped.suggested_command.as_deref(), Some("bun install"));
+        assert_eq!(
+            mapped.reason,
+            "Command 'npm' is mapped to use 'bun' instead. Try: bun install. Also acceptable: pnpm install"
+        );
+
+        let rules = CompiledRules::new(&config, &RuleContext::default()).unwrap();
+        assert_eq!(rules.evaluate_with_alternatives("npm test", 0), rules.evaluate("npm test"));
+        let one = rules.evaluate_with_alternatives("npm test", 1).unwrap();
+        assert!(one.reason.ends_with("Try: bun test. Also acceptable: pnpm test"));
+    }
+
+    #[test]
+    fn test_doc_links_end_the_reason() {
+        let config: Config = toml::from_str(
+            r#"
+[output]
+max_alternatives = 1
+
+[[rules]]
+pattern = "npm"
+replacement = "bun"
+doc = "https://wiki.internal/tooling#bun"
+
+[[rules]]
+pattern = "npm"
+replacement = "pnpm"
+
+[[rules]]
+pattern = "curl"
+message = "Downloads go through the proxy."
+severity = "warn"
+doc = "https://wiki.internal/proxy"
+"#,
+        )
+        .unwrap();
+        let context = RuleContext::default();
+
+        let mapped = evaluate_command(&config, "npm install", &context).unwrap().unwrap();
+        assert_eq!(
+            mapped.reason,
+            "Command 'npm' is mapped to use 'bun' instead. Try: bun install. Also acceptable: pnpm install. \
+             Why: https://wiki.internal/tooling#bun"
+        );
+        let warned = evaluate_command(&config, "curl -O x", &context).unwrap().unwrap();
+        assert_eq!(warned.reason, "Downloads go through the proxy. Why: https://wiki.internal/proxy");
+    }
+
+    #[test]
+    fn test_rule_severity() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[[rules]]
+pattern = "git push"
+message = "Remember to run the tests first"
+severity = "warn"
+
+[[rules]]
+pattern = "terraform apply"
+"#,
+        )
+        .unwrap();
+
+        let context = RuleContext::default();
+        let warned = evaluate_command(&config, "git push origin main", &context).unwrap().unwrap();
+        assert_eq!(warned.severity, Severity::Warn);
+        assert_eq!(warned.severity.exit_code(), crate::types::EXIT_WARN);
+
+        let blocked = evaluate_command(&config, "terraform apply", &context).unwrap().unwrap();
+        assert_eq!(blocked.severity, Severity::Block);
+        let mapped = evaluate_command(&config, "npm ci", &context).unwrap().unwrap();
+        assert_eq!(mapped.severity.exit_code(), crate::types::EXIT_BLOCK);
+    }
+
+    #[test]
+    fn test_parallel_evaluation_keeps_rule_order() {
+        let mut rules: Vec<Rule> = (0..PARALLEL_THRESHOLD * 2)
+            .map(|i| Rule {
+                pattern: format!("tool{i}"),
+                ..Rule::default()
+            })
+            .collect();
+        // Two equally specific rules far apart; the earlier one must win
+        rules[700].pattern = "deploy".to_string();
+        rules[700].message = Some("first".to_string());
+        rules[900].pattern = "deploy".to_string();
+        rules[900].message = Some("second".to_string());
+
+        let config = Config { rules, ..Config::default() };
+        let compiled = CompiledRules::new(&config, &RuleContext::default()).unwrap();
+        assert!(compiled.is_parallel());
+
+        for _ in 0..20 {
+            let result = compiled.evaluate("deploy --prod").unwrap();
+            assert_eq!(result.reason, "first");
+        }
+        assert_eq!(compiled.evaluate("tool1023 x").unwrap().pattern, "tool1023");
+        assert!(compiled.evaluate("cargo build").is_none());
+    }
+
+    #[test]
+    fn test_longest_match_wins_by_default() {
+        let mut commands = HashMap::new();
+        commands.insert("pip".to_string(), "uv pip".to_string());
+        commands.insert("pip install".to_string(), "uv add".to_string());
+        commands.insert("pip install -r".to_string(), "uv pip sync".to_string());
+        let config = Config { commands, ..Config::default() };
+
+        // Independent of HashMap iteration order
+        for _ in 0..10 {
+            assert_eq!(check_command_mappings(&config, "pip install requests").unwrap().unwrap().1, "uv add requests");
+            assert_eq!(check_command_mappings(&config, "pip install -r req.txt").unwrap().unwrap().1, "uv pip sync req.txt");
+            assert_eq!(check_command_mappings(&config, "pip list").unwrap().unwrap().1, "uv pip list");
+        }
+    }
+
+    #[test]
+    fn test_mappings_that_add_arguments_apply_once() {
+        let mut commands = HashMap::new();
+        commands.insert("cargo build".to_string(), "cargo build --workspace".to_string());
+        commands.insert("cargo test".to_string(), "cargo test -- --nocapture".to_string());
+        let config = Config { commands, ..Config::default() };
+
+        assert_eq!(
+            check_command_mappings(&config, "cargo build --release").unwrap().unwrap().1,
+            "cargo build --workspace --release"
+        );
+        assert!(check_command_mappings(&config, "cargo build --workspace").unwrap().is_none());
+        assert!(check_command_mappings(&config, "cargo build --release --workspace").unwrap().is_none());
+        assert!(check_command_mappings(&config, "cargo test -p core -- --nocapture").unwrap().is_none());
+        // Only the segment that lacks the arguments is rewritten
+        assert_eq!(
+            check_command_mappings(&config, "cargo build --workspace && cargo test").unwrap().unwrap().1,
+            "cargo build --workspace && cargo test -- --nocapture"
+        );
+    }
+
+    #[test]
+    fn test_chained_mappings_suggest_the_final_command() {
+        let mut commands = HashMap::new();
+        commands.insert("yarn".to_string(), "npm".to_string());
+        commands.insert("npm".to_string(), "bun".to_string());
+        commands.insert("make".to_string(), "just".to_string());
+        commands.insert("just".to_string(), "make".to_string());
+        let config = Config { commands, ..Config::default() };
+
+        let (_, replacement) = check_command_mappings(&config, "yarn install").unwrap().unwrap();
+        assert_eq!(replacement, "bun install");
+        let rule_match = evaluate_command(&config, "yarn install", &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(rule_match.reason, "Command 'yarn' is mapped to use 'npm' instead, which is mapped on to 'bun'. Try: bun install");
+
+        // A loop has no final command to suggest, so the command is only blocked
+        let rule_match = evaluate_command(&config, "make build", &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(rule_match.suggested_command, None);
+        assert!(rule_match.reason.contains("loop"));
+    }
+
+    #[test]
+    fn test_inject_flags() {
+        let config: Config = toml::from_str(
+            "[inject_flags]\n\"cargo test\" = [\"--all-features\", \"--locked\"]\nrsync = [\"--dry-run\"]\nmake = [\"--jobs=4\"]\n",
+        )
+        .unwrap();
+        let suggested = |command: &str| {
+            evaluate_command(&config, command, &RuleContext::default()).unwrap().map(|m| m.suggested_command.unwrap())
+        };
+
+        assert_eq!(suggested("cargo test -p core").as_deref(), Some("cargo test --all-features --locked -p core"));
+        // Flags already there are not added again, and arguments after `--` don't count
+        assert_eq!(suggested("cargo test --locked").as_deref(), Some("cargo test --all-features --locked"));
+        assert_eq!(suggested("cargo test --all-features --locked -- --locked"), None);
+        assert_eq!(suggested("cargo test -- --all-features").as_deref(), Some("cargo test --all-features --locked -- --all-features"));
+        assert_eq!(suggested("rsync -av a/ b/ && rsync --dry-run c/ d/").as_deref(), Some("rsync --dry-run -av a/ b/ && rsync --dry-run c/ d/"));
+        // A flag with a value is present with any value
+        assert_eq!(suggested("make --jobs=8"), None);
+        assert_eq!(suggested("make --jobs 2"), None);
+
+        let rule_match = evaluate_command(&config, "rsync x y", &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(rule_match.reason, "Command 'rsync' must be run with --dry-run. Try: rsync --dry-run x y");
+        assert_eq!(rule_match.severity, Severity::Block);
+    }
+
+    #[test]
+    fn test_forbidden_flags() {
+        let config: Config = toml::from_str(
+            r#"
+[forbidden_flags]
+"git push" = { flags = ["--force", "-f"] }
+"git commit" = { flags = ["--no-verify", "-n"], action = "block", message = "Commit hooks must run." }
+"npm install" = { flags = ["--registry"] }
+"#,
+        )
+        .unwrap();
+        let evaluate = |command: &str| evaluate_command(&config, command, &RuleContext::default()).unwrap();
+
+        let rule_match = evaluate("git push --force origin main").unwrap();
+        assert_eq!(rule_match.suggested_command.as_deref(), Some("git push origin main"));
+        assert_eq!(rule_match.reason, "Command 'git push' must not be run with --force or -f. Try: git push origin main");
+        assert_eq!(evaluate("cd app && git push -f && git push --force-with-lease").unwrap().suggested_command.as_deref(), Some("cd app && git push && git push --force-with-lease"));
+        assert!(evaluate("git push origin main").is_none());
+        // Quoted arguments and arguments after `--` are not flags
+        assert!(evaluate("git push origin \"--force\"").is_none());
+        assert!(evaluate("git push -- --force").is_none());
+
+        let rule_match = evaluate("git commit -n -m wip").unwrap();
+        assert_eq!(rule_match.suggested_command, None);
+        assert_eq!(rule_match.reason, "Commit hooks must run.");
+        assert!(evaluate("git commit -m 'skip -n'").is_none());
+
+        // A long flag is forbidden with any value
+        assert_eq!(evaluate("npm install --registry=https://evil.example x").unwrap().suggested_command.as_deref(), Some("npm install x"));
+    }
+
+    #[test]
+    fn test_max_chain_depth() {
+        let config = |depth: usize| -> Config {
+            toml::from_str(&format!("[matching]\nmax_chain_depth = {depth}\n\n[commands]\nyarn = \"npm\"\nnpm = \"pnpm\"\npnpm = \"bun\"\n")).unwrap()
+        };
+        let evaluate = |depth: usize| evaluate_command(&config(depth), "yarn add zod", &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(evaluate(16).suggested_command.as_deref(), Some("bun add zod"));
+        assert_eq!(evaluate(2).suggested_command.as_deref(), Some("bun add zod"));
+        assert_eq!(
+            evaluate(2).reason,
+            "Command 'yarn' is mapped to use 'npm' instead, which is mapped on to 'pnpm' and then 'bun'. Try: bun add zod"
+        );
+        // A chain longer than the depth is treated like a loop
+        assert_eq!(evaluate(1).suggested_command, None);
+
+        // Without chaining, the first replacement is suggested as it is
+        let rule_match = evaluate(0);
+        assert_eq!(rule_match.reason, "Command 'yarn' is mapped to use 'npm' instead. Try: npm add zod");
+    }
+
+    #[test]
+    fn test_rule_groups() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[groups.node]
+commands = { npm = "pnpm", yarn = "bun" }
+
+[[groups.node.rules]]
+pattern = "npx"
+message = "Use bunx"
+
+[groups.python]
+enabled = false
+commands = { pip = "uv pip" }
+"#,
+        )
+        .unwrap();
+
+        // Top-level mappings win over a group's mapping for the same pattern
+        assert_eq!(check_command_mappings(&config, "npm ci").unwrap().unwrap().1, "bun ci");
+        assert_eq!(check_command_mappings(&config, "yarn add x").unwrap().unwrap().1, "bun add x");
+        let rule_match = evaluate_command(&config, "npx tsc", &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(rule_match.reason, "Use bunx");
+        // Disabled groups are ignored
+        assert!(check_command_mappings(&config, "pip install x").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_snoozed_patterns_do_not_apply() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+"npm run" = "bun run"
+
+[[rules]]
+pattern = "git push"
+severity = "block"
+"#,
+        )
+        .unwrap();
+        let context = RuleContext { snoozed: vec!["npm run".to_string(), "git push".to_string()], ..RuleContext::default() };
+
+        // A less specific mapping still applies while the specific one is snoozed
+        let rule_match = evaluate_command(&config, "npm run build", &context).unwrap().unwrap();
+        assert_eq!(rule_match.suggested_command.as_deref(), Some("bun run build"));
+        assert!(evaluate_command(&config, "git push", &context).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_most_specific_match_wins() {
+        assert!(Specificity::new("npm run build", 0) > Specificity::new("npm", 0));
+        assert!(Specificity::new("npm run", 0) > Specificity::new("npm-run-all", 0));
+        assert!(Specificity::new("git push", 1) > Specificity::new("git push", 0));
+
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+"npm run build" = "bun run build:prod"
+"git push" = "git push --force-with-lease"
+
+[[rules]]
+pattern = "git push"
+message = "Agents must open a pull request instead of pushing"
+when = { source = "agent" }
+"#,
+        )
+        .unwrap();
+
+        let (_, replacement) = check_command_mappings(&config, "npm run build --watch").unwrap().unwrap();
+        assert_eq!(replacement, "bun run build:prod --watch");
+
+        // The conditional rule is more specific than the plain mapping when it applies
+        let agent = RuleContext { permission_mode: Some("bypassPermissions".to_string()), ..RuleContext::default() };
+        let result = evaluate_command(&config, "git push origin", &agent).unwrap().unwrap();
+        assert!(result.suggested_command.is_none());
+
+        let user = RuleContext::default();
+        let result = evaluate_command(&config, "git push origin", &user).unwrap().unwrap();
+        assert_eq!(result.suggested_command.as_deref(), Some("git push --force-with-lease origin"));
+    }
+
+    #[test]
+    fn test_explicit_priority_overrides_length() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+"pip install" = "uv add"
+
+[[rules]]
+pattern = "pip"
+message = "pip is not available in this environment"
+priority = 10
+
+[[rules]]
+pattern = "pip freeze"
+replacement = "uv pip freeze"
+"#,
+        )
+        .unwrap();
+        let context = RuleContext::default();
+
+        let result = evaluate_command(&config, "pip install requests", &context).unwrap().unwrap();
+        assert_eq!(result.pattern, "pip");
+
+        let rules = CompiledRules::new(&config, &context).unwrap();
+        let order: Vec<String> = rules.explain("pip freeze").into_iter().map(|m| m.pattern).collect();
+        assert_eq!(order, vec!["pip", "pip freeze"]);
+    }
+
+    #[test]
+    fn test_rules_parse_from_toml() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[[rules]]
+pattern = "terraform apply"
+message = "Run terraform plan first"
+when = { permission_mode = "acceptEdits", source = "agent" }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.rules.len(), 1);
+        let when = config.rules[0].when.as_ref().unwrap();
+        assert_eq!(when.permission_mode.as_deref(), Some("acceptEdits"));
+        assert_eq!(when.source, Some(RuleSource::Agent));
+    }
+}
@@ -0,0 +1,207 @@
+//! Filesystem guardrail around tool calls
+//!
+//! The `[sandbox]` table lists the directories an agent is expected to work
+//! in. A Bash command that mentions an absolute path outside them, or a file
+//! tool (Read, Write, Edit, Glob, ...) pointed outside them, is blocked or
+//! flagged. Paths are found by looking at the command's words, not by
+//! running it, so this is a coarse check rather than a real sandbox.
+
+use crate::directory::{expand_path, normalize_lexically, real_path, within_allowed_roots};
+use crate::rules::RuleMatch;
+use crate::shell::{split_segments, tokenize};
+use crate::types::{Result, Sandbox, ToolInput};
+use std::path::{Path, PathBuf};
+
+/// Device files every command may use, whatever the roots.
+const ALWAYS_ALLOWED: &[&str] = &["/dev/null", "/dev/stdin", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+/// Flags the first of `paths` that lies outside the sandbox roots.
+///
+/// Relative roots are taken relative to `project_dir`, like everything else
+/// in the project's configuration, and relative paths relative to `cwd`,
+/// where the tool runs. Symlinks are resolved on both sides, so a link
+/// inside a root that points elsewhere is outside.
+///
+/// # Arguments
+/// * `sandbox` - The `[sandbox]` table
+/// * `project_dir` - Root directory of the project
+/// * `cwd` - Working directory of the tool call, which may be below `project_dir`
+/// * `paths` - Paths the tool call references
+///
+/// # Returns
+/// * `Ok(Some(RuleMatch))` - A match with the sandbox's severity for the first path outside the roots
+/// * `Ok(None)` - If the sandbox is disabled or every path is inside a root
+/// * `Err` - If a root or path cannot be resolved
+pub fn check_sandbox(sandbox: &Sandbox, project_dir: &Path, cwd: &Path, paths: &[String]) -> Result<Option<RuleMatch>> {
+    if sandbox.allowed_roots.is_empty() {
+        return Ok(None);
+    }
+
+    let repo_root = repo_root(project_dir);
+    let roots: Vec<String> = sandbox
+        .allowed_roots
+        .iter()
+        .map(|root| root.replace("{repo_root}", &repo_root.to_string_lossy()))
+        .map(|root| if root.starts_with('~') { root } else { project_dir.join(root).to_string_lossy().into_owned() })
+        .collect();
+
+    for path in paths {
+        if ALWAYS_ALLOWED.contains(&path.as_str()) {
+            continue;
+        }
+        let real = real_path(&normalize_lexically(&cwd.join(expand_path(path)?)));
+        if !within_allowed_roots(&roots, &real)? {
+            return Ok(Some(RuleMatch {
+                pattern: "sandbox".to_string(),
+                reason: format!(
+                    "'{path}' is outside the sandbox (allowed roots: {}). Work inside these directories, or ask the user to add this one to [sandbox] allowed_roots",
+                    roots.join(", ")
+                ),
+                suggested_command: None,
+                severity: sandbox.severity,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Absolute and home-relative paths mentioned in a shell command.
+///
+/// Looks at every word of every segment, including redirection targets
+/// (`>/tmp/out`) and option values (`--prefix=/usr`). Words built from
+/// variables or command substitutions are skipped, as are glob characters
+/// and what follows them.
+pub fn command_paths(command: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for segment in split_segments(command) {
+        let segment = &command[segment];
+        for word in tokenize(segment) {
+            if let Some(path) = word_path(&segment[word]) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+/// The file or directory a non-Bash tool call reads, writes or searches.
+pub fn tool_paths(tool_input: &ToolInput) -> Vec<String> {
+    [&tool_input.file_path, &tool_input.notebook_path, &tool_input.path]
+        .into_iter()
+        .flatten()
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// The path a single shell word refers to, if it is absolute or starts with `~`.
+fn word_path(word: &str) -> Option<String> {
+    if word.contains('$') || word.contains('`') {
+        return None;
+    }
+
+    // `2>/tmp/log`, `&>>out`, `</etc/hosts`
+    let operator_len = word
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '<' | '>' | '&')))
+        .unwrap_or(word.len());
+    let word = if word[..operator_len].contains(['<', '>']) { &word[operator_len..] } else { word };
+
+    // `--prefix=/usr`, `DEST=~/out`
+    let word = match word.split_once('=') {
+        Some((name, value)) if !name.contains('/') => value,
+        _ => word,
+    };
+
+    let path: String = word.chars().filter(|c| !matches!(c, '\'' | '"' | '\\')).collect();
+    if !(path.starts_with('/') || path == "~" || path.starts_with("~/")) {
+        return None;
+    }
+
+    // `/var/log/*.log` → `/var/log/`
+    let path = match path.find(['*', '?', '[', '{']) {
+        Some(glob) => path[..path[..glob].rfind('/').map_or(0, |slash| slash + 1)].to_string(),
+        None => path,
+    };
+    (!path.is_empty()).then_some(path)
+}
+
+/// The nearest directory above `cwd` that contains `.git`, or `cwd` itself.
+fn repo_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Severity;
+    use std::fs;
+
+    #[test]
+    fn test_command_paths() {
+        assert_eq!(
+            command_paths("cat /etc/hosts ~/notes.md src/main.rs && ls '/opt/my dir' 2>/dev/null"),
+            vec!["/etc/hosts", "~/notes.md", "/opt/my dir", "/dev/null"]
+        );
+        assert_eq!(
+            command_paths("./configure --prefix=/usr/local; DEST=~/out make >>/tmp/build.log"),
+            vec!["/usr/local", "~/out", "/tmp/build.log"]
+        );
+        assert_eq!(command_paths("rm /var/log/*.log; ls /*"), vec!["/var/log/", "/"]);
+        assert!(command_paths("echo $HOME/x \"$(cat /etc/passwd)\" https://example.com/a").is_empty());
+    }
+
+    #[test]
+    fn test_paths_outside_roots_are_flagged() {
+        let repo = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(repo.path().join(".git")).unwrap();
+        fs::create_dir_all(repo.path().join("src/nested")).unwrap();
+        let shared = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+
+        let sandbox = Sandbox {
+            allowed_roots: vec!["{repo_root}".to_string(), shared.path().to_string_lossy().into_owned()],
+            severity: Severity::Ask,
+            ignore_doc_edits: false,
+        };
+        let cwd = repo.path().join("src/nested");
+        let check = |paths: &[&str]| {
+            let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+            check_sandbox(&sandbox, repo.path(), &cwd, &paths).unwrap()
+        };
+
+        let inside = repo.path().join("README.md");
+        let in_shared = shared.path().join("new/file.txt");
+        assert!(check(&[&inside.to_string_lossy(), &in_shared.to_string_lossy(), "../lib.rs", "/dev/null"]).is_none());
+
+        let escaped = check(&["../../../elsewhere"]).expect("parent of the repo is outside");
+        assert_eq!(escaped.severity, Severity::Ask);
+        assert!(escaped.reason.contains(&*repo.path().to_string_lossy()));
+        assert!(check(&[&outside.path().to_string_lossy()]).is_some());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.path(), repo.path().join("link")).unwrap();
+            assert!(check(&[&repo.path().join("link/secret").to_string_lossy()]).is_some());
+        }
+
+        let disabled = Sandbox::default();
+        assert!(check_sandbox(&disabled, repo.path(), &cwd, &["/etc/passwd".to_string()]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_relative_roots_follow_the_project() {
+        let repo = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(repo.path().join("src/nested")).unwrap();
+        let sandbox = Sandbox { allowed_roots: vec!["src".to_string()], severity: Severity::Block, ignore_doc_edits: false };
+        let cwd = repo.path().join("src/nested");
+        let check = |path: &str| check_sandbox(&sandbox, repo.path(), &cwd, &[path.to_string()]).unwrap();
+
+        // `src` is the project's src, not src/nested/src, while paths follow the tool's directory
+        assert!(check("lib.rs").is_none());
+        assert!(check("../main.rs").is_none());
+        assert!(check("../../README.md").is_some());
+    }
+}
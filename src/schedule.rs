@@ -0,0 +1,203 @@
+//! Weekly schedules for `when = { cron = "..." }` rule conditions
+//!
+//! Some commands are fine at one time and not at another: deploys during
+//! business hours only, or no migrations on a Friday afternoon. A schedule
+//! names days of the week and a time of day, such as `Mon-Fri 09:00-17:00`,
+//! and is read in the `[time] timezone`, or the system's local time zone.
+
+use crate::types::{AdvisorError, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A parsed schedule: any of its windows, or none of them when inverted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    windows: Vec<Window>,
+    inverted: bool,
+}
+
+/// Days of the week, and a time of day on them.
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    /// Indexed by `Weekday::num_days_from_monday`
+    days: [bool; 7],
+    /// Start and end in minutes since midnight; the end is exclusive and
+    /// before the start when the window runs past midnight
+    minutes: (u32, u32),
+}
+
+impl Schedule {
+    /// Parses a schedule such as `Mon-Fri 09:00-17:00`.
+    ///
+    /// Each window is a list of days or day ranges (`Mon-Fri`, `Sat,Sun`),
+    /// a time range (`09:00-17:00`), or both; a missing part means every day
+    /// or the whole day. Windows are separated by `;`, and a leading `!`
+    /// turns the schedule into the times outside it.
+    ///
+    /// # Returns
+    /// * `Ok(Schedule)` - The schedule
+    /// * `Err` - If a day or time can't be read
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = |reason: String| AdvisorError::Rule(format!("Invalid cron condition '{spec}': {reason}"));
+        let (inverted, rest) = match spec.trim().strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, spec),
+        };
+        let windows = rest.split(';').map(|window| parse_window(window.trim()).map_err(invalid)).collect::<Result<Vec<_>>>()?;
+        Ok(Schedule { windows, inverted })
+    }
+
+    /// Whether `time`, a local date and time, is in the schedule.
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        self.windows.iter().any(|window| window.contains(time)) != self.inverted
+    }
+}
+
+impl Window {
+    fn contains(&self, time: NaiveDateTime) -> bool {
+        let minute = time.hour() * 60 + time.minute();
+        let day = |weekday: Weekday| self.days[weekday.num_days_from_monday() as usize];
+        let (start, end) = self.minutes;
+        if start < end {
+            day(time.weekday()) && start <= minute && minute < end
+        } else {
+            // Past midnight, the window still belongs to the day it started on
+            (day(time.weekday()) && minute >= start) || (day(time.weekday().pred()) && minute < end)
+        }
+    }
+}
+
+fn parse_window(window: &str) -> std::result::Result<Window, String> {
+    let mut days = [true; 7];
+    let mut minutes = (0, MINUTES_PER_DAY);
+    let parts: Vec<&str> = window.split_whitespace().collect();
+    if parts.is_empty() || parts.len() > 2 {
+        return Err(format!("expected days, a time range or both, found '{window}'"));
+    }
+    for part in parts {
+        if part.contains(':') {
+            minutes = parse_times(part)?;
+        } else {
+            days = parse_days(part)?;
+        }
+    }
+    Ok(Window { days, minutes })
+}
+
+fn parse_days(spec: &str) -> std::result::Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    for item in spec.split(',') {
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+            None => (parse_day(item)?, parse_day(item)?),
+        };
+        // Ranges may wrap around the week, as in Fri-Mon
+        let mut day = first;
+        loop {
+            days[day.num_days_from_monday() as usize] = true;
+            if day == last {
+                break;
+            }
+            day = day.succ();
+        }
+    }
+    Ok(days)
+}
+
+fn parse_day(day: &str) -> std::result::Result<Weekday, String> {
+    day.parse().map_err(|_| format!("unknown day '{day}'"))
+}
+
+fn parse_times(spec: &str) -> std::result::Result<(u32, u32), String> {
+    let (start, end) = spec.split_once('-').ok_or_else(|| format!("expected a time range such as 09:00-17:00, found '{spec}'"))?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start == end {
+        return Err(format!("the time range '{spec}' is empty"));
+    }
+    Ok((start % MINUTES_PER_DAY, end))
+}
+
+/// Minutes since midnight of `HH:MM`; `24:00` is the end of the day.
+fn parse_time(time: &str) -> std::result::Result<u32, String> {
+    let parsed = time
+        .split_once(':')
+        .and_then(|(hours, minutes)| Some((hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?)))
+        .filter(|(hours, minutes)| *minutes < 60 && (*hours < 24 || (*hours == 24 && *minutes == 0)));
+    parsed.map(|(hours, minutes)| hours * 60 + minutes).ok_or_else(|| format!("invalid time '{time}'"))
+}
+
+/// `now` as a local date and time in `timezone`, an IANA name such as
+/// `Europe/Berlin`, or in the system's time zone without one.
+///
+/// # Returns
+/// * `Ok(NaiveDateTime)` - The local date and time
+/// * `Err` - If the time zone is unknown
+pub fn local_time(timezone: Option<&str>, now: DateTime<Utc>) -> Result<NaiveDateTime> {
+    match timezone {
+        Some(name) => {
+            let tz: Tz = name
+                .parse()
+                .map_err(|_| AdvisorError::Rule(format!("Unknown [time] timezone '{name}'; use an IANA name such as Europe/Berlin")))?;
+            Ok(now.with_timezone(&tz).naive_local())
+        }
+        None => Ok(now.with_timezone(&Local).naive_local()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// 2025-06-16 is a Monday.
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 6, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_business_hours() {
+        let schedule = Schedule::parse("Mon-Fri 09:00-17:00").unwrap();
+        assert!(schedule.contains(at(16, 9, 0)));
+        assert!(schedule.contains(at(20, 16, 59)));
+        assert!(!schedule.contains(at(20, 17, 0)));
+        assert!(!schedule.contains(at(16, 8, 59)));
+        assert!(!schedule.contains(at(21, 12, 0)));
+
+        let outside = Schedule::parse("!Mon-Fri 09:00-17:00").unwrap();
+        assert!(outside.contains(at(21, 12, 0)));
+        assert!(!outside.contains(at(18, 12, 0)));
+    }
+
+    #[test]
+    fn test_days_times_and_windows() {
+        let weekend = Schedule::parse("Sat,Sun").unwrap();
+        assert!(weekend.contains(at(22, 23, 59)));
+        assert!(!weekend.contains(at(23, 0, 0)));
+
+        // Ranges wrap around the week, and windows past midnight belong to the day they start
+        let nights = Schedule::parse("Fri-Mon 22:00-06:00").unwrap();
+        assert!(nights.contains(at(20, 23, 0)));
+        assert!(nights.contains(at(17, 5, 59)));
+        assert!(!nights.contains(at(18, 5, 0)));
+        assert!(!nights.contains(at(17, 22, 0)));
+
+        let friday_afternoon = Schedule::parse("Fri 12:00-24:00; Sat").unwrap();
+        assert!(friday_afternoon.contains(at(20, 23, 59)));
+        assert!(friday_afternoon.contains(at(21, 1, 0)));
+        assert!(Schedule::parse("monday 00:00-01:00").unwrap().contains(at(16, 0, 30)));
+
+        for invalid in ["Mon-Fry", "09:00", "09:00-25:00", "9-17", "10:00-10:00", "Mon 09:00-10:00 extra", ""] {
+            assert!(Schedule::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_local_time() {
+        let now = DateTime::parse_from_rfc3339("2025-01-10T08:30:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(local_time(Some("Europe/Berlin"), now).unwrap(), at(10, 9, 30).with_month(1).unwrap());
+        assert_eq!(local_time(Some("UTC"), now).unwrap().hour(), 8);
+        assert!(local_time(Some("Mars/Olympus"), now).is_err());
+    }
+}
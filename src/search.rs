@@ -0,0 +1,88 @@
+//! Project defaults for the Grep tool
+//!
+//! Claude's Grep calls often search more than the project wants: build
+//! output, vendored code, or every case variant of a name. `[search]` lists
+//! parameters to add to Grep calls that leave them unset, such as a glob
+//! excluding `target/`. The PreToolUse hook returns the call with them added
+//! as `updatedInput`. Parameters Claude set itself are never changed.
+
+use crate::directory::normalize_lexically;
+use crate::types::Search;
+use serde_json::Value;
+use std::path::Path;
+
+/// Adds the `[search]` defaults that a Grep call leaves unset.
+///
+/// # Arguments
+/// * `search` - The `[search]` table
+/// * `tool_input` - The Grep call's input, as sent
+///
+/// # Returns
+/// * `Some((Value, Vec<&str>))` - The input to run instead, and the parameters added
+/// * `None` - If every default is already set, or none is configured
+pub fn search_defaults(search: &Search, tool_input: &Value) -> Option<(Value, Vec<&'static str>)> {
+    let defaults: [(&'static str, Option<Value>); 4] = [
+        ("glob", search.glob.clone().map(Value::from)),
+        ("type", search.file_type.clone().map(Value::from)),
+        ("-i", search.case_insensitive.then_some(Value::Bool(true))),
+        ("head_limit", search.head_limit.map(Value::from)),
+    ];
+
+    let mut updated = tool_input.as_object()?.clone();
+    let mut added = Vec::new();
+    for (name, value) in defaults {
+        if let Some(value) = value.filter(|_| updated.get(name).is_none_or(Value::is_null)) {
+            updated.insert(name.to_string(), value);
+            added.push(name);
+        }
+    }
+    (!added.is_empty()).then_some((Value::Object(updated), added))
+}
+
+/// Whether a Grep call searches inside `project_dir`. Calls elsewhere are
+/// left alone, so a rewrite never approves a search Claude Code would have
+/// asked about.
+pub fn searches_project(project_dir: &Path, path: Option<&str>) -> bool {
+    let Some(path) = path else {
+        return true;
+    };
+    let project_dir = normalize_lexically(project_dir);
+    normalize_lexically(&project_dir.join(path)).starts_with(&project_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults_fill_unset_parameters() {
+        let search: Search = toml::from_str("glob = \"!{target,vendor}/**\"\ncase_insensitive = true\nhead_limit = 200\n").unwrap();
+
+        let (updated, added) = search_defaults(&search, &json!({ "pattern": "fn main", "output_mode": "content" })).unwrap();
+        assert_eq!(added, ["glob", "-i", "head_limit"]);
+        assert_eq!(
+            updated,
+            json!({ "pattern": "fn main", "output_mode": "content", "glob": "!{target,vendor}/**", "-i": true, "head_limit": 200 })
+        );
+
+        // Parameters Claude chose are kept
+        let (updated, added) = search_defaults(&search, &json!({ "pattern": "x", "glob": "*.rs", "-i": false })).unwrap();
+        assert_eq!(added, ["head_limit"]);
+        assert_eq!((updated["glob"].as_str(), updated["-i"].as_bool()), (Some("*.rs"), Some(false)));
+
+        let complete = json!({ "pattern": "x", "glob": "*.rs", "-i": false, "head_limit": 10 });
+        assert_eq!(search_defaults(&search, &complete), None);
+        assert_eq!(search_defaults(&Search::default(), &json!({ "pattern": "x" })), None);
+    }
+
+    #[test]
+    fn test_searches_project() {
+        let project = Path::new("/work/app");
+        assert!(searches_project(project, None));
+        assert!(searches_project(project, Some("src")));
+        assert!(searches_project(project, Some("/work/app/src/../tests")));
+        assert!(!searches_project(project, Some("../other")));
+        assert!(!searches_project(project, Some("/etc")));
+    }
+}
@@ -0,0 +1,323 @@
+//! HTTP policy service
+//!
+//! `--serve <ADDR>` answers hook inputs over HTTP, so Claude Code running in
+//! containers or VMs can use one policy kept on the host. `POST /evaluate`
+//! takes a hook input as its body and returns the JSON response the hook
+//! would give for it, as `--hook --stream` does. `GET /health` reports that
+//! the service is up.
+//!
+//! The service has no authentication: anyone who can reach it can evaluate
+//! commands and write session state under the server's checkout, keyed by a
+//! `session_id` they choose. It therefore only listens on a loopback address
+//! unless `--allow-remote` is given.
+//!
+//! Connections are served by a fixed pool of worker threads. Each request
+//! has to arrive in full within `REQUEST_TIMEOUT`, with at most
+//! `MAX_HEADER_BYTES` of request line and headers, so slow or oversized
+//! clients can't hold up the others. Every request is evaluated with the
+//! server's configuration. The server's working directory stands in for the `cwd` of every request, so
+//! session state, the audit log and the sandbox refer to the host checkout
+//! rather than to paths that only exist inside a container.
+
+use crate::hooks::evaluate_event;
+use crate::types::{AdvisorError, IoContext, Result};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Largest request body accepted; hook inputs are far smaller.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// Largest request line plus headers accepted.
+const MAX_HEADER_BYTES: u64 = 8 << 10;
+
+/// Time a client has to send its whole request, and to take the response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections served at the same time; further ones wait for a free worker.
+const WORKERS: usize = 16;
+
+/// An HTTP error status with the message sent back as `{"error": ...}`.
+type HttpError = (u16, String);
+
+/// A parsed HTTP request.
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Serves policy decisions over HTTP until the process is stopped.
+///
+/// # Arguments
+/// * `addr` - Address to listen on, such as `127.0.0.1:8787`
+/// * `config_path` - Path to the configuration file, as for `--hook`
+/// * `replace_mode` - If true, returns "replace" decision; if false, returns "block"
+/// * `allow_remote` - Accept an address other machines can reach
+///
+/// # Returns
+/// * `Err` - If the address can't be bound, or isn't a loopback address and
+///   `allow_remote` is off; failed requests are only reported
+pub fn serve(addr: &str, config_path: &str, replace_mode: bool, allow_remote: bool) -> Result<()> {
+    let listener = TcpListener::bind(addr).io_context(|| format!("Failed to listen on {addr}"))?;
+    let project_dir = std::env::current_dir().io_context(|| "Failed to read the working directory".to_string())?;
+    let local = listener.local_addr().io_context(|| format!("Failed to listen on {addr}"))?;
+    if !local.ip().is_loopback() {
+        if !allow_remote {
+            return Err(AdvisorError::other(
+                format!("Refusing to listen on {local}"),
+                "the service has no authentication; bind a loopback address such as 127.0.0.1, or pass --allow-remote",
+            ));
+        }
+        eprintln!("⚠️  Listening on {local} without authentication: anyone who can reach it can query the policy and write session state");
+    }
+    println!("🛰️  Serving policy decisions on http://{local}/evaluate");
+    println!("   Project directory: {}", project_dir.display());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(WORKERS)
+        .thread_name(|i| format!("serve-{i}"))
+        .build()
+        .map_err(|e| AdvisorError::other("Failed to start the worker threads", e))?;
+    let shared: Arc<(String, PathBuf)> = Arc::new((config_path.to_string(), project_dir));
+
+    for stream in listener.incoming() {
+        let stream = match stream.io_context(|| "Failed to accept a connection".to_string()) {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Warning: {e:#}");
+                continue;
+            }
+        };
+        let shared = Arc::clone(&shared);
+        pool.spawn(move || {
+            let (config_path, project_dir) = &*shared;
+            if let Err(e) = handle_connection(stream, config_path, replace_mode, project_dir) {
+                eprintln!("Warning: {e:#}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Reads one request from `stream`, answers it and closes the connection.
+fn handle_connection(mut stream: TcpStream, config_path: &str, replace_mode: bool, project_dir: &Path) -> Result<()> {
+    stream
+        .set_write_timeout(Some(REQUEST_TIMEOUT))
+        .io_context(|| "Failed to configure the connection".to_string())?;
+    let deadline = Deadline { stream: &stream, until: Instant::now() + REQUEST_TIMEOUT };
+    let request = read_request(&mut BufReader::new(deadline));
+    let (status, body) = match request.and_then(|request| route(&request, config_path, replace_mode, project_dir)) {
+        Ok(body) => (200, body),
+        Err((status, message)) => (status, json!({ "error": message })),
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason_phrase(status),
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).io_context(|| "Failed to send a response".to_string())
+}
+
+/// Answers a request.
+fn route(request: &Request, config_path: &str, replace_mode: bool, project_dir: &Path) -> std::result::Result<Value, HttpError> {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/evaluate") => evaluate(&request.body, config_path, replace_mode, project_dir),
+        ("GET", "/health") => Ok(json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") })),
+        (_, "/evaluate" | "/health") => Err((405, format!("{} is not supported on {}", request.method, request.path))),
+        _ => Err((404, format!("No such endpoint: {}", request.path))),
+    }
+}
+
+/// Evaluates a hook input, with the server's project directory as its `cwd`.
+fn evaluate(body: &[u8], config_path: &str, replace_mode: bool, project_dir: &Path) -> std::result::Result<Value, HttpError> {
+    let mut input: Value = serde_json::from_slice(body).map_err(|e| (400, format!("Body is not JSON: {e}")))?;
+    let Some(object) = input.as_object_mut() else {
+        return Err((400, "Body must be a hook input object".to_string()));
+    };
+    object.insert("cwd".to_string(), Value::String(project_dir.display().to_string()));
+
+    evaluate_event(&input.to_string(), config_path, replace_mode, true).map_err(|e| match e {
+        AdvisorError::Protocol(_) => (400, e.to_string()),
+        _ => (500, e.to_string()),
+    })
+}
+
+/// A connection whose reads fail once a single deadline for the whole
+/// request has passed, however the client spaces out its bytes.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Reads the request line, headers and body of an HTTP/1.1 request.
+fn read_request(reader: &mut impl BufRead) -> std::result::Result<Request, HttpError> {
+    let mut head = reader.by_ref().take(MAX_HEADER_BYTES);
+    let mut line = String::new();
+    read_head_line(&mut head, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err((400, "Malformed request line".to_string()));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        read_head_line(&mut head, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| (400, "Invalid Content-Length".to_string()))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err((413, format!("Request bodies are limited to {MAX_BODY_BYTES} bytes")));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| read_error("Failed to read the request body", e))?;
+    Ok(Request { method, path, body })
+}
+
+/// Reads one line of the request line and headers, which share one size limit.
+fn read_head_line(head: &mut io::Take<impl BufRead>, line: &mut String) -> std::result::Result<(), HttpError> {
+    head.read_line(line).map_err(|e| read_error("Failed to read the request", e))?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return Err((431, format!("Request headers are limited to {MAX_HEADER_BYTES} bytes")));
+    }
+    Ok(())
+}
+
+fn read_error(context: &str, error: io::Error) -> HttpError {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => (408, format!("{context}: timed out")),
+        _ => (400, format!("{context}: {error}")),
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    /// Sends `request` to a server handling one connection and returns the raw response.
+    fn exchange(request: &str, config_path: &str, project_dir: &Path) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, config_path, false, project_dir).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn post(body: &str) -> String {
+        format!("POST /evaluate HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}", body.len())
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /evaluate HTTP/1.1\r\ncontent-length: 2\r\nHost: x\r\n\r\n{}extra";
+        let request = read_request(&mut Cursor::new(raw)).unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/evaluate"));
+        assert_eq!(request.body, b"{}");
+
+        let too_large = format!("POST /evaluate HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        assert_eq!(read_request(&mut Cursor::new(too_large)).unwrap_err().0, 413);
+        assert_eq!(read_request(&mut Cursor::new("\r\n")).unwrap_err().0, 400);
+
+        let endless_header = format!("GET /health HTTP/1.1\r\nX-Filler: {}", "a".repeat(MAX_HEADER_BYTES as usize));
+        assert_eq!(read_request(&mut Cursor::new(endless_header)).unwrap_err().0, 431);
+        let many_headers = format!("GET /health HTTP/1.1\r\n{}\r\n", "X-Filler: a\r\n".repeat(1000));
+        assert_eq!(read_request(&mut Cursor::new(many_headers)).unwrap_err().0, 431);
+    }
+
+    #[test]
+    fn test_request_deadline_covers_the_whole_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        // A client trickling bytes never lets a single read time out
+        let trickle = std::thread::spawn(move || {
+            for byte in b"GET /health HTTP/1.1\r\nX-Slow: yes".iter() {
+                if client.write_all(&[*byte]).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let started = Instant::now();
+        let deadline = Deadline { stream: &stream, until: started + Duration::from_millis(200) };
+        assert_eq!(read_request(&mut BufReader::new(deadline)).unwrap_err().0, 408);
+        assert!(started.elapsed() < Duration::from_millis(600));
+        drop(stream);
+        trickle.join().unwrap();
+    }
+
+    #[test]
+    fn test_refuses_non_loopback_addresses() {
+        let error = serve("0.0.0.0:0", "policy.toml", false, false).unwrap_err();
+        assert!(error.to_string().contains("--allow-remote"), "{error}");
+    }
+
+    #[test]
+    fn test_evaluate_over_http() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("policy.toml");
+        fs::write(&config, "[commands]\nnpm = \"bun\"\n").unwrap();
+        let config = config.to_str().unwrap();
+
+        let input = r#"{"session_id":"c1","cwd":"/workspace","hook_event_name":"PreToolUse","tool_name":"Bash","tool_input":{"command":"npm ci"}}"#;
+        let response = exchange(&post(input), config, dir.path());
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["decision"], "block");
+        // Session state goes to the server's project directory, not the container's cwd
+        assert!(dir.path().join(".claude/advisor/state").exists());
+
+        assert!(exchange(&post("not json"), config, dir.path()).starts_with("HTTP/1.1 400"));
+        assert!(exchange("GET /evaluate HTTP/1.1\r\n\r\n", config, dir.path()).starts_with("HTTP/1.1 405"));
+        assert!(exchange("GET /nope HTTP/1.1\r\n\r\n", config, dir.path()).starts_with("HTTP/1.1 404"));
+        assert!(exchange("GET /health HTTP/1.1\r\n\r\n", config, dir.path()).contains(r#""status":"ok""#));
+    }
+}
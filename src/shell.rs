@@ -0,0 +1,583 @@
+//! Shell command parsing and normalization
+
+use std::ops::Range;
+
+/// Wrapper commands that run another command and are stripped before matching.
+const WRAPPER_COMMANDS: &[&str] = &[
+    "sudo", "env", "nice", "nohup", "time", "xargs", "command", "exec",
+];
+
+/// Shell reserved words that may precede a command in a segment.
+const COMMAND_KEYWORDS: &[&str] = &["if", "then", "else", "elif", "while", "until", "do", "!", "{", "("];
+
+/// A simple command with its wrappers stripped off.
+///
+/// Offsets are byte positions into the segment the command was normalized from,
+/// so the underlying command can be rewritten in place while the wrapper
+/// prefix is preserved verbatim.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedCommand {
+    /// Wrapper commands that were stripped, in order of appearance
+    pub wrappers: Vec<String>,
+    /// Byte offset where the underlying command starts
+    pub command_start: usize,
+}
+
+/// Splits a command line into the byte ranges of its simple commands.
+///
+/// Segments are separated by `;`, `&&`, `||`, `|`, `&` and newlines. Separators
+/// inside single or double quotes, backslash escapes, `$(...)` and backtick
+/// substitutions are ignored, as are `#` comments and heredoc bodies, so text such
+/// as `echo "a; npm install"` or a `sed` program never produces a segment of its
+/// own. A backslash-newline continues the command on the next line, and a
+/// substitution may span lines and contain heredocs of its own. Leading and
+/// trailing whitespace and line continuations are trimmed from each range and
+/// empty segments are dropped.
+pub fn split_segments(command: &str) -> Vec<Range<usize>> {
+    let bytes = command.as_bytes();
+    let mut segments = Vec::new();
+    let mut pending_heredocs: Vec<Heredoc> = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += continuation_len(&bytes[i..]).max(2),
+            b'\'' | b'"' | b'`' => i = skip_quoted(command, i),
+            b'$' if bytes.get(i + 1) == Some(&b'(') => i = skip_substitution(command, i + 1),
+            b'#' if i == 0 || bytes[i - 1].is_ascii_whitespace() => {
+                push_trimmed(command, start..i, &mut segments);
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                start = i;
+            }
+            b'<' if bytes.get(i + 1) == Some(&b'<') && bytes.get(i + 2) != Some(&b'<') => {
+                let (heredoc, next) = parse_heredoc_operator(command, i + 2);
+                pending_heredocs.extend(heredoc);
+                i = next;
+            }
+            b'\n' => {
+                push_trimmed(command, start..i, &mut segments);
+                i += 1;
+                for heredoc in pending_heredocs.drain(..) {
+                    i = skip_heredoc_body(command, i, &heredoc);
+                }
+                start = i;
+            }
+            // Redirections such as `2>&1`, `<&3` and `&>log` are not separators
+            b'&' if i > 0 && matches!(bytes[i - 1], b'>' | b'<') => i += 1,
+            b'&' if bytes.get(i + 1) == Some(&b'>') => i += 2,
+            b';' | b'&' | b'|' => {
+                push_trimmed(command, start..i, &mut segments);
+                i += if bytes.get(i + 1) == Some(&bytes[i]) && bytes[i] != b';' { 2 } else { 1 };
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    push_trimmed(command, start.min(command.len())..command.len(), &mut segments);
+
+    segments
+}
+
+/// A heredoc whose body starts after the next newline.
+struct Heredoc {
+    delimiter: String,
+    strip_tabs: bool,
+}
+
+/// Parses the delimiter following a `<<` or `<<-` operator.
+///
+/// Returns the heredoc (if a delimiter was found) and the index just past it.
+fn parse_heredoc_operator(command: &str, mut i: usize) -> (Option<Heredoc>, usize) {
+    let bytes = command.as_bytes();
+    let strip_tabs = bytes.get(i) == Some(&b'-');
+    if strip_tabs {
+        i += 1;
+    }
+    while i < bytes.len() && (bytes[i] == b' ' || bytes[i] == b'\t') {
+        i += 1;
+    }
+
+    let word_start = i;
+    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b";&|<>()".contains(&bytes[i]) {
+        i = if bytes[i] == b'\'' || bytes[i] == b'"' { skip_quoted(command, i) } else { i + 1 };
+    }
+
+    let delimiter: String = command[word_start..i.min(command.len())]
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '"' | '\\'))
+        .collect();
+
+    if delimiter.is_empty() {
+        (None, i)
+    } else {
+        (Some(Heredoc { delimiter, strip_tabs }), i)
+    }
+}
+
+/// Skips a heredoc body starting at `i`, returning the index after its terminator line.
+fn skip_heredoc_body(command: &str, mut i: usize, heredoc: &Heredoc) -> usize {
+    while i < command.len() {
+        let line_end = command[i..].find('\n').map(|n| i + n).unwrap_or(command.len());
+        let line = &command[i..line_end];
+        let line = if heredoc.strip_tabs { line.trim_start_matches('\t') } else { line };
+        i = (line_end + 1).min(command.len());
+        if line == heredoc.delimiter {
+            break;
+        }
+    }
+    i
+}
+
+/// Skips a quoted string or backtick substitution starting at `i`.
+///
+/// A `$(...)` inside double quotes is skipped as a whole, since its own quotes
+/// don't end the string: `"$(cat <<'EOF' ... EOF)"` is one word even when the
+/// heredoc quotes something.
+///
+/// Returns the index just past the closing quote, or the end of input if the
+/// quote is unterminated.
+fn skip_quoted(command: &str, i: usize) -> usize {
+    let bytes = command.as_bytes();
+    let quote = bytes[i];
+    let mut j = i + 1;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' if quote != b'\'' => j += 2,
+            b'$' if quote == b'"' && bytes.get(j + 1) == Some(&b'(') => j = skip_substitution(command, j + 1),
+            b if b == quote => return j + 1,
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Skips a `$(...)` substitution whose opening parenthesis is at `i`.
+///
+/// Nested parentheses, quotes, `#` comments and heredoc bodies are tracked so
+/// the matching `)` is found; a `)` or an apostrophe in a heredoc or comment
+/// doesn't end the substitution early.
+fn skip_substitution(command: &str, i: usize) -> usize {
+    let bytes = command.as_bytes();
+    let mut pending_heredocs: Vec<Heredoc> = Vec::new();
+    let mut depth = 0;
+    let mut j = i;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' => j += 2,
+            b'\'' | b'"' | b'`' => j = skip_quoted(command, j),
+            b'#' if bytes[j - 1].is_ascii_whitespace() => {
+                while j < bytes.len() && bytes[j] != b'\n' {
+                    j += 1;
+                }
+            }
+            b'<' if bytes.get(j + 1) == Some(&b'<') && bytes.get(j + 2) != Some(&b'<') => {
+                let (heredoc, next) = parse_heredoc_operator(command, j + 2);
+                pending_heredocs.extend(heredoc);
+                j = next;
+            }
+            b'\n' => {
+                j += 1;
+                for heredoc in pending_heredocs.drain(..) {
+                    j = skip_heredoc_body(command, j, &heredoc);
+                }
+            }
+            b'(' => {
+                depth += 1;
+                j += 1;
+            }
+            b')' => {
+                depth -= 1;
+                j += 1;
+                if depth == 0 {
+                    return j;
+                }
+            }
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Finds the command substitutions in a simple command.
+///
+/// Both `$(...)` and backtick forms are found, bare or inside double quotes;
+/// single-quoted text is literal. Substitutions nested inside another are
+/// not returned, since they are part of the outer one's command line, and
+/// `$((...))` arithmetic is not a command.
+///
+/// # Arguments
+/// * `segment` - A single simple command, as produced by `split_segments`
+///
+/// # Returns
+/// The byte ranges of each substitution's command line, without the `$(`,
+/// backtick or closing delimiter
+pub fn substitutions(segment: &str) -> Vec<Range<usize>> {
+    let bytes = segment.as_bytes();
+    let mut found = Vec::new();
+    let mut in_double_quotes = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\'' if !in_double_quotes => i = skip_quoted(segment, i),
+            b'"' => {
+                in_double_quotes = !in_double_quotes;
+                i += 1;
+            }
+            b'`' => {
+                let end = skip_quoted(segment, i);
+                let closed = bytes[end - 1] == b'`' && end > i + 1;
+                found.push(i + 1..if closed { end - 1 } else { end });
+                i = end;
+            }
+            b'$' if bytes.get(i + 1) == Some(&b'(') && bytes.get(i + 2) != Some(&b'(') => {
+                let end = skip_substitution(segment, i + 1);
+                let closed = bytes[end - 1] == b')';
+                found.push(i + 2..if closed { end - 1 } else { end });
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    found
+}
+
+/// Splits a command line into its simple commands, including those inside
+/// `$(...)` and backtick substitutions at any depth.
+///
+/// Policies that must not be sidestepped by hiding a command in a
+/// substitution, such as `echo $(curl ...)`, walk these rather than
+/// `split_segments`.
+///
+/// # Returns
+/// The byte ranges of each simple command in `command`, each followed by
+/// those of the substitutions inside it
+pub fn nested_segments(command: &str) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    collect_nested(command, 0..command.len(), &mut segments);
+    segments
+}
+
+fn collect_nested(command: &str, range: Range<usize>, segments: &mut Vec<Range<usize>>) {
+    let text = &command[range.clone()];
+    for segment in split_segments(text) {
+        let start = range.start + segment.start;
+        segments.push(start..range.start + segment.end);
+        for inner in substitutions(&text[segment]) {
+            collect_nested(command, start + inner.start..start + inner.end, segments);
+        }
+    }
+}
+
+/// Strips common wrapper commands (sudo, env, nice, xargs, ...), leading
+/// `VAR=value` assignments and shell keywords such as `if` or `do` from a
+/// simple command.
+///
+/// # Arguments
+/// * `segment` - A single simple command, as produced by `split_segments`
+///
+/// # Returns
+/// * `NormalizedCommand` - The stripped wrappers and where the real command begins
+pub fn normalize(segment: &str) -> NormalizedCommand {
+    let tokens = tokenize(segment);
+    let mut wrappers = Vec::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let word = &segment[tokens[index].clone()];
+
+        if is_assignment(word) || COMMAND_KEYWORDS.contains(&word) {
+            index += 1;
+            continue;
+        }
+
+        if !WRAPPER_COMMANDS.contains(&word) {
+            break;
+        }
+
+        wrappers.push(word.to_string());
+        index = skip_wrapper_options(segment, &tokens, index + 1, word);
+    }
+
+    // A subshell or group opener may be attached to the command, as in `(cd app`
+    let command_start = tokens
+        .get(index)
+        .map(|range| {
+            let word = &segment[range.clone()];
+            range.start + word.len() - word.trim_start_matches(['(', '{']).len()
+        })
+        .unwrap_or(segment.len());
+
+    NormalizedCommand { wrappers, command_start }
+}
+
+/// Skips the options (and their arguments) of a wrapper command.
+///
+/// Returns the token index of the first word after the wrapper's options.
+fn skip_wrapper_options(segment: &str, tokens: &[Range<usize>], mut index: usize, wrapper: &str) -> usize {
+    // Options that consume the following word as their argument
+    let options_with_argument: &[&str] = match wrapper {
+        "sudo" => &["-u", "-g", "-C", "-D", "-h", "-p", "-r", "-t", "-U"],
+        "env" => &["-u", "-C", "-S"],
+        "nice" => &["-n"],
+        "xargs" => &["-n", "-I", "-L", "-P", "-d", "-E", "-s", "-a"],
+        _ => &[],
+    };
+
+    while index < tokens.len() {
+        let word = &segment[tokens[index].clone()];
+
+        if word == "--" {
+            return index + 1;
+        }
+
+        if wrapper == "env" && is_assignment(word) {
+            index += 1;
+            continue;
+        }
+
+        if !word.starts_with('-') || word == "-" {
+            break;
+        }
+
+        index += if options_with_argument.contains(&word) { 2 } else { 1 };
+    }
+
+    index
+}
+
+/// Returns true for shell variable assignments such as `FOO=1`.
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// Splits a simple command into whitespace-separated word ranges.
+///
+/// Quoted strings and substitutions are kept within a single word, and line
+/// continuations separate words like any other whitespace.
+pub fn tokenize(segment: &str) -> Vec<Range<usize>> {
+    let bytes = segment.as_bytes();
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let separator = match continuation_len(&bytes[i..]) {
+            0 => usize::from(bytes[i].is_ascii_whitespace()),
+            len => len,
+        };
+        if separator > 0 {
+            if let Some(s) = start.take() {
+                tokens.push(s..i);
+            }
+            i += separator;
+            continue;
+        }
+
+        start.get_or_insert(i);
+        i = match bytes[i] {
+            b'\\' => i + 2,
+            b'\'' | b'"' | b'`' => skip_quoted(segment, i),
+            b'$' if bytes.get(i + 1) == Some(&b'(') => skip_substitution(segment, i + 1),
+            _ => i + 1,
+        }
+        .min(bytes.len());
+    }
+    if let Some(s) = start {
+        tokens.push(s..segment.len());
+    }
+
+    tokens
+}
+
+/// Length of the line continuation (a backslash before a newline) at the
+/// start of `bytes`, or 0 if there is none.
+fn continuation_len(bytes: &[u8]) -> usize {
+    match bytes {
+        [b'\\', b'\n', ..] => 2,
+        [b'\\', b'\r', b'\n', ..] => 3,
+        _ => 0,
+    }
+}
+
+/// Pushes a range onto `segments` after trimming surrounding whitespace and
+/// line continuations.
+fn push_trimmed(command: &str, range: Range<usize>, segments: &mut Vec<Range<usize>>) {
+    let bytes = command.as_bytes();
+    let (mut start, mut end) = (range.start, range.end);
+    loop {
+        start += command[start..end].len() - command[start..end].trim_start().len();
+        match continuation_len(&bytes[start..end]) {
+            0 => break,
+            len => start += len,
+        }
+    }
+    loop {
+        end = start + command[start..end].trim_end().len();
+        if end > start && continuation_len(&bytes[end - 1..range.end]) > 0 {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+    if start < end {
+        segments.push(start..end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(command: &str) -> Vec<&str> {
+        split_segments(command).into_iter().map(|r| &command[r]).collect()
+    }
+
+    fn underlying(segment: &str) -> &str {
+        &segment[normalize(segment).command_start..]
+    }
+
+    #[test]
+    fn test_split_segments() {
+        assert_eq!(segments("npm install"), vec!["npm install"]);
+        assert_eq!(
+            segments("cd app && npm ci || echo failed; ls | wc -l"),
+            vec!["cd app", "npm ci", "echo failed", "ls", "wc -l"]
+        );
+        assert_eq!(segments("  ;; "), Vec::<&str>::new());
+        assert_eq!(segments("npm test 2>&1 | tee log"), vec!["npm test 2>&1", "tee log"]);
+        assert_eq!(segments("npm test &> log & wait"), vec!["npm test &> log", "wait"]);
+    }
+
+    #[test]
+    fn test_split_segments_ignores_quoted_separators() {
+        assert_eq!(segments(r#"echo "a; npm install""#), vec![r#"echo "a; npm install""#]);
+        assert_eq!(segments("echo 'x && npm ci' && ls"), vec!["echo 'x && npm ci'", "ls"]);
+        assert_eq!(segments(r"echo a\; npm"), vec![r"echo a\; npm"]);
+        assert_eq!(segments("echo $(cd x; npm view y) | cat"), vec!["echo $(cd x; npm view y)", "cat"]);
+        assert_eq!(segments("echo `cd x; npm view y`"), vec!["echo `cd x; npm view y`"]);
+        assert_eq!(segments("ls # npm install; yarn"), vec!["ls"]);
+    }
+
+    #[test]
+    fn test_split_segments_program_arguments() {
+        assert_eq!(segments("grep -rn 'npm; yarn' src"), vec!["grep -rn 'npm; yarn' src"]);
+        assert_eq!(segments(r#"sed -i 's/npm install; yarn/bun/' file"#), vec![r#"sed -i 's/npm install; yarn/bun/' file"#]);
+        assert_eq!(
+            segments(r#"awk '{ print "npm" }; END { print "yarn" }' log"#),
+            vec![r#"awk '{ print "npm" }; END { print "yarn" }' log"#]
+        );
+    }
+
+    #[test]
+    fn test_split_segments_skips_heredoc_bodies() {
+        let command = "cat <<EOF > notes.txt\nnpm install\nyarn build\nEOF\nls";
+        assert_eq!(segments(command), vec!["cat <<EOF > notes.txt", "ls"]);
+
+        let command = "cat <<-'END'\n\tnpm ci\n\tEND\necho done";
+        assert_eq!(segments(command), vec!["cat <<-'END'", "echo done"]);
+    }
+
+    #[test]
+    fn test_split_segments_multi_line_commands() {
+        // Heredocs on a line with other commands, and two heredocs on one line
+        let command = "cat <<EOF | npm install && ls\nyarn; rm -rf /\nEOF\necho done";
+        assert_eq!(segments(command), vec!["cat <<EOF", "npm install", "ls", "echo done"]);
+        let command = "diff <(cat) <<A 3<<B\nnpm\nA\nyarn\nB\nls";
+        assert_eq!(segments(command), vec!["diff <(cat) <<A 3<<B", "ls"]);
+        assert_eq!(segments("grep x <<< 'npm; yarn'"), vec!["grep x <<< 'npm; yarn'"]);
+
+        // Line continuations join lines, and are trimmed from the ends
+        assert_eq!(segments("npm install \\\n  --save-dev x && \\\n  ls"), vec!["npm install \\\n  --save-dev x", "ls"]);
+        assert_eq!(segments("npm ci \\\n"), vec!["npm ci"]);
+        assert_eq!(segments("npm ci \\\r\n  --silent"), vec!["npm ci \\\r\n  --silent"]);
+        assert_eq!(segments(r"echo a\\"), vec![r"echo a\\"]);
+
+        // A heredoc quoted inside a substitution, as in Claude's commit messages
+        let command = "git commit -m \"$(cat <<'EOF'\nDon't \"npm install\" (fixes #12)\nnpm publish; yarn\nEOF\n)\" && git push";
+        assert_eq!(segments(command), vec![&command[..command.len() - " && git push".len()], "git push"]);
+        let command = "VERSION=$(\n  # don't guess\n  cat <<EOF\n)\nEOF\n) npm publish";
+        assert_eq!(segments(command), vec![command]);
+    }
+
+    #[test]
+    fn test_tokenize_line_continuations() {
+        let words = |segment: &'static str| tokenize(segment).into_iter().map(|r| &segment[r]).collect::<Vec<_>>();
+        assert_eq!(words("npm \\\n  install \\\r\n x"), vec!["npm", "install", "x"]);
+        assert_eq!(words(r"echo a\ b"), vec![r"echo", r"a\ b"]);
+        assert_eq!(underlying("sudo \\\n  npm ci"), "npm ci");
+    }
+
+    #[test]
+    fn test_substitutions() {
+        let inner = |segment: &'static str| substitutions(segment).into_iter().map(|r| &segment[r]).collect::<Vec<_>>();
+        assert_eq!(inner("echo $(npm view x version)"), vec!["npm view x version"]);
+        assert_eq!(inner("echo `npm bin` \"$(yarn global dir)\""), vec!["npm bin", "yarn global dir"]);
+        assert_eq!(inner("echo $(cat $(npm root)/x)"), vec!["cat $(npm root)/x"]);
+        assert_eq!(inner("echo $(printf ')' ; npm ci)"), vec!["printf ')' ; npm ci"]);
+
+        // Literal text and arithmetic are not substitutions
+        assert!(inner("echo '$(npm ci)' \\$(x)").is_empty());
+        assert!(inner("echo $((1 + 2)) ${HOME}").is_empty());
+        // An unterminated substitution runs to the end
+        assert_eq!(inner("echo $(npm ci"), vec!["npm ci"]);
+    }
+
+    #[test]
+    fn test_nested_segments() {
+        let command = "echo $(curl -s a.io && echo `wget b.io`) | x=$(cat $(npm root)/y)";
+        let segments: Vec<&str> = nested_segments(command).into_iter().map(|r| &command[r]).collect();
+        assert_eq!(
+            segments,
+            vec![
+                "echo $(curl -s a.io && echo `wget b.io`)",
+                "curl -s a.io",
+                "echo `wget b.io`",
+                "wget b.io",
+                "x=$(cat $(npm root)/y)",
+                "cat $(npm root)/y",
+                "npm root",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_wrappers() {
+        assert_eq!(underlying("sudo npm install"), "npm install");
+        assert_eq!(underlying("sudo -u deploy -E npm install"), "npm install");
+        assert_eq!(underlying("env FOO=1 BAR=2 npm ci"), "npm ci");
+        assert_eq!(underlying("FOO=1 npm ci"), "npm ci");
+        assert_eq!(underlying("nice -n 10 cargo build"), "cargo build");
+        assert_eq!(underlying("xargs -n 1 npm install"), "npm install");
+        assert_eq!(underlying("sudo env FOO=1 nice npm test"), "npm test");
+        assert_eq!(underlying(r#"env MSG="a b" npm test"#), "npm test");
+        assert_eq!(underlying("if npm test"), "npm test");
+        assert_eq!(underlying("then sudo npm publish"), "npm publish");
+        assert_eq!(underlying("(cd app"), "cd app");
+
+        let normalized = normalize("sudo env FOO=1 npm test");
+        assert_eq!(normalized.wrappers, vec!["sudo", "env"]);
+    }
+
+    #[test]
+    fn test_normalize_plain_command() {
+        let normalized = normalize("npm install");
+        assert!(normalized.wrappers.is_empty());
+        assert_eq!(normalized.command_start, 0);
+
+        // A bare wrapper has no underlying command
+        assert_eq!(underlying("sudo"), "");
+    }
+}
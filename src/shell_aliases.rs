@@ -0,0 +1,174 @@
+//! Shell alias expansion
+//!
+//! A user whose shell defines `gco='git checkout'` may see Claude use `gco`
+//! too, and a rule about `git checkout` would never see it. The `[aliases]`
+//! table lists such aliases; the command word of each simple command is
+//! expanded with it before any policy is checked, so rules apply to the
+//! command that actually runs. `--import-aliases` fills the table from the
+//! output of the shell's `alias` builtin or from an rc file.
+
+use crate::shell::{normalize, split_segments};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Most aliases one command word is expanded through, as a guard against
+/// aliases defined in terms of each other.
+const MAX_EXPANSIONS: usize = 16;
+
+/// Expands the aliases at the start of each simple command in `command`.
+///
+/// As in the shell, an expansion starting with another alias is expanded
+/// again, but never with an alias already used for that word, so
+/// `ls = "ls --color"` is fine. Quoted or escaped words are left alone.
+/// Unlike the shell, aliases are also expanded after wrappers such as
+/// `sudo`, since the policy is about what runs.
+///
+/// # Arguments
+/// * `aliases` - The `[aliases]` table
+/// * `command` - The command line as sent
+///
+/// # Returns
+/// The command with aliases expanded; borrowed when nothing was expanded
+pub fn expand_aliases<'a>(aliases: &HashMap<String, String>, command: &'a str) -> Cow<'a, str> {
+    let mut expanded = Cow::Borrowed(command);
+    if aliases.is_empty() {
+        return expanded;
+    }
+
+    // Work from the last command back, so earlier byte ranges stay valid
+    for segment in split_segments(command).into_iter().rev() {
+        let start = segment.start + normalize(&command[segment.clone()]).command_start;
+        let end = command[start..segment.end].find(char::is_whitespace).map_or(segment.end, |i| start + i);
+        if let Some(expansion) = expand_word(aliases, &command[start..end]) {
+            expanded.to_mut().replace_range(start..end, &expansion);
+        }
+    }
+    expanded
+}
+
+/// The full expansion of a command word, or `None` if it isn't an alias.
+fn expand_word(aliases: &HashMap<String, String>, word: &str) -> Option<String> {
+    let mut expansion = aliases.get(word)?.trim_start().to_string();
+    let mut used = vec![word.to_string()];
+    while used.len() < MAX_EXPANSIONS {
+        let first = expansion.split_whitespace().next().unwrap_or_default();
+        let Some(next) = aliases.get(first).filter(|_| !used.iter().any(|alias| alias == first)) else {
+            break;
+        };
+        used.push(first.to_string());
+        expansion = format!("{}{}", next.trim_start(), &expansion[first.len()..]);
+    }
+    Some(expansion)
+}
+
+/// Reads alias definitions from shell text.
+///
+/// Understands lines of the form `alias name='value'`, as printed by the
+/// `alias` builtin of bash and by `alias -L` in zsh, and as written in rc
+/// files. Values may be single quoted (with `'\''` for a quote), double
+/// quoted or bare. Other lines, including aliases with options such as
+/// `alias -g`, are skipped.
+///
+/// # Returns
+/// The aliases in the order they appear; a later definition of a name wins
+pub fn parse_alias_definitions(text: &str) -> Vec<(String, String)> {
+    text.lines().filter_map(parse_alias_line).collect()
+}
+
+fn parse_alias_line(line: &str) -> Option<(String, String)> {
+    let definition = line.trim().strip_prefix("alias ")?.trim_start();
+    let (name, value) = definition.split_once('=')?;
+    let valid_name = !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_alphanumeric() || "_-.:+@%".contains(c));
+    if !valid_name {
+        return None;
+    }
+    let value = unquote(value)?;
+    (!value.trim().is_empty()).then(|| (name.to_string(), value))
+}
+
+/// The value of a shell word: quotes removed, escapes resolved, and anything
+/// after the word (such as a comment) dropped. `None` if a quote isn't closed.
+fn unquote(word: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => loop {
+                match chars.next()? {
+                    '\'' => break,
+                    c => value.push(c),
+                }
+            },
+            '"' => loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            },
+            '\\' => value.push(chars.next()?),
+            c if c.is_whitespace() => break,
+            c => value.push(c),
+        }
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let aliases = table(&[("gco", "git checkout"), ("gp", "git push"), ("ll", "ls -la"), ("ls", "ls --color"), ("g", "git")]);
+        let expand = |command| expand_aliases(&aliases, command).into_owned();
+
+        assert_eq!(expand("gco main"), "git checkout main");
+        assert_eq!(expand("gco -b x && gp --force"), "git checkout -b x && git push --force");
+        assert_eq!(expand("sudo gco main"), "sudo git checkout main");
+        assert_eq!(expand("cd app; FOO=1 gp"), "cd app; FOO=1 git push");
+        // Expansions are expanded again, but an alias never expands itself
+        assert_eq!(expand("ll src"), "ls --color -la src");
+        // Only command words are aliases
+        assert_eq!(expand("echo gco | grep g"), "echo gco | grep g");
+        assert_eq!(expand("'gco' main"), "'gco' main");
+        assert_eq!(expand("\\gco main"), "\\gco main");
+
+        assert!(matches!(expand_aliases(&aliases, "git status"), Cow::Borrowed(_)));
+        assert!(matches!(expand_aliases(&HashMap::new(), "gco main"), Cow::Borrowed("gco main")));
+
+        // Aliases defined in terms of each other stop expanding
+        assert_eq!(expand_aliases(&table(&[("a", "b"), ("b", "a")]), "a x"), "a x");
+    }
+
+    #[test]
+    fn test_parse_alias_definitions() {
+        let text = r#"
+# ~/.bashrc
+export PATH="$HOME/bin:$PATH"
+alias gco='git checkout'
+alias gst="git status -sb"  # short status
+alias k=kubectl
+alias say='echo '\''hi'\'''
+alias -g G='| grep'
+alias broken='never closed
+    alias gp='git push'
+"#;
+        assert_eq!(
+            parse_alias_definitions(text),
+            [
+                ("gco".to_string(), "git checkout".to_string()),
+                ("gst".to_string(), "git status -sb".to_string()),
+                ("k".to_string(), "kubectl".to_string()),
+                ("say".to_string(), "echo 'hi'".to_string()),
+                ("gp".to_string(), "git push".to_string()),
+            ]
+        );
+    }
+}
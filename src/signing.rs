@@ -0,0 +1,269 @@
+//! Detached signatures on policy files
+//!
+//! A policy file such as an organization baseline may carry a signature in a
+//! file next to it, named like the policy with `.minisig` appended. Signatures
+//! are checked against the trusted `public_keys` before the policy is
+//! applied. Both minisign signatures (`minisign -S`, including the default
+//! pre-hashed form, and `-l` legacy ones) and a bare base64 Ed25519 signature
+//! of the file are understood. With `require_signed = true`, a policy file
+//! without a valid signature is refused.
+
+use crate::types::{ConfigError, Result, Signatures};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use blake2::{Blake2b512, Digest};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to a policy file's name to find its signature.
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// An Ed25519 public key, with its minisign key id if it came from minisign.
+#[derive(Debug, PartialEq, Eq)]
+struct PublicKey {
+    id: Option<[u8; 8]>,
+    key: [u8; 32],
+}
+
+/// A detached signature of a policy file.
+#[derive(Debug)]
+struct Signature {
+    /// Whether the signature covers the BLAKE2b-512 hash of the file rather than the file
+    prehashed: bool,
+    id: Option<[u8; 8]>,
+    signature: [u8; 64],
+    /// Minisign's trusted comment and its signature, which also covers `signature`
+    trusted_comment: Option<(String, [u8; 64])>,
+}
+
+/// Where the signature of the policy file at `path` is kept.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{SIGNATURE_EXTENSION}"));
+    path.with_file_name(name)
+}
+
+/// Checks the detached signature of a policy file before it is applied.
+///
+/// # Arguments
+/// * `signatures` - The trusted keys and whether a signature is required
+/// * `path` - The policy file, used to find its signature
+/// * `content` - The policy file's content as read
+///
+/// # Returns
+/// * `Ok(true)` - If the signature is valid for one of the trusted keys
+/// * `Ok(false)` - If the file has no signature and none is required
+/// * `Err` - If the signature is invalid, made with an untrusted key, or
+///   missing while `require_signed` is on
+pub fn verify_policy_file(signatures: &Signatures, path: &Path, content: &[u8]) -> Result<bool> {
+    let signature_path = signature_path(path);
+    let fail = |reason: String| ConfigError::InvalidSignature(format!("{}: {reason}", path.display()));
+
+    let text = match std::fs::read_to_string(&signature_path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if signatures.require_signed {
+                return Err(fail(format!("unsigned, but require_signed is on (expected {})", signature_path.display())).into());
+            }
+            return Ok(false);
+        }
+        Err(e) => return Err(fail(format!("can't read {}: {e}", signature_path.display())).into()),
+    };
+
+    let keys = signatures
+        .public_keys
+        .iter()
+        .map(|key| parse_public_key(key))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| fail(format!("invalid entry in the trusted public_keys: {e}")))?;
+    let signature = parse_signature(&text).map_err(|e| fail(format!("{}: {e}", signature_path.display())))?;
+    verify(content, &signature, &keys).map_err(fail)?;
+    Ok(true)
+}
+
+fn decode(text: &str) -> std::result::Result<Vec<u8>, String> {
+    STANDARD.decode(text.trim()).map_err(|e| format!("not valid base64 ({e})"))
+}
+
+/// Parses a minisign public key (the `RW...` line, or the whole `.pub` file)
+/// or a bare base64 Ed25519 key.
+fn parse_public_key(text: &str) -> std::result::Result<PublicKey, String> {
+    let encoded = text
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .unwrap_or_default();
+    let bytes = decode(encoded)?;
+    match bytes.len() {
+        42 if bytes.starts_with(b"Ed") => Ok(PublicKey {
+            id: Some(bytes[2..10].try_into().unwrap()),
+            key: bytes[10..].try_into().unwrap(),
+        }),
+        32 => Ok(PublicKey { id: None, key: bytes.try_into().unwrap() }),
+        _ => Err(format!("'{encoded}' is not a minisign or Ed25519 public key")),
+    }
+}
+
+/// Parses a minisign signature file or a bare base64 Ed25519 signature.
+fn parse_signature(text: &str) -> std::result::Result<Signature, String> {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    if !lines.first().is_some_and(|line| line.starts_with("untrusted comment:")) {
+        let [encoded] = lines[..] else {
+            return Err("expected a minisign signature or one line of base64".to_string());
+        };
+        let signature = decode(encoded)?.try_into().map_err(|_| "an Ed25519 signature is 64 bytes".to_string())?;
+        return Ok(Signature { prehashed: false, id: None, signature, trusted_comment: None });
+    }
+
+    let [_, encoded, comment, global] = lines[..] else {
+        return Err("incomplete minisign signature".to_string());
+    };
+    let bytes = decode(encoded)?;
+    if bytes.len() != 74 {
+        return Err("minisign signature has the wrong length".to_string());
+    }
+    let prehashed = match &bytes[..2] {
+        b"Ed" => false,
+        b"ED" => true,
+        _ => return Err("unsupported minisign signature algorithm".to_string()),
+    };
+    let comment = comment.strip_prefix("trusted comment: ").ok_or("missing trusted comment")?;
+    let global = decode(global)?.try_into().map_err(|_| "trusted comment signature is not 64 bytes".to_string())?;
+    Ok(Signature {
+        prehashed,
+        id: Some(bytes[2..10].try_into().unwrap()),
+        signature: bytes[10..].try_into().unwrap(),
+        trusted_comment: Some((comment.to_string(), global)),
+    })
+}
+
+/// How minisign prints a key id.
+fn key_id(id: &[u8; 8]) -> String {
+    format!("{:016X}", u64::from_le_bytes(*id))
+}
+
+fn verify(content: &[u8], signature: &Signature, keys: &[PublicKey]) -> std::result::Result<(), String> {
+    let candidates: Vec<&PublicKey> = keys
+        .iter()
+        .filter(|key| match (key.id, signature.id) {
+            (Some(key), Some(signed)) => key == signed,
+            _ => true,
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Err(match signature.id {
+            Some(id) if !keys.is_empty() => format!("signed with key {}, which is not in the trusted public_keys", key_id(&id)),
+            _ => "signed, but the trusted public_keys is empty".to_string(),
+        });
+    }
+
+    let hash;
+    let message = if signature.prehashed {
+        // minisign's pre-hashed signatures cover the file's BLAKE2b-512 digest
+        hash = Blake2b512::digest(content);
+        &hash[..]
+    } else {
+        content
+    };
+    for key in candidates {
+        let public_key = UnparsedPublicKey::new(&ED25519, key.key);
+        if public_key.verify(message, &signature.signature).is_err() {
+            continue;
+        }
+        if let Some((comment, global)) = &signature.trusted_comment {
+            let signed = [&signature.signature[..], comment.as_bytes()].concat();
+            if public_key.verify(&signed, global).is_err() {
+                return Err("the trusted comment does not match its signature".to_string());
+            }
+        }
+        return Ok(());
+    }
+    Err("the signature does not match the file with any trusted key".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// Signs `content` the way `minisign -S` does, returning the public key line and signature file.
+    fn minisign(pair: &Ed25519KeyPair, content: &[u8], prehashed: bool) -> (String, String) {
+        let id = *b"\x01\x02\x03\x04\x05\x06\x07\x08";
+        let public_key = STANDARD.encode([&b"Ed"[..], &id, pair.public_key().as_ref()].concat());
+        let message = if prehashed { Blake2b512::digest(content).to_vec() } else { content.to_vec() };
+        let signature = pair.sign(&message);
+        let algorithm: &[u8] = if prehashed { b"ED" } else { b"Ed" };
+        let comment = "timestamp:1760000000\tfile:org.toml";
+        let global = pair.sign(&[signature.as_ref(), comment.as_bytes()].concat());
+        let file = format!(
+            "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {comment}\n{}\n",
+            STANDARD.encode([algorithm, &id, signature.as_ref()].concat()),
+            STANDARD.encode(global.as_ref())
+        );
+        (public_key, file)
+    }
+
+    #[test]
+    fn test_blake2b_512() {
+        assert_eq!(
+            hex(&Blake2b512::digest(b"")),
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+        );
+        assert_eq!(
+            hex(&Blake2b512::digest(b"abc")),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+
+    #[test]
+    fn test_verify_policy_file() {
+        let dir = TempDir::new().unwrap();
+        let policy = dir.path().join("org.toml");
+        let content = b"[[rules]]\npattern = \"curl\"\nmessage = \"Use the proxy\"\n";
+        fs::write(&policy, content).unwrap();
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let other = Ed25519KeyPair::from_seed_unchecked(&[8; 32]).unwrap();
+
+        let mut signatures = Signatures::default();
+        assert!(!verify_policy_file(&signatures, &policy, content).unwrap());
+        signatures.require_signed = true;
+        assert!(verify_policy_file(&signatures, &policy, content).unwrap_err().to_string().contains("unsigned"));
+
+        for prehashed in [true, false] {
+            let (public_key, file) = minisign(&pair, content, prehashed);
+            fs::write(signature_path(&policy), file).unwrap();
+            signatures.public_keys = vec![format!("untrusted comment: minisign public key\n{public_key}")];
+            assert!(verify_policy_file(&signatures, &policy, content).unwrap());
+            assert!(verify_policy_file(&signatures, &policy, b"# tampered\n").is_err());
+        }
+
+        // A signature from a key that isn't trusted
+        let (_, file) = minisign(&other, content, true);
+        fs::write(signature_path(&policy), file).unwrap();
+        assert!(verify_policy_file(&signatures, &policy, content).is_err());
+
+        // A bare Ed25519 signature and key
+        fs::write(signature_path(&policy), STANDARD.encode(pair.sign(content).as_ref())).unwrap();
+        signatures.public_keys = vec![STANDARD.encode(pair.public_key().as_ref())];
+        assert!(verify_policy_file(&signatures, &policy, content).unwrap());
+        signatures.public_keys.clear();
+        assert!(verify_policy_file(&signatures, &policy, content).unwrap_err().to_string().contains("public_keys is empty"));
+    }
+
+    #[test]
+    fn test_trusted_comment_is_verified() {
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let (public_key, file) = minisign(&pair, b"policy", true);
+        let tampered = file.replace("file:org.toml", "file:other.toml");
+        let keys = vec![parse_public_key(&public_key).unwrap()];
+        assert!(verify(b"policy", &parse_signature(&file).unwrap(), &keys).is_ok());
+        assert!(verify(b"policy", &parse_signature(&tampered).unwrap(), &keys).unwrap_err().contains("trusted comment"));
+        assert_eq!(signature_path(Path::new("/etc/org.toml")), Path::new("/etc/org.toml.minisig"));
+    }
+}
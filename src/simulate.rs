@@ -0,0 +1,403 @@
+//! Replaying scenario files through the hook pipeline
+//!
+//! A scenario is a YAML list of prompts, Bash commands and Stop events. Each
+//! step is sent to this binary's `--hook` mode as the JSON Claude Code would
+//! send, so mappings, rules, limits, plan gates and the Stop checklist all
+//! apply exactly as in a real session. Commands that are allowed are reported
+//! back through PostToolUse with the step's exit code, which builds the
+//! session history later steps depend on.
+//!
+//! Session state, the audit log and the telemetry queue are written to a
+//! temporary directory, so a simulation never touches the project's own.
+
+use crate::rules::{CompiledRules, RuleContext};
+use crate::types::{AdvisorError, Config, ConfigError, IoContext, Result, Severity};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A scenario file: named steps replayed in order within one session.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+/// One event of a scenario; exactly one of `prompt`, `command` or `stop` is set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Step {
+    /// A user prompt (UserPromptSubmit)
+    pub prompt: Option<String>,
+    /// A Bash tool call (PreToolUse, then PostToolUse if it runs)
+    pub command: Option<String>,
+    /// Exit code the command reports if it runs (default 0)
+    pub exit_code: Option<i32>,
+    /// Claude finishing its turn (Stop)
+    pub stop: bool,
+    /// Decision the step must produce
+    pub expect: Option<Decision>,
+    /// Command the step must suggest instead
+    pub suggests: Option<String>,
+}
+
+/// What the hook decided for a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Warn,
+    Ask,
+    Block,
+    Replace,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Decision::Allow => "allow",
+            Decision::Warn => "warn",
+            Decision::Ask => "ask",
+            Decision::Block => "block",
+            Decision::Replace => "replace",
+        })
+    }
+}
+
+/// Harmless command the self-test expects every configuration to allow.
+const SELF_TEST_COMMAND: &str = "echo claude-hook-advisor self-test";
+
+/// The outcome of one step.
+#[derive(Debug)]
+pub struct StepReport {
+    /// What was sent: the prompt, the command, or "stop"
+    pub input: String,
+    pub decision: Decision,
+    /// The reason or context the hook gave, if any
+    pub message: Option<String>,
+    /// The command suggested instead, if any
+    pub suggested: Option<String>,
+    /// Unmet expectations
+    pub failures: Vec<String>,
+}
+
+impl Scenario {
+    /// Parses and validates a scenario file.
+    ///
+    /// # Arguments
+    /// * `content` - YAML text of the scenario
+    /// * `path` - Scenario file path, used in errors
+    ///
+    /// # Returns
+    /// * `Ok(Scenario)` - A scenario whose steps each have exactly one event
+    /// * `Err(ConfigError::ParseError)` - If the YAML is malformed or a step is ambiguous
+    pub fn parse(content: &str, path: &Path) -> Result<Self> {
+        let error = |message: String| ConfigError::ParseError(format!("{}: {message}", path.display()));
+        let scenario: Scenario = serde_yaml::from_str(content).map_err(|e| error(e.to_string()))?;
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let events = usize::from(step.prompt.is_some()) + usize::from(step.command.is_some()) + usize::from(step.stop);
+            if events != 1 {
+                return Err(error(format!("step {} must have exactly one of prompt, command or stop", index + 1)).into());
+            }
+            if step.exit_code.is_some() && step.command.is_none() {
+                return Err(error(format!("step {} has an exit_code but no command", index + 1)).into());
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    /// A smoke test of `config` through the hook: the first command mapping
+    /// that blocks must be blocked (or replaced) with the suggestion the rules
+    /// give, and a harmless command must be allowed.
+    ///
+    /// # Arguments
+    /// * `config` - Configuration the hook will load
+    /// * `replace_mode` - Whether the hook runs with `--replace`
+    pub fn self_test(config: &Config, replace_mode: bool) -> Result<Self> {
+        let rules = CompiledRules::new(config, &RuleContext::default())?;
+        let mut patterns: Vec<&String> = config.active_commands().into_iter().map(|(pattern, _)| pattern).collect();
+        patterns.sort();
+
+        let mapped = patterns.into_iter().find_map(|pattern| {
+            let suggests = rules
+                .evaluate(pattern)
+                .filter(|m| m.severity == Severity::Block)
+                .and_then(|m| m.suggested_command)?;
+            Some(Step {
+                command: Some(pattern.clone()),
+                expect: Some(if replace_mode { Decision::Replace } else { Decision::Block }),
+                suggests: Some(suggests),
+                ..Step::default()
+            })
+        });
+        let allowed = Step { command: Some(SELF_TEST_COMMAND.to_string()), expect: Some(Decision::Allow), ..Step::default() };
+
+        Ok(Scenario { name: Some("self-test".to_string()), steps: mapped.into_iter().chain([allowed]).collect() })
+    }
+}
+
+/// Replays `scenario` through the hook binary at `exe`.
+///
+/// # Arguments
+/// * `exe` - The claude-hook-advisor binary to run in `--hook` mode
+/// * `hook_args` - Extra arguments for the hook, such as `--config` or `--replace`
+/// * `scenario` - The scenario to replay
+///
+/// # Returns
+/// * `Ok(Vec<StepReport>)` - One report per step, in order
+/// * `Err` - If the hook cannot be run or fails
+pub fn run_scenario(exe: &Path, hook_args: &[String], scenario: &Scenario) -> Result<Vec<StepReport>> {
+    let state_dir = SimulationDir::create()?;
+    let session = Session { exe, hook_args, cwd: state_dir.path.to_string_lossy().into_owned() };
+
+    scenario
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let mut report = session.run_step(index, step)?;
+            report.failures = unmet_expectations(step, &report);
+            Ok(report)
+        })
+        .collect()
+}
+
+/// Compares a step's expectations with what the hook did.
+fn unmet_expectations(step: &Step, report: &StepReport) -> Vec<String> {
+    let mut failures = Vec::new();
+    if let Some(expected) = step.expect.filter(|expected| *expected != report.decision) {
+        failures.push(format!("expected {expected}, got {}", report.decision));
+    }
+    if let Some(expected) = &step.suggests {
+        match &report.suggested {
+            Some(suggested) if suggested == expected => {}
+            Some(suggested) => failures.push(format!("expected suggestion '{expected}', got '{suggested}'")),
+            None => failures.push(format!("expected suggestion '{expected}', got none")),
+        }
+    }
+    failures
+}
+
+/// The simulated session the steps run in.
+struct Session<'a> {
+    exe: &'a Path,
+    hook_args: &'a [String],
+    /// Project directory reported to the hook, holding the session's state
+    cwd: String,
+}
+
+impl Session<'_> {
+    fn run_step(&self, index: usize, step: &Step) -> Result<StepReport> {
+        if let Some(prompt) = &step.prompt {
+            let (stdout, _) = self.send(json!({ "hook_event_name": "UserPromptSubmit", "prompt": prompt }))?;
+            return Ok(StepReport {
+                input: prompt.clone(),
+                decision: Decision::Allow,
+                message: (!stdout.trim().is_empty()).then(|| stdout.trim().to_string()),
+                suggested: None,
+                failures: Vec::new(),
+            });
+        }
+
+        if let Some(command) = &step.command {
+            let tool_use_id = format!("simulated-{}", index + 1);
+            let (stdout, stderr) = self.send(json!({
+                "hook_event_name": "PreToolUse",
+                "tool_name": "Bash",
+                "tool_input": { "command": command },
+                "tool_use_id": tool_use_id,
+            }))?;
+            let report = classify_pre_tool_use(command, &stdout, &stderr);
+
+            // Blocked commands never run; anything else reports its exit code
+            let ran = match report.decision {
+                Decision::Block => None,
+                Decision::Replace => report.suggested.clone(),
+                _ => Some(command.clone()),
+            };
+            if let Some(ran) = ran {
+                self.send(json!({
+                    "hook_event_name": "PostToolUse",
+                    "tool_name": "Bash",
+                    "tool_input": { "command": ran },
+                    "tool_use_id": tool_use_id,
+                    "tool_response": { "exit_code": step.exit_code.unwrap_or(0), "stdout": "", "stderr": "" },
+                }))?;
+            }
+            return Ok(report);
+        }
+
+        let (stdout, _) = self.send(json!({ "hook_event_name": "Stop", "stop_hook_active": false }))?;
+        let output: Value = serde_json::from_str(&stdout).unwrap_or(Value::Null);
+        let blocked = output["decision"] == "block";
+        Ok(StepReport {
+            input: "stop".to_string(),
+            decision: if blocked { Decision::Block } else { Decision::Allow },
+            message: output["reason"].as_str().map(str::to_string),
+            suggested: None,
+            failures: Vec::new(),
+        })
+    }
+
+    /// Runs the hook with one event and returns its stdout and stderr.
+    fn send(&self, mut event: Value) -> Result<(String, String)> {
+        event["session_id"] = json!("simulated-session");
+        event["cwd"] = json!(self.cwd);
+
+        let mut child = Command::new(self.exe)
+            .arg("--hook")
+            .args(self.hook_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .io_context(|| format!("Failed to run {}", self.exe.display()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(event.to_string().as_bytes()).io_context(|| "Failed to send hook input".to_string())?;
+        }
+        let output = child.wait_with_output().io_context(|| "Failed to read hook output".to_string())?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        if !output.status.success() {
+            return Err(AdvisorError::Protocol(format!(
+                "hook exited with {} on {}: {}",
+                output.status,
+                event["hook_event_name"],
+                stderr.trim()
+            )));
+        }
+        Ok((stdout, stderr))
+    }
+}
+
+/// Reads the decision from a PreToolUse hook's output.
+fn classify_pre_tool_use(command: &str, stdout: &str, stderr: &str) -> StepReport {
+    let output: Value = serde_json::from_str(stdout.trim()).unwrap_or(Value::Null);
+    let text = |value: &Value| value.as_str().map(str::to_string);
+
+    let (decision, message, suggested) = if output["decision"] == "replace" {
+        (Decision::Replace, text(&output["reason"]), text(&output["replacement_command"]))
+    } else if output["decision"] == "block" {
+        (Decision::Block, text(&output["reason"]), text(&output["suggested_command"]))
+    } else if output["hookSpecificOutput"]["permissionDecision"] == "ask" {
+        (Decision::Ask, text(&output["hookSpecificOutput"]["permissionDecisionReason"]), None)
+    } else if let Some(warning) = stderr.lines().find_map(|line| line.strip_prefix("⚠️  ")) {
+        (Decision::Warn, Some(warning.to_string()), None)
+    } else {
+        (Decision::Allow, None, None)
+    };
+
+    StepReport { input: command.to_string(), decision, message, suggested, failures: Vec::new() }
+}
+
+/// A temporary project directory, removed when the simulation ends.
+struct SimulationDir {
+    path: PathBuf,
+}
+
+impl SimulationDir {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("claude-hook-advisor-simulate-{}", std::process::id()));
+        std::fs::create_dir_all(&path).io_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for SimulationDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scenario() {
+        let yaml = r#"
+name: npm is redirected to bun
+steps:
+  - prompt: install the dependencies
+  - command: npm install
+    expect: block
+    suggests: bun install
+  - command: bun install
+    exit_code: 1
+  - stop: true
+    expect: allow
+"#;
+        let scenario = Scenario::parse(yaml, Path::new("npm.yaml")).unwrap();
+        assert_eq!(scenario.name.as_deref(), Some("npm is redirected to bun"));
+        assert_eq!(scenario.steps.len(), 4);
+        assert_eq!(scenario.steps[1].expect, Some(Decision::Block));
+        assert_eq!(scenario.steps[2].exit_code, Some(1));
+        assert!(scenario.steps[3].stop);
+
+        let err = Scenario::parse("steps:\n  - command: ls\n    stop: true\n", Path::new("bad.yaml")).unwrap_err();
+        assert!(err.to_string().contains("step 1 must have exactly one"));
+        assert!(Scenario::parse("steps:\n  - command: ls\n    expected: block\n", Path::new("typo.yaml")).is_err());
+    }
+
+    #[test]
+    fn test_classify_pre_tool_use() {
+        let block = r#"{"decision":"block","reason":"Command 'npm' is mapped to use 'bun' instead. Try: bun ci. Why: https://wiki.example/bun","suggested_command":"bun ci"}"#;
+        let report = classify_pre_tool_use("npm ci", block, "");
+        assert_eq!(report.decision, Decision::Block);
+        assert_eq!(report.suggested.as_deref(), Some("bun ci"));
+
+        let ask = r#"{"hookSpecificOutput":{"hookEventName":"PreToolUse","permissionDecision":"ask","permissionDecisionReason":"Confirm"}}"#;
+        assert_eq!(classify_pre_tool_use("kubectl delete pod x", ask, "").decision, Decision::Ask);
+
+        let report = classify_pre_tool_use("git push", "", "⚠️  Run the tests first\n");
+        assert_eq!(report.decision, Decision::Warn);
+        assert_eq!(report.message.as_deref(), Some("Run the tests first"));
+
+        assert_eq!(classify_pre_tool_use("ls", r#"{"suppressOutput":true}"#, "").decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_unmet_expectations() {
+        let step = Step { expect: Some(Decision::Block), suggests: Some("bun ci".to_string()), ..Step::default() };
+        let report = classify_pre_tool_use("npm ci", "", "");
+        assert_eq!(
+            unmet_expectations(&step, &report),
+            vec!["expected block, got allow".to_string(), "expected suggestion 'bun ci', got none".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_self_test_scenario() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+yarn = "bun"
+npm = "bun"
+
+[[rules]]
+pattern = "npm"
+severity = "warn"
+"#,
+        )
+        .unwrap();
+
+        // The warning rule outranks the npm mapping, so the self-test uses yarn
+        let scenario = Scenario::self_test(&config, true).unwrap();
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(scenario.steps[0].command.as_deref(), Some("yarn"));
+        assert_eq!(scenario.steps[0].expect, Some(Decision::Replace));
+        assert_eq!(scenario.steps[0].suggests.as_deref(), Some("bun"));
+        assert_eq!(scenario.steps[1].expect, Some(Decision::Allow));
+
+        let scenario = Scenario::self_test(&Config::default(), false).unwrap();
+        assert_eq!(scenario.steps.len(), 1);
+    }
+}
@@ -0,0 +1,224 @@
+//! Guardrails for commands run over SSH
+//!
+//! `ssh host <cmd>` runs `<cmd>` on another machine, where the mappings and
+//! rules would otherwise never see it. The remote command is evaluated like a
+//! local one, and a suggested replacement is put back in its place. The
+//! `[ssh]` table can also restrict which hosts ssh, scp, sftp and rsync
+//! connect to.
+
+use crate::network::{command_endpoints, host_matches, value_options};
+use crate::rules::{evaluate_command, RuleContext, RuleMatch};
+use crate::shell::{nested_segments, normalize, tokenize};
+use crate::types::{Config, Result, Ssh};
+use std::ops::Range;
+
+/// Tools that connect over SSH.
+const SSH_TOOLS: &[&str] = &["ssh", "scp", "sftp", "rsync"];
+
+/// A command that `ssh` runs on a remote host.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemoteCommand {
+    /// The host as written, with any `user@` prefix
+    pub host: String,
+    /// The remote command, with the quotes around it removed
+    pub command: String,
+    /// Byte range of the remote command in the local command, including
+    /// the quotes around it
+    range: Range<usize>,
+    /// The quote character around the remote command, if it is one quoted word
+    quote: Option<char>,
+}
+
+/// Refuses ssh, scp, sftp and rsync connections to hosts missing from `[ssh] allowed_hosts`.
+///
+/// # Arguments
+/// * `ssh` - The `[ssh]` table
+/// * `command` - The command about to run
+///
+/// # Returns
+/// * `Some(RuleMatch)` - A match with the table's severity for the first host not allowed
+/// * `None` - If no allowlist is configured or every host is on it
+pub fn check_ssh_hosts(ssh: &Ssh, command: &str) -> Option<RuleMatch> {
+    if ssh.allowed_hosts.is_empty() {
+        return None;
+    }
+
+    command_endpoints(command)
+        .into_iter()
+        .filter(|endpoint| SSH_TOOLS.contains(&endpoint.tool.as_str()))
+        .find(|endpoint| !ssh.allowed_hosts.iter().any(|allowed| host_matches(allowed, &endpoint.host)))
+        .map(|endpoint| RuleMatch {
+            pattern: "ssh".to_string(),
+            reason: format!(
+                "'{}' connects to {}, which is not in [ssh] allowed_hosts ({}); ask the user before connecting to other hosts",
+                endpoint.tool,
+                endpoint.host,
+                ssh.allowed_hosts.join(", ")
+            ),
+            suggested_command: None,
+            severity: ssh.severity,
+        })
+}
+
+/// Evaluates the mappings and rules against the commands `ssh` runs remotely.
+///
+/// # Arguments
+/// * `config` - Configuration containing command mappings and rules
+/// * `command` - The local command, such as `ssh prod 'npm install'`
+/// * `context` - Hook context used to evaluate rule conditions
+///
+/// # Returns
+/// * `Ok(Some(RuleMatch))` - The first match in a remote command; its reason
+///   names the host and its suggestion is the whole local command with the
+///   remote part rewritten
+/// * `Ok(None)` - If `check_remote_commands` is off or nothing matches
+/// * `Err` - If regex compilation fails
+pub fn evaluate_remote_commands(config: &Config, command: &str, context: &RuleContext) -> Result<Option<RuleMatch>> {
+    if !config.ssh.check_remote_commands {
+        return Ok(None);
+    }
+
+    for remote in remote_commands(command) {
+        if let Some(mut rule_match) = evaluate_command(config, &remote.command, context)? {
+            rule_match.reason = format!("On {}: {}", remote.host, rule_match.reason);
+            if let Some(suggested) = rule_match.suggested_command.take() {
+                let quoted = match remote.quote {
+                    Some(quote) => format!("{quote}{suggested}{quote}"),
+                    None => suggested.clone(),
+                };
+                let local = format!("{}{quoted}{}", &command[..remote.range.start], &command[remote.range.end..]);
+                // A mapping's reason ends with the suggestion, which should be the local command
+                rule_match.reason = rule_match.reason.replace(&format!("Try: {suggested}"), &format!("Try: {local}"));
+                rule_match.suggested_command = Some(local);
+            }
+            return Ok(Some(rule_match));
+        }
+    }
+    Ok(None)
+}
+
+/// The commands run on remote hosts by `ssh host <cmd>` segments of a
+/// command, including ones inside substitutions.
+pub fn remote_commands(command: &str) -> Vec<RemoteCommand> {
+    let mut remotes = Vec::new();
+    for segment in nested_segments(command) {
+        let offset = segment.start + normalize(&command[segment.clone()]).command_start;
+        let text = &command[offset..segment.end];
+        let words = tokenize(text);
+        let Some(program) = words.first().map(|word| &text[word.clone()]) else {
+            continue;
+        };
+        if program.rsplit('/').next() != Some("ssh") {
+            continue;
+        }
+
+        // The first operand is the host and everything after it the remote command
+        let value_options = value_options("ssh");
+        let mut index = 1;
+        while let Some(word) = words.get(index).map(|word| &text[word.clone()]) {
+            if !word.starts_with('-') {
+                break;
+            }
+            index += if value_options.contains(&word) { 2 } else { 1 };
+        }
+        let (Some(host), Some(first)) = (words.get(index), words.get(index + 1)) else {
+            continue;
+        };
+
+        let range = offset + first.start..segment.end;
+        let remote = &command[range.clone()];
+        let quote = remote.chars().next().filter(|c| matches!(c, '\'' | '"')).filter(|_| words.len() == index + 2);
+        let remote = match quote {
+            Some(quote) if remote.len() >= 2 && remote.ends_with(quote) => &remote[1..remote.len() - 1],
+            _ => remote,
+        };
+        remotes.push(RemoteCommand {
+            host: text[host.clone()].to_string(),
+            command: remote.to_string(),
+            range,
+            quote,
+        });
+    }
+    remotes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[[rules]]
+pattern = "rm -rf"
+message = "Recursive deletes need a human"
+
+[ssh]
+allowed_hosts = ["prod", "*.internal"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_remote_commands() {
+        let remotes = remote_commands("cd app && ssh -p 2222 -i key deploy@prod 'npm install && npm test'; ssh db uptime -p");
+        assert_eq!(remotes.len(), 2);
+        assert_eq!(remotes[0].host, "deploy@prod");
+        assert_eq!(remotes[0].command, "npm install && npm test");
+        assert_eq!(remotes[0].quote, Some('\''));
+        assert_eq!(remotes[1].command, "uptime -p");
+        assert_eq!(remotes[1].quote, None);
+        assert!(remote_commands("ssh prod; ssh -N -L 8080:localhost:80 bastion; scp a prod:b").is_empty());
+    }
+
+    #[test]
+    fn test_rules_apply_to_remote_commands() {
+        let config = config();
+        let context = RuleContext::default();
+        let evaluate = |command: &str| evaluate_remote_commands(&config, command, &context).unwrap();
+
+        let mapped = evaluate("ssh deploy@prod \"npm install\" && echo done").unwrap();
+        assert_eq!(mapped.suggested_command.as_deref(), Some("ssh deploy@prod \"bun install\" && echo done"));
+        assert!(mapped.reason.starts_with("On deploy@prod: "));
+        assert!(mapped.reason.ends_with("Try: ssh deploy@prod \"bun install\" && echo done"));
+
+        let unquoted = evaluate("ssh web.internal npm ci").unwrap();
+        assert_eq!(unquoted.suggested_command.as_deref(), Some("ssh web.internal bun ci"));
+        assert_eq!(evaluate("ssh prod 'rm -rf /srv/app'").unwrap().reason, "On prod: Recursive deletes need a human");
+        assert!(evaluate("ssh prod uptime").is_none());
+
+        let mut off = toml::from_str::<Config>("[commands]\nnpm = \"bun\"").unwrap();
+        off.ssh.check_remote_commands = false;
+        assert!(evaluate_remote_commands(&off, "ssh prod npm ci", &context).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_ssh_host_allowlist() {
+        let ssh = config().ssh;
+        for allowed in ["ssh deploy@prod uptime", "ssh root@prod", "scp build.tgz web.internal:/srv", "ssh -t db.internal", "curl example.com"] {
+            assert!(check_ssh_hosts(&ssh, allowed).is_none(), "{allowed}");
+        }
+        let denied = check_ssh_hosts(&ssh, "rsync -a src/ backup@203.0.113.4:/data").unwrap();
+        assert!(denied.reason.contains("'rsync' connects to 203.0.113.4"));
+        assert!(check_ssh_hosts(&ssh, "ssh root@staging").is_some());
+
+        // Connections hidden in substitutions are checked too
+        let hidden = check_ssh_hosts(&ssh, "echo $(ssh prod.example.com cat /etc/shadow)").unwrap();
+        assert!(hidden.reason.contains("'ssh' connects to prod.example.com"), "{}", hidden.reason);
+        assert!(check_ssh_hosts(&ssh, "v=`scp staging:/etc/passwd .`").is_some());
+    }
+
+    #[test]
+    fn test_remote_commands_in_substitutions() {
+        let command = "echo \"$(ssh prod npm ci)\"";
+        let remotes = remote_commands(command);
+        assert_eq!(remotes.len(), 1);
+        assert_eq!(remotes[0].command, "npm ci");
+        let mapped = evaluate_remote_commands(&config(), command, &RuleContext::default()).unwrap().unwrap();
+        assert_eq!(mapped.suggested_command.as_deref(), Some("echo \"$(ssh prod bun ci)\""));
+    }
+}
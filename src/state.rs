@@ -0,0 +1,648 @@
+//! Per-session state shared between hook invocations
+//!
+//! Each hook event runs in a new process, so anything that must be remembered
+//! across events (such as which replacements were suggested) is stored as JSON
+//! in `.claude/advisor/state/<session_id>.json` under the project directory.
+//! Sessions running side by side in one repository each get their own file;
+//! files of sessions idle for `STALE_SESSION_DAYS` are removed.
+
+use crate::atomic::write_atomic;
+use crate::types::{IoContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// State directory relative to the project directory.
+pub const STATE_DIR: &str = ".claude/advisor/state";
+
+/// Suggestions remembered per session; older ones are dropped first.
+const MAX_SUGGESTIONS: usize = 50;
+
+/// Tool calls still waiting for PostToolUse after this long are forgotten.
+/// PreToolUse runs for blocked commands too, which never get a PostToolUse.
+const PENDING_TIMEOUT_SECS: i64 = 60 * 60;
+
+/// A human override armed in a prompt lapses if unused for this long.
+const OVERRIDE_TIMEOUT_MINUTES: i64 = 30;
+
+/// Finished commands remembered per session; older ones are dropped first.
+const MAX_HISTORY: usize = 200;
+
+/// Project-wide duration samples kept; older ones are dropped first.
+const MAX_DURATION_SAMPLES: usize = 500;
+
+/// Sessions whose state has not changed for this many days are removed.
+pub const STALE_SESSION_DAYS: i64 = 7;
+
+/// File in the state directory holding duration samples across sessions.
+const DURATIONS_FILE: &str = "durations.json";
+
+/// File in the state directory holding snoozed rule patterns.
+const SNOOZES_FILE: &str = "snoozes.json";
+
+/// Marker recording that the first-run notice was shown in a project.
+const ONBOARDING_MARKER: &str = "onboarding-shown";
+
+/// Marker recording the configuration last checked for deprecated constructs.
+const DEPRECATIONS_MARKER: &str = "deprecations-checked";
+
+/// Project-wide files in the state directory that are not sessions.
+const PROJECT_FILES: &[&str] = &[DURATIONS_FILE, SNOOZES_FILE];
+
+/// A replacement the advisor suggested for a blocked command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Suggestion {
+    /// The command Claude tried to run
+    pub original: String,
+    /// The replacement the advisor suggested
+    pub suggested: String,
+    /// The rule pattern that matched
+    pub pattern: String,
+    pub created_at: DateTime<Utc>,
+    /// Set when the suggested command exited with a nonzero status
+    #[serde(default)]
+    pub failed: bool,
+    /// Tool call that was blocked with this suggestion
+    #[serde(default)]
+    pub tool_use_id: Option<String>,
+    /// Tool call that ran the suggested command, once Claude accepted it
+    #[serde(default)]
+    pub accepted_by: Option<String>,
+    /// Exit code of the accepting tool call, once it finished
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Set once the original command was let through after the replacement failed
+    #[serde(default)]
+    pub retried: bool,
+    /// The rule's `sample_rate` when the suggestion was recorded, so statistics
+    /// can count the hits that were not
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+}
+
+/// Everything remembered about one Claude Code session.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionState {
+    #[serde(default)]
+    pub suggestions: Vec<Suggestion>,
+    /// Start times of tool calls seen by PreToolUse, keyed by correlation key
+    #[serde(default)]
+    pub pending: HashMap<String, DateTime<Utc>>,
+    /// Advice patterns already given in this session
+    #[serde(default)]
+    pub advised: Vec<String>,
+    /// Bash commands PostToolUse saw finish, oldest first
+    #[serde(default)]
+    pub history: Vec<CommandRun>,
+    /// When the user armed a one-shot override with the `[override]` phrase
+    #[serde(default)]
+    pub override_armed_at: Option<DateTime<Utc>>,
+}
+
+/// A Bash command that finished in a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandRun {
+    pub command: String,
+    /// Exit status, when Claude Code reported one
+    pub exit_code: Option<i32>,
+    /// Run time, when PreToolUse saw the command start
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl CommandRun {
+    /// Whether the command exited with status 0.
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+impl SessionState {
+    /// Loads the state of a session; a missing or unreadable file yields empty state.
+    pub fn load(project_dir: &Path, session_id: &str) -> Self {
+        fs::read_to_string(session_file(project_dir, session_id))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the state of a session.
+    ///
+    /// The first write of a session also removes stale sessions, so state
+    /// files do not pile up in long-lived checkouts.
+    pub fn save(&self, project_dir: &Path, session_id: &str) -> Result<()> {
+        let path = session_file(project_dir, session_id);
+        let is_new = !path.exists();
+        create_ignored_dir(&project_dir.join(STATE_DIR))?;
+        write_atomic(&path, &serde_json::to_string_pretty(self)?)
+            .io_context(|| format!("Failed to write session state: {}", path.display()))?;
+
+        if is_new {
+            // Cleanup is best effort; it must never cost a session its state
+            let _ = remove_sessions(project_dir, chrono::Duration::days(STALE_SESSION_DAYS));
+        }
+        Ok(())
+    }
+
+    /// Remembers that `suggested` was offered in place of `original`.
+    ///
+    /// An earlier suggestion for the same command is replaced unless Claude
+    /// already accepted it, so its outcome still counts in the statistics.
+    ///
+    /// # Returns
+    /// The recorded suggestion
+    pub fn record_suggestion(&mut self, original: &str, suggested: &str, pattern: &str, tool_use_id: Option<&str>) -> &mut Suggestion {
+        self.suggestions
+            .retain(|s| s.original != original || s.accepted_by.is_some());
+        self.suggestions.push(Suggestion {
+            original: original.to_string(),
+            suggested: suggested.to_string(),
+            pattern: pattern.to_string(),
+            created_at: Utc::now(),
+            failed: false,
+            tool_use_id: tool_use_id.map(str::to_string),
+            accepted_by: None,
+            exit_code: None,
+            retried: false,
+            sample_rate: None,
+        });
+
+        let excess = self.suggestions.len().saturating_sub(MAX_SUGGESTIONS);
+        self.suggestions.drain(..excess);
+        self.suggestions.last_mut().expect("a suggestion was just recorded")
+    }
+
+    /// Appends a finished command to the session history.
+    pub fn record_run(&mut self, command: &str, exit_code: Option<i32>, duration_ms: Option<u64>) {
+        self.history.push(CommandRun {
+            command: command.trim().to_string(),
+            exit_code,
+            duration_ms,
+            finished_at: Utc::now(),
+        });
+
+        let excess = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..excess);
+    }
+
+    /// Records that a tool call identified by `key` is about to run.
+    pub fn start_tool(&mut self, key: &str) {
+        let cutoff = Utc::now() - chrono::Duration::seconds(PENDING_TIMEOUT_SECS);
+        self.pending.retain(|_, started| *started > cutoff);
+        self.pending.insert(key.to_string(), Utc::now());
+    }
+
+    /// Returns how long the tool call identified by `key` ran, in milliseconds.
+    pub fn finish_tool(&mut self, key: &str) -> Option<u64> {
+        let started = self.pending.remove(key)?;
+        u64::try_from((Utc::now() - started).num_milliseconds()).ok()
+    }
+
+    /// Links the tool call identified by `key` to the suggestion it follows.
+    ///
+    /// Called from PreToolUse: when Claude runs a suggested replacement, the most
+    /// recent not yet accepted suggestion for that command is marked accepted.
+    ///
+    /// # Returns
+    /// * `true` - If the command was a pending suggestion
+    pub fn accept_suggestion(&mut self, command: &str, key: &str) -> bool {
+        let command = command.trim();
+        match self
+            .suggestions
+            .iter_mut()
+            .rev()
+            .find(|s| s.accepted_by.is_none() && s.suggested == command)
+        {
+            Some(suggestion) => {
+                suggestion.accepted_by = Some(key.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Finds the suggestion accepted by the tool call identified by `key`.
+    pub fn suggestion_for_tool(&mut self, key: &str) -> Option<&mut Suggestion> {
+        self.suggestions
+            .iter_mut()
+            .rev()
+            .find(|s| s.accepted_by.as_deref() == Some(key))
+    }
+
+    /// Returns a failed suggestion made for `command` and marks it retried.
+    ///
+    /// Used to let the original command through once after its replacement failed.
+    pub fn take_failed_original(&mut self, command: &str) -> Option<Suggestion> {
+        let command = command.trim();
+        let suggestion = self
+            .suggestions
+            .iter_mut()
+            .rev()
+            .find(|s| s.failed && !s.retried && s.original == command)?;
+        suggestion.retried = true;
+        Some(suggestion.clone())
+    }
+
+    /// Arms a one-shot human override for the next tool call that would be stopped.
+    pub fn arm_override(&mut self) {
+        self.override_armed_at = Some(Utc::now());
+    }
+
+    /// Uses up an armed human override; true if one was armed and hasn't lapsed.
+    pub fn take_override(&mut self) -> bool {
+        self.override_armed_at
+            .take()
+            .is_some_and(|armed| Utc::now() - armed < chrono::Duration::minutes(OVERRIDE_TIMEOUT_MINUTES))
+    }
+}
+
+/// A session recorded for a project.
+#[derive(Debug)]
+pub struct SessionInfo {
+    /// Session id as used in the state file name
+    pub id: String,
+    /// When the session's state last changed
+    pub modified: DateTime<Utc>,
+    pub state: SessionState,
+}
+
+/// Lists every session recorded for a project, most recently active first.
+///
+/// Unreadable session files are skipped.
+pub fn list_sessions(project_dir: &Path) -> Vec<SessionInfo> {
+    let Ok(entries) = fs::read_dir(project_dir.join(STATE_DIR)) else {
+        return Vec::new();
+    };
+
+    let mut sessions: Vec<SessionInfo> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "json")
+                && path.file_name().is_some_and(|name| !PROJECT_FILES.iter().any(|file| name == *file))
+        })
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            let state = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some(SessionInfo {
+                id: path.file_stem()?.to_string_lossy().into_owned(),
+                modified: modified.into(),
+                state,
+            })
+        })
+        .collect();
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.modified));
+    sessions
+}
+
+/// Loads the state of every session recorded for a project.
+#[cfg_attr(not(feature = "analytics"), allow(dead_code))]
+pub fn load_all_sessions(project_dir: &Path) -> Vec<SessionState> {
+    list_sessions(project_dir).into_iter().map(|session| session.state).collect()
+}
+
+/// Removes sessions whose state has not changed for `max_idle`.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - Ids of the removed sessions
+/// * `Err` - If a state file could not be removed
+pub fn remove_sessions(project_dir: &Path, max_idle: chrono::Duration) -> Result<Vec<String>> {
+    let cutoff = Utc::now() - max_idle;
+    let mut removed = Vec::new();
+    for session in list_sessions(project_dir).into_iter().filter(|s| s.modified < cutoff) {
+        let path = session_file(project_dir, &session.id);
+        fs::remove_file(&path).io_context(|| format!("Failed to remove session state: {}", path.display()))?;
+        removed.push(session.id);
+    }
+    Ok(removed)
+}
+
+/// A rule pattern that does not fire until `until`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snooze {
+    pub pattern: String,
+    pub until: DateTime<Utc>,
+}
+
+/// Rule patterns temporarily suspended in a project, across all sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SnoozeList {
+    #[serde(default)]
+    pub snoozes: Vec<Snooze>,
+}
+
+impl SnoozeList {
+    /// Loads the project's snoozes; a missing or unreadable file yields none.
+    pub fn load(project_dir: &Path) -> Self {
+        fs::read_to_string(project_dir.join(STATE_DIR).join(SNOOZES_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the project's snoozes.
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let dir = project_dir.join(STATE_DIR);
+        create_ignored_dir(&dir)?;
+        let path = dir.join(SNOOZES_FILE);
+        write_atomic(&path, &serde_json::to_string(self)?)
+            .io_context(|| format!("Failed to write snoozes: {}", path.display()))
+    }
+
+    /// Suspends `pattern` for `duration` from now, replacing an earlier
+    /// snooze of it and dropping expired ones.
+    pub fn snooze(&mut self, pattern: &str, duration: chrono::Duration) -> DateTime<Utc> {
+        let now = Utc::now();
+        let until = now + duration;
+        self.snoozes.retain(|snooze| snooze.pattern != pattern && snooze.until > now);
+        self.snoozes.push(Snooze { pattern: pattern.to_string(), until });
+        until
+    }
+
+    /// Snoozes that have not expired yet, ending soonest first.
+    pub fn active(&self) -> Vec<&Snooze> {
+        let now = Utc::now();
+        let mut active: Vec<&Snooze> = self.snoozes.iter().filter(|snooze| snooze.until > now).collect();
+        active.sort_by_key(|snooze| snooze.until);
+        active
+    }
+
+    /// Patterns of the active snoozes.
+    pub fn active_patterns(&self) -> Vec<String> {
+        self.active().into_iter().map(|snooze| snooze.pattern.clone()).collect()
+    }
+}
+
+/// One measured command execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DurationSample {
+    pub command: String,
+    pub duration_ms: u64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Recent command durations across all sessions of a project.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DurationLog {
+    #[serde(default)]
+    pub samples: Vec<DurationSample>,
+}
+
+impl DurationLog {
+    /// Loads the project's duration log; a missing or unreadable file yields an empty log.
+    pub fn load(project_dir: &Path) -> Self {
+        fs::read_to_string(project_dir.join(STATE_DIR).join(DURATIONS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the project's duration log.
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let dir = project_dir.join(STATE_DIR);
+        create_ignored_dir(&dir)?;
+        let path = dir.join(DURATIONS_FILE);
+        write_atomic(&path, &serde_json::to_string(self)?)
+            .io_context(|| format!("Failed to write duration log: {}", path.display()))
+    }
+
+    /// Adds a sample, dropping the oldest ones beyond the retention limit.
+    pub fn record(&mut self, command: &str, duration_ms: u64) {
+        self.samples.push(DurationSample {
+            command: command.to_string(),
+            duration_ms,
+            recorded_at: Utc::now(),
+        });
+
+        let excess = self.samples.len().saturating_sub(MAX_DURATION_SAMPLES);
+        self.samples.drain(..excess);
+    }
+}
+
+/// Records that the first-run notice is being shown in `project_dir`.
+///
+/// # Returns
+/// * `true` the first time it is called for a project; `false` afterwards,
+///   or when the marker cannot be written, so a read-only checkout is not
+///   shown the notice on every event
+pub fn mark_onboarding_shown(project_dir: &Path) -> bool {
+    let dir = project_dir.join(STATE_DIR);
+    create_ignored_dir(&dir).is_ok()
+        && fs::OpenOptions::new().write(true).create_new(true).open(dir.join(ONBOARDING_MARKER)).is_ok()
+}
+
+/// Records that the configuration identified by `key` was checked for
+/// deprecated constructs in `project_dir`.
+///
+/// # Returns
+/// * `true` if the last check was of a different key, or there was none
+/// * `false` if it was of the same key, or when the marker cannot be
+///   written, so a read-only checkout is not shown the notice on every event
+pub fn mark_deprecations_checked(project_dir: &Path, key: &str) -> bool {
+    let dir = project_dir.join(STATE_DIR);
+    let marker = dir.join(DEPRECATIONS_MARKER);
+    if fs::read_to_string(&marker).is_ok_and(|checked| checked == key) {
+        return false;
+    }
+    create_ignored_dir(&dir).is_ok() && write_atomic(&marker, key).is_ok()
+}
+
+/// Creates a directory under `.claude/advisor/` containing a `.gitignore`
+/// that keeps its generated files out of version control.
+pub fn create_ignored_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .io_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, "*\n")?;
+    }
+    Ok(())
+}
+
+fn session_file(project_dir: &Path, session_id: &str) -> PathBuf {
+    // Session ids come from hook input; keep them from escaping the state dir
+    let name: String = session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    project_dir.join(STATE_DIR).join(format!("{name}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_session_state_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut state = SessionState::default();
+        state.record_suggestion("npm ci", "bun ci", "npm", None);
+        state.save(temp_dir.path(), "session-1").unwrap();
+
+        let mut loaded = SessionState::load(temp_dir.path(), "session-1");
+        assert!(loaded.accept_suggestion(" bun ci ", "toolu_1"));
+        let suggestion = loaded.suggestion_for_tool("toolu_1").unwrap();
+        assert_eq!(suggestion.original, "npm ci");
+        assert!(!suggestion.failed);
+
+        assert!(SessionState::load(temp_dir.path(), "other").suggestions.is_empty());
+        assert!(temp_dir.path().join(STATE_DIR).join(".gitignore").exists());
+    }
+
+    #[test]
+    fn test_take_failed_original() {
+        let mut state = SessionState::default();
+        state.record_suggestion("npm ci", "bun ci", "npm", None);
+        assert!(state.take_failed_original("npm ci").is_none());
+
+        state.suggestions[0].failed = true;
+        assert_eq!(state.take_failed_original("npm ci").unwrap().suggested, "bun ci");
+        // Only let through once
+        assert!(state.take_failed_original("npm ci").is_none());
+    }
+
+    #[test]
+    fn test_override_is_one_shot_and_lapses() {
+        let mut state = SessionState::default();
+        assert!(!state.take_override());
+        state.arm_override();
+        assert!(state.take_override());
+        assert!(!state.take_override());
+
+        state.override_armed_at = Some(Utc::now() - chrono::Duration::minutes(OVERRIDE_TIMEOUT_MINUTES + 1));
+        assert!(!state.take_override());
+    }
+
+    #[test]
+    fn test_accepted_suggestions_are_correlated() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut state = SessionState::default();
+        state.record_suggestion("npm ci", "bun ci", "npm", Some("toolu_1"));
+        assert!(!state.accept_suggestion("yarn", "toolu_2"));
+        assert!(state.accept_suggestion("bun ci", "toolu_2"));
+        // Already accepted; a second run of the same command is not a new acceptance
+        assert!(!state.accept_suggestion("bun ci", "toolu_3"));
+
+        state.suggestion_for_tool("toolu_2").unwrap().exit_code = Some(0);
+        assert!(state.suggestion_for_tool("toolu_3").is_none());
+
+        // A new suggestion for the same command keeps the accepted one
+        state.record_suggestion("npm ci", "bun ci", "npm", Some("toolu_4"));
+        assert_eq!(state.suggestions.len(), 2);
+
+        state.save(temp_dir.path(), "session-1").unwrap();
+        SessionState::default().save(temp_dir.path(), "session-2").unwrap();
+        DurationLog::default().save(temp_dir.path()).unwrap();
+        let sessions = load_all_sessions(temp_dir.path());
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.iter().map(|s| s.suggestions.len()).sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_tool_durations() {
+        let mut state = SessionState::default();
+        assert_eq!(state.finish_tool("toolu_1"), None);
+
+        state.start_tool("toolu_1");
+        assert!(state.finish_tool("toolu_1").is_some());
+        assert_eq!(state.finish_tool("toolu_1"), None);
+
+        // Tool calls that never finished (e.g. blocked ones) expire
+        state.pending.insert("stale".to_string(), Utc::now() - chrono::Duration::days(1));
+        state.start_tool("toolu_2");
+        assert!(!state.pending.contains_key("stale"));
+    }
+
+    #[test]
+    fn test_duration_log_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut log = DurationLog::default();
+        for i in 0..MAX_DURATION_SAMPLES + 1 {
+            log.record("grep -r foo .", i as u64);
+        }
+        log.save(temp_dir.path()).unwrap();
+
+        let loaded = DurationLog::load(temp_dir.path());
+        assert_eq!(loaded.samples.len(), MAX_DURATION_SAMPLES);
+        assert_eq!(loaded.samples[0].duration_ms, 1);
+    }
+
+    #[test]
+    fn test_suggestions_are_capped() {
+        let mut state = SessionState::default();
+        for i in 0..MAX_SUGGESTIONS + 5 {
+            state.record_suggestion(&format!("npm run task{i}"), &format!("bun run task{i}"), "npm", None);
+        }
+        assert_eq!(state.suggestions.len(), MAX_SUGGESTIONS);
+        assert_eq!(state.suggestions[0].original, "npm run task5");
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut state = SessionState::default();
+        for i in 0..MAX_HISTORY + 5 {
+            state.record_run(&format!("cmd {i} "), Some(0), None);
+        }
+        assert_eq!(state.history.len(), MAX_HISTORY);
+        assert_eq!(state.history[0].command, "cmd 5");
+        assert!(state.history[0].succeeded());
+    }
+
+    #[test]
+    fn test_sessions_are_isolated_and_cleaned_up() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut first = SessionState::default();
+        first.record_run("cargo test", Some(0), None);
+        first.save(temp_dir.path(), "session-a").unwrap();
+        SessionState::default().save(temp_dir.path(), "session-b").unwrap();
+        DurationLog::default().save(temp_dir.path()).unwrap();
+        SnoozeList::default().save(temp_dir.path()).unwrap();
+
+        assert!(SessionState::load(temp_dir.path(), "session-b").history.is_empty());
+        let sessions = list_sessions(temp_dir.path());
+        let mut ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["session-a", "session-b"]);
+
+        assert!(remove_sessions(temp_dir.path(), chrono::Duration::days(1)).unwrap().is_empty());
+        let removed = remove_sessions(temp_dir.path(), chrono::Duration::zero()).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(list_sessions(temp_dir.path()).is_empty());
+        // The duration log and snoozes are not sessions
+        assert!(temp_dir.path().join(STATE_DIR).join(DURATIONS_FILE).exists());
+        assert!(temp_dir.path().join(STATE_DIR).join(SNOOZES_FILE).exists());
+    }
+
+    #[test]
+    fn test_onboarding_is_shown_once() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(mark_onboarding_shown(temp_dir.path()));
+        assert!(!mark_onboarding_shown(temp_dir.path()));
+        assert!(list_sessions(temp_dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_snoozes_expire_and_replace() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut snoozes = SnoozeList::default();
+        snoozes.snooze("npm", chrono::Duration::minutes(5));
+        snoozes.snooze("git push", chrono::Duration::hours(2));
+        snoozes.snooze("npm", chrono::Duration::hours(1));
+        snoozes.snoozes.push(Snooze { pattern: "yarn".to_string(), until: Utc::now() - chrono::Duration::minutes(1) });
+        snoozes.save(temp_dir.path()).unwrap();
+
+        let loaded = SnoozeList::load(temp_dir.path());
+        assert_eq!(loaded.active_patterns(), ["npm", "git push"]);
+        assert!(loaded.active()[0].until > Utc::now() + chrono::Duration::minutes(59));
+    }
+
+    #[test]
+    fn test_session_file_is_sanitized() {
+        let path = session_file(Path::new("/project"), "../../etc/passwd");
+        assert_eq!(path, Path::new("/project").join(STATE_DIR).join("______etc_passwd.json"));
+    }
+}
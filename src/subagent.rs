@@ -0,0 +1,118 @@
+//! Checks on work delegated to subagents
+//!
+//! A Task call hands a prompt written by Claude to a subagent. The subagent's
+//! Bash commands still go through the hooks, but by then the destructive
+//! plan is already under way. `[subagents] banned` refuses prompts that ask
+//! for something the project never delegates, such as a force push. With
+//! `share_conventions = true`, the project's conventions are appended to every
+//! prompt, so the subagent follows them from its first command.
+
+use crate::conventions::list_conventions;
+use crate::rules::RuleMatch;
+use crate::types::{Config, Severity, Subagents};
+
+/// Heading that starts the conventions appended to a prompt.
+const CONVENTIONS_HEADING: &str = "## Project conventions (from claude-hook-advisor)";
+
+/// Refuses a Task prompt that contains a `[subagents] banned` phrase.
+///
+/// # Arguments
+/// * `subagents` - The `[subagents]` table
+/// * `prompt` - The prompt Claude wrote for the subagent
+///
+/// # Returns
+/// * `Some(RuleMatch)` - A blocking match naming the first banned phrase found
+/// * `None` - If the prompt contains none of them
+pub fn check_subagent_prompt(subagents: &Subagents, prompt: &str) -> Option<RuleMatch> {
+    let text = format!(" {}", words(prompt));
+    let phrase = subagents
+        .banned
+        .iter()
+        .find(|phrase| !words(phrase).is_empty() && text.contains(&format!(" {}", words(phrase))))?;
+
+    let mut reason = format!(
+        "The subagent prompt asks to '{phrase}', which this project doesn't delegate. \
+         Leave it out of the task, or ask the user and do it yourself"
+    );
+    if let Some(message) = &subagents.message {
+        reason = format!("{reason}. {message}");
+    }
+    Some(RuleMatch { pattern: phrase.clone(), reason, suggested_command: None, severity: Severity::Block })
+}
+
+/// The prompt with the project's conventions appended, when
+/// `share_conventions` is on and there are conventions to share.
+///
+/// # Returns
+/// * `Some(String)` - The prompt to give the subagent instead
+/// * `None` - If nothing should be added, including when the prompt already has them
+pub fn with_conventions(config: &Config, prompt: &str) -> Option<String> {
+    if !config.subagents.share_conventions || prompt.contains(CONVENTIONS_HEADING) {
+        return None;
+    }
+    let conventions = list_conventions(config);
+    if !conventions.starts_with("## ") {
+        // Nothing is configured
+        return None;
+    }
+    Some(format!(
+        "{}\n\n{CONVENTIONS_HEADING}\n\nFollow these in every command you run. Commands that break them are blocked.\n\n{conventions}",
+        prompt.trim_end()
+    ))
+}
+
+/// Lowercase words separated by single spaces, so "Force-push" matches "force push".
+fn words(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+
+[subagents]
+banned = ["force push", "delete branch"]
+message = "Branch cleanup is done by the release manager."
+share_conventions = true
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_banned_phrases() {
+        let subagents = config().subagents;
+        let refused = check_subagent_prompt(&subagents, "Rebase onto main, then Force-Push the result.").unwrap();
+        assert_eq!(refused.pattern, "force push");
+        assert_eq!(refused.severity, Severity::Block);
+        assert!(refused.reason.ends_with("Branch cleanup is done by the release manager."));
+        assert!(check_subagent_prompt(&subagents, "Delete  branches that were merged").is_some());
+
+        assert!(check_subagent_prompt(&subagents, "Push the branch and open a PR").is_none());
+        assert!(check_subagent_prompt(&subagents, "Undelete branch protection docs").is_none());
+    }
+
+    #[test]
+    fn test_conventions_are_appended_once() {
+        let config = config();
+        let prompt = with_conventions(&config, "Add a test for the parser.\n").unwrap();
+        assert!(prompt.starts_with("Add a test for the parser.\n\n## Project conventions"));
+        assert!(prompt.contains("- `npm` → use `bun`"));
+        assert_eq!(with_conventions(&config, &prompt), None);
+
+        let off: Config = toml::from_str("[commands]\nnpm = \"bun\"\n").unwrap();
+        assert_eq!(with_conventions(&off, "Add a test"), None);
+        let empty: Config = toml::from_str("[subagents]\nshare_conventions = true\n").unwrap();
+        assert_eq!(with_conventions(&empty, "Add a test"), None);
+    }
+}
@@ -0,0 +1,374 @@
+//! Forwarding of decision events to a central collector
+//!
+//! With a `[telemetry]` sink configured, the events written to the audit log
+//! are also sent to syslog or an HTTPS endpoint so platform teams can aggregate
+//! agent activity across machines. Each hook invocation is a short-lived
+//! process, so events are queued in `.claude/advisor/telemetry/` and sent in
+//! batches; a failed send leaves them queued for the next attempt.
+
+use crate::atomic::write_atomic;
+use crate::audit::{AuditEvent, Redactor};
+use crate::state::create_ignored_dir;
+use crate::types::{AdvisorError, ConfigError, IoContext, Result, Telemetry, TelemetrySink};
+use chrono::Utc;
+use std::fs;
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Queue directory relative to the project directory.
+pub const TELEMETRY_DIR: &str = ".claude/advisor/telemetry";
+
+/// File in the queue directory holding events not yet sent.
+const QUEUE_FILE: &str = "queue.jsonl";
+
+/// Queued events kept while the collector is unreachable; older ones are dropped first.
+const MAX_QUEUED_EVENTS: usize = 10_000;
+
+/// Where syslog messages go unless `endpoint` says otherwise.
+const DEFAULT_SYSLOG_ENDPOINT: &str = "unix:///dev/log";
+
+/// Time allowed for one HTTP request.
+#[cfg(feature = "network")]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before the first retry; doubled for each further retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Syslog priority: facility "user" (1), severity "informational" (6).
+const SYSLOG_PRIORITY: u8 = 8 + 6;
+
+/// Queues an event and sends the queue once a batch is due.
+///
+/// # Arguments
+/// * `telemetry` - The `[telemetry]` configuration
+/// * `redact` - Extra redaction patterns from `[audit]`, applied before queueing
+/// * `project_dir` - Directory holding the queue
+/// * `event` - The decision event
+///
+/// # Returns
+/// * `Ok(())` - If telemetry is disabled, the event was queued, or the batch was sent
+/// * `Err` - If the batch could not be sent; the events stay queued
+pub fn ship_event(telemetry: &Telemetry, redact: &[String], project_dir: &Path, event: AuditEvent) -> Result<()> {
+    let Some(sink) = telemetry.sink else {
+        return Ok(());
+    };
+
+    let mut queue = load_queue(project_dir);
+    queue.push(Redactor::new(redact)?.redact_event(event));
+
+    if batch_due(telemetry, &queue) {
+        match send_with_retry(telemetry, sink, &queue) {
+            Ok(()) => queue.clear(),
+            Err(e) => {
+                save_queue(project_dir, &mut queue)?;
+                return Err(e);
+            }
+        }
+    }
+
+    save_queue(project_dir, &mut queue)
+}
+
+/// Sends every queued event now, regardless of batch size or age.
+///
+/// # Returns
+/// * `Ok(count)` - Number of events sent
+/// * `Err` - If no sink is configured or sending failed
+pub fn flush(telemetry: &Telemetry, project_dir: &Path) -> Result<usize> {
+    let sink = telemetry.sink.ok_or_else(|| ConfigError::InvalidFormat("No [telemetry] sink is configured".to_string()))?;
+    let mut queue = load_queue(project_dir);
+    if !queue.is_empty() {
+        send_with_retry(telemetry, sink, &queue)?;
+    }
+
+    let count = queue.len();
+    queue.clear();
+    save_queue(project_dir, &mut queue)?;
+    Ok(count)
+}
+
+/// A batch is due when it is full or its oldest event has waited long enough.
+fn batch_due(telemetry: &Telemetry, queue: &[AuditEvent]) -> bool {
+    let max_age = chrono::Duration::seconds(telemetry.flush_interval_secs as i64);
+    queue.len() >= telemetry.batch_size
+        || queue.first().is_some_and(|oldest| Utc::now() - oldest.timestamp >= max_age)
+}
+
+fn send_with_retry(telemetry: &Telemetry, sink: TelemetrySink, events: &[AuditEvent]) -> Result<()> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        let result = match sink {
+            TelemetrySink::Http => send_http(telemetry, events),
+            TelemetrySink::Syslog => send_syslog(telemetry, events),
+        };
+        match result {
+            Err(_) if attempt < telemetry.max_retries => {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// POSTs the events as a JSON array.
+#[cfg(feature = "network")]
+fn send_http(telemetry: &Telemetry, events: &[AuditEvent]) -> Result<()> {
+    let url = telemetry
+        .endpoint
+        .as_deref()
+        .ok_or_else(|| ConfigError::InvalidFormat("[telemetry] endpoint is required for the http sink".to_string()))?;
+    check_http_endpoint("[telemetry] endpoint", url)?;
+
+    let mut request = ureq::post(url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json");
+    for (name, value) in &telemetry.headers {
+        request = request.set(name, value);
+    }
+
+    request
+        .send_string(&serde_json::to_string(events)?)
+        .map_err(|e| AdvisorError::other(format!("Failed to send telemetry to {url}"), e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "network"))]
+fn send_http(_telemetry: &Telemetry, _events: &[AuditEvent]) -> Result<()> {
+    let message = "[telemetry] sink = \"http\" needs a build with the `network` feature";
+    Err(AdvisorError::from(ConfigError::InvalidFormat(message.to_string())))
+}
+
+/// Events carry commands, so only HTTPS is allowed, except to this machine.
+///
+/// # Arguments
+/// * `setting` - The setting holding `url`, for the error, such as `[telemetry] endpoint`
+/// * `url` - The URL to check
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+pub fn check_http_endpoint(setting: &str, url: &str) -> Result<()> {
+    let loopback = ["http://localhost", "http://127.0.0.1", "http://[::1]"]
+        .iter()
+        .any(|prefix| {
+            url.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([':', '/']))
+        });
+    if url.starts_with("https://") || loopback {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidFormat(format!("{setting} must use https: {url}")).into())
+    }
+}
+
+/// Sends one RFC 5424 message per event.
+fn send_syslog(telemetry: &Telemetry, events: &[AuditEvent]) -> Result<()> {
+    let endpoint = telemetry.endpoint.as_deref().unwrap_or(DEFAULT_SYSLOG_ENDPOINT);
+    let messages = events
+        .iter()
+        .map(syslog_message)
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(address) = endpoint.strip_prefix("udp://") {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        for message in &messages {
+            socket
+                .send_to(message.as_bytes(), address)
+                .io_context(|| format!("Failed to send syslog message to {address}"))?;
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = endpoint.strip_prefix("unix://") {
+        return send_unix_syslog(path, &messages);
+    }
+
+    Err(ConfigError::InvalidFormat(format!(
+        "[telemetry] syslog endpoint must start with udp:// or unix://: {endpoint}"
+    ))
+    .into())
+}
+
+#[cfg(unix)]
+fn send_unix_syslog(path: &str, messages: &[String]) -> Result<()> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    for message in messages {
+        socket
+            .send_to(message.as_bytes(), path)
+            .io_context(|| format!("Failed to send syslog message to {path}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_unix_syslog(_path: &str, _messages: &[String]) -> Result<()> {
+    bail!("unix:// syslog endpoints are not supported on this platform")
+}
+
+fn syslog_message(event: &AuditEvent) -> Result<String> {
+    let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string());
+    Ok(format!(
+        "<{SYSLOG_PRIORITY}>1 {} {hostname} claude-hook-advisor - - - {}",
+        event.timestamp.to_rfc3339(),
+        serde_json::to_string(event)?
+    ))
+}
+
+fn queue_file(project_dir: &Path) -> PathBuf {
+    project_dir.join(TELEMETRY_DIR).join(QUEUE_FILE)
+}
+
+/// Loads queued events; unreadable lines are dropped.
+fn load_queue(project_dir: &Path) -> Vec<AuditEvent> {
+    fs::read_to_string(queue_file(project_dir))
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_queue(project_dir: &Path, queue: &mut Vec<AuditEvent>) -> Result<()> {
+    let excess = queue.len().saturating_sub(MAX_QUEUED_EVENTS);
+    queue.drain(..excess);
+
+    create_ignored_dir(&project_dir.join(TELEMETRY_DIR))?;
+    let mut content = String::new();
+    for event in queue.iter() {
+        content.push_str(&serde_json::to_string(event)?);
+        content.push('\n');
+    }
+    let path = queue_file(project_dir);
+    write_atomic(&path, content)
+        .io_context(|| format!("Failed to write telemetry queue: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    fn event(command: &str) -> AuditEvent {
+        AuditEvent {
+            timestamp: Utc::now(),
+            session_id: "session-1".to_string(),
+            tool_use_id: None,
+            command: command.to_string(),
+            decision: "block".to_string(),
+            pattern: Some("npm".to_string()),
+            suggested: None,
+        }
+    }
+
+    fn telemetry(sink: TelemetrySink, endpoint: &str) -> Telemetry {
+        Telemetry {
+            sink: Some(sink),
+            endpoint: Some(endpoint.to_string()),
+            batch_size: 2,
+            max_retries: 0,
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn test_disabled_telemetry_queues_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        ship_event(&Telemetry::default(), &[], temp_dir.path(), event("npm ci")).unwrap();
+        assert!(!temp_dir.path().join(TELEMETRY_DIR).exists());
+        assert!(flush(&Telemetry::default(), temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_syslog_batches() {
+        let temp_dir = TempDir::new().unwrap();
+        let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+        collector.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let config = telemetry(TelemetrySink::Syslog, &format!("udp://{}", collector.local_addr().unwrap()));
+
+        ship_event(&config, &[], temp_dir.path(), event("npm ci --token=abc")).unwrap();
+        assert_eq!(load_queue(temp_dir.path()).len(), 1);
+
+        ship_event(&config, &[], temp_dir.path(), event("npm test")).unwrap();
+        assert!(load_queue(temp_dir.path()).is_empty());
+
+        let mut buf = [0u8; 4096];
+        let len = collector.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(message.starts_with("<14>1 "));
+        assert!(message.contains("claude-hook-advisor"));
+        // Secrets are redacted before they leave the machine
+        assert!(message.contains("--token=[REDACTED]"));
+        let len = collector.recv(&mut buf).unwrap();
+        assert!(std::str::from_utf8(&buf[..len]).unwrap().contains("npm test"));
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_http_batch() {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let temp_dir = TempDir::new().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut config = telemetry(TelemetrySink::Http, &format!("http://{}/ingest", listener.local_addr().unwrap()));
+        config.headers.insert("Authorization".to_string(), "Bearer collector".to_string());
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                headers.push_str(&line);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            (headers, String::from_utf8(body).unwrap())
+        });
+
+        ship_event(&config, &[], temp_dir.path(), event("npm ci")).unwrap();
+        assert_eq!(flush(&config, temp_dir.path()).unwrap(), 1);
+
+        let (headers, body) = server.join().unwrap();
+        assert!(headers.starts_with("POST /ingest"));
+        assert!(headers.contains("Authorization: Bearer collector"));
+        let events: Vec<AuditEvent> = serde_json::from_str(&body).unwrap();
+        assert_eq!(events[0].command, "npm ci");
+        assert!(load_queue(temp_dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_failed_send_keeps_events_queued() {
+        let temp_dir = TempDir::new().unwrap();
+        // Nothing listens on a port we just released
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let config = telemetry(TelemetrySink::Http, &format!("http://127.0.0.1:{port}"));
+
+        ship_event(&config, &[], temp_dir.path(), event("npm ci")).unwrap();
+        assert!(ship_event(&config, &[], temp_dir.path(), event("npm test")).is_err());
+        assert_eq!(load_queue(temp_dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn test_http_endpoint_must_be_https() {
+        assert!(check_http_endpoint("[telemetry] endpoint", "https://logs.example.com/ingest").is_ok());
+        assert!(check_http_endpoint("[telemetry] endpoint", "http://localhost:8080/ingest").is_ok());
+        assert!(check_http_endpoint("[telemetry] endpoint", "http://logs.example.com/ingest").is_err());
+        assert!(check_http_endpoint("[telemetry] endpoint", "http://localhost.example.com").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Rules on arbitrary tool input fields
+//!
+//! Bash commands, file paths and Task prompts each have their own checks,
+//! written against the tool inputs this crate knows about. Claude gains new
+//! tools, and MCP servers add their own, faster than that. A `[[tool_rules]]`
+//! entry names a tool, a field of its input and a regex, such as WebFetch's
+//! `$.url` or Write's `$.file_path`, so any tool can be governed from the
+//! configuration alone.
+
+use crate::rules::RuleMatch;
+use crate::types::{AdvisorError, Result, Severity, ToolRule};
+use regex::Regex;
+use serde_json::Value;
+
+/// Longest field value quoted in a reason.
+const MAX_QUOTED_VALUE: usize = 80;
+
+/// One step of a field path.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// An object member, or an array element when the key is a number
+    Key(String),
+    /// Every element of an array or member of an object
+    Any,
+}
+
+/// Checks a tool call against the `[[tool_rules]]` for its tool.
+///
+/// # Arguments
+/// * `rules` - The `[[tool_rules]]` tables
+/// * `tool_name` - Name of the tool being called
+/// * `tool_input` - The tool input as sent
+///
+/// # Returns
+/// * `Ok(Some((RuleMatch, value)))` - The first rule that matches, and the field value it matched
+/// * `Ok(None)` - If no rule matches
+/// * `Err` - If a rule's tool, field or regex is invalid
+pub fn check_tool_rules(rules: &[ToolRule], tool_name: &str, tool_input: &Value) -> Result<Option<(RuleMatch, String)>> {
+    for rule in rules {
+        if !tool_matches(&rule.tool, tool_name)? {
+            continue;
+        }
+        let matches = compile(&rule.matches)?;
+        let unless = rule.unless.as_deref().map(compile).transpose()?;
+        let steps = parse_field(&rule.field)
+            .map_err(|e| AdvisorError::Rule(format!("Invalid tool rule field {}: {e}", rule.field)))?;
+
+        let mut values = Vec::new();
+        select(tool_input, &steps, &mut values);
+        let hit = values
+            .into_iter()
+            .filter_map(field_text)
+            .find(|value| matches.is_match(value) && !unless.as_ref().is_some_and(|unless| unless.is_match(value)));
+        if let Some(value) = hit {
+            return Ok(Some((describe(rule, tool_name, &value), value)));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `pattern` selects `tool_name`, the way Claude Code hook matchers
+/// do: `*` or an empty pattern selects every tool, anything else is a regex
+/// the whole name must match, such as `Write|Edit` or `mcp__github__.*`.
+fn tool_matches(pattern: &str, tool_name: &str) -> Result<bool> {
+    if pattern.is_empty() || pattern == "*" {
+        return Ok(true);
+    }
+    Ok(compile(&format!("^(?:{pattern})$"))?.is_match(tool_name))
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| AdvisorError::Rule(format!("Invalid tool rule pattern {pattern}: {e}")))
+}
+
+/// Parses a field as a JSON pointer (`/edits/0/new_string`) or a path
+/// (`$.edits[0].new_string`, `$.edits[*].new_string`, `$['file_path']`).
+/// A bare name such as `url` is the member of that name; an empty field or
+/// `$` is the whole input.
+fn parse_field(field: &str) -> std::result::Result<Vec<Step>, String> {
+    if let Some(pointer) = field.strip_prefix('/') {
+        return Ok(pointer.split('/').map(|key| Step::Key(key.replace("~1", "/").replace("~0", "~"))).collect());
+    }
+
+    let path = match field.strip_prefix('$') {
+        Some(path) => path.to_string(),
+        None if field.is_empty() => String::new(),
+        None => format!(".{field}"),
+    };
+    let mut steps = Vec::new();
+    let mut rest = path.as_str();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            steps.push(match &after[..end] {
+                "" => return Err("a `.` must be followed by a name".to_string()),
+                "*" => Step::Any,
+                name => Step::Key(name.to_string()),
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or("a `[` is never closed")?;
+            steps.push(match after[..end].trim() {
+                "*" => Step::Any,
+                key => Step::Key(key.trim_matches(['\'', '"']).to_string()),
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(format!("expected `.` or `[` before '{rest}'"));
+        }
+    }
+    Ok(steps)
+}
+
+/// Collects the values `steps` lead to from `value`.
+fn select<'v>(value: &'v Value, steps: &[Step], found: &mut Vec<&'v Value>) {
+    let Some((step, rest)) = steps.split_first() else {
+        found.push(value);
+        return;
+    };
+    match (step, value) {
+        (Step::Key(key), Value::Object(members)) => {
+            if let Some(member) = members.get(key) {
+                select(member, rest, found);
+            }
+        }
+        (Step::Key(key), Value::Array(elements)) => {
+            if let Some(element) = key.parse::<usize>().ok().and_then(|index| elements.get(index)) {
+                select(element, rest, found);
+            }
+        }
+        (Step::Any, Value::Object(members)) => members.values().for_each(|member| select(member, rest, found)),
+        (Step::Any, Value::Array(elements)) => elements.iter().for_each(|element| select(element, rest, found)),
+        _ => {}
+    }
+}
+
+/// The text a field value is matched as: strings as they are, other values
+/// as JSON. A missing or null field matches nothing.
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn describe(rule: &ToolRule, tool_name: &str, value: &str) -> RuleMatch {
+    let severity = rule.severity.unwrap_or_default();
+    let quoted = match value.char_indices().nth(MAX_QUOTED_VALUE) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value.to_string(),
+    };
+    let reason = rule.message.clone().unwrap_or_else(|| {
+        let verdict = match severity {
+            Severity::Warn => "is discouraged",
+            Severity::Ask => "needs confirmation",
+            Severity::Block => "is blocked",
+        };
+        format!("{tool_name} with {} '{quoted}' {verdict} by project policy", rule.field)
+    });
+    RuleMatch { pattern: format!("{} {}", rule.tool, rule.field), reason, suggested_command: None, severity }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(tool: &str, field: &str, matches: &str) -> ToolRule {
+        ToolRule { tool: tool.to_string(), field: field.to_string(), matches: matches.to_string(), ..ToolRule::default() }
+    }
+
+    #[test]
+    fn test_parse_field() {
+        let key = |name: &str| Step::Key(name.to_string());
+        assert_eq!(parse_field("/url").unwrap(), [key("url")]);
+        assert_eq!(parse_field("/a~1b/0").unwrap(), [key("a/b"), key("0")]);
+        assert_eq!(parse_field("$.url").unwrap(), [key("url")]);
+        assert_eq!(parse_field("url").unwrap(), [key("url")]);
+        assert_eq!(parse_field("$.edits[*].new_string").unwrap(), [key("edits"), Step::Any, key("new_string")]);
+        assert_eq!(parse_field("$['file_path']").unwrap(), [key("file_path")]);
+        assert_eq!(parse_field("$").unwrap(), []);
+
+        assert!(parse_field("$.edits[0").is_err());
+        assert!(parse_field("$..url").is_err());
+        assert!(parse_field("$url").is_err());
+    }
+
+    #[test]
+    fn test_check_tool_rules() {
+        let rules = [
+            ToolRule { message: Some("Internal hosts are off limits.".to_string()), ..rule("WebFetch", "$.url", r"^https?://[^/]*\.internal\b") },
+            ToolRule { unless: Some(r"\.example$".to_string()), ..rule("Write|Edit", "/file_path", "(^|/)secrets/") },
+            ToolRule { severity: Some(Severity::Ask), ..rule("mcp__db__.*", "$.queries[*].sql", "(?i)^drop ") },
+        ];
+        let check = |tool: &str, input: Value| check_tool_rules(&rules, tool, &input).unwrap();
+
+        let (fetch, value) = check("WebFetch", json!({"url": "https://wiki.internal/page", "prompt": "x"})).unwrap();
+        assert_eq!(fetch.reason, "Internal hosts are off limits.");
+        assert_eq!(fetch.severity, Severity::Block);
+        assert_eq!(value, "https://wiki.internal/page");
+        assert!(check("WebFetch", json!({"url": "https://docs.rs"})).is_none());
+
+        let (write, _) = check("Edit", json!({"file_path": "/repo/secrets/prod.env"})).unwrap();
+        assert_eq!(write.reason, "Edit with /file_path '/repo/secrets/prod.env' is blocked by project policy");
+        assert!(check("Write", json!({"file_path": "/repo/secrets/prod.env.example"})).is_none());
+        // Tool names match whole, like hook matchers
+        assert!(check("WriteFile", json!({"file_path": "secrets/x"})).is_none());
+        assert!(check("Read", json!({"file_path": "secrets/x"})).is_none());
+
+        let (query, value) =
+            check("mcp__db__query", json!({"queries": [{"sql": "select 1"}, {"sql": "DROP TABLE users"}]})).unwrap();
+        assert_eq!(query.severity, Severity::Ask);
+        assert_eq!(value, "DROP TABLE users");
+        assert!(check("mcp__db__query", json!({"queries": "DROP TABLE users"})).is_none());
+
+        // Non-string values are matched as JSON
+        let numbers = [rule("*", "$.timeout", "^[0-9]{7,}$")];
+        assert!(check_tool_rules(&numbers, "Bash", &json!({"timeout": 6000000})).unwrap().is_some());
+        assert!(check_tool_rules(&numbers, "Bash", &json!({"timeout": 600})).unwrap().is_none());
+
+        assert!(check_tool_rules(&[rule("Write", "$.file_path", "(")], "Write", &json!({})).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 //! Type definitions for Claude Hook Advisor
 
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 
 /// Supported configuration file names in order of priority
 pub const CONFIG_FILE_NAMES: &[&str] = &[
@@ -15,6 +18,17 @@ pub const DEFAULT_CONFIG_FILE: &str = ".claude.toml";
 /// Backup file suffix for migration
 pub const BACKUP_SUFFIX: &str = ".backup";
 
+/// Exit code: the command is allowed (no rule matched)
+pub const EXIT_ALLOW: i32 = 0;
+/// Exit code: a warning rule matched, the command is still allowed
+pub const EXIT_WARN: i32 = 3;
+/// Exit code: a blocking rule or command mapping matched
+pub const EXIT_BLOCK: i32 = 4;
+/// Exit code: the configuration could not be read or parsed
+pub const EXIT_CONFIG_ERROR: i32 = 5;
+/// Exit code: an ask rule matched, the command needs confirmation
+pub const EXIT_ASK: i32 = 6;
+
 /// Configuration-related errors
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
@@ -35,6 +49,25 @@ pub enum ConfigError {
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Failed to read config file {path}: {source}")]
+    ReadFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Signature check failed for {0}")]
+    InvalidSignature(String),
+
+    #[error("Directory alias '{0}' not found")]
+    UnknownAlias(String),
+
+    #[error("Rule group '{0}' not found")]
+    UnknownGroup(String),
+
+    #[error("Directory alias '{alias}' points to {path}, outside the allowed roots")]
+    DirectoryNotAllowed { alias: String, path: String },
 }
 
 impl From<toml::de::Error> for ConfigError {
@@ -43,45 +76,1193 @@ impl From<toml::de::Error> for ConfigError {
     }
 }
 
-impl From<anyhow::Error> for ConfigError {
-    fn from(err: anyhow::Error) -> Self {
-        ConfigError::InvalidFormat(err.to_string())
+/// Errors returned throughout the crate, grouped by what failed.
+///
+/// Library consumers can match on the variant to tell failure kinds apart;
+/// the variants are stable, and new ones may be added.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum AdvisorError {
+    /// The configuration could not be found, read, parsed or validated
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    /// A file, process or network operation failed
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Hook input or output did not follow the Claude Code hook protocol
+    #[error("Hook protocol error: {0}")]
+    Protocol(String),
+
+    /// A rule pattern could not be compiled or evaluated
+    #[error("Rule error: {0}")]
+    Rule(String),
+
+    /// Installing hooks or updating Claude Code settings failed
+    #[error("Installation failed: {0}")]
+    Installer(String),
+}
+
+/// Result type used throughout the crate.
+pub type Result<T, E = AdvisorError> = std::result::Result<T, E>;
+
+impl AdvisorError {
+    /// An I/O failure described by `context`.
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        AdvisorError::Io { context: context.into(), source }
+    }
+
+    /// A failure of another kind of operation, reported as I/O.
+    pub fn other(context: impl Into<String>, error: impl std::fmt::Display) -> Self {
+        Self::io(context, std::io::Error::other(error.to_string()))
+    }
+}
+
+impl From<std::io::Error> for AdvisorError {
+    fn from(source: std::io::Error) -> Self {
+        Self::io("I/O error", source)
+    }
+}
+
+impl From<serde_json::Error> for AdvisorError {
+    fn from(err: serde_json::Error) -> Self {
+        AdvisorError::Protocol(err.to_string())
+    }
+}
+
+impl From<toml::de::Error> for AdvisorError {
+    fn from(err: toml::de::Error) -> Self {
+        AdvisorError::Config(err.into())
+    }
+}
+
+impl From<toml::ser::Error> for AdvisorError {
+    fn from(err: toml::ser::Error) -> Self {
+        AdvisorError::Config(ConfigError::InvalidFormat(err.to_string()))
+    }
+}
+
+impl From<regex::Error> for AdvisorError {
+    fn from(err: regex::Error) -> Self {
+        AdvisorError::Rule(err.to_string())
+    }
+}
+
+/// Attaches a description of the failed operation to an I/O error.
+pub trait IoContext<T> {
+    fn io_context<F: FnOnce() -> String>(self, context: F) -> Result<T>;
+}
+
+impl<T, E: Into<std::io::Error>> IoContext<T> for std::result::Result<T, E> {
+    fn io_context<F: FnOnce() -> String>(self, context: F) -> Result<T> {
+        self.map_err(|source| AdvisorError::io(context(), source.into()))
     }
 }
 
 /// Configuration structure for command mappings and directory aliasing.
 ///
 /// Loaded from .claude.toml or .claude-hook-advisor.toml files, this struct contains
-/// the mapping from original commands to their preferred replacements
-/// and semantic directory aliases for natural language references.
-#[derive(Debug, Deserialize, Serialize)]
+/// the mapping from original commands to their preferred replacements,
+/// conditional command rules, and semantic directory aliases for natural
+/// language references.
+///
+/// The parsed config is cached with bincode, which cannot round-trip fields
+/// using `skip_serializing_if`; don't add that attribute to config types.
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Config {
+    /// Schema version the file was written for (see `CONFIG_VERSION`)
+    #[serde(default)]
+    pub version: Option<u32>,
+    #[serde(default)]
     pub commands: HashMap<String, String>,
+    /// Flags a command must be run with, such as `"cargo test" = ["--all-features"]`;
+    /// a command missing any of them is rewritten with the missing ones added
+    #[serde(default)]
+    pub inject_flags: HashMap<String, Vec<String>>,
+    /// Flags a command must not be run with, such as `"git push" = { flags = ["--force"] }`
+    #[serde(default)]
+    pub forbidden_flags: HashMap<String, ForbiddenFlags>,
     #[serde(default)]
     pub semantic_directories: HashMap<String, String>,
+    /// Shell aliases expanded before any policy is checked, such as `gco = "git checkout"`
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    #[serde(default)]
+    pub directories: DirectoryOptions,
+    #[serde(default)]
+    pub sandbox: Sandbox,
+    #[serde(default)]
+    pub network: Network,
+    #[serde(default)]
+    pub packages: Packages,
+    #[serde(default)]
+    pub ssh: Ssh,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub allowed: Allowed,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Rules on the input fields of any tool
+    #[serde(default)]
+    pub tool_rules: Vec<ToolRule>,
+    #[serde(default)]
+    pub behavior: Behavior,
+    #[serde(default)]
+    pub output: Output,
+    #[serde(default)]
+    pub matching: Matching,
+    #[serde(default)]
+    pub time: TimeOptions,
+    #[serde(default)]
+    pub advice: Vec<Advice>,
+    #[serde(default)]
+    pub audit: Audit,
+    #[serde(default)]
+    pub telemetry: Telemetry,
+    #[serde(default)]
+    pub approvals: Approvals,
+    #[serde(default)]
+    pub notifications: Notifications,
+    #[serde(default)]
+    pub container: Container,
+    #[serde(default)]
+    pub iac: Iac,
+    #[serde(default)]
+    pub checklist: Checklist,
+    /// Command pattern → per-session budget
+    #[serde(default)]
+    pub limits: HashMap<String, Limit>,
+    /// Expected decisions checked by `--test-config`
+    #[serde(default)]
+    pub tests: Vec<ConfigTest>,
+    /// Named groups of rules and mappings that can be switched off together
+    #[serde(default)]
+    pub groups: HashMap<String, RuleGroup>,
+    /// Extra organization baseline file whose rules always apply (see
+    /// `baseline::load_baseline`); it adds to the system baseline, never replaces it
+    #[serde(default)]
+    pub baseline: Option<String>,
+    /// Prompt phrase that lets one stopped tool call through
+    #[serde(default, rename = "override")]
+    pub human_override: HumanOverride,
+    /// Checks on prompts delegated to subagents with the Task tool
+    #[serde(default)]
+    pub subagents: Subagents,
+    /// Defaults added to Grep tool calls
+    #[serde(default)]
+    pub search: Search,
+    /// Aliases from the user-wide directories file (see
+    /// `load_user_directories`); `semantic_directories` take precedence
+    #[serde(skip)]
+    pub user_directories: HashMap<String, String>,
+}
+
+impl Config {
+    /// Names of the enabled groups, sorted so evaluation order is stable.
+    fn enabled_groups(&self) -> Vec<(&String, &RuleGroup)> {
+        let mut groups: Vec<(&String, &RuleGroup)> = self.groups.iter().filter(|(_, group)| group.enabled).collect();
+        groups.sort_by_key(|(name, _)| *name);
+        groups
+    }
+
+    /// The `[[rules]]` that apply: top-level rules, then those of enabled
+    /// groups in group name order.
+    pub fn active_rules(&self) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .chain(self.enabled_groups().into_iter().flat_map(|(_, group)| &group.rules))
+            .collect()
+    }
+
+    /// The `[commands]` mappings that apply: top-level mappings, then those of
+    /// enabled groups in group name order.
+    pub fn active_commands(&self) -> Vec<(&String, &String)> {
+        self.commands
+            .iter()
+            .chain(self.enabled_groups().into_iter().flat_map(|(_, group)| &group.commands))
+            .collect()
+    }
+
+    /// The `sample_rate` of the active rule with `pattern`, if it sets one.
+    pub fn sample_rate(&self, pattern: &str) -> Option<f64> {
+        self.active_rules().into_iter().filter(|rule| rule.pattern == pattern).find_map(|rule| rule.sample_rate)
+    }
+}
+
+/// Flags forbidden on one command, from a `[forbidden_flags]` entry.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ForbiddenFlags {
+    /// The flags, such as `--force` or `-y`; `--name` also forbids `--name=value`
+    #[serde(default)]
+    pub flags: Vec<String>,
+    /// Whether a command with one of them is rewritten without it or only blocked
+    #[serde(default)]
+    pub action: ForbiddenFlagAction,
+    /// Why the flags are forbidden, given in place of the default reason
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// What happens to a command that has a forbidden flag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForbiddenFlagAction {
+    /// Suggest the command without the forbidden flags
+    #[default]
+    Strip,
+    /// Block the command with the reason only
+    Block,
+}
+
+/// A named set of rules and mappings from a `[groups.<name>]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RuleGroup {
+    /// Disabled groups are ignored, as if their tables were commented out
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub commands: HashMap<String, String>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Default for RuleGroup {
+    fn default() -> Self {
+        Self { enabled: true, commands: HashMap::new(), rules: Vec::new() }
+    }
 }
 
+fn default_enabled() -> bool {
+    true
+}
+
+/// A command rule declared in a `[[rules]]` table.
+///
+/// Unlike the `[commands]` shorthand, rules can carry conditions. A rule with a
+/// `replacement` suggests the replacement like a command mapping; a rule without
+/// one blocks the matched command with `message` as the reason.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Rule {
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub when: Option<RuleCondition>,
+    /// "warn" lets the command run and only reports it; defaults to "block"
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// Higher priorities are checked first; defaults to 0, like `[commands]` mappings
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Link to why the rule exists, such as a wiki page; added to the reason
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// Regex the matched command, including its arguments, must also contain
+    #[serde(default)]
+    pub matches: Option<String>,
+    /// Regex that exempts the matched command when it contains a match
+    #[serde(default)]
+    pub unless: Option<String>,
+    /// Command that must have succeeded earlier in the session; the rule only
+    /// applies while it has not
+    #[serde(default)]
+    pub requires: Option<Requirement>,
+    /// Last day (or time) the rule applies; afterwards it is ignored
+    #[serde(default)]
+    pub expires: Option<RuleTime>,
+    /// First and last day (or time) the rule applies, such as a freeze week
+    #[serde(default)]
+    pub active_between: Option<[RuleTime; 2]>,
+    /// Share of the rule's hits recorded in usage counters, the audit log,
+    /// telemetry and suggestion statistics, such as 0.1; all of them by default
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+}
+
+impl Rule {
+    /// Whether `expires` and `active_between` let the rule apply at `now`.
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires.as_ref().is_none_or(|expires| now < expires.end)
+            && self.active_between.as_ref().is_none_or(|[from, until]| from.start <= now && now < until.end)
+    }
+
+    /// When the rule stopped applying for good, if that is before `now`.
+    pub fn expired_at(&self, now: DateTime<Utc>) -> Option<&RuleTime> {
+        self.expires
+            .iter()
+            .chain(self.active_between.as_ref().map(|[_, until]| until))
+            .find(|time| now >= time.end)
+    }
+}
+
+/// A rule on one field of a tool's input, from a `[[tool_rules]]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ToolRule {
+    /// Tool the rule applies to, such as `WebFetch`; like a hook matcher,
+    /// `*` is every tool and anything else a regex the name must match whole
+    pub tool: String,
+    /// Field of the tool input: a JSON pointer such as `/url`, or a path such
+    /// as `$.url` or `$.edits[*].new_string`
+    pub field: String,
+    /// Regex the field's value must contain a match for
+    pub matches: String,
+    /// Regex that exempts a value when it contains a match
+    #[serde(default)]
+    pub unless: Option<String>,
+    /// Reason given to Claude instead of the generated one
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Defaults to "block"
+    #[serde(default)]
+    pub severity: Option<Severity>,
+}
+
+/// A point in time for rule windows: a date such as `2025-06-30`, meaning the
+/// whole day in local time, or an RFC 3339 timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RuleTime {
+    text: String,
+    /// Start of the day, or the timestamp
+    start: DateTime<Utc>,
+    /// End of the day (exclusive), or the timestamp
+    end: DateTime<Utc>,
+}
+
+impl TryFrom<String> for RuleTime {
+    type Error = String;
+
+    fn try_from(text: String) -> std::result::Result<Self, String> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(&text) {
+            let time = time.with_timezone(&Utc);
+            return Ok(RuleTime { text, start: time, end: time });
+        }
+        let date = NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .map_err(|_| format!("'{text}' is not a date (YYYY-MM-DD) or an RFC 3339 time"))?;
+        let midnight = |date: NaiveDate| {
+            date.and_hms_opt(0, 0, 0)
+                .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+                .map(|midnight| midnight.with_timezone(&Utc))
+                .ok_or_else(|| format!("'{text}' has no local midnight"))
+        };
+        let next = date.succ_opt().ok_or_else(|| format!("'{text}' is out of range"))?;
+        Ok(RuleTime { start: midnight(date)?, end: midnight(next)?, text })
+    }
+}
+
+impl From<RuleTime> for String {
+    fn from(time: RuleTime) -> Self {
+        time.text
+    }
+}
+
+impl fmt::Display for RuleTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// A prerequisite command, from a rule's `requires` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Requirement {
+    /// Pattern the prerequisite command must match, like a `[commands]` key
+    pub before: String,
+    /// How many of the most recent commands to look back; the whole session when unset
+    #[serde(default)]
+    pub within_commands: Option<usize>,
+}
+
+/// Data-driven advice from an `[[advice]]` table: when commands matching
+/// `pattern` are repeatedly slow, Claude is told about a faster alternative.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Advice {
+    pub pattern: String,
+    /// A run counts as slow when it takes longer than this
+    pub slower_than_ms: u64,
+    /// Slow runs needed, among recent runs of the command, before advising
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,
+    /// The faster alternative to suggest
+    pub suggest: String,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+fn default_min_samples() -> usize {
+    3
+}
+
+/// Audit logging from the `[audit]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Audit {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Log file, relative to the project directory; defaults to `.claude/advisor/audit/audit.jsonl`
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Extra regexes masked before writing, on top of the built-in secret patterns
+    #[serde(default)]
+    pub redact: Vec<String>,
+    /// age public keys (`age1...`); when set, every record is encrypted to them
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    /// Records older than this many days are rolled up into daily counts
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Most records kept in the log; older ones are rolled up into daily counts
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+}
+
+/// Approval of `ask` decisions by a webhook, from the `[approvals]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Approvals {
+    /// HTTPS URL pending commands are POSTed to; approvals are off when unset
+    #[serde(default)]
+    pub webhook: Option<String>,
+    /// Extra HTTP headers, e.g. for authentication
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// How long to wait for a verdict; keep it below the hook's own timeout
+    #[serde(default = "default_approval_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Time between checks on a pending request
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// What happens when no verdict arrives in time
+    #[serde(default)]
+    pub on_timeout: ApprovalFallback,
+}
+
+impl Default for Approvals {
+    fn default() -> Self {
+        Approvals {
+            webhook: None,
+            headers: HashMap::new(),
+            timeout_secs: default_approval_timeout_secs(),
+            poll_interval_secs: default_poll_interval_secs(),
+            on_timeout: ApprovalFallback::default(),
+        }
+    }
+}
+
+fn default_approval_timeout_secs() -> u64 {
+    30
+}
+
+fn default_poll_interval_secs() -> u64 {
+    2
+}
+
+/// What happens to an `ask` decision the approval webhook didn't answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalFallback {
+    /// Ask the user in Claude Code's permission prompt, as without a webhook
+    #[default]
+    Ask,
+    /// Stop the command
+    Block,
+}
+
+/// Chat messages about stopped commands, from the `[notifications]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Notifications {
+    /// Slack or Discord incoming webhook URL; notifications are off when unset
+    #[serde(default)]
+    pub webhook: Option<String>,
+    /// Message format; guessed from the webhook URL when unset
+    #[serde(default)]
+    pub service: Option<NotificationService>,
+    /// Decisions that send a message, as written to the audit log
+    #[serde(default = "default_notify_decisions")]
+    pub decisions: Vec<String>,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Notifications { webhook: None, service: None, decisions: default_notify_decisions() }
+    }
+}
+
+fn default_notify_decisions() -> Vec<String> {
+    vec!["block".to_string(), "denied".to_string()]
+}
+
+/// Chat services `[notifications]` can post to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationService {
+    Slack,
+    Discord,
+}
+
+/// Forwarding of decision events from the `[telemetry]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Telemetry {
+    /// Where events go; telemetry is off when unset
+    #[serde(default)]
+    pub sink: Option<TelemetrySink>,
+    /// HTTPS URL for the http sink; `udp://host:port` or `unix:///path` for syslog
+    /// (defaults to `unix:///dev/log`)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Extra HTTP headers, e.g. for authentication
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Events sent together; smaller batches are sent once their oldest event is
+    /// `flush_interval_secs` old
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+    /// Retries after a failed send before the batch is left for the next attempt
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Telemetry {
+            sink: None,
+            endpoint: None,
+            headers: HashMap::new(),
+            batch_size: default_batch_size(),
+            flush_interval_secs: default_flush_interval_secs(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+fn default_batch_size() -> usize {
+    20
+}
+
+fn default_flush_interval_secs() -> u64 {
+    60
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+/// Collector types for `[telemetry]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetrySink {
+    Syslog,
+    Http,
+}
+
+/// Commands that run inside containers, from the `[container]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Container {
+    #[serde(default)]
+    pub runtime: ContainerRuntime,
+    /// Compose service name → commands that run in it
+    #[serde(default)]
+    pub services: HashMap<String, Vec<String>>,
+    /// Container name → commands that run in it
+    #[serde(default)]
+    pub containers: HashMap<String, Vec<String>>,
+}
+
+/// Infrastructure-as-code safeguards, from the `[iac]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Iac {
+    /// Block `terraform apply` and `pulumi up` until a plan or preview succeeded in the session
+    #[serde(default)]
+    pub plan_before_apply: bool,
+}
+
+/// Items checked before Claude ends its turn, from the `[checklist]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Checklist {
+    /// Command patterns whose latest run in the session must have succeeded
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Text that must not appear in lines added to the working tree, like "TODO"
+    #[serde(default)]
+    pub forbidden_markers: Vec<String>,
+}
+
+/// An expectation declared in a `[[tests]]` table: what the rules must
+/// decide for `command`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ConfigTest {
+    pub command: String,
+    /// The decision the rules must reach
+    #[serde(default)]
+    pub expect: Option<Expected>,
+    /// The command that must be suggested instead
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
+
+/// Decision a `[[tests]]` entry expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expected {
+    Allow,
+    Warn,
+    Ask,
+    Block,
+}
+
+impl Expected {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Expected::Allow => "allow",
+            Expected::Warn => "warn",
+            Expected::Ask => "ask",
+            Expected::Block => "block",
+        }
+    }
+}
+
+impl From<Option<Severity>> for Expected {
+    /// The decision for a command matching a rule of `severity`, if any.
+    fn from(severity: Option<Severity>) -> Self {
+        match severity {
+            None => Expected::Allow,
+            Some(Severity::Warn) => Expected::Warn,
+            Some(Severity::Ask) => Expected::Ask,
+            Some(Severity::Block) => Expected::Block,
+        }
+    }
+}
+
+/// A per-session budget for a command, from the `[limits]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Limit {
+    /// Runs allowed per session
+    #[serde(default)]
+    pub max_per_session: Option<usize>,
+    /// Total run time allowed per session, in seconds
+    #[serde(default)]
+    pub max_total_secs: Option<u64>,
+}
+
+/// Container engine used to exec into containers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// The runtime's executable name.
+    pub fn binary(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+}
+
+/// Hook output behavior from the `[behavior]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Behavior {
+    /// Emit `suppressOutput` instead of warnings for commands that are
+    /// allowed to run
+    #[serde(default)]
+    pub silent_allow: bool,
+    /// Print each tracked Bash run from PostToolUse as JSON with
+    /// `suppressOutput`; runs are always recorded in the session state
+    #[serde(default)]
+    pub tracking_output: bool,
+}
+
+/// Wording of hook responses from the `[output]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Output {
+    /// Other commands that matching rules suggest, listed after the
+    /// preferred one in a block reason; 0 lists none
+    #[serde(default)]
+    pub max_alternatives: usize,
+    /// End a block reason that suggests a command with a word diff from the
+    /// original to the suggested command
+    #[serde(default)]
+    pub show_diff: bool,
+}
+
+/// How times in rule conditions are read, from the `[time]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TimeOptions {
+    /// IANA time zone `cron` conditions are read in, such as `Europe/Berlin`;
+    /// the system's local time zone when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// Which parts of a command line rules see, from the `[matching]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Matching {
+    /// Apply `[commands]` and `[[rules]]` to the commands inside `$(...)` and
+    /// backtick substitutions too, rewriting them in place
+    #[serde(default = "default_enabled")]
+    pub check_substitutions: bool,
+    /// Most mappings a suggestion is passed on through after the first, as in
+    /// `yarn` → `npm` → `bun`; a longer chain counts as a loop, and 0
+    /// suggests the first replacement as it is
+    #[serde(default = "default_max_chain_depth")]
+    pub max_chain_depth: usize,
+}
+
+impl Default for Matching {
+    fn default() -> Self {
+        Matching { check_substitutions: true, max_chain_depth: default_max_chain_depth() }
+    }
+}
+
+fn default_max_chain_depth() -> usize {
+    16
+}
+
+/// Semantic directory handling from the `[directories]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DirectoryOptions {
+    /// Create an alias's directory (like `mkdir -p`) the first time a prompt
+    /// refers to it, instead of warning that it doesn't exist
+    #[serde(default)]
+    pub create_missing: bool,
+    /// Which path to present when a target is or passes through a symlink
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    /// Directories that targets must be inside, after resolving symlinks;
+    /// empty allows any target
+    #[serde(default)]
+    pub allowed_roots: Vec<String>,
+}
+
+/// Filesystem guardrail from the `[sandbox]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Sandbox {
+    /// Directories that tool calls may reference absolute paths in; empty
+    /// disables the sandbox. `{repo_root}` stands for the repository the
+    /// session runs in.
+    #[serde(default)]
+    pub allowed_roots: Vec<String>,
+    /// What happens to a tool call that references a path outside the roots
+    #[serde(default)]
+    pub severity: Severity,
+    /// Let Write, Edit and MultiEdit calls that only change documentation
+    /// files or comments through without a check
+    #[serde(default)]
+    pub ignore_doc_edits: bool,
+}
+
+/// Network egress policy from the `[network]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Network {
+    /// Hosts commands may connect to; `*.example.com` matches its
+    /// subdomains. Empty allows any host.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Refuse connections to IP addresses rather than host names
+    #[serde(default)]
+    pub block_raw_ips: bool,
+    /// Refuse plain `http://` and `ftp://` URLs
+    #[serde(default)]
+    pub require_https: bool,
+    /// Ask the user before any command that reaches the network
+    #[serde(default)]
+    pub ask: bool,
+    /// What happens to a command that breaks the policy
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl Network {
+    /// Whether any part of the policy is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_hosts.is_empty() || self.block_raw_ips || self.require_https || self.ask
+    }
+}
+
+/// Package install policy from the `[packages]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Packages {
+    /// Ecosystem (`pip`, `npm`, `cargo`, `gem`) → registry installs must use
+    #[serde(default)]
+    pub registries: HashMap<String, String>,
+    /// Refuse global installs such as `npm install -g`
+    #[serde(default)]
+    pub block_global: bool,
+    /// Package names, or glob patterns, that may not be installed
+    #[serde(default)]
+    pub denylist: Vec<String>,
+    /// What happens to a denylisted or global install
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Guardrails for commands run over SSH, from the `[ssh]` table.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ssh {
+    /// Apply `[commands]` and `[[rules]]` to the command `ssh host <cmd>`
+    /// runs on the remote host
+    #[serde(default = "default_enabled")]
+    pub check_remote_commands: bool,
+    /// Hosts ssh, scp, sftp and rsync may connect to, without any `user@`;
+    /// `*.example.com` matches its subdomains. Empty allows any host.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// What happens to a connection to a host missing from `allowed_hosts`
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl Default for Ssh {
+    fn default() -> Self {
+        Ssh { check_remote_commands: true, allowed_hosts: Vec::new(), severity: Severity::default() }
+    }
+}
+
+/// What happens to commands no rule or gate mentions, from the `[mode]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Mode {
+    /// `allow` lets them run; `deny` blocks every command not on the
+    /// `[allowed]` list (see `allowlist::check_allowlist`)
+    #[serde(default)]
+    pub default: DefaultDecision,
+}
+
+/// The decision for commands in [`Mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultDecision {
+    #[default]
+    Allow,
+    Deny,
+}
+
+/// Commands that may run in default-deny mode, from the `[allowed]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Allowed {
+    /// Command patterns, matched like `[commands]` patterns
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Extra guidance added to the reason a command is blocked, such as how to request access
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Human override of a wrong block, from the `[override]` table.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HumanOverride {
+    /// Phrase that, in a user prompt, lets the next tool call that would be
+    /// blocked or need confirmation through once; unset disables overrides
+    #[serde(default)]
+    pub phrase: Option<String>,
+}
+
+/// Defaults added to Grep tool calls, from the `[search]` table (see
+/// `search::search_defaults`). Each applies only when Claude left that
+/// parameter unset.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Search {
+    /// Glob limiting the files searched, such as `"!{target,vendor}/**"`
+    #[serde(default)]
+    pub glob: Option<String>,
+    /// ripgrep file type to search, such as `"rust"`
+    #[serde(default, rename = "type")]
+    pub file_type: Option<String>,
+    /// Match case-insensitively
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Most result lines or files to return
+    #[serde(default)]
+    pub head_limit: Option<u64>,
+}
+
+/// Checks on work delegated to subagents, from the `[subagents]` table (see
+/// `subagent::check_subagent_prompt`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Subagents {
+    /// Phrases a Task prompt may not contain, such as "force push"; case,
+    /// punctuation and spacing are ignored
+    #[serde(default)]
+    pub banned: Vec<String>,
+    /// Extra guidance appended when a prompt is refused
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Append the project's conventions to every Task prompt
+    #[serde(default)]
+    pub share_conventions: bool,
+}
+
+/// Keys that sign policy files, from the system's `signatures.toml` (see
+/// `baseline::load_trust` and `signing::verify_policy_file`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Signatures {
+    /// Trusted Ed25519 keys: minisign public keys (the `RW...` line) or bare
+    /// base64 keys
+    #[serde(default)]
+    pub public_keys: Vec<String>,
+    /// Refuse policy files that don't have a valid signature
+    #[serde(default)]
+    pub require_signed: bool,
+}
+
+/// How resolved directory paths treat symlinks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Present the canonical path, with every symlink resolved
+    #[default]
+    Resolve,
+    /// Present the path as configured, with only `.` and `..` normalized
+    Preserve,
+}
+
+/// How strongly a rule objects to a matched command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Report the command but let it run
+    Warn,
+    /// Let the command run only after the user confirms it
+    Ask,
+    /// Stop the command
+    #[default]
+    Block,
+}
+
+impl Severity {
+    /// The stable CLI exit code for this decision.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Severity::Warn => EXIT_WARN,
+            Severity::Ask => EXIT_ASK,
+            Severity::Block => EXIT_BLOCK,
+        }
+    }
+}
+
+/// Conditions under which a rule applies; all present fields must hold.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RuleCondition {
+    /// Claude Code permission mode, e.g. "default", "plan", "acceptEdits", "bypassPermissions"
+    #[serde(default)]
+    pub permission_mode: Option<String>,
+    /// Whether tool calls are auto-approved ("agent") or reviewed by the user ("user")
+    #[serde(default)]
+    pub source: Option<RuleSource>,
+    /// Whether Claude Code runs inside a Nix shell (`IN_NIX_SHELL` is set)
+    #[serde(default)]
+    pub in_nix_shell: Option<bool>,
+    /// Whether Claude Code runs inside a dev container
+    #[serde(default)]
+    pub in_devcontainer: Option<bool>,
+    /// Regex the current kubectl context must match; only looked up for kubectl commands
+    #[serde(default)]
+    pub kube_context: Option<String>,
+    /// Whether the working tree has uncommitted changes, untracked files
+    /// included; only looked up for commands a rule with this condition matches
+    #[serde(default)]
+    pub git_dirty: Option<bool>,
+    /// Weekly schedule the local time must fall in, such as `Mon-Fri 09:00-17:00`;
+    /// read in the `[time] timezone`
+    #[serde(default)]
+    pub cron: Option<String>,
+}
+
+impl RuleCondition {
+    /// Number of conditions set.
+    pub fn count(&self) -> usize {
+        usize::from(self.permission_mode.is_some())
+            + usize::from(self.source.is_some())
+            + usize::from(self.in_nix_shell.is_some())
+            + usize::from(self.in_devcontainer.is_some())
+            + usize::from(self.kube_context.is_some())
+            + usize::from(self.git_dirty.is_some())
+            + usize::from(self.cron.is_some())
+    }
+}
+
+/// Who is effectively in control of a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSource {
+    /// The agent acts autonomously (`acceptEdits` or `bypassPermissions` mode)
+    Agent,
+    /// The user reviews tool calls (any other permission mode)
+    User,
+}
+
+/// Generation of the hook input JSON, as detected from the fields present.
+///
+/// Claude Code's hook payloads evolve; fields that were renamed are accepted
+/// under both names (see `SCHEMA_FIELDS`) and unknown fields are ignored, so
+/// the detected version is informational rather than a gate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaVersion {
+    /// Early payloads that report PostToolUse output as `tool_result`
+    /// or its exit code as `exitCode`
+    Legacy,
+    /// Payloads using `tool_response`, `permission_mode` and `tool_use_id`
+    #[default]
+    Current,
+}
+
+impl SchemaVersion {
+    /// Every version this build understands, oldest first.
+    pub const ALL: [SchemaVersion; 2] = [SchemaVersion::Legacy, SchemaVersion::Current];
+
+    /// Detects the schema version of a raw hook payload.
+    pub fn detect(raw: &serde_json::Value) -> Self {
+        let legacy_fields = ["/tool_result", "/tool_response/exitCode"];
+        if legacy_fields.iter().any(|field| raw.pointer(field).is_some()) {
+            SchemaVersion::Legacy
+        } else {
+            SchemaVersion::Current
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SchemaVersion::Legacy => "legacy",
+            SchemaVersion::Current => "current",
+        }
+    }
+}
+
+/// A hook input field this build reads, with the names it is accepted under.
+#[derive(Debug)]
+pub struct SchemaField {
+    /// Field name in the current schema (nested fields use `parent.child`)
+    pub name: &'static str,
+    /// Older names accepted for the same field
+    pub legacy_names: &'static [&'static str],
+    /// Hook events that carry the field; empty means every event
+    pub events: &'static [&'static str],
+    /// Whether parsing fails without it
+    pub required: bool,
+}
+
+/// The hook input fields this build handles; anything else is ignored.
+pub const SCHEMA_FIELDS: &[SchemaField] = &[
+    SchemaField { name: "session_id", legacy_names: &[], events: &[], required: true },
+    SchemaField { name: "hook_event_name", legacy_names: &[], events: &[], required: true },
+    SchemaField { name: "transcript_path", legacy_names: &[], events: &[], required: false },
+    SchemaField { name: "cwd", legacy_names: &[], events: &[], required: false },
+    SchemaField { name: "permission_mode", legacy_names: &[], events: &[], required: false },
+    SchemaField { name: "tool_name", legacy_names: &[], events: &["PreToolUse", "PostToolUse"], required: false },
+    SchemaField { name: "tool_input.command", legacy_names: &[], events: &["PreToolUse", "PostToolUse"], required: false },
+    SchemaField { name: "tool_input.file_path", legacy_names: &[], events: &["PreToolUse"], required: false },
+    SchemaField { name: "tool_input.notebook_path", legacy_names: &[], events: &["PreToolUse"], required: false },
+    SchemaField { name: "tool_input.path", legacy_names: &[], events: &["PreToolUse"], required: false },
+    SchemaField { name: "tool_input.prompt", legacy_names: &[], events: &["PreToolUse"], required: false },
+    SchemaField { name: "tool_use_id", legacy_names: &[], events: &["PreToolUse", "PostToolUse"], required: false },
+    SchemaField { name: "tool_response", legacy_names: &["tool_result"], events: &["PostToolUse"], required: false },
+    SchemaField { name: "tool_response.exit_code", legacy_names: &["exitCode"], events: &["PostToolUse"], required: false },
+    SchemaField { name: "prompt", legacy_names: &[], events: &["UserPromptSubmit"], required: false },
+    SchemaField { name: "stop_hook_active", legacy_names: &[], events: &["Stop"], required: false },
+    SchemaField { name: "source", legacy_names: &[], events: &["SessionStart"], required: false },
+];
+
 /// Input data received from Claude Code hook system.
 /// 
 /// This struct represents the JSON data sent from different hook events,
 /// containing information about the tool being invoked and its parameters.
+/// Unknown fields are ignored; renamed fields are accepted under their
+/// legacy names too.
+///
+/// Every hook invocation parses one of these, so its text fields borrow from
+/// the payload instead of copying it; only strings with JSON escapes, such
+/// as commands containing quotes, are unescaped into an owned copy.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct HookInput {
-    #[allow(dead_code)]
-    pub session_id: String,
-    #[allow(dead_code)]
-    pub transcript_path: Option<String>,
+pub struct HookInput<'a> {
+    #[serde(borrow)]
+    pub session_id: Cow<'a, str>,
     #[allow(dead_code)]
-    pub cwd: Option<String>,
-    pub hook_event_name: String,
-    #[serde(default)]
-    pub tool_name: Option<String>,
-    #[serde(default)]
-    pub tool_input: Option<ToolInput>,
-    #[serde(default)]
-    pub prompt: Option<String>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub transcript_path: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub cwd: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub hook_event_name: Cow<'a, str>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub tool_name: Option<Cow<'a, str>>,
+    #[serde(default, borrow)]
+    pub tool_input: Option<ToolInput<'a>>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub prompt: Option<Cow<'a, str>>,
+    #[serde(default, borrow, alias = "tool_result")]
+    pub tool_response: Option<ToolResponse<'a>>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub permission_mode: Option<Cow<'a, str>>,
+    /// Identifies one tool call across its PreToolUse and PostToolUse events
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub tool_use_id: Option<Cow<'a, str>>,
+    /// Set on Stop events when Claude is already continuing because of a Stop hook
     #[serde(default)]
-    pub tool_response: Option<ToolResponse>,
+    pub stop_hook_active: bool,
+    /// Why a SessionStart event fired: "startup", "resume", "clear" or "compact"
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub source: Option<Cow<'a, str>>,
+    /// Schema version detected by `HookInput::parse`
+    #[serde(skip)]
+    pub schema: SchemaVersion,
+    /// The payload as received, for `raw_tool_input`
+    #[serde(skip)]
+    payload: &'a str,
+}
+
+impl<'a> HookInput<'a> {
+    /// Parses a hook payload of any supported schema version.
+    ///
+    /// # Arguments
+    /// * `json` - Hook input JSON as read from stdin, which the input borrows
+    ///
+    /// # Returns
+    /// * `Ok(HookInput)` - Parsed input with `schema` set
+    /// * `Err(AdvisorError::Protocol)` - If the JSON is malformed or lacks a required field
+    pub fn parse(json: &'a str) -> Result<Self> {
+        let protocol = |e: serde_json::Error| AdvisorError::Protocol(format!("Failed to parse hook input JSON: {e}"));
+        let mut input: HookInput = serde_json::from_str(json).map_err(protocol)?;
+        // Only a payload mentioning a legacy field name can be legacy, so
+        // current payloads skip building the full JSON tree
+        if LEGACY_FIELD_NAMES.iter().any(|name| json.contains(name)) {
+            input.schema = SchemaVersion::detect(&serde_json::from_str(json).map_err(protocol)?);
+        }
+        input.payload = json;
+        Ok(input)
+    }
+
+    /// `tool_input` as sent, including fields `ToolInput` doesn't model, for
+    /// responses that rewrite it through `updatedInput`.
+    ///
+    /// The payload is parsed again for this, which only the few responses
+    /// that rewrite the input need. Returns `Null` if there is no tool input.
+    pub fn raw_tool_input(&self) -> serde_json::Value {
+        serde_json::from_str::<serde_json::Value>(self.payload)
+            .ok()
+            .and_then(|mut raw| raw.get_mut("tool_input").map(serde_json::Value::take))
+            .unwrap_or_default()
+    }
+}
+
+/// Field names that only legacy payloads use (see `SchemaVersion::detect`).
+const LEGACY_FIELD_NAMES: [&str; 2] = ["\"tool_result\"", "\"exitCode\""];
+
+/// Deserializes an optional string that borrows from the input when it has
+/// no escapes. A plain `Option<Cow<str>>` field would always copy it.
+fn borrow_optional<'de: 'a, 'a, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Cow<'a, str>>, D::Error> {
+    #[derive(Deserialize)]
+    struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+    Ok(Option::<Borrowed>::deserialize(deserializer)?.map(|borrowed| borrowed.0))
 }
 
 /// Tool response data from PostToolUse hooks.
@@ -89,24 +1270,38 @@ pub struct HookInput {
 /// Contains execution results and status information for tracking
 /// command success rates and confidence adjustment.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct ToolResponse {
-    #[allow(dead_code)]
+pub struct ToolResponse<'a> {
+    #[serde(default, alias = "exitCode")]
     pub exit_code: Option<i32>,
     #[allow(dead_code)]
-    pub stdout: Option<String>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub stdout: Option<Cow<'a, str>>,
     #[allow(dead_code)]
-    pub stderr: Option<String>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub stderr: Option<Cow<'a, str>>,
 }
 
 /// Tool-specific input parameters from Claude Code.
 /// 
 /// Contains the actual command and optional description for Bash tool invocations.
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
-pub struct ToolInput {
-    #[serde(default)]
-    pub command: Option<String>,
-    #[allow(dead_code)]
-    pub description: Option<String>,
+pub struct ToolInput<'a> {
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub command: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub description: Option<Cow<'a, str>>,
+    /// Instructions for the subagent started by Task
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub prompt: Option<Cow<'a, str>>,
+    /// File read or written by Read, Write, Edit and MultiEdit
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub file_path: Option<Cow<'a, str>>,
+    /// Notebook edited by NotebookEdit
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub notebook_path: Option<Cow<'a, str>>,
+    /// Directory searched by Glob and Grep
+    #[serde(default, borrow, deserialize_with = "borrow_optional")]
+    pub path: Option<Cow<'a, str>>,
 }
 
 /// Response data sent back to Claude Code hook system.
@@ -119,6 +1314,98 @@ pub struct HookOutput {
     pub reason: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub replacement_command: Option<String>,
+    /// The fully rewritten command a block suggests, for Claude to run verbatim
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_command: Option<String>,
+}
+
+/// Stop response that keeps Claude working, with the reason as its instructions.
+#[derive(Debug, Serialize)]
+pub struct StopOutput {
+    pub decision: String,
+    pub reason: String,
+}
+
+/// PreToolUse response that leaves the permission decision to the user.
+#[derive(Debug, Serialize)]
+pub struct PermissionOutput {
+    #[serde(rename = "hookSpecificOutput")]
+    pub hook_specific_output: PermissionDecision,
+}
+
+/// PreToolUse permission decision: "allow", "deny" or "ask".
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionDecision {
+    pub hook_event_name: String,
+    pub permission_decision: String,
+    pub permission_decision_reason: String,
+    /// Tool input to run instead of the one Claude sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<serde_json::Value>,
+}
+
+/// Response that makes no decision and hides the hook's stdout from the
+/// transcript, used for allowed commands when `silent_allow` is enabled.
+#[derive(Debug, Serialize)]
+pub struct SuppressedOutput {
+    #[serde(rename = "suppressOutput")]
+    pub suppress_output: bool,
+}
+
+/// PostToolUse response describing a tracked Bash run, printed when
+/// `tracking_output` is enabled. Its stdout is kept out of the transcript.
+#[derive(Debug, Serialize)]
+pub struct TrackedOutput {
+    #[serde(rename = "suppressOutput")]
+    pub suppress_output: bool,
+    pub tracked: TrackedRun,
+}
+
+/// A finished Bash command as reported in [`TrackedOutput`].
+#[derive(Debug, Serialize)]
+pub struct TrackedRun {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub duration_ms: Option<u64>,
+}
+
+/// Response that shows a notice to the user without making a decision.
+#[derive(Debug, Serialize)]
+pub struct SystemMessageOutput {
+    #[serde(rename = "systemMessage")]
+    pub system_message: String,
+}
+
+/// PostToolUse response that feeds information back to Claude.
+///
+/// A "block" decision on PostToolUse cannot undo the command; it makes
+/// Claude Code show `reason` to Claude, with `additionalContext` as detail.
+/// Without a decision, only the additional context is passed along.
+#[derive(Debug, Serialize)]
+pub struct PostToolUseOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(rename = "hookSpecificOutput")]
+    pub hook_specific_output: HookSpecificOutput,
+}
+
+/// SessionStart response adding text to Claude's context.
+#[derive(Debug, Serialize)]
+pub struct SessionStartOutput {
+    #[serde(rename = "hookSpecificOutput")]
+    pub hook_specific_output: HookSpecificOutput,
+}
+
+/// Event-specific fields of a hook response.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookSpecificOutput {
+    pub hook_event_name: String,
+    pub additional_context: String,
 }
 
 /// Result of directory resolution operation.
@@ -130,12 +1417,64 @@ pub struct DirectoryResolution {
     pub canonical_path: String,
     pub alias_used: String,
     pub variables_substituted: Vec<(String, String)>,
+    /// Where the alias was defined
+    pub scope: DirectoryScope,
+    /// Whether the directory exists, or was just created
+    pub status: DirectoryStatus,
+}
+
+/// Whether a resolved directory is on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryStatus {
+    Exists,
+    /// Created on this lookup because `create_missing` is set
+    Created,
+    /// Not on disk; `canonical_path` is the absolute path it would have
+    Missing,
+}
+
+/// Where a semantic directory alias is defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryScope {
+    /// `[semantic_directories]` in the project's configuration file
+    Project,
+    /// The user-wide directories file, shared by every project
+    User,
+}
+
+impl fmt::Display for DirectoryScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            DirectoryScope::Project => "project",
+            DirectoryScope::User => "user",
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rule_windows() {
+        let rule: Rule = toml::from_str(
+            "pattern = \"deploy\"\nactive_between = [\"2025-06-23\", \"2025-06-27\"]\nexpires = \"2025-12-31T23:59:59Z\"",
+        )
+        .unwrap();
+        let [from, until] = rule.active_between.as_ref().unwrap();
+        assert!(!rule.is_active_at(from.start - chrono::Duration::seconds(1)));
+        assert!(rule.is_active_at(from.start));
+        // The last day counts in full
+        assert!(rule.is_active_at(until.end - chrono::Duration::seconds(1)));
+        assert!(!rule.is_active_at(until.end));
+        assert_eq!(rule.expired_at(until.end).map(ToString::to_string).as_deref(), Some("2025-06-27"));
+        assert!(rule.expired_at(from.start).is_none());
+
+        let expired: Rule = toml::from_str("pattern = \"x\"\nexpires = \"2000-01-01\"").unwrap();
+        assert!(!expired.is_active_at(Utc::now()));
+        assert!(toml::from_str::<Rule>("pattern = \"x\"\nexpires = \"next week\"").unwrap_err().to_string().contains("YYYY-MM-DD"));
+    }
+
     #[test]
     fn test_hook_input_deserialization() {
         let json = r#"{
@@ -211,4 +1550,61 @@ mod tests {
         assert_eq!(input.tool_name.unwrap(), "Bash");
         assert_eq!(input.tool_response.unwrap().exit_code.unwrap(), 0);
     }
+
+    #[test]
+    fn test_legacy_post_tool_use_schema() {
+        let json = r#"{
+            "session_id": "test",
+            "hook_event_name": "PostToolUse",
+            "tool_name": "Bash",
+            "tool_input": { "command": "bun install" },
+            "tool_result": { "exitCode": 1, "stderr": "lockfile mismatch" }
+        }"#;
+
+        let input = HookInput::parse(json).unwrap();
+        assert_eq!(input.schema, SchemaVersion::Legacy);
+        assert_eq!(input.tool_response.unwrap().exit_code, Some(1));
+    }
+
+    #[test]
+    fn test_current_schema_tolerates_unknown_fields() {
+        let json = r#"{
+            "session_id": "test",
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": { "command": "npm ci", "timeout": 60000, "run_in_background": false },
+            "tool_use_id": "toolu_01",
+            "effort": { "level": "high" }
+        }"#;
+
+        let input = HookInput::parse(json).unwrap();
+        assert_eq!(input.schema, SchemaVersion::Current);
+        assert_eq!(input.tool_input.unwrap().command.as_deref(), Some("npm ci"));
+
+        let err = HookInput::parse(r#"{"hook_event_name": "Stop"}"#).unwrap_err();
+        assert!(matches!(err, AdvisorError::Protocol(_)));
+    }
+
+    #[test]
+    fn test_hook_input_borrows_from_payload() {
+        let json = r#"{
+            "session_id": "test",
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Task",
+            "tool_input": { "prompt": "say \"hi\"", "description": "Greet", "subagent_type": "general-purpose" }
+        }"#;
+
+        let input = HookInput::parse(json).unwrap();
+        assert!(matches!(input.session_id, Cow::Borrowed("test")));
+        assert!(matches!(input.tool_name, Some(Cow::Borrowed("Task"))));
+        let tool_input = input.tool_input.as_ref().unwrap();
+        assert!(matches!(tool_input.description, Some(Cow::Borrowed("Greet"))));
+        // Escaped strings can't be borrowed and are unescaped into a copy
+        assert!(matches!(tool_input.prompt.as_deref(), Some("say \"hi\"")));
+        assert!(matches!(tool_input.prompt, Some(Cow::Owned(_))));
+
+        // Fields ToolInput doesn't model are kept for updatedInput
+        assert_eq!(input.raw_tool_input()["subagent_type"], "general-purpose");
+        assert!(HookInput::parse(r#"{"session_id": "s", "hook_event_name": "Stop"}"#).unwrap().raw_tool_input().is_null());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,103 @@
+//! Terminal styling for the CLI
+//!
+//! Status lines start with an emoji saying how a step went: ✅, ⚠️, ❌ or 💡.
+//! The helpers here color those lines to match, so a long install or doctor
+//! run can be scanned at a glance, and keep the decision whether to color in
+//! one place. Colors are off when stdout isn't a terminal, when `NO_COLOR` is
+//! set, or with `--no-color` (alias `--plain`).
+//!
+//! Management commands can also leave the prose out entirely: `--quiet`
+//! prints nothing and lets the exit status speak, and `--format json` prints
+//! one JSON object with the command's result. Their prose goes through
+//! `say!`, which checks the output mode.
+
+use console::{measure_text_width, style, StyledObject};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How management commands report their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Prose with emoji, for people
+    Text,
+    /// Nothing on stdout; the exit status is the result
+    Quiet,
+    /// A single JSON object on stdout
+    Json,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(OutputMode::Text as u8);
+
+/// Sets the output mode for the rest of the process.
+pub fn set_mode(mode: OutputMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+/// The current output mode.
+pub fn mode() -> OutputMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => OutputMode::Quiet,
+        2 => OutputMode::Json,
+        _ => OutputMode::Text,
+    }
+}
+
+/// `println!` for a management command's prose, which only the text mode prints.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::ui::mode() == $crate::ui::OutputMode::Text {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use say;
+
+/// Turns colors off for the rest of the process, for `--no-color`.
+pub fn disable_colors() {
+    console::set_colors_enabled(false);
+    console::set_colors_enabled_stderr(false);
+}
+
+/// A command's title, in bold and underlined with `=` to its width.
+pub fn heading(title: &str) -> String {
+    format!("{}\n{}", style(title).bold(), "=".repeat(measure_text_width(title)))
+}
+
+/// A step that went as it should.
+pub fn success<D: Display>(text: D) -> StyledObject<D> {
+    style(text).green()
+}
+
+/// Something that works but deserves attention.
+pub fn warning<D: Display>(text: D) -> StyledObject<D> {
+    style(text).yellow()
+}
+
+/// A step that failed or a setup that can't work.
+pub fn error<D: Display>(text: D) -> StyledObject<D> {
+    style(text).red()
+}
+
+/// A suggested next step.
+pub fn hint<D: Display>(text: D) -> StyledObject<D> {
+    style(text).cyan()
+}
+
+/// Column headers of a table.
+#[cfg_attr(not(feature = "analytics"), allow(dead_code))]
+pub fn header<D: Display>(text: D) -> StyledObject<D> {
+    style(text).bold()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_output_is_unchanged() {
+        disable_colors();
+        assert_eq!(heading("🩺 Claude Hook Advisor Doctor"), "🩺 Claude Hook Advisor Doctor\n=============================");
+        assert_eq!(success("✅ Hooks installed").to_string(), "✅ Hooks installed");
+        assert_eq!(error(format!("❌ {}", 1)).to_string(), "❌ 1");
+    }
+}
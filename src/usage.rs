@@ -0,0 +1,271 @@
+//! Anonymous usage counters, strictly opt-in
+//!
+//! Nothing is collected until the user runs `--telemetry on`. From then on
+//! hooks add to a few aggregate counters: hook events and decisions by kind,
+//! and which configuration features and built-in rule packs are in use. No
+//! commands, paths, patterns, session ids or host names are recorded, and
+//! there is no install id. The counters live in the user config directory,
+//! where `--telemetry status` shows them. Once a day they are sent with the
+//! crate version and OS to the collector the release was built with, then
+//! reset; `--telemetry off` stops collection and deletes them.
+//!
+//! This is separate from `[telemetry]`, which forwards full decision events
+//! to a collector the project configures.
+
+use crate::atomic::write_atomic;
+use crate::config::user_config_dir;
+use crate::project::{builtin_templates, ProjectTemplate};
+use crate::types::{AdvisorError, Config, DefaultDecision, IoContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker file in the user config directory; collection is on while it exists.
+const CONSENT_FILE: &str = "usage-telemetry-enabled";
+
+/// Counters waiting to be sent, in the user config directory.
+const QUEUE_FILE: &str = "usage-queue.json";
+
+/// How long counters accumulate before they are sent.
+const SEND_INTERVAL_HOURS: i64 = 24;
+
+/// Time allowed for sending the counters.
+#[cfg(feature = "network")]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Collector for usage counters, set when a release is built. Without one,
+/// counters only accumulate locally.
+pub const USAGE_ENDPOINT: Option<&str> = option_env!("CLAUDE_HOOK_ADVISOR_USAGE_ENDPOINT");
+
+/// Hook events counted by name; anything else counts as "other".
+const KNOWN_EVENTS: &[&str] = &["PreToolUse", "UserPromptSubmit", "PostToolUse", "Stop", "PreCompact", "SessionStart"];
+
+/// The counters collected since they were last sent, exactly as they will be sent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub version: String,
+    pub os: String,
+    pub period_start: DateTime<Utc>,
+    pub counters: BTreeMap<String, u64>,
+}
+
+impl UsageReport {
+    fn new(now: DateTime<Utc>) -> Self {
+        UsageReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            period_start: now,
+            counters: BTreeMap::new(),
+        }
+    }
+}
+
+/// Where usage telemetry keeps its files, if a home directory is known.
+pub fn usage_dir() -> Option<PathBuf> {
+    user_config_dir()
+}
+
+/// Whether the user opted in to usage telemetry.
+pub fn is_enabled(dir: &Path) -> bool {
+    dir.join(CONSENT_FILE).exists()
+}
+
+/// Opts in to, or out of, usage telemetry. Opting out deletes the queued counters.
+///
+/// # Arguments
+/// * `dir` - The user config directory
+/// * `enabled` - Whether to collect counters from now on
+///
+/// # Returns
+/// * `Err` - If the consent marker or queue can't be written or removed
+pub fn set_enabled(dir: &Path, enabled: bool) -> Result<()> {
+    let consent = dir.join(CONSENT_FILE);
+    if enabled {
+        fs::create_dir_all(dir).io_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        let note = format!(
+            "Anonymous usage telemetry enabled on {}. Run `claude-hook-advisor --telemetry off` to stop it.\n",
+            Utc::now().format("%Y-%m-%d")
+        );
+        return write_atomic(&consent, &note).io_context(|| format!("Failed to write {}", consent.display()));
+    }
+
+    for path in [consent, dir.join(QUEUE_FILE)] {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(AdvisorError::io(format!("Failed to remove {}", path.display()), e));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The counters waiting to be sent, if any were collected.
+pub fn queued(dir: &Path) -> Option<UsageReport> {
+    serde_json::from_str(&fs::read_to_string(dir.join(QUEUE_FILE)).ok()?).ok()
+}
+
+/// Adds one to each counter in `keys`, then sends the counters if a day's
+/// worth has accumulated and a collector is known. Does nothing unless the
+/// user opted in.
+///
+/// # Arguments
+/// * `dir` - The user config directory
+/// * `keys` - Counter names, such as `events.PreToolUse`
+/// * `endpoint` - Collector to send to, normally `USAGE_ENDPOINT`
+///
+/// # Returns
+/// * `Err` - If the queue can't be saved; a failed send keeps the counters for later
+pub fn record(dir: &Path, keys: &[String], endpoint: Option<&str>) -> Result<()> {
+    if !is_enabled(dir) {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let mut report = queued(dir).unwrap_or_else(|| UsageReport::new(now));
+    for key in keys {
+        *report.counters.entry(key.clone()).or_insert(0) += 1;
+    }
+
+    let due = now - report.period_start >= chrono::Duration::hours(SEND_INTERVAL_HOURS);
+    if let Some(endpoint) = endpoint.filter(|_| due) {
+        if send(endpoint, &report).is_ok() {
+            report = UsageReport::new(now);
+        }
+    }
+
+    let path = dir.join(QUEUE_FILE);
+    write_atomic(&path, &serde_json::to_string_pretty(&report)?)
+        .io_context(|| format!("Failed to write usage counters: {}", path.display()))
+}
+
+/// Records `keys` in the user config directory; see [`record`].
+pub fn count(keys: &[String]) -> Result<()> {
+    usage_dir().map_or(Ok(()), |dir| record(&dir, keys, USAGE_ENDPOINT))
+}
+
+#[cfg(feature = "network")]
+fn send(endpoint: &str, report: &UsageReport) -> Result<()> {
+    ureq::post(endpoint)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(&serde_json::to_string(report)?)
+        .map_err(|e| AdvisorError::other(format!("Failed to send usage counters to {endpoint}"), e))?;
+    Ok(())
+}
+
+/// Without the `network` feature counters only accumulate locally.
+#[cfg(not(feature = "network"))]
+fn send(_endpoint: &str, _report: &UsageReport) -> Result<()> {
+    Err(AdvisorError::other("Failed to send usage counters", "built without the `network` feature"))
+}
+
+/// The counters one hook invocation adds: its event, the configuration
+/// features in use and the built-in rule packs the configuration contains.
+pub fn invocation_counters(config: &Config, event: &str) -> Vec<String> {
+    let event = if KNOWN_EVENTS.contains(&event) { event } else { "other" };
+    let mut keys = vec![format!("events.{event}")];
+
+    let features = [
+        ("commands", !config.commands.is_empty()),
+        ("inject_flags", !config.inject_flags.is_empty()),
+        ("forbidden_flags", !config.forbidden_flags.is_empty()),
+        ("rules", !config.rules.is_empty()),
+        ("groups", !config.groups.is_empty()),
+        ("semantic_directories", !config.semantic_directories.is_empty()),
+        ("limits", !config.limits.is_empty()),
+        ("tests", !config.tests.is_empty()),
+        ("audit", config.audit.enabled),
+        ("telemetry", config.telemetry.sink.is_some()),
+        ("container", !config.container.services.is_empty()),
+        ("sandbox", !config.sandbox.allowed_roots.is_empty()),
+        ("network", config.network.is_enabled()),
+        ("packages", !config.packages.registries.is_empty() || config.packages.block_global || !config.packages.denylist.is_empty()),
+        ("ssh", !config.ssh.allowed_hosts.is_empty()),
+        ("default_deny", config.mode.default == DefaultDecision::Deny),
+        ("search", config.search.glob.is_some() || config.search.file_type.is_some() || config.search.case_insensitive || config.search.head_limit.is_some()),
+        ("subagents", !config.subagents.banned.is_empty() || config.subagents.share_conventions),
+    ];
+    keys.extend(features.iter().filter(|(_, used)| *used).map(|(name, _)| format!("features.{name}")));
+    keys.extend(
+        builtin_templates()
+            .iter()
+            .filter(|template| pack_in_use(template, config))
+            .map(|template| format!("packs.{}", template.name)),
+    );
+    keys
+}
+
+/// Whether every mapping and rule of a built-in pack is in the configuration.
+fn pack_in_use(template: &ProjectTemplate, config: &Config) -> bool {
+    let configured: HashSet<&String> = config
+        .active_commands()
+        .into_iter()
+        .map(|(pattern, _)| pattern)
+        .chain(config.active_rules().into_iter().map(|rule| &rule.pattern))
+        .collect();
+    let mut patterns = template.commands.keys().chain(template.rules.iter().map(|rule| &rule.pattern)).peekable();
+    patterns.peek().is_some() && patterns.all(|pattern| configured.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_nothing_is_recorded_without_opt_in() {
+        let dir = TempDir::new().unwrap();
+        record(dir.path(), &["events.Stop".to_string()], None).unwrap();
+        assert!(queued(dir.path()).is_none());
+        assert!(!dir.path().join(QUEUE_FILE).exists());
+    }
+
+    #[test]
+    fn test_counters_accumulate_until_opt_out() {
+        let dir = TempDir::new().unwrap();
+        set_enabled(dir.path(), true).unwrap();
+        assert!(is_enabled(dir.path()));
+
+        let keys = vec!["events.PreToolUse".to_string(), "decisions.block".to_string()];
+        record(dir.path(), &keys, None).unwrap();
+        record(dir.path(), &keys[..1], None).unwrap();
+        let report = queued(dir.path()).unwrap();
+        assert_eq!(report.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(report.counters["events.PreToolUse"], 2);
+        assert_eq!(report.counters["decisions.block"], 1);
+
+        set_enabled(dir.path(), false).unwrap();
+        assert!(!is_enabled(dir.path()));
+        assert!(queued(dir.path()).is_none());
+        set_enabled(dir.path(), false).unwrap();
+    }
+
+    #[test]
+    fn test_invocation_counters_are_anonymous() {
+        let config: Config = toml::from_str(
+            r#"
+[commands]
+npm = "bun"
+yarn = "bun"
+pnpm = "bun"
+"npx" = "bunx"
+"secret-internal-tool" = "other"
+
+[sandbox]
+allowed_roots = ["/home/someone/work"]
+"#,
+        )
+        .unwrap();
+
+        let keys = invocation_counters(&config, "PreToolUse");
+        assert_eq!(keys[0], "events.PreToolUse");
+        assert!(keys.contains(&"features.commands".to_string()));
+        assert!(keys.contains(&"features.sandbox".to_string()));
+        assert!(!keys.contains(&"features.network".to_string()));
+        assert!(keys.iter().all(|key| !key.contains("secret") && !key.contains("someone")));
+        assert_eq!(invocation_counters(&config, "SomethingNew")[0], "events.other");
+    }
+}
@@ -0,0 +1,137 @@
+//! Word-level diffs between a command and its rewrite
+//!
+//! A rewrite often changes a single word of a long command line, such as an
+//! injected flag or a swapped package manager, and printing both lines
+//! leaves the reader to spot the difference. A word diff marks only what
+//! changed, the way `git diff --word-diff` does: `[-removed-]` and
+//! `{+added+}`, with the unchanged words between them.
+
+/// Most words per command compared word by word; longer commands only have
+/// their common start and end kept, and everything between is marked.
+const MAX_WORDS: usize = 256;
+
+/// One word of the diff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+}
+
+/// Renders the change from `original` to `changed` as a word diff.
+///
+/// Words are separated by whitespace, and the diff joins them with single
+/// spaces, so only changed words are marked, never changed spacing.
+///
+/// # Arguments
+/// * `original` - The command as Claude wrote it
+/// * `changed` - The command the rules suggest
+///
+/// # Returns
+/// The changed command with removed words as `[-...-]` and added ones as `{+...+}`
+pub fn word_diff(original: &str, changed: &str) -> String {
+    let old: Vec<&str> = original.split_whitespace().collect();
+    let new: Vec<&str> = changed.split_whitespace().collect();
+    let ops = if old.len() <= MAX_WORDS && new.len() <= MAX_WORDS { common_words(&old, &new) } else { common_ends(&old, &new) };
+    render(&ops)
+}
+
+/// Diffs by the longest common subsequence of words.
+fn common_words<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    // lengths[i][j] is the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] { lengths[i + 1][j + 1] + 1 } else { lengths[i + 1][j].max(lengths[i][j + 1]) };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Keep(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            ops.push(Op::Remove(old[i]));
+            i += 1;
+        } else {
+            ops.push(Op::Add(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|word| Op::Remove(word)));
+    ops.extend(new[j..].iter().map(|word| Op::Add(word)));
+    ops
+}
+
+/// Keeps the words both commands start and end with and marks the rest.
+fn common_ends<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+
+    let mut ops: Vec<Op> = old[..prefix].iter().map(|word| Op::Keep(word)).collect();
+    ops.extend(old[prefix..old.len() - suffix].iter().map(|word| Op::Remove(word)));
+    ops.extend(new[prefix..new.len() - suffix].iter().map(|word| Op::Add(word)));
+    ops.extend(old[old.len() - suffix..].iter().map(|word| Op::Keep(word)));
+    ops
+}
+
+/// Joins the words, grouping each run of changes into one removal and one addition.
+fn render(ops: &[Op]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut removed: Vec<&str> = Vec::new();
+    let mut added: Vec<&str> = Vec::new();
+    let flush = |parts: &mut Vec<String>, removed: &mut Vec<&str>, added: &mut Vec<&str>| {
+        let mut change = String::new();
+        if !removed.is_empty() {
+            change.push_str(&format!("[-{}-]", removed.join(" ")));
+            removed.clear();
+        }
+        if !added.is_empty() {
+            change.push_str(&format!("{{+{}+}}", added.join(" ")));
+            added.clear();
+        }
+        if !change.is_empty() {
+            parts.push(change);
+        }
+    };
+
+    for op in ops {
+        match *op {
+            Op::Keep(word) => {
+                flush(&mut parts, &mut removed, &mut added);
+                parts.push(word.to_string());
+            }
+            Op::Remove(word) => removed.push(word),
+            Op::Add(word) => added.push(word),
+        }
+    }
+    flush(&mut parts, &mut removed, &mut added);
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_diff() {
+        assert_eq!(word_diff("cargo test --release", "cargo test --all-features --release"), "cargo test {+--all-features+} --release");
+        assert_eq!(word_diff("cd web && npm ci && npm test", "cd web && bun ci && bun test"), "cd web && [-npm-]{+bun+} ci && [-npm-]{+bun+} test");
+        assert_eq!(word_diff("rsync -av src/ host:dst", "rsync -av --dry-run src/ host:dst"), "rsync -av {+--dry-run+} src/ host:dst");
+        assert_eq!(word_diff("pip install  x", "uv pip install x"), "{+uv+} pip install x");
+        assert_eq!(word_diff("ls", "ls"), "ls");
+    }
+
+    #[test]
+    fn test_long_commands_keep_common_ends() {
+        let old = format!("echo {} end", "a ".repeat(MAX_WORDS));
+        let new = format!("echo {} b end", "a ".repeat(MAX_WORDS));
+        assert!(word_diff(&old, &new).ends_with("a {+b+} end"));
+        let old = format!("npm {} x", "a ".repeat(MAX_WORDS));
+        let new = format!("bun {} x", "a ".repeat(MAX_WORDS));
+        assert!(word_diff(&old, &new).starts_with("[-npm-]{+bun+} a a"));
+    }
+}